@@ -3,7 +3,7 @@ mod io;
 use std::{
     env,
     fs::{self},
-    net::{Ipv4Addr, TcpListener},
+    net::{Ipv4Addr, SocketAddr, TcpListener},
     path::{Path, PathBuf},
     process::Stdio,
     sync::{
@@ -18,9 +18,10 @@ use std::{
 use anyhow::anyhow;
 use axum::{
     Router,
-    http::{StatusCode, Uri},
+    http::{StatusCode, Uri, header::LINK},
+    middleware,
     response::IntoResponse,
-    routing::any,
+    routing::{any, get},
 };
 use clap::Parser;
 use directories::{BaseDirs, ProjectDirs};
@@ -28,9 +29,12 @@ use eframe::{
     egui::{self},
     icon_data,
 };
+use futures::future::{BoxFuture, FutureExt};
 use manatan_server_public::{
     app::build_router_without_cors, build_state, config::Config as ManatanServerConfig,
 };
+use manatan_gateway::{API_PREFIX, DEPRECATION, mark_deprecated};
+use manatan_tls::TlsSettings;
 use reqwest::{
     Client, Method,
     header::{
@@ -42,7 +46,7 @@ use rust_embed::RustEmbed;
 use self_update::update::ReleaseUpdate;
 use serde::Serialize;
 use tokio::process::Command;
-use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::cors::CorsLayer;
 use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 
@@ -596,6 +600,10 @@ fn main() -> eframe::Result<()> {
     tracing_subscriber::fmt().with_env_filter(env_filter).init();
 
     let data_dir = resolve_data_dir();
+    if let Err(err) = manatan_config::init(None, &data_dir) {
+        error!("{err}");
+        std::process::exit(1);
+    }
 
     let server_data_dir = data_dir.clone();
     let gui_data_dir = data_dir.clone();
@@ -932,7 +940,7 @@ impl eframe::App for MyApp {
                     } else {
                         self.host.to_string()
                     };
-                    let url = format!("http://{host_target}:{}", self.port);
+                    let url = format!("{}://{host_target}:{}", manatan_tls::scheme(), self.port);
                     let _ = open::that(url);
                 }
             });
@@ -1151,16 +1159,28 @@ async fn run_server(
         .map_err(|err| anyhow!("Failed runtime bridge preflight: {err}"))?;
     let manatan_router = build_router_without_cors(manatan_state);
 
-    info!("🌍 Starting Web Interface at http://{}:{}", host, port);
+    let tls = TlsSettings::from_env().map_err(|err| anyhow!("Invalid TLS settings: {err}"))?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    info!("🌍 Starting Web Interface at {scheme}://{host}:{port}");
 
-    let ocr_router = manatan_ocr_server::create_router(data_dir.clone());
-    let yomitan_router = manatan_yomitan_server::create_router(data_dir.clone());
+    let (ocr_router, ocr_shutdown) =
+        manatan_ocr_server::create_router_with_shutdown(data_dir.clone());
+    let ocr_router = manatan_ocr_server::config::serve_standalone(ocr_router, &ocr_shutdown);
+    let (yomitan_router, yomitan_shutdown) =
+        manatan_yomitan_server::create_router_with_shutdown(data_dir.clone());
     let audio_router = manatan_audio_server::create_router(data_dir.clone());
-    let sync_router = manatan_sync_server::create_router(data_dir.clone());
-    let system_router = Router::new().route("/version", any(current_version_handler));
-
+    let (sync_router, sync_shutdown) =
+        manatan_sync_server::create_router_with_shutdown(data_dir.clone());
+    let config_router = Router::new()
+        .route("/config", get(manatan_config::config_handler))
+        .route("/tasks", get(manatan_tasks::tasks_handler));
+    let system_router = Router::new()
+        .route("/version", any(current_version_handler))
+        .merge(manatan_auth::protect(config_router));
+
+    let cors_config = manatan_auth::cors::CorsConfig::from_env();
     let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::mirror_request())
+        .allow_origin(cors_config.allow_origin())
         .allow_methods([
             Method::GET,
             Method::POST,
@@ -1177,27 +1197,56 @@ async fn run_server(
             ACCESS_CONTROL_ALLOW_HEADERS,
             ACCESS_CONTROL_REQUEST_METHOD,
         ])
-        .allow_credentials(true);
+        .expose_headers([DEPRECATION, LINK])
+        .allow_credentials(cors_config.allow_credentials());
+
+    let mut servers = Router::new();
+    if let Some(ocr_router) = ocr_router {
+        servers = servers.nest("/ocr", ocr_router);
+    }
+    let servers = servers
+        .nest("/audio", audio_router)
+        .nest("/sync", sync_router)
+        .nest("/system", system_router)
+        .nest("/yomitan", yomitan_router);
 
+    // `/api/ocr`, ... stay as deprecated aliases of the versioned paths
     let app = Router::new()
-        .nest("/api/ocr", ocr_router)
-        .nest("/api/audio", audio_router)
-        .nest("/api/sync", sync_router)
-        .nest("/api/system", system_router)
-        .nest("/api/yomitan", yomitan_router)
+        .nest(API_PREFIX, servers.clone())
+        .nest("/api", servers.layer(middleware::from_fn(mark_deprecated)))
         .merge(manatan_router)
         .fallback(serve_react_app)
         .layer(cors);
 
-    let listener_addr = format!("{}:{}", host, port);
-    let listener = tokio::net::TcpListener::bind(&listener_addr)
-        .await
-        .map_err(|err| anyhow!("Failed to create main server socket: {err:?}"))?;
-
-    let server_future = axum::serve(listener, app).with_graceful_shutdown(async move {
+    let shutdown = async move {
         let _ = shutdown_signal.recv().await;
         info!("🛑 Shutdown signal received.");
-    });
+        tokio::join!(
+            ocr_shutdown.drain(),
+            yomitan_shutdown.drain(),
+            sync_shutdown.drain()
+        );
+    };
+    let server_future: BoxFuture<'_, std::io::Result<()>> = match tls {
+        Some(tls) => {
+            let tls_config = tls
+                .rustls_config(data_dir)
+                .await
+                .map_err(|err| anyhow!("Failed to set up TLS: {err}"))?;
+            let addr = SocketAddr::from((host, port));
+            manatan_tls::serve(addr, app, tls_config, shutdown).boxed()
+        }
+        None => {
+            let listener_addr = format!("{}:{}", host, port);
+            let listener = tokio::net::TcpListener::bind(&listener_addr)
+                .await
+                .map_err(|err| anyhow!("Failed to create main server socket: {err:?}"))?;
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown)
+                .into_future()
+                .boxed()
+        }
+    };
 
     info!("✅ Unified Server Running.");
 
@@ -1532,15 +1581,20 @@ fn build_updater_with_download(
 }
 
 async fn open_webpage_when_ready(host: Ipv4Addr, port: u16) {
-    let client = Client::new();
+    let scheme = manatan_tls::scheme();
+    // Only polls our own listener, whose certificate may be self-signed
+    let client = Client::builder()
+        .danger_accept_invalid_certs(scheme == "https")
+        .build()
+        .unwrap_or_default();
 
     let host_target = if host == Ipv4Addr::new(0, 0, 0, 0) {
         "localhost".to_string()
     } else {
         host.to_string()
     };
-    let url = format!("http://{host_target}:{port}");
-    let health_url = format!("http://{host_target}:{port}/health");
+    let url = format!("{scheme}://{host_target}:{port}");
+    let health_url = format!("{scheme}://{host_target}:{port}/health");
 
     info!("⏳ Polling health endpoint for readiness (timeout 10s)...");
 
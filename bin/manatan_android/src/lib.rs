@@ -55,7 +55,7 @@ const TACHI_DATA_DIR_NAME: &str = "tachidesk_data";
 const DEFAULT_GOOGLE_OAUTH_BROKER_ENDPOINT: &str = "https://manatan.com/auth/google";
 
 fn env_bool(key: &str, default: bool) -> bool {
-    std::env::var(key)
+    manatan_config::var(key)
         .ok()
         .and_then(|value| match value.to_lowercase().as_str() {
             "1" | "true" | "yes" | "on" => Some(true),
@@ -66,7 +66,7 @@ fn env_bool(key: &str, default: bool) -> bool {
 }
 
 fn normalized_env_var(key: &str) -> Option<String> {
-    std::env::var(key)
+    manatan_config::var(key)
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty())
@@ -1147,6 +1147,7 @@ async fn start_web_server(
     default_local_anime_dir: PathBuf,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("🚀 Initializing Manatan Server on port 4568...");
+    manatan_config::init(None, &data_dir)?;
     configure_oauth_broker_env();
 
     // Pick a WebUI directory that actually contains an index.html.
@@ -1177,28 +1178,30 @@ async fn start_web_server(
     info!("WebUI directory set to {}", webui_dir.display());
     let _ = WEBUI_DIR.set(webui_dir);
 
-    let manatan_db_path = std::env::var("MANATAN_DB_PATH")
-        .unwrap_or_else(|_| data_dir.join("manatan.sqlite").to_string_lossy().to_string());
-    let manatan_migrate_path = std::env::var("MANATAN_MIGRATE_PATH").ok();
-    let manatan_runtime_url = std::env::var("MANATAN_JAVA_URL")
+    let manatan_db_path = manatan_config::var("MANATAN_DB_PATH").unwrap_or_else(|_| {
+        data_dir
+            .join("manatan.sqlite")
+            .to_string_lossy()
+            .to_string()
+    });
+    let manatan_migrate_path = manatan_config::var("MANATAN_MIGRATE_PATH").ok();
+    let manatan_runtime_url = manatan_config::var("MANATAN_JAVA_URL")
         .unwrap_or_else(|_| "http://127.0.0.1:4566".to_string());
     let tracker_remote_search = env_bool("MANATAN_TRACKER_REMOTE_SEARCH", true);
-    let tracker_search_ttl_seconds = std::env::var("MANATAN_TRACKER_SEARCH_TTL_SECONDS")
+    let tracker_search_ttl_seconds = manatan_config::var("MANATAN_TRACKER_SEARCH_TTL_SECONDS")
         .ok()
         .and_then(|value| value.parse::<i64>().ok())
         .unwrap_or(3600);
-    let downloads_path = std::env::var("MANATAN_DOWNLOADS_PATH")
+    let downloads_path = manatan_config::var("MANATAN_DOWNLOADS_PATH")
         .unwrap_or_else(|_| data_dir.join("downloads").to_string_lossy().to_string());
-    let aidoku_index_url = std::env::var("MANATAN_AIDOKU_INDEX").unwrap_or_default();
+    let aidoku_index_url = manatan_config::var("MANATAN_AIDOKU_INDEX").unwrap_or_default();
     let aidoku_enabled = env_bool("MANATAN_AIDOKU_ENABLED", true);
-    let aidoku_cache_path = std::env::var("MANATAN_AIDOKU_CACHE")
+    let aidoku_cache_path = manatan_config::var("MANATAN_AIDOKU_CACHE")
         .unwrap_or_else(|_| data_dir.join("aidoku").to_string_lossy().to_string());
-    let local_manga_path = std::env::var("MANATAN_LOCAL_MANGA_PATH").unwrap_or_else(|_| {
-        default_local_manga_dir.to_string_lossy().to_string()
-    });
-    let local_anime_path = std::env::var("MANATAN_LOCAL_ANIME_PATH").unwrap_or_else(|_| {
-        default_local_anime_dir.to_string_lossy().to_string()
-    });
+    let local_manga_path = manatan_config::var("MANATAN_LOCAL_MANGA_PATH")
+        .unwrap_or_else(|_| default_local_manga_dir.to_string_lossy().to_string());
+    let local_anime_path = manatan_config::var("MANATAN_LOCAL_ANIME_PATH")
+        .unwrap_or_else(|_| default_local_anime_dir.to_string_lossy().to_string());
     let manatan_config = ManatanServerConfig {
         host: "0.0.0.0".to_string(),
         port: 4568,
@@ -1217,14 +1220,30 @@ async fn start_web_server(
     };
     let manatan_state = build_state(manatan_config).await?;
     let manatan_router = build_router_without_cors(manatan_state);
-    let sync_router = manatan_sync_server::create_router(data_dir.clone());
-
-    let ocr_router = manatan_ocr_server::create_router(data_dir.clone());
-    let yomitan_router = manatan_yomitan_server::create_router(data_dir.clone());
+    let (sync_router, sync_shutdown) =
+        manatan_sync_server::create_router_with_shutdown(data_dir.clone());
+
+    let (ocr_router, ocr_shutdown) =
+        manatan_ocr_server::create_router_with_shutdown(data_dir.clone());
+    let ocr_router = manatan_ocr_server::config::serve_standalone(ocr_router, &ocr_shutdown);
+    let (yomitan_router, yomitan_shutdown) =
+        manatan_yomitan_server::create_router_with_shutdown(data_dir.clone());
     let audio_router = manatan_audio_server::create_router(data_dir.clone());
 
+    // This crate's tower-http predates the workspace's, so the origins are matched here
+    let cors_config = manatan_auth::cors::CorsConfig::from_env();
+    let allow_credentials = cors_config.allow_credentials();
+    let allow_origin = if cors_config.permissive {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::predicate(
+            move |origin: &axum::http::HeaderValue, _: &axum::http::request::Parts| {
+                cors_config.allows(origin)
+            },
+        )
+    };
     let cors = CorsLayer::new()
-        .allow_origin(AllowOrigin::mirror_request())
+        .allow_origin(allow_origin)
         .allow_methods([
             Method::GET,
             Method::POST,
@@ -1241,30 +1260,77 @@ async fn start_web_server(
             axum::http::header::ACCESS_CONTROL_ALLOW_HEADERS,
             axum::http::header::ACCESS_CONTROL_REQUEST_METHOD,
         ])
-        .allow_credentials(true);
+        .expose_headers([manatan_gateway::DEPRECATION, axum::http::header::LINK])
+        .allow_credentials(allow_credentials);
+
+    let mut servers = Router::new();
+    if let Some(ocr_router) = ocr_router {
+        servers = servers.nest_service("/ocr", ocr_router);
+    }
+    let servers = servers
+        .route("/system/version", any(current_version_handler))
+        .merge(manatan_auth::protect(
+            Router::new()
+                .route(
+                    "/system/config",
+                    axum::routing::get(manatan_config::config_handler),
+                )
+                .route(
+                    "/system/tasks",
+                    axum::routing::get(manatan_tasks::tasks_handler),
+                ),
+        ))
+        .route(
+            "/system/download-update",
+            axum::routing::post(download_update_handler),
+        )
+        .route("/system/install-update", any(install_update_handler))
+        .nest("/sync", sync_router)
+        .nest_service("/yomitan", yomitan_router)
+        .nest_service("/audio", audio_router);
 
+    // `/api/ocr`, ... stay as deprecated aliases of the versioned paths
     let app = Router::new()
         .route("/api/v1/webview", any(webview_shim_handler))
-        .route("/api/system/version", any(current_version_handler))
-        .route(
-            "/api/system/download-update",
-            axum::routing::post(download_update_handler),
+        .nest(manatan_gateway::API_PREFIX, servers.clone())
+        .nest(
+            "/api",
+            servers.layer(axum::middleware::from_fn(manatan_gateway::mark_deprecated)),
         )
-        .route("/api/system/install-update", any(install_update_handler))
-        .nest("/api/sync", sync_router)
-        .nest_service("/api/ocr", ocr_router)
-        .nest_service("/api/yomitan", yomitan_router)
-        .nest_service("/api/audio", audio_router)
         .merge(manatan_router)
         .fallback(serve_react_app)
         .layer(cors);
 
     let listener = TcpListener::bind("0.0.0.0:4568").await?;
     info!("✅ Web Server listening on 0.0.0.0:4568");
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            wait_for_sigterm().await;
+            info!("🛑 SIGTERM received, draining servers...");
+            tokio::join!(
+                ocr_shutdown.drain(),
+                yomitan_shutdown.drain(),
+                sync_shutdown.drain()
+            );
+        })
+        .await?;
     Ok(())
 }
 
+async fn wait_for_sigterm() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+        }
+        Err(err) => {
+            error!("Failed to listen for SIGTERM: {err}");
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
 async fn serve_react_app(uri: Uri) -> impl IntoResponse {
     let Some(webui_dir) = WEBUI_DIR.get() else {
         return (
@@ -0,0 +1,281 @@
+//! Admin tasks against a running Manatan over HTTP.
+//!
+//! Talks to the main server (`/api/yomitan`, `/api/ocr`, `/api/sync` on port 4568) by
+//! default, or to `manatan-gateway` (`/api/v1/dict`, ...) with `--gateway`. Responses
+//! are printed as JSON so the output can be piped into `jq`.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, bail};
+use clap::{Parser, Subcommand};
+use futures_util::StreamExt;
+use reqwest::{Client, RequestBuilder, Response, multipart};
+use tokio::io::AsyncWriteExt;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Admin tasks against a running Manatan server")]
+struct Cli {
+    /// Server to talk to [default: http://127.0.0.1:4568, or :4569 with --gateway]
+    #[arg(long, env = "MANATAN_CTL_URL")]
+    url: Option<String>,
+
+    /// Use the gateway's /api/v1 paths
+    #[arg(long)]
+    gateway: bool,
+
+    /// Sent as `X-API-Key` when the server requires one
+    #[arg(long, env = "MANATAN_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Dictionaries
+    #[command(subcommand)]
+    Dict(DictCommand),
+    /// OCR cache and chapter jobs
+    #[command(subcommand)]
+    Ocr(OcrCommand),
+    /// Sync with the configured backend
+    #[command(subcommand)]
+    Sync(SyncCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum DictCommand {
+    /// List installed dictionaries
+    List,
+    /// Import a Yomitan dictionary zip
+    Import { path: PathBuf },
+}
+
+#[derive(Subcommand, Debug)]
+enum OcrCommand {
+    /// List chapter jobs
+    Jobs,
+    /// Delete every cached OCR result
+    Purge,
+    /// Write the OCR cache to a file
+    Export {
+        path: PathBuf,
+        /// `ndjson` (default) or `json`
+        #[arg(long)]
+        format: Option<String>,
+        /// `gzip` to compress the export
+        #[arg(long)]
+        compression: Option<String>,
+        /// Only export chapters of this manga
+        #[arg(long)]
+        manga_id: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SyncCommand {
+    /// Sign-in state and pending local changes
+    Status,
+    /// Transfer and operation counters
+    Stats,
+    /// Pushed versions that can be rolled back to
+    History,
+    /// Pull the remote sync file now
+    Pull,
+    /// Merge a payload file into the remote data and push the result
+    Merge { payload: PathBuf },
+}
+
+/// Where each server is mounted.
+struct Api {
+    client: Client,
+    api_key: Option<String>,
+    dict: String,
+    ocr: String,
+    sync: String,
+}
+
+impl Api {
+    fn new(cli: &Cli) -> Self {
+        let default_url = if cli.gateway {
+            "http://127.0.0.1:4569"
+        } else {
+            "http://127.0.0.1:4568"
+        };
+        let url = cli
+            .url
+            .as_deref()
+            .unwrap_or(default_url)
+            .trim_end_matches('/');
+        let (dict, ocr, sync) = if cli.gateway {
+            ("api/v1/dict", "api/v1/ocr", "api/v1/sync")
+        } else {
+            ("api/yomitan", "api/ocr", "api/sync")
+        };
+        Self {
+            client: Client::new(),
+            api_key: cli.api_key.clone(),
+            dict: format!("{url}/{dict}"),
+            ocr: format!("{url}/{ocr}"),
+            sync: format!("{url}/{sync}"),
+        }
+    }
+
+    fn get(&self, url: String) -> RequestBuilder {
+        self.authorize(self.client.get(url))
+    }
+
+    fn post(&self, url: String) -> RequestBuilder {
+        self.authorize(self.client.post(url))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.header("x-api-key", api_key),
+            None => request,
+        }
+    }
+}
+
+/// Sends `request` and fails on an error status, including the body in the message.
+async fn send(request: RequestBuilder) -> anyhow::Result<Response> {
+    let response = request.send().await.context("Request failed")?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Server answered {status}: {}", body.trim());
+    }
+    Ok(response)
+}
+
+async fn print_json(request: RequestBuilder) -> anyhow::Result<()> {
+    let body: serde_json::Value = send(request)
+        .await?
+        .json()
+        .await
+        .context("Response is not JSON")?;
+    println!("{}", serde_json::to_string_pretty(&body)?);
+    Ok(())
+}
+
+async fn import_dictionary(api: &Api, path: &Path) -> anyhow::Result<()> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "dictionary.zip".to_string());
+    let form = multipart::Form::new().part(
+        "file",
+        multipart::Part::bytes(bytes)
+            .file_name(file_name)
+            .mime_str("application/zip")?,
+    );
+    let body: serde_json::Value = send(api.post(format!("{}/import", api.dict)).multipart(form))
+        .await?
+        .json()
+        .await?;
+    if body["status"] != "ok" {
+        bail!(
+            "Import failed: {}",
+            body["message"].as_str().unwrap_or("unknown error")
+        );
+    }
+    println!("{}", body["message"].as_str().unwrap_or("Imported"));
+    Ok(())
+}
+
+async fn export_ocr_cache(
+    api: &Api,
+    path: &Path,
+    format: Option<String>,
+    compression: Option<String>,
+    manga_id: Option<String>,
+) -> anyhow::Result<()> {
+    let mut query = Vec::new();
+    if let Some(format) = format {
+        query.push(("format", format));
+    }
+    if let Some(compression) = compression {
+        query.push(("compression", compression));
+    }
+    if let Some(manga_id) = manga_id {
+        query.push(("manga_id", manga_id));
+    }
+    let response = send(api.get(format!("{}/export-cache", api.ocr)).query(&query)).await?;
+
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut written = 0u64;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.context("Export was interrupted")?;
+        file.write_all(&chunk).await?;
+        written += chunk.len() as u64;
+    }
+    file.flush().await?;
+    println!("Wrote {written} bytes to {}", path.display());
+    Ok(())
+}
+
+async fn sync_status(api: &Api) -> anyhow::Result<()> {
+    let auth: serde_json::Value = send(api.get(format!("{}/auth/status", api.sync)))
+        .await?
+        .json()
+        .await?;
+    let changes: serde_json::Value = send(api.get(format!("{}/notify-change", api.sync)))
+        .await?
+        .json()
+        .await?;
+    let status = serde_json::json!({ "auth": auth, "changes": changes });
+    println!("{}", serde_json::to_string_pretty(&status)?);
+    Ok(())
+}
+
+async fn merge_payload(api: &Api, path: &Path) -> anyhow::Result<()> {
+    let payload = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let payload: serde_json::Value =
+        serde_json::from_slice(&payload).context("Payload is not JSON")?;
+    print_json(
+        api.post(format!("{}/merge", api.sync))
+            .json(&serde_json::json!({ "payload": payload })),
+    )
+    .await
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let api = Api::new(&cli);
+
+    match cli.command {
+        Command::Dict(DictCommand::List) => {
+            print_json(api.get(format!("{}/dictionaries", api.dict))).await
+        }
+        Command::Dict(DictCommand::Import { path }) => import_dictionary(&api, &path).await,
+        Command::Ocr(OcrCommand::Jobs) => print_json(api.get(format!("{}/jobs", api.ocr))).await,
+        Command::Ocr(OcrCommand::Purge) => {
+            print_json(api.post(format!("{}/purge-cache", api.ocr))).await
+        }
+        Command::Ocr(OcrCommand::Export {
+            path,
+            format,
+            compression,
+            manga_id,
+        }) => export_ocr_cache(&api, &path, format, compression, manga_id).await,
+        Command::Sync(SyncCommand::Status) => sync_status(&api).await,
+        Command::Sync(SyncCommand::Stats) => {
+            print_json(api.get(format!("{}/stats", api.sync))).await
+        }
+        Command::Sync(SyncCommand::History) => {
+            print_json(api.get(format!("{}/history", api.sync))).await
+        }
+        Command::Sync(SyncCommand::Pull) => print_json(api.get(format!("{}/pull", api.sync))).await,
+        Command::Sync(SyncCommand::Merge { payload }) => merge_payload(&api, &payload).await,
+    }
+}
@@ -0,0 +1,170 @@
+//! Which browser origins may call the servers.
+//!
+//! - `MANATAN_CORS_ALLOWED_ORIGINS`: comma-separated origins allowed on top of the
+//!   default ones, e.g. `https://reader.example.com`.
+//! - `MANATAN_CORS_PERMISSIVE`: `true` allows every origin, but without credentials, so
+//!   no page can make requests with the user's cookies. Clients authenticate with an API
+//!   key header instead.
+//!
+//! By default only origins on this machine are allowed: any scheme and port on
+//! `localhost`, `127.0.0.1` or `[::1]`, which covers the web UI and the app webviews
+//! (`capacitor://localhost`, `http://localhost`). Requests from the web UI's own origin
+//! need no CORS at all, so serving it on a LAN address keeps working.
+
+use axum::http::{HeaderValue, Method, header, request::Parts};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+const ALLOWED_ORIGINS_ENV: &str = "MANATAN_CORS_ALLOWED_ORIGINS";
+const PERMISSIVE_ENV: &str = "MANATAN_CORS_PERMISSIVE";
+const LOCAL_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+#[derive(Clone, Debug, Default)]
+pub struct CorsConfig {
+    pub permissive: bool,
+    /// Allowed besides the local origins.
+    pub allowed_origins: Vec<HeaderValue>,
+}
+
+impl CorsConfig {
+    pub fn from_env() -> Self {
+        let permissive = manatan_config::var(PERMISSIVE_ENV).is_ok_and(|value| {
+            matches!(
+                value.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes"
+            )
+        });
+        let allowed_origins = manatan_config::var(ALLOWED_ORIGINS_ENV)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|origin| origin.trim().trim_end_matches('/'))
+                    .filter(|origin| !origin.is_empty())
+                    .filter_map(|origin| match HeaderValue::from_str(origin) {
+                        Ok(origin) => Some(origin),
+                        Err(_) => {
+                            tracing::warn!(
+                                "[CORS] Ignoring invalid origin in {ALLOWED_ORIGINS_ENV}: {origin}"
+                            );
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            permissive,
+            allowed_origins,
+        }
+    }
+
+    pub fn allows(&self, origin: &HeaderValue) -> bool {
+        self.permissive || is_local_origin(origin) || self.allowed_origins.contains(origin)
+    }
+
+    pub fn allow_origin(&self) -> AllowOrigin {
+        if self.permissive {
+            return AllowOrigin::any();
+        }
+        let config = self.clone();
+        AllowOrigin::predicate(move |origin: &HeaderValue, _: &Parts| config.allows(origin))
+    }
+
+    /// Credentials are only allowed for the listed origins, never in permissive mode.
+    pub fn allow_credentials(&self) -> bool {
+        !self.permissive
+    }
+
+    /// CORS for a server router: the configured origins and the methods and headers the
+    /// servers use.
+    pub fn layer(&self) -> CorsLayer {
+        CorsLayer::new()
+            .allow_origin(self.allow_origin())
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+                Method::OPTIONS,
+            ])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                header::ACCEPT,
+                header::HeaderName::from_static(crate::API_KEY_HEADER),
+            ])
+            .allow_credentials(self.allow_credentials())
+    }
+}
+
+/// Whether `origin` is on this machine, whatever its scheme and port.
+pub fn is_local_origin(origin: &HeaderValue) -> bool {
+    let Some((_, authority)) = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.split_once("://"))
+    else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next(),
+        None => authority.split(':').next(),
+    };
+    host.is_some_and(|host| LOCAL_HOSTS.contains(&host))
+}
+
+/// The CORS layer for the configured origins, see [`CorsConfig::layer`].
+pub fn layer() -> CorsLayer {
+    CorsConfig::from_env().layer()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::{CorsConfig, is_local_origin};
+
+    fn origin(value: &str) -> HeaderValue {
+        HeaderValue::from_str(value).expect("valid origin")
+    }
+
+    #[test]
+    fn local_origins_are_recognized_on_any_scheme_and_port() {
+        assert!(is_local_origin(&origin("http://localhost:4568")));
+        assert!(is_local_origin(&origin("capacitor://localhost")));
+        assert!(is_local_origin(&origin("https://127.0.0.1")));
+        assert!(is_local_origin(&origin("http://[::1]:4568")));
+        assert!(!is_local_origin(&origin("http://localhost.example.com")));
+        assert!(!is_local_origin(&origin("http://192.168.1.5:4568")));
+        assert!(!is_local_origin(&origin("null")));
+    }
+
+    #[test]
+    fn configured_origins_are_allowed_besides_local_ones() {
+        let config = CorsConfig {
+            permissive: false,
+            allowed_origins: vec![origin("https://reader.example.com")],
+        };
+        assert!(config.allows(&origin("https://reader.example.com")));
+        assert!(config.allows(&origin("http://localhost:3000")));
+        assert!(!config.allows(&origin("https://evil.example.com")));
+
+        let permissive = CorsConfig {
+            permissive: true,
+            allowed_origins: Vec::new(),
+        };
+        assert!(permissive.allows(&origin("https://evil.example.com")));
+    }
+
+    #[test]
+    fn credentials_are_only_allowed_for_listed_origins() {
+        assert!(CorsConfig::default().allow_credentials());
+        let permissive = CorsConfig {
+            permissive: true,
+            allowed_origins: Vec::new(),
+        };
+        assert!(!permissive.allow_credentials());
+        // Would panic with credentials allowed for any origin
+        let _ = permissive.layer();
+    }
+}
@@ -0,0 +1,259 @@
+'use strict';
+
+const API = '/api/v1';
+const REFRESH_MS = 5000;
+const KEY_STORAGE = 'manatan-api-key';
+
+// Basic auth is prompted for by the browser; an API key has to be asked for here.
+async function api(path, options = {}) {
+  const headers = new Headers(options.headers);
+  const key = sessionStorage.getItem(KEY_STORAGE);
+  if (key) headers.set('X-API-Key', key);
+  const response = await fetch(path.startsWith('/healthz') ? path : API + path, {
+    ...options,
+    headers,
+  });
+  if (response.status === 401 && !response.headers.has('WWW-Authenticate')) {
+    // Another request may have asked for the key in the meantime
+    const stored = sessionStorage.getItem(KEY_STORAGE);
+    if (stored && stored !== key) return api(path, options);
+    const entered = window.prompt('API key');
+    if (entered) {
+      sessionStorage.setItem(KEY_STORAGE, entered);
+      return api(path, options);
+    }
+  }
+  if (!response.ok) {
+    throw new Error(`${path}: ${response.status} ${await response.text()}`);
+  }
+  return response.json();
+}
+
+function showMessage(text, isError = false) {
+  const message = document.getElementById('message');
+  message.textContent = text;
+  message.className = isError ? 'error' : '';
+  message.hidden = false;
+}
+
+function cell(text, className) {
+  const td = document.createElement('td');
+  td.textContent = text ?? '';
+  if (className) {
+    td.className = className;
+    td.title = text ?? '';
+  }
+  return td;
+}
+
+function button(label, onClick, className) {
+  const element = document.createElement('button');
+  element.type = 'button';
+  element.textContent = label;
+  if (className) element.className = className;
+  element.addEventListener('click', onClick);
+  return element;
+}
+
+function fillList(list, entries) {
+  list.replaceChildren(
+    ...entries.flatMap(([term, value]) => {
+      const dt = document.createElement('dt');
+      dt.textContent = term;
+      const dd = document.createElement('dd');
+      dd.textContent = value ?? '–';
+      return [dt, dd];
+    }),
+  );
+}
+
+function formatTime(millis) {
+  return millis ? new Date(millis).toLocaleString() : '–';
+}
+
+function formatBytes(bytes) {
+  const units = ['B', 'KiB', 'MiB', 'GiB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit += 1;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+}
+
+async function runAction(label, action) {
+  try {
+    await action();
+    showMessage(`${label}: done`);
+  } catch (err) {
+    showMessage(`${label}: ${err.message}`, true);
+  }
+  refresh();
+}
+
+// The dictionary server answers 200 with `status: "error"` when something fails
+async function dictionaryRequest(path, options) {
+  const result = await api(path, options);
+  if (result.status === 'error') throw new Error(result.message);
+  return result;
+}
+
+function manageDictionary(action, payload) {
+  return dictionaryRequest('/dict/manage', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ action, payload }),
+  });
+}
+
+async function loadHealth() {
+  const badge = document.getElementById('health');
+  try {
+    const health = await api('/healthz');
+    badge.textContent = health.status;
+    badge.className = `badge ${health.status}`;
+  } catch {
+    badge.textContent = 'unavailable';
+    badge.className = 'badge unavailable';
+  }
+}
+
+async function loadDictionaries() {
+  const { dictionaries, status } = await api('/dict/dictionaries');
+  document.getElementById('dict-status').textContent = status === 'loading' ? '(loading)' : '';
+  document.querySelector('#dictionaries tbody').replaceChildren(
+    ...dictionaries.map((dict) => {
+      const row = document.createElement('tr');
+      const enabled = document.createElement('input');
+      enabled.type = 'checkbox';
+      enabled.checked = dict.enabled;
+      enabled.addEventListener('change', () =>
+        runAction(`Toggle ${dict.name}`, () =>
+          manageDictionary('Toggle', { id: dict.id, enabled: enabled.checked }),
+        ),
+      );
+      const toggle = document.createElement('td');
+      toggle.append(enabled);
+      const actions = document.createElement('td');
+      actions.append(
+        button(
+          'Delete',
+          () => {
+            if (window.confirm(`Delete ${dict.name}?`)) {
+              runAction(`Delete ${dict.name}`, () => manageDictionary('Delete', { id: dict.id }));
+            }
+          },
+          'danger',
+        ),
+      );
+      row.append(cell(dict.name), cell(String(dict.priority)), toggle, actions);
+      return row;
+    }),
+  );
+}
+
+async function loadOcr() {
+  const [status, jobs] = await Promise.all([api('/ocr'), api('/ocr/jobs')]);
+  fillList(document.getElementById('ocr-stats'), [
+    ['Cached pages', status.items_in_cache],
+    ['Requests processed', status.requests_processed],
+    ['In flight', status.ocr_in_flight],
+    ['Jobs', status.jobs_paused ? 'paused' : 'running'],
+    ['Backends', status.degraded ? 'degraded' : 'ok'],
+  ]);
+  document.querySelector('#ocr-jobs tbody').replaceChildren(
+    ...jobs.map((job) => {
+      const row = document.createElement('tr');
+      row.append(
+        cell(job.base_url, 'url'),
+        cell(job.status),
+        cell(job.priority),
+        cell(String(job.pages)),
+        cell(formatTime(job.queued_at * 1000)),
+      );
+      return row;
+    }),
+  );
+}
+
+async function loadSync() {
+  const [auth, stats, changes] = await Promise.all([
+    api('/sync/auth/status'),
+    api('/sync/stats'),
+    api('/sync/notify-change'),
+  ]);
+  fillList(document.getElementById('sync-stats'), [
+    ['Backend', auth.backend],
+    ['Connected', auth.connected ? auth.email ?? 'yes' : 'no'],
+    ['Last sync', formatTime(auth.lastSync)],
+    ['Change pending', changes.pending ? 'yes' : 'no'],
+    ['Pulls / pushes / merges', `${stats.pulls} / ${stats.pushes} / ${stats.merges}`],
+    [
+      'Transferred',
+      `${formatBytes(stats.bytesUploaded)} up, ${formatBytes(stats.bytesDownloaded)} down`,
+    ],
+    ['Errors', stats.errors],
+    ['Last error', stats.lastError],
+  ]);
+}
+
+async function loadTasks() {
+  const tasks = await api('/tasks');
+  document.querySelector('#tasks tbody').replaceChildren(
+    ...tasks.map((task) => {
+      const row = document.createElement('tr');
+      row.append(
+        cell(task.name),
+        cell(task.state),
+        cell(String(task.restarts)),
+        cell(task.last_error, 'url'),
+      );
+      return row;
+    }),
+  );
+}
+
+async function refresh() {
+  const results = await Promise.allSettled([
+    loadHealth(),
+    loadDictionaries(),
+    loadOcr(),
+    loadSync(),
+    loadTasks(),
+  ]);
+  const failed = results.find((result) => result.status === 'rejected');
+  if (failed) showMessage(failed.reason.message, true);
+}
+
+const actions = {
+  'ocr-pause': () => api('/ocr/jobs/pause', { method: 'POST' }),
+  'ocr-resume': () => api('/ocr/jobs/resume', { method: 'POST' }),
+  'ocr-maintenance': () => api('/ocr/cache/maintenance', { method: 'POST' }),
+  'ocr-purge': () => api('/ocr/purge-cache', { method: 'POST' }),
+  'sync-pull': () => api('/sync/pull'),
+};
+
+document.querySelectorAll('[data-action]').forEach((element) => {
+  element.addEventListener('click', () => {
+    const action = element.dataset.action;
+    if (element.classList.contains('danger') && !window.confirm(`${element.textContent}?`)) {
+      return;
+    }
+    runAction(element.textContent, actions[action]);
+  });
+});
+
+document.getElementById('dict-import').addEventListener('submit', (event) => {
+  event.preventDefault();
+  const form = event.target;
+  runAction('Import', async () => {
+    await dictionaryRequest('/dict/import', { method: 'POST', body: new FormData(form) });
+    form.reset();
+  });
+});
+
+document.getElementById('refresh').addEventListener('click', refresh);
+
+refresh();
+setInterval(refresh, REFRESH_MS);
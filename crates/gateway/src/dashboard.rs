@@ -0,0 +1,71 @@
+//! `GET /dashboard/`: a status page for headless deployments.
+//!
+//! The page is static, embedded from `dashboard/` at build time, and reads everything
+//! from the `/api/v1` routes: the dictionaries, the OCR cache and job queue, the sync
+//! status and the background tasks, with buttons for the usual chores (importing or
+//! removing a dictionary, pausing OCR jobs, purging the cache, pulling from the sync
+//! backend). The assets hold no data, so they are served without authentication;
+//! the API calls they make go through `manatan-auth` like any other client's.
+
+use axum::{
+    Router,
+    extract::Path,
+    http::{StatusCode, header},
+    response::{IntoResponse, Redirect, Response},
+    routing::get,
+};
+use rust_embed::RustEmbed;
+
+pub const DASHBOARD_PATH: &str = "/dashboard";
+
+#[derive(RustEmbed)]
+#[folder = "dashboard/"]
+struct DashboardAssets;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            DASHBOARD_PATH,
+            get(|| async { Redirect::permanent(&format!("{DASHBOARD_PATH}/")) }),
+        )
+        .route(&format!("{DASHBOARD_PATH}/"), get(index_handler))
+        .route(&format!("{DASHBOARD_PATH}/{{*path}}"), get(asset_handler))
+}
+
+async fn index_handler() -> Response {
+    serve_asset("index.html")
+}
+
+async fn asset_handler(Path(path): Path<String>) -> Response {
+    serve_asset(&path)
+}
+
+fn serve_asset(path: &str) -> Response {
+    let Some(content) = DashboardAssets::get(path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    (
+        [
+            (header::CONTENT_TYPE, mime.as_ref()),
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        content.data,
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DashboardAssets;
+
+    #[test]
+    fn index_references_only_embedded_assets() {
+        let index = DashboardAssets::get("index.html").expect("index.html is embedded");
+        let index = std::str::from_utf8(&index.data).expect("index.html is UTF-8");
+        for asset in ["dashboard.css", "dashboard.js"] {
+            assert!(index.contains(asset), "index.html links {asset}");
+            assert!(DashboardAssets::get(asset).is_some(), "{asset} is embedded");
+        }
+    }
+}
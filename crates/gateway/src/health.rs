@@ -0,0 +1,53 @@
+//! `GET /healthz`: one status per subsystem for the app's diagnostics screen.
+//!
+//! Each server reports `ok`, `degraded` or `unavailable` from its own probe; the
+//! overall status is the worst of them, and answers 503 when anything is unavailable.
+
+use axum::{
+    Json,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+/// Health probes of the mounted servers.
+#[derive(Clone)]
+pub struct Health {
+    pub dict: manatan_yomitan_server::health::HealthProbe,
+    pub ocr: manatan_ocr_server::health::HealthProbe,
+    pub sync: manatan_sync_server::health::HealthProbe,
+}
+
+pub async fn healthz_handler(State(health): State<Health>) -> Response {
+    // The dictionary and OCR probes query their databases
+    let reports = tokio::task::spawn_blocking(move || {
+        (health.dict.check(), health.ocr.check(), health.sync.check())
+    })
+    .await;
+    let Ok((dict, ocr, sync)) = reports else {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "unavailable" })),
+        )
+            .into_response();
+    };
+
+    let statuses = [dict.status, ocr.status, sync.status];
+    let status = ["unavailable", "degraded"]
+        .into_iter()
+        .find(|worst| statuses.contains(worst))
+        .unwrap_or("ok");
+    let code = if status == "unavailable" {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    let body = json!({
+        "status": status,
+        "dict": dict,
+        "ocr": ocr,
+        "sync": sync,
+    });
+    (code, Json(body)).into_response()
+}
@@ -0,0 +1,198 @@
+//! One listener for the dictionary, OCR and sync servers.
+//!
+//! Each server keeps its own router and state; the gateway mounts them under
+//! `/api/v1/dict`, `/api/v1/ocr` and `/api/v1/sync` and adds the middleware they share
+//! (CORS for the origins `manatan-auth` allows, and request tracing), so an embedding
+//! app or a reverse proxy only deals with one port. `GET /api/v1/config` shows the
+//! settings in effect, `GET /api/v1/tasks` the background tasks of `manatan-tasks`, and
+//! `GET /healthz` sums up the health of all three servers, see [`health`]. A status page
+//! for headless deployments is served at `/dashboard/`, see [`dashboard`].
+//!
+//! The unversioned paths (`/dict/lookup`, `/config`, ...) still answer but are deprecated:
+//! their responses carry a `Deprecation` header and a `Link` to the `/api/v1` path. A
+//! breaking change to a response shape goes into a new version instead of changing v1.
+//!
+//! All of it but the dashboard's static assets sits behind the authentication
+//! `manatan-auth` reads from the config, if any is set, and is served over HTTPS when
+//! `manatan-tls` finds a certificate configured.
+
+pub mod dashboard;
+pub mod health;
+
+use std::{
+    future::Future,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+};
+
+use axum::{
+    Router,
+    extract::Request,
+    http::{HeaderName, HeaderValue, header},
+    middleware::{self, Next},
+    response::Response,
+    routing::get,
+};
+use dashboard::DASHBOARD_PATH;
+use health::Health;
+use manatan_tls::TlsSettings;
+use tower_http::trace::TraceLayer;
+
+pub const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+pub const DEFAULT_PORT: u16 = 4569;
+/// Prefix of the current API version.
+pub const API_PREFIX: &str = "/api/v1";
+
+/// Set on responses to the unversioned paths, see [`mark_deprecated`].
+pub const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+
+#[derive(Clone, Debug)]
+pub struct GatewayConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Shared by all servers, each keeps its files in its own subdirectory.
+    pub data_dir: PathBuf,
+}
+
+impl GatewayConfig {
+    pub fn new(data_dir: PathBuf) -> Self {
+        Self {
+            host: DEFAULT_HOST,
+            port: DEFAULT_PORT,
+            data_dir,
+        }
+    }
+
+    pub fn bind_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+}
+
+/// The mounted routers, along with what has to run before the process exits.
+pub struct Gateway {
+    pub router: Router,
+    pub shutdown: Shutdown,
+    pub health: Health,
+}
+
+/// Drain handles of the mounted servers.
+#[derive(Clone)]
+pub struct Shutdown {
+    pub ocr: manatan_ocr_server::shutdown::ShutdownHandle,
+    pub dict: manatan_yomitan_server::shutdown::ShutdownHandle,
+    pub sync: manatan_sync_server::ShutdownHandle,
+}
+
+impl Shutdown {
+    /// Checkpoints OCR jobs, lets a running import commit and a running sync finish
+    /// its push, all at once, each bounded by its own deadline.
+    pub async fn drain(&self) {
+        tokio::join!(self.ocr.drain(), self.dict.drain(), self.sync.drain());
+    }
+}
+
+/// Builds the routers of all three servers and mounts them under their prefixes.
+pub fn build(config: &GatewayConfig) -> Gateway {
+    let (ocr_router, ocr) = manatan_ocr_server::create_router_with_handles(config.data_dir.clone());
+    let (dict_router, dict) =
+        manatan_yomitan_server::create_router_with_handles(config.data_dir.clone());
+    let (sync_router, sync) =
+        manatan_sync_server::create_router_with_handles(config.data_dir.clone());
+    let health = Health {
+        dict: dict.health,
+        ocr: ocr.health,
+        sync: sync.health,
+    };
+
+    let cors = manatan_auth::cors::layer().expose_headers([DEPRECATION, header::LINK]);
+
+    let servers = Router::new()
+        .merge(manatan_auth::protect(
+            Router::new()
+                .route("/config", get(manatan_config::config_handler))
+                .route("/tasks", get(manatan_tasks::tasks_handler)),
+        ))
+        .nest("/dict", dict_router)
+        .nest("/ocr", ocr_router)
+        .nest("/sync", sync_router);
+
+    let router = Router::new()
+        .merge(manatan_auth::protect(
+            Router::new()
+                .route("/healthz", get(health::healthz_handler))
+                .with_state(health.clone()),
+        ))
+        .merge(dashboard::router())
+        .nest(API_PREFIX, servers.clone())
+        .merge(servers.layer(middleware::from_fn(mark_deprecated)))
+        .layer(TraceLayer::new_for_http())
+        .layer(cors);
+
+    Gateway {
+        router,
+        shutdown: Shutdown {
+            ocr: ocr.shutdown,
+            dict: dict.shutdown,
+            sync: sync.shutdown,
+        },
+        health,
+    }
+}
+
+/// Marks a response to an unversioned path as deprecated in favor of its `/api/v1` path.
+///
+/// The path is taken as the router sees it, so a router nested at `/api` gets its
+/// successor under `/api/v1` too.
+pub async fn mark_deprecated(request: Request, next: Next) -> Response {
+    let successor = format!(
+        "<{API_PREFIX}{}>; rel=\"successor-version\"",
+        request.uri().path()
+    );
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION, HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+/// Serves the gateway until `shutdown` resolves, then drains all three servers.
+pub async fn serve(
+    config: &GatewayConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let Gateway {
+        router,
+        shutdown: servers,
+        ..
+    } = build(config);
+
+    let addr = config.bind_addr();
+    let shutdown = async move {
+        shutdown.await;
+        tracing::info!("[Gateway] Shutting down, draining the servers...");
+        servers.drain().await;
+    };
+
+    if let Some(tls) = TlsSettings::from_env()? {
+        let tls_config = tls.rustls_config(&config.data_dir).await?;
+        tracing::info!("[Gateway] Serving {API_PREFIX} on https://{addr}");
+        tracing::info!("[Gateway] Dashboard at https://{addr}{DASHBOARD_PATH}/");
+        manatan_tls::serve(addr, router, tls_config, shutdown)
+            .await
+            .map_err(|err| anyhow::anyhow!("Failed to serve the gateway on {addr}: {err}"))?;
+        return Ok(());
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|err| anyhow::anyhow!("Failed to bind the gateway to {addr}: {err}"))?;
+    tracing::info!("[Gateway] Serving {API_PREFIX} on http://{addr}");
+    tracing::info!("[Gateway] Dashboard at http://{addr}{DASHBOARD_PATH}/");
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::{net::IpAddr, path::PathBuf, str::FromStr};
+
+use clap::Parser;
+use manatan_gateway::{DEFAULT_HOST, DEFAULT_PORT, GatewayConfig};
+use tracing_subscriber::EnvFilter;
+
+/// Serves the dictionary, OCR and sync servers on one port.
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Cli {
+    /// Interface to listen on [default: 127.0.0.1]
+    #[arg(long)]
+    host: Option<IpAddr>,
+
+    /// Port to listen on [default: 4569]
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Directory the servers keep their data in
+    #[arg(long, env = "MANATAN_GATEWAY_DATA_DIR")]
+    data_dir: PathBuf,
+
+    /// Config file [default: manatan.toml in the data directory]
+    #[arg(long, env = manatan_config::CONFIG_PATH_ENV)]
+    config: Option<PathBuf>,
+}
+
+/// A flag, else its setting from the environment or the config file, else `default`
+fn setting<T: FromStr>(flag: Option<T>, key: &str, default: T) -> anyhow::Result<T> {
+    if let Some(value) = flag {
+        return Ok(value);
+    }
+    match manatan_config::var(key) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid {key}: {value}")),
+        Err(_) => Ok(default),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt().with_env_filter(env_filter).init();
+
+    let cli = Cli::parse();
+    manatan_config::init(cli.config, &cli.data_dir)?;
+    let config = GatewayConfig {
+        host: setting(cli.host, "MANATAN_GATEWAY_HOST", DEFAULT_HOST)?,
+        port: setting(cli.port, "MANATAN_GATEWAY_PORT", DEFAULT_PORT)?,
+        data_dir: cli.data_dir,
+    };
+
+    manatan_gateway::serve(&config, async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl+C: {err}");
+            std::future::pending::<()>().await;
+        }
+    })
+    .await
+}
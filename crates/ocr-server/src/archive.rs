@@ -0,0 +1,455 @@
+//! OCR for pages packed in an archive (CBZ/ZIP or EPUB) instead of served by Suwayomi.
+//!
+//! CBZ pages are read in natural file name order. EPUB pages follow the spine: the
+//! images referenced by each spine document, in document order.
+//!
+//! Pages are cached under `<base_url>/<entry name>`. The base URL is `local/<path>`
+//! for archives inside `MANATAN_OCR_LOCAL_DIR` and `archive/<hash>` for uploads, and its
+//! chapter key gets the usual `chapter_pages` / `chapter_cache` rows, so
+//! `/is-chapter-preprocessed` and `/preprocess/events` work for archives too.
+
+use std::{
+    cmp::Ordering as CmpOrdering,
+    io::{Cursor, Read},
+    iter::Peekable,
+    str::Chars,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::anyhow;
+use futures::StreamExt;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    backend::OcrBackend,
+    concurrency,
+    jobs::JobEvent,
+    language::OcrLanguage,
+    logic,
+    merge::MergeConfig,
+    shutdown::PageGuard,
+    state::{AppState, CacheEntry, JobProgress},
+};
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "avif", "gif", "bmp"];
+/// Largest entry read from an archive, whatever size the archive declares for it.
+pub const MAX_ENTRY_BYTES: u64 = 32 * 1024 * 1024;
+/// Largest total of the entries read from one archive.
+pub const MAX_TOTAL_UNCOMPRESSED_BYTES: u64 = 1024 * 1024 * 1024;
+
+static TAG: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<\s*([a-z][\w:-]*)\b([^>]*)>").expect("valid tag regex"));
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?s)([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid attribute regex")
+});
+
+/// One page of an archive, in reading order.
+pub struct ArchivePage {
+    pub url: String,
+    pub bytes: Vec<u8>,
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(ch) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(ch);
+        chars.next();
+    }
+    digits
+}
+
+/// Compares strings with digit runs ordered by value, so `page2` sorts before `page10`.
+pub fn natural_cmp(a: &str, b: &str) -> CmpOrdering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return CmpOrdering::Equal,
+            (None, Some(_)) => return CmpOrdering::Less,
+            (Some(_), None) => return CmpOrdering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut a_chars);
+                let y_digits = take_digits(&mut b_chars);
+                let x_trimmed = x_digits.trim_start_matches('0');
+                let y_trimmed = y_digits.trim_start_matches('0');
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()));
+                if ordering != CmpOrdering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != CmpOrdering::Equal {
+                    return ordering;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+pub(crate) fn is_image_name(name: &str) -> bool {
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+    })
+}
+
+/// Base URL for an uploaded archive, derived from its contents.
+pub fn upload_base_url(bytes: &[u8]) -> String {
+    let digest = format!("{:x}", Sha256::digest(bytes));
+    format!("archive/{}", &digest[..16])
+}
+
+/// Reads the image entries of a ZIP/CBZ archive in natural order.
+pub fn read_zip_pages(bytes: &[u8], base_url: &str) -> anyhow::Result<Vec<ArchivePage>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut names: Vec<String> = archive
+        .file_names()
+        .filter(|name| is_image_name(name) && !name.starts_with("__MACOSX/"))
+        .map(str::to_string)
+        .collect();
+    names.sort_by(|a, b| natural_cmp(a, b));
+
+    let mut remaining = MAX_TOTAL_UNCOMPRESSED_BYTES;
+    let mut pages = Vec::with_capacity(names.len());
+    for name in names {
+        let page_bytes = read_entry(&mut archive, &name, &mut remaining)?;
+        pages.push(ArchivePage {
+            url: format!("{base_url}/{name}"),
+            bytes: page_bytes,
+        });
+    }
+    Ok(pages)
+}
+
+/// Tags with one of `names` (ignoring namespace prefixes) and their attributes, in
+/// document order.
+fn tags<'a>(
+    document: &'a str,
+    names: &'a [&'a str],
+) -> impl Iterator<Item = Vec<(String, String)>> + 'a {
+    TAG.captures_iter(document).filter_map(move |tag| {
+        let tag_name = tag[1].rsplit(':').next().unwrap_or_default();
+        if !names.iter().any(|name| tag_name.eq_ignore_ascii_case(name)) {
+            return None;
+        }
+        let attributes = ATTRIBUTE
+            .captures_iter(&tag[2])
+            .map(|attribute| {
+                let key = attribute[1].rsplit(':').next().unwrap_or_default();
+                let value = attribute
+                    .get(2)
+                    .or_else(|| attribute.get(3))
+                    .map_or("", |value| value.as_str());
+                (key.to_ascii_lowercase(), value.to_string())
+            })
+            .collect();
+        Some(attributes)
+    })
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Resolves an `href` found in the document at `base` to an archive path.
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = percent_decode(href.split('#').next().unwrap_or_default());
+    let mut parts: Vec<&str> = match base.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => Vec::new(),
+    };
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Reads entry `name`, taking its size off `remaining`. Fails past `MAX_ENTRY_BYTES`, and
+/// sets `remaining` to 0 when the archive as a whole is too large.
+fn read_entry(
+    archive: &mut zip::ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    remaining: &mut u64,
+) -> anyhow::Result<Vec<u8>> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|err| anyhow!("{name}: {err}"))?;
+    let limit = MAX_ENTRY_BYTES.min(*remaining);
+    let mut bytes = Vec::new();
+    entry.take(limit + 1).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 > limit {
+        if limit < MAX_ENTRY_BYTES {
+            *remaining = 0;
+            return Err(anyhow!(
+                "Archive is larger than {MAX_TOTAL_UNCOMPRESSED_BYTES} bytes uncompressed"
+            ));
+        }
+        return Err(anyhow!("{name} is larger than {MAX_ENTRY_BYTES} bytes"));
+    }
+    *remaining -= bytes.len() as u64;
+    Ok(bytes)
+}
+
+/// Reads the images of an EPUB in spine order. Falls back to the image items of the
+/// manifest when the spine documents reference none.
+pub fn read_epub_pages(bytes: &[u8], base_url: &str) -> anyhow::Result<Vec<ArchivePage>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut remaining = MAX_TOTAL_UNCOMPRESSED_BYTES;
+
+    let container = String::from_utf8(read_entry(
+        &mut archive,
+        "META-INF/container.xml",
+        &mut remaining,
+    )?)?;
+    let opf_path = tags(&container, &["rootfile"])
+        .find_map(|attributes| attribute(&attributes, "full-path").map(str::to_string))
+        .ok_or_else(|| anyhow!("container.xml has no rootfile"))?;
+    let opf = String::from_utf8(read_entry(&mut archive, &opf_path, &mut remaining)?)?;
+
+    // (id, path, media type)
+    let manifest: Vec<(String, String, String)> = tags(&opf, &["item"])
+        .filter_map(|attributes| {
+            let id = attribute(&attributes, "id")?.to_string();
+            let href = attribute(&attributes, "href")?;
+            let media_type = attribute(&attributes, "media-type").unwrap_or_default();
+            Some((id, resolve_href(&opf_path, href), media_type.to_string()))
+        })
+        .collect();
+    let spine: Vec<String> = tags(&opf, &["itemref"])
+        .filter_map(|attributes| attribute(&attributes, "idref").map(str::to_string))
+        .collect();
+
+    let mut image_paths: Vec<String> = Vec::new();
+    for idref in &spine {
+        let Some((_, document_path, _)) = manifest.iter().find(|(id, _, _)| id == idref) else {
+            continue;
+        };
+        if is_image_name(document_path) {
+            image_paths.push(document_path.clone());
+            continue;
+        }
+        let document = match read_entry(&mut archive, document_path, &mut remaining) {
+            Ok(document) => document,
+            Err(err) if remaining == 0 => return Err(err),
+            Err(_) => continue,
+        };
+        let document = String::from_utf8_lossy(&document);
+        for attributes in tags(&document, &["img", "image"]) {
+            let source = attribute(&attributes, "src").or_else(|| attribute(&attributes, "href"));
+            if let Some(source) = source {
+                image_paths.push(resolve_href(document_path, source));
+            }
+        }
+    }
+    if image_paths.is_empty() {
+        image_paths = manifest
+            .iter()
+            .filter(|(_, _, media_type)| media_type.starts_with("image/"))
+            .map(|(_, path, _)| path.clone())
+            .collect();
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    image_paths.retain(|path| seen.insert(path.clone()));
+
+    let mut pages = Vec::with_capacity(image_paths.len());
+    for path in image_paths {
+        match read_entry(&mut archive, &path, &mut remaining) {
+            Ok(page_bytes) => pages.push(ArchivePage {
+                url: format!("{base_url}/{path}"),
+                bytes: page_bytes,
+            }),
+            Err(err) if remaining == 0 => return Err(err),
+            Err(err) => tracing::warn!("[Archive] Skipping EPUB image {err}"),
+        }
+    }
+    Ok(pages)
+}
+
+/// OCRs `pages` as one chapter in the background. Returns `false` if a job for the
+/// same chapter is already running.
+pub fn spawn_archive_job(
+    state: &AppState,
+    base_url: String,
+    pages: Vec<ArchivePage>,
+    context: String,
+    language: OcrLanguage,
+    backend: OcrBackend,
+) -> bool {
+    let chapter_key = logic::get_cache_key(&base_url, Some(language));
+    let total = pages.len();
+    {
+        let mut active = state.active_chapter_jobs.write().expect("lock poisoned");
+        if active.contains_key(&chapter_key) {
+            return false;
+        }
+        active.insert(chapter_key.clone(), JobProgress { current: 0, total });
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        concurrency::in_background(run_archive_job(
+            &state,
+            &chapter_key,
+            pages,
+            &context,
+            language,
+            backend,
+        ))
+        .await;
+        state
+            .active_chapter_jobs
+            .write()
+            .expect("lock poisoned")
+            .remove(&chapter_key);
+    });
+    true
+}
+
+async fn run_archive_job(
+    state: &AppState,
+    chapter_key: &str,
+    pages: Vec<ArchivePage>,
+    context: &str,
+    language: OcrLanguage,
+    backend: OcrBackend,
+) {
+    let total = pages.len();
+    tracing::info!("[Archive] Started for {context} ({total} pages)");
+    state.active_jobs.fetch_add(1, Ordering::Relaxed);
+    state.set_chapter_progress(chapter_key, total, 0).await;
+
+    let completed_counter = Arc::new(AtomicUsize::new(0));
+    let processed_counter = Arc::new(AtomicUsize::new(0));
+    let merge_config = MergeConfig::default();
+    let concurrency_limit = if cfg!(target_os = "android") { 1 } else { 2 };
+
+    futures::stream::iter(pages)
+        .for_each_concurrent(concurrency_limit, |page| {
+            let completed_counter = completed_counter.clone();
+            let processed_counter = processed_counter.clone();
+            let merge_config = &merge_config;
+            async move {
+                state.job_queue.wait_while_paused().await;
+                let cache_key = logic::page_cache_key(&page.url, language, backend, merge_config);
+
+                if state.has_cache_entry(&cache_key) {
+                    state.insert_chapter_cache(chapter_key, &cache_key).await;
+                    processed_counter.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    let _in_flight = PageGuard::new(state);
+                    match logic::process_image_bytes(
+                        &page.bytes,
+                        &page.url,
+                        merge_config,
+                        language,
+                        backend,
+                        None,
+                        Some(state),
+                        false,
+                    )
+                    .await
+                    {
+                        Ok(data) => {
+                            state
+                                .insert_cache_entry(
+                                    &cache_key,
+                                    &CacheEntry {
+                                        context: context.to_string(),
+                                        data,
+                                    },
+                                )
+                                .await;
+                            state.insert_chapter_cache(chapter_key, &cache_key).await;
+                            processed_counter.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(err) => {
+                            tracing::warn!("[Archive] {} failed: {err:?}", page.url);
+                            state.publish_job_event(JobEvent::PageFailed {
+                                chapter_key: chapter_key.to_string(),
+                                url: page.url.clone(),
+                                error: err.to_string(),
+                                attempts: 1,
+                            });
+                        }
+                    }
+                }
+
+                let current = completed_counter.fetch_add(1, Ordering::Relaxed) + 1;
+                let processed = processed_counter.load(Ordering::Relaxed);
+                state
+                    .set_chapter_progress(chapter_key, total, processed)
+                    .await;
+                if let Some(progress) = state
+                    .active_chapter_jobs
+                    .write()
+                    .expect("lock poisoned")
+                    .get_mut(chapter_key)
+                {
+                    progress.current = current;
+                }
+                state.publish_job_event(JobEvent::Page {
+                    chapter_key: chapter_key.to_string(),
+                    url: page.url,
+                    current,
+                    processed,
+                    total,
+                });
+            }
+        })
+        .await;
+
+    let processed = processed_counter.load(Ordering::Relaxed);
+    state
+        .set_chapter_progress(chapter_key, total, processed)
+        .await;
+    state.active_jobs.fetch_sub(1, Ordering::Relaxed);
+    state.publish_job_event(JobEvent::Completed {
+        chapter_key: chapter_key.to_string(),
+        processed,
+        total,
+    });
+    tracing::info!("[Archive] Finished for {context} ({processed}/{total} pages)");
+}
@@ -0,0 +1,37 @@
+//! Credentials forwarded to Suwayomi on API and image requests.
+//!
+//! Requests carry Basic auth as `user`/`pass`, or a raw `authorization` header value such
+//! as `Bearer <token>` for servers using token or session auth. The header wins when both
+//! are given.
+
+use reqwest::{RequestBuilder, header::AUTHORIZATION};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuwayomiAuth {
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub authorization: Option<String>,
+}
+
+impl SuwayomiAuth {
+    pub fn new(user: Option<String>, pass: Option<String>, authorization: Option<String>) -> Self {
+        Self {
+            user,
+            pass,
+            authorization: authorization
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+        }
+    }
+
+    /// Adds the credentials to a request for Suwayomi.
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match (&self.authorization, &self.user) {
+            (Some(authorization), _) => request.header(AUTHORIZATION, authorization),
+            (None, Some(user)) => request.basic_auth(user, self.pass.as_ref()),
+            (None, None) => request,
+        }
+    }
+}
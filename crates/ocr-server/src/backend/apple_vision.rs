@@ -0,0 +1,106 @@
+//! Offline OCR through Apple's Vision framework (`VNRecognizeTextRequest`).
+
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use image::{ImageFormat, RgbaImage};
+use objc2::AllocAnyThread;
+use objc2_foundation::{NSArray, NSData, NSDictionary, NSString};
+use objc2_vision::{
+    VNImageRequestHandler, VNRecognizeTextRequest, VNRequest, VNRequestTextRecognitionLevel,
+};
+
+use crate::{
+    language::OcrLanguage,
+    logic::{self, BoundingBox, OcrResult},
+};
+
+/// Recognizes the lines in `chunk`. Blocking; call from a blocking thread.
+pub fn recognize(chunk: &RgbaImage, language: OcrLanguage) -> anyhow::Result<Vec<OcrResult>> {
+    let chunk_width = chunk.width() as f64;
+    let chunk_height = chunk.height() as f64;
+
+    let mut image_buffer = Cursor::new(Vec::new());
+    chunk
+        .write_to(&mut image_buffer, ImageFormat::Png)
+        .map_err(|err| anyhow!("Failed write_to: {err:?}"))?;
+    let data = NSData::with_bytes(&image_buffer.into_inner());
+
+    let observations = unsafe {
+        let handler = VNImageRequestHandler::initWithData_options(
+            VNImageRequestHandler::alloc(),
+            &data,
+            &NSDictionary::new(),
+        );
+
+        let request = VNRecognizeTextRequest::new();
+        request.setRecognitionLevel(VNRequestTextRecognitionLevel::Accurate);
+        request.setUsesLanguageCorrection(true);
+        request.setRecognitionLanguages(&NSArray::from_retained_slice(&[NSString::from_str(
+            language.bcp47_tag(),
+        )]));
+
+        let base_request: &VNRequest = &request;
+        let requests = NSArray::from_slice(&[base_request]);
+        handler
+            .performRequests_error(&requests)
+            .map_err(|err| anyhow!("Vision request failed: {}", err.localizedDescription()))?;
+
+        request.results().unwrap_or_default()
+    };
+
+    let mut lines = Vec::new();
+    for observation in observations.iter() {
+        let (text, confidence, bounds) = unsafe {
+            let Some(candidate) = observation.topCandidates(1).firstObject() else {
+                continue;
+            };
+            (
+                candidate.string().to_string(),
+                candidate.confidence(),
+                observation.boundingBox(),
+            )
+        };
+
+        let text = logic::post_process_text(text, language);
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        // Vision boxes are normalized with a bottom-left origin.
+        let width = bounds.size.width * chunk_width;
+        let height = bounds.size.height * chunk_height;
+        let x = bounds.origin.x * chunk_width;
+        let y = (1.0 - bounds.origin.y - bounds.size.height) * chunk_height;
+
+        let is_vertical = language.prefers_vertical() && width <= height;
+        lines.push(OcrResult {
+            text,
+            is_merged: Some(false),
+            forced_orientation: Some(if is_vertical {
+                "vertical".into()
+            } else {
+                "horizontal".into()
+            }),
+            confidence: Some(confidence),
+            block_id: None,
+            lines: Vec::new(),
+            ruby: Vec::new(),
+            order: None,
+            bubble_id: None,
+            full_width: None,
+            full_height: None,
+            animated: None,
+            translation: None,
+            tight_bounding_box: BoundingBox {
+                x,
+                y,
+                width,
+                height,
+                rotation: None,
+            },
+        });
+    }
+
+    Ok(lines)
+}
@@ -0,0 +1,178 @@
+use std::{io::Cursor, sync::LazyLock};
+
+use anyhow::anyhow;
+use chrome_lens_ocr::LensClient;
+use image::{ImageFormat, RgbaImage};
+use tokio::sync::watch;
+
+use super::rate_limit;
+use crate::{
+    language::OcrLanguage,
+    logic::{self, BoundingBox, OcrResult},
+    proxy::redact_credentials,
+};
+
+const MAX_THROTTLED_ATTEMPTS: u32 = 5;
+
+static ABORT: LazyLock<watch::Sender<bool>> = LazyLock::new(|| watch::channel(false).0);
+
+/// Fails every Lens request in flight or started afterwards; used on shutdown.
+pub fn abort_requests() {
+    ABORT.send_replace(true);
+}
+
+pub struct LensSession {
+    client: LensClient,
+}
+
+impl LensSession {
+    /// `proxy_url` comes from `proxy::resolve`.
+    pub fn new(proxy_url: Option<&str>) -> anyhow::Result<Self> {
+        let client = match proxy_url {
+            Some(proxy_url) => {
+                tracing::info!(
+                    "Using proxy for Google Lens: {}",
+                    redact_credentials(proxy_url)
+                );
+                LensClient::new_with_proxy(None, Some(proxy_url))
+                    .map_err(|e| anyhow!("Failed to create LensClient with proxy: {}", e))?
+            }
+            None => LensClient::new(None),
+        };
+
+        Ok(Self { client })
+    }
+
+    pub async fn recognize(
+        &self,
+        chunk: &RgbaImage,
+        language: OcrLanguage,
+    ) -> anyhow::Result<Vec<OcrResult>> {
+        let chunk_width = chunk.width();
+        let chunk_height = chunk.height();
+
+        let mut image_buffer = Cursor::new(Vec::new());
+        chunk
+            .write_to(&mut image_buffer, ImageFormat::Png)
+            .map_err(|err| anyhow!("Failed write_to: {err:?}"))?;
+        let chunk_png_bytes = image_buffer.into_inner();
+
+        // Throttled responses wait out the limiter's cooldown and are retried here,
+        // so they don't count against the page's fetch retries.
+        let limiter = rate_limit::lens_limiter();
+        let mut aborted = ABORT.subscribe();
+        let mut throttled_attempts = 0;
+        let lens_response = loop {
+            let request = async {
+                limiter.acquire().await;
+                self.client
+                    .process_image_bytes(&chunk_png_bytes, Some("jp"))
+                    .await
+            };
+            let result = tokio::select! {
+                result = request => result,
+                _ = aborted.wait_for(|aborted| *aborted) => {
+                    return Err(anyhow!("Lens request aborted, the server is shutting down"));
+                }
+            };
+            match result {
+                Ok(response) => {
+                    limiter.succeeded();
+                    break response;
+                }
+                Err(err) => {
+                    let message = format!("{err:?}");
+                    if !rate_limit::is_throttle_error(&message)
+                        || throttled_attempts >= MAX_THROTTLED_ATTEMPTS
+                    {
+                        return Err(anyhow!("Failed process_image_bytes: {message}"));
+                    }
+                    throttled_attempts += 1;
+                    limiter.throttled();
+                }
+            }
+        };
+
+        let mut flat_ocr_lines = Vec::new();
+        for (block_id, paragraph) in lens_response.paragraphs.into_iter().enumerate() {
+            for line in paragraph.lines {
+                if let Some(geometry) = line.geometry {
+                    let clean_text = logic::post_process_text(line.text, language);
+                    if clean_text.trim().is_empty() {
+                        continue;
+                    }
+
+                    let rotation = geometry.rotation_z as f64;
+                    let cx = (geometry.center_x * chunk_width as f32) as f64;
+                    let cy = (geometry.center_y * chunk_height as f32) as f64;
+                    let w = (geometry.width * chunk_width as f32) as f64;
+                    let h = (geometry.height * chunk_height as f32) as f64;
+
+                    let hw = w / 2.0;
+                    let hh = h / 2.0;
+                    let cos_a = rotation.cos();
+                    let sin_a = rotation.sin();
+
+                    let corners = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)];
+
+                    let mut min_x = f64::INFINITY;
+                    let mut max_x = f64::NEG_INFINITY;
+                    let mut min_y = f64::INFINITY;
+                    let mut max_y = f64::NEG_INFINITY;
+
+                    for (lx, ly) in corners {
+                        let rx = lx * cos_a - ly * sin_a + cx;
+                        let ry = lx * sin_a + ly * cos_a + cy;
+                        min_x = min_x.min(rx);
+                        max_x = max_x.max(rx);
+                        min_y = min_y.min(ry);
+                        max_y = max_y.max(ry);
+                    }
+
+                    let aabb_w = max_x - min_x;
+                    let aabb_h = max_y - min_y;
+
+                    let is_vertical = if language.prefers_vertical() {
+                        if rotation.abs() > 0.1 {
+                            (rotation.abs() - std::f32::consts::FRAC_PI_2 as f64).abs() < 0.5
+                        } else {
+                            aabb_w <= aabb_h
+                        }
+                    } else {
+                        false
+                    };
+
+                    flat_ocr_lines.push(OcrResult {
+                        text: clean_text,
+                        is_merged: Some(false),
+                        forced_orientation: Some(if is_vertical {
+                            "vertical".into()
+                        } else {
+                            "horizontal".into()
+                        }),
+                        // Lens does not report per-line confidence.
+                        confidence: None,
+                        block_id: Some(block_id as u32),
+                        lines: Vec::new(),
+                        ruby: Vec::new(),
+                        order: None,
+                        bubble_id: None,
+                        full_width: None,
+                        full_height: None,
+                        animated: None,
+                        translation: None,
+                        tight_bounding_box: BoundingBox {
+                            x: min_x,
+                            y: min_y,
+                            width: aabb_w,
+                            height: aabb_h,
+                            rotation: None,
+                        },
+                    });
+                }
+            }
+        }
+
+        Ok(flat_ocr_lines)
+    }
+}
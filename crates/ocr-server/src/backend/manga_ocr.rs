@@ -0,0 +1,341 @@
+//! Local Japanese OCR using the manga-ocr model exported to ONNX.
+//!
+//! The model directory is expected to contain:
+//! - `detector.onnx`: comic-text-detector, used to locate text blocks on the page
+//! - `encoder_model.onnx` / `decoder_model.onnx`: the manga-ocr vision encoder-decoder
+//! - `vocab.txt`: the character vocabulary of the decoder tokenizer
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use anyhow::anyhow;
+use image::{
+    DynamicImage, RgbImage, RgbaImage,
+    imageops::{self, FilterType},
+};
+use ort::{session::Session, value::Tensor};
+
+use crate::logic::{BoundingBox, OcrResult};
+
+const MODEL_DIR_ENV: &str = "MANATAN_MANGA_OCR_MODEL_DIR";
+const DETECTOR_INPUT_SIZE: u32 = 1024;
+const ENCODER_INPUT_SIZE: u32 = 224;
+const DETECTION_CONFIDENCE: f32 = 0.4;
+const NMS_IOU_THRESHOLD: f32 = 0.35;
+const CLS_TOKEN_ID: i64 = 2;
+const SEP_TOKEN_ID: i64 = 3;
+const MAX_DECODE_TOKENS: usize = 300;
+
+static DEFAULT_MODEL_DIR: OnceLock<PathBuf> = OnceLock::new();
+static MODEL: Mutex<Option<Arc<MangaOcrModel>>> = Mutex::new(None);
+
+/// Sets the model directory used when `MANATAN_MANGA_OCR_MODEL_DIR` is not set.
+pub fn set_default_model_dir(dir: PathBuf) {
+    let _ = DEFAULT_MODEL_DIR.set(dir);
+}
+
+fn model_dir() -> anyhow::Result<PathBuf> {
+    if let Some(dir) = manatan_config::var(MODEL_DIR_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+    {
+        return Ok(PathBuf::from(dir));
+    }
+    DEFAULT_MODEL_DIR
+        .get()
+        .cloned()
+        .ok_or_else(|| anyhow!("{MODEL_DIR_ENV} is not set"))
+}
+
+/// Returns the process-wide model, loading it on first use.
+pub fn shared_model() -> anyhow::Result<Arc<MangaOcrModel>> {
+    let mut cached = MODEL.lock().expect("lock poisoned");
+    if let Some(model) = cached.as_ref() {
+        return Ok(model.clone());
+    }
+
+    let dir = model_dir()?;
+    let model = Arc::new(MangaOcrModel::load(&dir)?);
+    tracing::info!("Loaded manga-ocr models from {}", dir.display());
+    *cached = Some(model.clone());
+    Ok(model)
+}
+
+fn ort_err(err: impl std::fmt::Display) -> anyhow::Error {
+    anyhow!("onnxruntime error: {err}")
+}
+
+fn load_session(path: &Path) -> anyhow::Result<Session> {
+    let builder = Session::builder().map_err(ort_err)?;
+    builder
+        .commit_from_file(path)
+        .map_err(|err| anyhow!("Failed to load {}: {err}", path.display()))
+}
+
+struct TextRegion {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    confidence: f32,
+}
+
+impl TextRegion {
+    fn iou(&self, other: &TextRegion) -> f32 {
+        let x_overlap = (self.x + self.width).min(other.x + other.width) - self.x.max(other.x);
+        let y_overlap = (self.y + self.height).min(other.y + other.height) - self.y.max(other.y);
+        if x_overlap <= 0.0 || y_overlap <= 0.0 {
+            return 0.0;
+        }
+        let intersection = x_overlap * y_overlap;
+        let union = self.width * self.height + other.width * other.height - intersection;
+        if union <= 0.0 {
+            0.0
+        } else {
+            intersection / union
+        }
+    }
+}
+
+pub struct MangaOcrModel {
+    detector: Mutex<Session>,
+    encoder: Mutex<Session>,
+    decoder: Mutex<Session>,
+    vocab: Vec<String>,
+}
+
+impl MangaOcrModel {
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let vocab = std::fs::read_to_string(dir.join("vocab.txt"))
+            .map_err(|err| anyhow!("Failed to read manga-ocr vocab: {err}"))?
+            .lines()
+            .map(str::to_string)
+            .collect();
+
+        Ok(Self {
+            detector: Mutex::new(load_session(&dir.join("detector.onnx"))?),
+            encoder: Mutex::new(load_session(&dir.join("encoder_model.onnx"))?),
+            decoder: Mutex::new(load_session(&dir.join("decoder_model.onnx"))?),
+            vocab,
+        })
+    }
+
+    /// Detects text blocks in `chunk` and reads each one with manga-ocr.
+    pub fn recognize(&self, chunk: &RgbaImage) -> anyhow::Result<Vec<OcrResult>> {
+        let image = DynamicImage::ImageRgba8(chunk.clone()).to_rgb8();
+        let (image_width, image_height) = image.dimensions();
+
+        let mut lines = Vec::new();
+        for region in self.detect(&image)? {
+            let x = region.x.max(0.0) as u32;
+            let y = region.y.max(0.0) as u32;
+            let width = (region.width as u32).min(image_width.saturating_sub(x));
+            let height = (region.height as u32).min(image_height.saturating_sub(y));
+            if width < 2 || height < 2 {
+                continue;
+            }
+
+            let crop = imageops::crop_imm(&image, x, y, width, height).to_image();
+            let text = self.read_text(&crop)?;
+            if text.is_empty() {
+                continue;
+            }
+
+            let is_vertical = height > width;
+            lines.push(OcrResult {
+                text,
+                is_merged: Some(false),
+                forced_orientation: Some(if is_vertical {
+                    "vertical".into()
+                } else {
+                    "horizontal".into()
+                }),
+                confidence: Some(region.confidence),
+                block_id: None,
+                lines: Vec::new(),
+                ruby: Vec::new(),
+                order: None,
+                bubble_id: None,
+                full_width: None,
+                full_height: None,
+                animated: None,
+                translation: None,
+                tight_bounding_box: BoundingBox {
+                    x: x as f64,
+                    y: y as f64,
+                    width: width as f64,
+                    height: height as f64,
+                    rotation: None,
+                },
+            });
+        }
+
+        Ok(lines)
+    }
+
+    fn detect(&self, image: &RgbImage) -> anyhow::Result<Vec<TextRegion>> {
+        let (width, height) = image.dimensions();
+        let scale = DETECTOR_INPUT_SIZE as f32 / width.max(height).max(1) as f32;
+        let resized_width = ((width as f32 * scale).round() as u32).clamp(1, DETECTOR_INPUT_SIZE);
+        let resized_height = ((height as f32 * scale).round() as u32).clamp(1, DETECTOR_INPUT_SIZE);
+        let resized = imageops::resize(image, resized_width, resized_height, FilterType::Triangle);
+
+        // Letterboxed into the top-left corner, CHW, scaled to 0..1.
+        let size = DETECTOR_INPUT_SIZE as usize;
+        let mut input = vec![0.0f32; 3 * size * size];
+        for (x, y, pixel) in resized.enumerate_pixels() {
+            let offset = y as usize * size + x as usize;
+            for channel in 0..3 {
+                input[channel * size * size + offset] = pixel[channel] as f32 / 255.0;
+            }
+        }
+        let tensor = Tensor::from_array(([1usize, 3, size, size], input)).map_err(ort_err)?;
+
+        let mut candidates = Vec::new();
+        {
+            let mut detector = self.detector.lock().expect("lock poisoned");
+            let outputs = detector
+                .run(ort::inputs!["images" => tensor])
+                .map_err(ort_err)?;
+            let (shape, blocks) = outputs["blk"]
+                .try_extract_tensor::<f32>()
+                .map_err(ort_err)?;
+
+            // Each row is [cx, cy, w, h, objectness, class scores...].
+            let stride = shape.last().copied().unwrap_or(0) as usize;
+            if stride < 5 {
+                return Err(anyhow!(
+                    "Unexpected detector output shape: {:?}",
+                    shape.to_vec()
+                ));
+            }
+            for row in blocks.chunks_exact(stride) {
+                let class_score = row[5..].iter().copied().reduce(f32::max).unwrap_or(1.0);
+                let confidence = row[4] * class_score;
+                if confidence < DETECTION_CONFIDENCE {
+                    continue;
+                }
+                candidates.push(TextRegion {
+                    x: (row[0] - row[2] / 2.0) / scale,
+                    y: (row[1] - row[3] / 2.0) / scale,
+                    width: row[2] / scale,
+                    height: row[3] / scale,
+                    confidence,
+                });
+            }
+        }
+
+        candidates.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let mut regions: Vec<TextRegion> = Vec::new();
+        for candidate in candidates {
+            if regions
+                .iter()
+                .all(|kept| kept.iou(&candidate) < NMS_IOU_THRESHOLD)
+            {
+                regions.push(candidate);
+            }
+        }
+
+        Ok(regions)
+    }
+
+    fn read_text(&self, crop: &RgbImage) -> anyhow::Result<String> {
+        // manga-ocr reads grayscale images replicated to 3 channels, normalized to -1..1.
+        let gray = imageops::grayscale(crop);
+        let resized = imageops::resize(
+            &gray,
+            ENCODER_INPUT_SIZE,
+            ENCODER_INPUT_SIZE,
+            FilterType::Triangle,
+        );
+        let size = ENCODER_INPUT_SIZE as usize;
+        let mut pixels = vec![0.0f32; 3 * size * size];
+        for (x, y, pixel) in resized.enumerate_pixels() {
+            let value = (pixel[0] as f32 / 255.0 - 0.5) / 0.5;
+            let offset = y as usize * size + x as usize;
+            for channel in 0..3 {
+                pixels[channel * size * size + offset] = value;
+            }
+        }
+        let pixel_values =
+            Tensor::from_array(([1usize, 3, size, size], pixels)).map_err(ort_err)?;
+
+        let (hidden_shape, hidden_states) = {
+            let mut encoder = self.encoder.lock().expect("lock poisoned");
+            let outputs = encoder
+                .run(ort::inputs!["pixel_values" => pixel_values])
+                .map_err(ort_err)?;
+            let (shape, data) = outputs["last_hidden_state"]
+                .try_extract_tensor::<f32>()
+                .map_err(ort_err)?;
+            let [batch, sequence, hidden] = shape[..] else {
+                return Err(anyhow!(
+                    "Unexpected encoder output shape: {:?}",
+                    shape.to_vec()
+                ));
+            };
+            (
+                [batch as usize, sequence as usize, hidden as usize],
+                data.to_vec(),
+            )
+        };
+
+        // Greedy decoding, starting from [CLS] and stopping at [SEP].
+        let mut token_ids = vec![CLS_TOKEN_ID];
+        let mut decoder = self.decoder.lock().expect("lock poisoned");
+        while token_ids.len() < MAX_DECODE_TOKENS {
+            let input_ids = Tensor::from_array(([1usize, token_ids.len()], token_ids.clone()))
+                .map_err(ort_err)?;
+            let encoder_hidden_states =
+                Tensor::from_array((hidden_shape, hidden_states.clone())).map_err(ort_err)?;
+            let outputs = decoder
+                .run(ort::inputs![
+                    "input_ids" => input_ids,
+                    "encoder_hidden_states" => encoder_hidden_states
+                ])
+                .map_err(ort_err)?;
+            let (shape, logits) = outputs["logits"]
+                .try_extract_tensor::<f32>()
+                .map_err(ort_err)?;
+
+            let vocab_size = shape.last().copied().unwrap_or(0) as usize;
+            if vocab_size == 0 || logits.len() < vocab_size {
+                break;
+            }
+            let next_token = logits[logits.len() - vocab_size..]
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(index, _)| index as i64)
+                .unwrap_or(SEP_TOKEN_ID);
+            if next_token == SEP_TOKEN_ID {
+                break;
+            }
+            token_ids.push(next_token);
+        }
+        drop(decoder);
+
+        Ok(self.decode_tokens(&token_ids[1..]))
+    }
+
+    fn decode_tokens(&self, token_ids: &[i64]) -> String {
+        let mut text = String::new();
+        for &token_id in token_ids {
+            let Some(token) = usize::try_from(token_id)
+                .ok()
+                .and_then(|index| self.vocab.get(index))
+            else {
+                continue;
+            };
+            if token.starts_with('[') && token.ends_with(']') {
+                continue;
+            }
+            text.push_str(token.trim_start_matches("##"));
+        }
+
+        // Mirrors manga-ocr's own post-processing.
+        text.replace(char::is_whitespace, "").replace('…', "...")
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(all(feature = "apple-vision", target_vendor = "apple"))]
+pub mod apple_vision;
+pub mod lens;
+#[cfg(feature = "manga-ocr")]
+pub mod manga_ocr;
+pub mod paddle_ocr;
+pub mod rate_limit;
+#[cfg(all(feature = "windows-ocr", windows))]
+pub mod windows_ocr;
+
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{language::OcrLanguage, logic::OcrResult};
+
+/// OCR engine used to recognize the text in a page.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OcrBackend {
+    #[default]
+    Lens,
+    MangaOcr,
+    PaddleOcr,
+    AppleVision,
+    WindowsOcr,
+}
+
+impl OcrBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OcrBackend::Lens => "lens",
+            OcrBackend::MangaOcr => "manga-ocr",
+            OcrBackend::PaddleOcr => "paddle-ocr",
+            OcrBackend::AppleVision => "apple-vision",
+            OcrBackend::WindowsOcr => "windows-ocr",
+        }
+    }
+}
+
+/// Loads the models of the local backends built in, so the first page doesn't wait for
+/// them. The other backends have nothing to load. A model that fails to load is only
+/// logged: requests for that backend report the error themselves.
+pub async fn warm_up() {
+    #[cfg(feature = "manga-ocr")]
+    match tokio::task::spawn_blocking(manga_ocr::shared_model).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(err)) => tracing::warn!("[OCR] manga-ocr models not loaded: {err}"),
+        Err(err) => tracing::warn!("[OCR] Loading the manga-ocr models failed: {err}"),
+    }
+}
+
+/// A backend prepared to process every chunk of a single image.
+pub enum BackendSession {
+    Lens(lens::LensSession),
+    #[cfg(feature = "manga-ocr")]
+    MangaOcr(std::sync::Arc<manga_ocr::MangaOcrModel>),
+    PaddleOcr(paddle_ocr::PaddleOcrSession),
+    #[cfg(all(feature = "apple-vision", target_vendor = "apple"))]
+    AppleVision,
+    #[cfg(all(feature = "windows-ocr", windows))]
+    WindowsOcr,
+}
+
+impl BackendSession {
+    pub async fn connect(
+        backend: OcrBackend,
+        language: OcrLanguage,
+        proxy_url: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        match backend {
+            OcrBackend::Lens => Ok(Self::Lens(lens::LensSession::new(proxy_url)?)),
+            OcrBackend::MangaOcr => {
+                if !language.is_japanese() {
+                    anyhow::bail!("manga-ocr backend only supports Japanese");
+                }
+                #[cfg(feature = "manga-ocr")]
+                {
+                    Ok(Self::MangaOcr(manga_ocr::shared_model()?))
+                }
+                #[cfg(not(feature = "manga-ocr"))]
+                {
+                    anyhow::bail!("manga-ocr backend is not enabled in this build")
+                }
+            }
+            OcrBackend::PaddleOcr => Ok(Self::PaddleOcr(paddle_ocr::PaddleOcrSession::from_env())),
+            OcrBackend::AppleVision => {
+                #[cfg(all(feature = "apple-vision", target_vendor = "apple"))]
+                {
+                    Ok(Self::AppleVision)
+                }
+                #[cfg(not(all(feature = "apple-vision", target_vendor = "apple")))]
+                {
+                    anyhow::bail!("apple-vision backend is not available in this build")
+                }
+            }
+            OcrBackend::WindowsOcr => {
+                #[cfg(all(feature = "windows-ocr", windows))]
+                {
+                    Ok(Self::WindowsOcr)
+                }
+                #[cfg(not(all(feature = "windows-ocr", windows)))]
+                {
+                    anyhow::bail!("windows-ocr backend is not available in this build")
+                }
+            }
+        }
+    }
+
+    /// Recognizes the text lines in `chunk`.
+    /// Boxes are returned in chunk pixel coordinates, ready for `merge::auto_merge`.
+    pub async fn recognize(
+        &self,
+        chunk: &RgbaImage,
+        language: OcrLanguage,
+    ) -> anyhow::Result<Vec<OcrResult>> {
+        match self {
+            Self::Lens(session) => session.recognize(chunk, language).await,
+            #[cfg(feature = "manga-ocr")]
+            Self::MangaOcr(model) => {
+                let model = model.clone();
+                let chunk = chunk.clone();
+                tokio::task::spawn_blocking(move || model.recognize(&chunk)).await?
+            }
+            Self::PaddleOcr(session) => session.recognize(chunk, language).await,
+            #[cfg(all(feature = "apple-vision", target_vendor = "apple"))]
+            Self::AppleVision => {
+                let chunk = chunk.clone();
+                tokio::task::spawn_blocking(move || apple_vision::recognize(&chunk, language))
+                    .await?
+            }
+            #[cfg(all(feature = "windows-ocr", windows))]
+            Self::WindowsOcr => {
+                let chunk = chunk.clone();
+                tokio::task::spawn_blocking(move || windows_ocr::recognize(&chunk, language))
+                    .await?
+            }
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use base64::Engine as _;
+use image::{ImageFormat, RgbaImage};
+use serde::Deserialize;
+
+use crate::{
+    language::OcrLanguage,
+    logic::{self, BoundingBox, OcrResult},
+};
+
+const PADDLE_OCR_URL_ENV: &str = "MANATAN_PADDLEOCR_URL";
+const DEFAULT_PADDLE_OCR_URL: &str = "http://127.0.0.1:8866/predict/ocr_system";
+
+#[derive(Deserialize)]
+struct PaddleResponse {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    results: Vec<Vec<PaddleLine>>,
+}
+
+#[derive(Deserialize)]
+struct PaddleLine {
+    text: String,
+    #[serde(default)]
+    confidence: Option<f32>,
+    /// Quadrilateral corners as `[[x, y]; 4]`, in image pixels.
+    text_region: Vec<[f64; 2]>,
+}
+
+/// Talks to a PaddleOCR hub-serving instance (`hub serving start -m ocr_system`).
+pub struct PaddleOcrSession {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl PaddleOcrSession {
+    pub fn from_env() -> Self {
+        let endpoint = manatan_config::var(PADDLE_OCR_URL_ENV)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .unwrap_or_else(|| DEFAULT_PADDLE_OCR_URL.to_string());
+
+        Self {
+            client: reqwest::Client::new(),
+            endpoint,
+        }
+    }
+
+    pub async fn recognize(
+        &self,
+        chunk: &RgbaImage,
+        language: OcrLanguage,
+    ) -> anyhow::Result<Vec<OcrResult>> {
+        let mut image_buffer = Cursor::new(Vec::new());
+        chunk
+            .write_to(&mut image_buffer, ImageFormat::Png)
+            .map_err(|err| anyhow!("Failed write_to: {err:?}"))?;
+        let encoded = base64::engine::general_purpose::STANDARD.encode(image_buffer.into_inner());
+
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .json(&serde_json::json!({ "images": [encoded] }))
+            .send()
+            .await
+            .map_err(|err| anyhow!("PaddleOCR request failed ({}): {err}", self.endpoint))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "[Failed to read body]".to_string());
+            return Err(anyhow!(
+                "PaddleOCR request failed (Status: {status}). Body: {body}"
+            ));
+        }
+
+        let parsed: PaddleResponse = response
+            .json()
+            .await
+            .map_err(|err| anyhow!("Error decoding PaddleOCR response: {err}"))?;
+        if !parsed.status.is_empty() && parsed.status != "000" {
+            return Err(anyhow!(
+                "PaddleOCR returned status {}: {}",
+                parsed.status,
+                parsed.msg
+            ));
+        }
+
+        let mut lines = Vec::new();
+        for line in parsed.results.into_iter().flatten() {
+            let clean_text = logic::post_process_text(line.text, language);
+            if clean_text.trim().is_empty() || line.text_region.is_empty() {
+                continue;
+            }
+
+            let min_x = line
+                .text_region
+                .iter()
+                .map(|p| p[0])
+                .fold(f64::INFINITY, f64::min);
+            let max_x = line
+                .text_region
+                .iter()
+                .map(|p| p[0])
+                .fold(f64::NEG_INFINITY, f64::max);
+            let min_y = line
+                .text_region
+                .iter()
+                .map(|p| p[1])
+                .fold(f64::INFINITY, f64::min);
+            let max_y = line
+                .text_region
+                .iter()
+                .map(|p| p[1])
+                .fold(f64::NEG_INFINITY, f64::max);
+            let width = max_x - min_x;
+            let height = max_y - min_y;
+
+            let is_vertical = language.prefers_vertical() && width <= height;
+            lines.push(OcrResult {
+                text: clean_text,
+                is_merged: Some(false),
+                forced_orientation: Some(if is_vertical {
+                    "vertical".into()
+                } else {
+                    "horizontal".into()
+                }),
+                confidence: line.confidence,
+                block_id: None,
+                lines: Vec::new(),
+                ruby: Vec::new(),
+                order: None,
+                bubble_id: None,
+                full_width: None,
+                full_height: None,
+                animated: None,
+                translation: None,
+                tight_bounding_box: BoundingBox {
+                    x: min_x,
+                    y: min_y,
+                    width,
+                    height,
+                    rotation: None,
+                },
+            });
+        }
+
+        Ok(lines)
+    }
+}
@@ -0,0 +1,127 @@
+//! Process-wide token bucket for Google Lens requests.
+//!
+//! When Lens answers with a 429 or a captcha page, the refill rate is halved and all
+//! callers wait out a cooldown. The rate recovers gradually as requests succeed again.
+
+use std::{
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+const LENS_RATE_ENV: &str = "MANATAN_LENS_RATE_PER_SEC";
+const LENS_BURST_ENV: &str = "MANATAN_LENS_BURST";
+const DEFAULT_RATE_PER_SEC: f64 = 2.0;
+const DEFAULT_BURST: f64 = 4.0;
+const MIN_RATE_FACTOR: f64 = 1.0 / 16.0;
+const RECOVERY_STEP: f64 = 1.1;
+const BASE_COOLDOWN: Duration = Duration::from_secs(10);
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+
+static LENS_LIMITER: LazyLock<RateLimiter> = LazyLock::new(RateLimiter::from_env);
+
+pub fn lens_limiter() -> &'static RateLimiter {
+    &LENS_LIMITER
+}
+
+/// Whether a Lens error looks like throttling rather than a real failure.
+pub fn is_throttle_error(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("429")
+        || message.contains("too many requests")
+        || message.contains("captcha")
+        || message.contains("unusual traffic")
+        || message.contains("/sorry/")
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    rate_factor: f64,
+    cooldown_until: Option<Instant>,
+    throttle_streak: u32,
+}
+
+pub struct RateLimiter {
+    rate_per_sec: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimiter {
+    pub fn new(rate_per_sec: f64, burst: f64) -> Self {
+        Self {
+            rate_per_sec,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+                rate_factor: 1.0,
+                cooldown_until: None,
+                throttle_streak: 0,
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let read = |key: &str| {
+            manatan_config::var(key)
+                .ok()
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| value.is_finite() && *value > 0.0)
+        };
+        Self::new(
+            read(LENS_RATE_ENV).unwrap_or(DEFAULT_RATE_PER_SEC),
+            read(LENS_BURST_ENV).unwrap_or(DEFAULT_BURST).max(1.0),
+        )
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().expect("lock poisoned");
+                let now = Instant::now();
+                match bucket.cooldown_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        let rate = self.rate_per_sec * bucket.rate_factor;
+                        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.burst);
+                        bucket.last_refill = now;
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - bucket.tokens) / rate)
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Records a throttled response: slows the bucket down and starts a cooldown.
+    pub fn throttled(&self) {
+        let mut bucket = self.bucket.lock().expect("lock poisoned");
+        bucket.rate_factor = (bucket.rate_factor / 2.0).max(MIN_RATE_FACTOR);
+        bucket.throttle_streak = bucket.throttle_streak.saturating_add(1);
+        bucket.tokens = 0.0;
+
+        let cooldown = BASE_COOLDOWN
+            .saturating_mul(1 << bucket.throttle_streak.saturating_sub(1).min(5))
+            .min(MAX_COOLDOWN);
+        bucket.cooldown_until = Some(Instant::now() + cooldown);
+        tracing::warn!(
+            "Google Lens is throttling requests; pausing for {}s at {:.0}% speed",
+            cooldown.as_secs(),
+            bucket.rate_factor * 100.0
+        );
+    }
+
+    /// Records a successful response, letting the rate creep back up.
+    pub fn succeeded(&self) {
+        let mut bucket = self.bucket.lock().expect("lock poisoned");
+        bucket.throttle_streak = 0;
+        bucket.rate_factor = (bucket.rate_factor * RECOVERY_STEP).min(1.0);
+    }
+}
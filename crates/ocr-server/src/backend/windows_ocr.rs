@@ -0,0 +1,118 @@
+//! Offline OCR through `Windows.Media.Ocr`.
+//!
+//! Recognition languages come from the OCR language packs installed in Windows
+//! (Settings > Time & language > Language > Optional features).
+
+use anyhow::anyhow;
+use image::RgbaImage;
+use windows::{
+    Globalization::Language,
+    Graphics::Imaging::{BitmapAlphaMode, BitmapPixelFormat, SoftwareBitmap},
+    Media::Ocr::OcrEngine,
+    Storage::Streams::DataWriter,
+    Win32::System::WinRT::{RO_INIT_MULTITHREADED, RoInitialize},
+    core::HSTRING,
+};
+
+use crate::{
+    language::OcrLanguage,
+    logic::{self, BoundingBox, OcrResult},
+};
+
+fn win_err(err: windows::core::Error) -> anyhow::Error {
+    anyhow!("Windows OCR error: {}", err.message())
+}
+
+/// Recognizes the lines in `chunk`. Blocking; call from a blocking thread.
+pub fn recognize(chunk: &RgbaImage, language: OcrLanguage) -> anyhow::Result<Vec<OcrResult>> {
+    // Already-initialized apartments return S_FALSE / RPC_E_CHANGED_MODE, both usable here.
+    let _ = unsafe { RoInitialize(RO_INIT_MULTITHREADED) };
+
+    let tag = HSTRING::from(language.bcp47_tag());
+    let ocr_language = Language::CreateLanguage(&tag).map_err(win_err)?;
+    if !OcrEngine::IsLanguageSupported(&ocr_language).map_err(win_err)? {
+        return Err(anyhow!(
+            "Windows OCR language pack for '{}' is not installed",
+            language.bcp47_tag()
+        ));
+    }
+    let engine = OcrEngine::TryCreateFromLanguage(&ocr_language).map_err(win_err)?;
+
+    // SoftwareBitmap expects BGRA8.
+    let mut bgra = chunk.as_raw().clone();
+    for pixel in bgra.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    let writer = DataWriter::new().map_err(win_err)?;
+    writer.WriteBytes(&bgra).map_err(win_err)?;
+    let buffer = writer.DetachBuffer().map_err(win_err)?;
+    let bitmap = SoftwareBitmap::CreateCopyWithAlphaFromBuffer(
+        &buffer,
+        BitmapPixelFormat::Bgra8,
+        chunk.width() as i32,
+        chunk.height() as i32,
+        BitmapAlphaMode::Premultiplied,
+    )
+    .map_err(win_err)?;
+
+    let result = engine
+        .RecognizeAsync(&bitmap)
+        .map_err(win_err)?
+        .get()
+        .map_err(win_err)?;
+
+    let mut lines = Vec::new();
+    for line in result.Lines().map_err(win_err)? {
+        let text = logic::post_process_text(line.Text().map_err(win_err)?.to_string(), language);
+        if text.trim().is_empty() {
+            continue;
+        }
+
+        let mut min_x = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+        for word in line.Words().map_err(win_err)? {
+            let rect = word.BoundingRect().map_err(win_err)?;
+            min_x = min_x.min(rect.X as f64);
+            max_x = max_x.max((rect.X + rect.Width) as f64);
+            min_y = min_y.min(rect.Y as f64);
+            max_y = max_y.max((rect.Y + rect.Height) as f64);
+        }
+        if !min_x.is_finite() || !min_y.is_finite() {
+            continue;
+        }
+
+        let width = max_x - min_x;
+        let height = max_y - min_y;
+        let is_vertical = language.prefers_vertical() && width <= height;
+        lines.push(OcrResult {
+            text,
+            is_merged: Some(false),
+            forced_orientation: Some(if is_vertical {
+                "vertical".into()
+            } else {
+                "horizontal".into()
+            }),
+            confidence: None,
+            block_id: None,
+            lines: Vec::new(),
+            ruby: Vec::new(),
+            order: None,
+            bubble_id: None,
+            full_width: None,
+            full_height: None,
+            animated: None,
+            translation: None,
+            tight_bounding_box: BoundingBox {
+                x: min_x,
+                y: min_y,
+                width,
+                height,
+                rotation: None,
+            },
+        });
+    }
+
+    Ok(lines)
+}
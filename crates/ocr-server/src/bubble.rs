@@ -0,0 +1,110 @@
+//! Lightweight speech-bubble segmentation used to keep merging inside one bubble.
+//!
+//! Bubbles are bright regions enclosed by a darker outline. The chunk is downscaled,
+//! thresholded, and its bright pixels are labelled into connected components. Each OCR
+//! line is assigned the component covering most of the bright pixels inside its box.
+//! Components covering a large share of the chunk are page background, not bubbles.
+
+use image::{GrayImage, RgbaImage, imageops};
+
+use crate::logic::OcrResult;
+
+const MAX_SIDE: u32 = 512;
+const BRIGHT_THRESHOLD: u8 = 200;
+const MAX_BUBBLE_AREA_RATIO: f64 = 0.25;
+const MIN_BUBBLE_PIXELS: usize = 16;
+
+/// Sets `OcrResult::bubble_id` on lines that sit inside a detected bubble.
+/// `lines` must be in `chunk` pixel coordinates.
+pub fn assign_bubbles(chunk: &RgbaImage, lines: &mut [OcrResult]) {
+    if lines.len() < 2 || chunk.width() == 0 || chunk.height() == 0 {
+        return;
+    }
+
+    let scale = (MAX_SIDE as f64 / chunk.width().max(chunk.height()) as f64).min(1.0);
+    let width = ((chunk.width() as f64 * scale).round() as u32).max(1);
+    let height = ((chunk.height() as f64 * scale).round() as u32).max(1);
+    let gray = imageops::grayscale(chunk);
+    let small = imageops::resize(&gray, width, height, imageops::FilterType::Triangle);
+
+    let (labels, sizes) = label_bright_components(&small);
+    let max_area = (width as f64 * height as f64 * MAX_BUBBLE_AREA_RATIO) as usize;
+
+    for line in lines {
+        let bbox = &line.tight_bounding_box;
+        let x0 = ((bbox.x * scale).floor().max(0.0) as u32).min(width - 1);
+        let y0 = ((bbox.y * scale).floor().max(0.0) as u32).min(height - 1);
+        let x1 = (((bbox.x + bbox.width) * scale).ceil() as u32).clamp(x0 + 1, width);
+        let y1 = (((bbox.y + bbox.height) * scale).ceil() as u32).clamp(y0 + 1, height);
+
+        let mut counts: Vec<(u32, usize)> = Vec::new();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let label = labels[(y * width + x) as usize];
+                if label == 0 {
+                    continue;
+                }
+                match counts.iter_mut().find(|(seen, _)| *seen == label) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((label, 1)),
+                }
+            }
+        }
+
+        line.bubble_id = counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(label, _)| label)
+            .filter(|label| {
+                let size = sizes[*label as usize];
+                (MIN_BUBBLE_PIXELS..=max_area).contains(&size)
+            });
+    }
+}
+
+/// 4-connected components of bright pixels. Label 0 means "not bright";
+/// `sizes[label]` is the pixel count of each component.
+fn label_bright_components(image: &GrayImage) -> (Vec<u32>, Vec<usize>) {
+    let (width, height) = image.dimensions();
+    let mut labels = vec![0u32; (width * height) as usize];
+    let mut sizes = vec![0usize];
+    let mut stack = Vec::new();
+
+    for start in 0..labels.len() {
+        if labels[start] != 0 || image.as_raw()[start] < BRIGHT_THRESHOLD {
+            continue;
+        }
+        let label = sizes.len() as u32;
+        let mut size = 0;
+        labels[start] = label;
+        stack.push(start);
+
+        while let Some(index) = stack.pop() {
+            size += 1;
+            let x = index as u32 % width;
+            let y = index as u32 / width;
+            let mut visit = |nx: u32, ny: u32| {
+                let neighbor = (ny * width + nx) as usize;
+                if labels[neighbor] == 0 && image.as_raw()[neighbor] >= BRIGHT_THRESHOLD {
+                    labels[neighbor] = label;
+                    stack.push(neighbor);
+                }
+            };
+            if x > 0 {
+                visit(x - 1, y);
+            }
+            if x + 1 < width {
+                visit(x + 1, y);
+            }
+            if y > 0 {
+                visit(x, y - 1);
+            }
+            if y + 1 < height {
+                visit(x, y + 1);
+            }
+        }
+        sizes.push(size);
+    }
+
+    (labels, sizes)
+}
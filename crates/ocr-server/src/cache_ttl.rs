@@ -0,0 +1,77 @@
+//! Optional maximum age for OCR cache entries.
+//!
+//! `MANATAN_OCR_CACHE_TTL_DAYS` applies to every entry, and
+//! `MANATAN_OCR_CACHE_TTL_DAYS_<LANGUAGE>` (e.g. `..._JAPANESE`) overrides it per language.
+//! Entries older than their TTL are treated as cache misses, so they are re-processed
+//! the next time they are requested.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::language::OcrLanguage;
+
+const TTL_DAYS_ENV: &str = "MANATAN_OCR_CACHE_TTL_DAYS";
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CacheTtl {
+    /// Max age in seconds for entries without a language-specific TTL.
+    pub global_secs: Option<i64>,
+    /// Max age in seconds, keyed by `OcrLanguage::as_str`.
+    pub per_language_secs: HashMap<String, i64>,
+}
+
+fn parse_days(value: &str) -> Option<i64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|days| days.is_finite() && *days > 0.0)
+        .map(|days| (days * SECONDS_PER_DAY) as i64)
+}
+
+impl CacheTtl {
+    pub fn from_env() -> Self {
+        let mut ttl = Self::default();
+        let language_prefix = format!("{TTL_DAYS_ENV}_");
+
+        for (key, value) in manatan_config::vars() {
+            if key == TTL_DAYS_ENV {
+                ttl.global_secs = parse_days(&value);
+            } else if let Some(language) = key.strip_prefix(&language_prefix) {
+                let language = language.to_ascii_lowercase();
+                let known = serde_json::from_value::<OcrLanguage>(serde_json::Value::String(
+                    language.clone(),
+                ))
+                .is_ok();
+                if !known {
+                    tracing::warn!("Ignoring {key}: unknown OCR language");
+                    continue;
+                }
+                if let Some(secs) = parse_days(&value) {
+                    ttl.per_language_secs.insert(language, secs);
+                }
+            }
+        }
+
+        ttl
+    }
+
+    /// Max age for a cache key, based on its `lang/<language>/` prefix.
+    pub fn max_age_secs(&self, cache_key: &str) -> Option<i64> {
+        cache_key
+            .strip_prefix("lang/")
+            .and_then(|rest| rest.split('/').next())
+            .and_then(|language| self.per_language_secs.get(language).copied())
+            .or(self.global_secs)
+    }
+
+    /// Oldest `last_processed_at` still considered fresh for `cache_key`.
+    /// Returns 0 when entries never expire.
+    pub fn min_processed_at(&self, cache_key: &str, now: i64) -> i64 {
+        self.max_age_secs(cache_key)
+            .map(|max_age| now.saturating_sub(max_age))
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,233 @@
+//! Concurrency limits, whose best values differ a lot between phones and servers.
+//!
+//! - `MANATAN_OCR_PAGE_CONCURRENCY`: pages OCR'd at once within a chapter job, default 2
+//!   on Android and 6 elsewhere.
+//! - `MANATAN_OCR_STATUS_CONCURRENCY`: Suwayomi page count lookups at once while
+//!   answering a batch chapter status request, default 4.
+//! - `MANATAN_OCR_BACKEND_CONCURRENCY`: OCR backend calls at once across the whole
+//!   server, from `/ocr` requests and jobs alike, default 2 on Android and 8 elsewhere.
+//!   Work run through `in_background` (jobs, read-ahead, the local directory watcher)
+//!   yields to `/ocr` requests waiting for a call, so a big preprocess job can't starve
+//!   the reader or trip the backend's rate limits.
+//!
+//! All can be changed at runtime with `PUT /settings/concurrency`. Chapter jobs read
+//! the page limit when they start, so running jobs keep theirs; the backend limit
+//! applies to the next call.
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+const PAGE_CONCURRENCY_ENV: &str = "MANATAN_OCR_PAGE_CONCURRENCY";
+const STATUS_CONCURRENCY_ENV: &str = "MANATAN_OCR_STATUS_CONCURRENCY";
+const BACKEND_CONCURRENCY_ENV: &str = "MANATAN_OCR_BACKEND_CONCURRENCY";
+const DEFAULT_PAGE_CONCURRENCY: usize = if cfg!(target_os = "android") { 2 } else { 6 };
+const DEFAULT_STATUS_CONCURRENCY: usize = 4;
+const DEFAULT_BACKEND_CONCURRENCY: usize = if cfg!(target_os = "android") { 2 } else { 8 };
+/// Upper bound for any limit, so a typo can't start thousands of requests.
+pub const MAX_CONCURRENCY: usize = 64;
+
+tokio::task_local! {
+    static BACKGROUND: ();
+}
+
+/// Who a backend call is for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallPriority {
+    /// A client waiting for the page, e.g. `/ocr`.
+    Interactive,
+    /// Jobs and read-ahead, which yield to interactive calls.
+    Background,
+}
+
+/// Runs `work` with background priority for its backend calls.
+pub async fn in_background<F: Future>(work: F) -> F::Output {
+    BACKGROUND.scope((), work).await
+}
+
+/// The priority of backend calls made by the current task.
+pub fn current_priority() -> CallPriority {
+    if BACKGROUND.try_with(|_| ()).is_ok() {
+        CallPriority::Background
+    } else {
+        CallPriority::Interactive
+    }
+}
+
+#[derive(Debug, Default)]
+struct BackendCalls {
+    active: usize,
+    interactive_waiting: usize,
+}
+
+#[derive(Debug)]
+pub struct ConcurrencyLimits {
+    page: AtomicUsize,
+    status: AtomicUsize,
+    backend: AtomicUsize,
+    backend_calls: Mutex<BackendCalls>,
+    backend_released: Notify,
+}
+
+/// A running backend call; the slot is freed on drop.
+pub struct BackendPermit<'a> {
+    limits: &'a ConcurrencyLimits,
+}
+
+impl Drop for BackendPermit<'_> {
+    fn drop(&mut self) {
+        self.limits
+            .backend_calls
+            .lock()
+            .expect("lock poisoned")
+            .active -= 1;
+        self.limits.backend_released.notify_waiters();
+    }
+}
+
+/// An interactive call waiting for a slot, holding off background ones until it gets
+/// one or gives up.
+struct InteractiveWaiter<'a> {
+    limits: &'a ConcurrencyLimits,
+}
+
+impl Drop for InteractiveWaiter<'_> {
+    fn drop(&mut self) {
+        self.limits
+            .backend_calls
+            .lock()
+            .expect("lock poisoned")
+            .interactive_waiting -= 1;
+        self.limits.backend_released.notify_waiters();
+    }
+}
+
+/// Current values, as reported by `GET /settings/concurrency`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct ConcurrencySettings {
+    pub page_concurrency: usize,
+    pub status_concurrency: usize,
+    pub backend_concurrency: usize,
+}
+
+/// Body of `PUT /settings/concurrency`; unset fields keep their value.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct ConcurrencyUpdate {
+    pub page_concurrency: Option<usize>,
+    pub status_concurrency: Option<usize>,
+    pub backend_concurrency: Option<usize>,
+}
+
+impl ConcurrencyLimits {
+    pub fn new(
+        page_concurrency: usize,
+        status_concurrency: usize,
+        backend_concurrency: usize,
+    ) -> Self {
+        Self {
+            page: AtomicUsize::new(page_concurrency.clamp(1, MAX_CONCURRENCY)),
+            status: AtomicUsize::new(status_concurrency.clamp(1, MAX_CONCURRENCY)),
+            backend: AtomicUsize::new(backend_concurrency.clamp(1, MAX_CONCURRENCY)),
+            backend_calls: Mutex::new(BackendCalls::default()),
+            backend_released: Notify::new(),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: usize| {
+            manatan_config::var(name)
+                .ok()
+                .and_then(|value| value.trim().parse::<usize>().ok())
+                .filter(|value| *value > 0)
+                .unwrap_or(default)
+        };
+        Self::new(
+            read(PAGE_CONCURRENCY_ENV, DEFAULT_PAGE_CONCURRENCY),
+            read(STATUS_CONCURRENCY_ENV, DEFAULT_STATUS_CONCURRENCY),
+            read(BACKEND_CONCURRENCY_ENV, DEFAULT_BACKEND_CONCURRENCY),
+        )
+    }
+
+    pub fn page(&self) -> usize {
+        self.page.load(Ordering::Relaxed)
+    }
+
+    pub fn status(&self) -> usize {
+        self.status.load(Ordering::Relaxed)
+    }
+
+    pub fn backend(&self) -> usize {
+        self.backend.load(Ordering::Relaxed)
+    }
+
+    /// Backend calls running right now.
+    pub fn backend_in_use(&self) -> usize {
+        self.backend_calls.lock().expect("lock poisoned").active
+    }
+
+    pub fn settings(&self) -> ConcurrencySettings {
+        ConcurrencySettings {
+            page_concurrency: self.page(),
+            status_concurrency: self.status(),
+            backend_concurrency: self.backend(),
+        }
+    }
+
+    /// Waits for a free backend call slot. Background calls also wait while an
+    /// interactive one is queued, so pages a reader is waiting for go first.
+    pub async fn acquire_backend(&self, priority: CallPriority) -> BackendPermit<'_> {
+        let mut waiter = None;
+        loop {
+            let mut released = pin!(self.backend_released.notified());
+            released.as_mut().enable();
+            {
+                let mut calls = self.backend_calls.lock().expect("lock poisoned");
+                let yields = priority == CallPriority::Background && calls.interactive_waiting > 0;
+                if calls.active < self.backend() && !yields {
+                    calls.active += 1;
+                    return BackendPermit { limits: self };
+                }
+                if priority == CallPriority::Interactive && waiter.is_none() {
+                    calls.interactive_waiting += 1;
+                    waiter = Some(InteractiveWaiter { limits: self });
+                }
+            }
+            released.await;
+        }
+    }
+
+    /// Applies `update`, or changes nothing if any value is outside `1..=MAX_CONCURRENCY`.
+    pub fn apply(&self, update: ConcurrencyUpdate) -> Result<ConcurrencySettings, String> {
+        let values = [
+            ("page_concurrency", update.page_concurrency),
+            ("status_concurrency", update.status_concurrency),
+            ("backend_concurrency", update.backend_concurrency),
+        ];
+        for (name, value) in values {
+            if let Some(value) = value
+                && !(1..=MAX_CONCURRENCY).contains(&value)
+            {
+                return Err(format!("{name} must be between 1 and {MAX_CONCURRENCY}"));
+            }
+        }
+        if let Some(value) = update.page_concurrency {
+            self.page.store(value, Ordering::Relaxed);
+        }
+        if let Some(value) = update.status_concurrency {
+            self.status.store(value, Ordering::Relaxed);
+        }
+        if let Some(value) = update.backend_concurrency {
+            self.backend.store(value, Ordering::Relaxed);
+            self.backend_released.notify_waiters();
+        }
+        Ok(self.settings())
+    }
+}
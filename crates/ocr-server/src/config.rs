@@ -0,0 +1,139 @@
+//! Network exposure of the OCR router.
+//!
+//! - `MANATAN_OCR_ALLOWED_ORIGINS`: comma-separated origins allowed to call the OCR API.
+//!   Requests carrying any other `Origin` get 403, so include the origin the web UI is
+//!   served from. Unset leaves it to the shared CORS settings of `manatan_auth::cors`.
+//! - `MANATAN_OCR_PORT`: serve the OCR router on its own listener at this port instead
+//!   of the embedding server's.
+//! - `MANATAN_OCR_HOST`: interface for that listener, default `127.0.0.1`.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+};
+
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::shutdown::ShutdownHandle;
+
+const ALLOWED_ORIGINS_ENV: &str = "MANATAN_OCR_ALLOWED_ORIGINS";
+const HOST_ENV: &str = "MANATAN_OCR_HOST";
+const PORT_ENV: &str = "MANATAN_OCR_PORT";
+const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+#[derive(Clone, Debug, Default)]
+pub struct OcrServerConfig {
+    /// Empty allows every origin.
+    pub allowed_origins: Vec<HeaderValue>,
+    /// Address of the dedicated OCR listener, if any.
+    pub bind_addr: Option<SocketAddr>,
+}
+
+impl OcrServerConfig {
+    pub fn from_env() -> Self {
+        let allowed_origins = manatan_config::var(ALLOWED_ORIGINS_ENV)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|origin| origin.trim().trim_end_matches('/'))
+                    .filter(|origin| !origin.is_empty())
+                    .filter_map(|origin| match HeaderValue::from_str(origin) {
+                        Ok(origin) => Some(origin),
+                        Err(_) => {
+                            tracing::warn!(
+                                "Ignoring invalid origin in {ALLOWED_ORIGINS_ENV}: {origin}"
+                            );
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let host = match manatan_config::var(HOST_ENV) {
+            Ok(value) => value.trim().parse::<IpAddr>().unwrap_or_else(|_| {
+                tracing::warn!("Ignoring invalid {HOST_ENV}={value}, using {DEFAULT_HOST}");
+                DEFAULT_HOST
+            }),
+            Err(_) => DEFAULT_HOST,
+        };
+        let bind_addr = manatan_config::var(PORT_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u16>().ok())
+            .map(|port| SocketAddr::new(host, port));
+
+        Self {
+            allowed_origins,
+            bind_addr,
+        }
+    }
+
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        self.allowed_origins.is_empty() || self.allowed_origins.contains(origin)
+    }
+
+    /// CORS for the dedicated listener; the unified server brings its own.
+    pub fn cors_layer(&self) -> CorsLayer {
+        let layer = manatan_auth::cors::layer();
+        if self.allowed_origins.is_empty() {
+            layer
+        } else {
+            layer.allow_origin(AllowOrigin::list(self.allowed_origins.clone()))
+        }
+    }
+}
+
+/// Middleware returning 403 for browser requests from origins outside the allow list.
+pub async fn enforce_allowed_origins(
+    State(config): State<Arc<OcrServerConfig>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let rejected = request
+        .headers()
+        .get(header::ORIGIN)
+        .is_some_and(|origin| !config.allows_origin(origin));
+    if rejected {
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+    next.run(request).await
+}
+
+/// Serves `router` on the dedicated listener until `shutdown` has drained, and returns
+/// `None`. Without a dedicated port, or if it can't be bound, returns the router for the
+/// embedding server to mount instead.
+pub fn serve_standalone(router: Router, shutdown: &ShutdownHandle) -> Option<Router> {
+    let config = OcrServerConfig::from_env();
+    let Some(addr) = config.bind_addr else {
+        return Some(router);
+    };
+    let listener = std::net::TcpListener::bind(addr).and_then(|listener| {
+        listener.set_nonblocking(true)?;
+        tokio::net::TcpListener::from_std(listener)
+    });
+    let listener = match listener {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!("[OCR] Failed to bind {addr}, serving on the main port: {err}");
+            return Some(router);
+        }
+    };
+
+    tracing::info!("[OCR] Dedicated listener on http://{addr}");
+    let shutdown = shutdown.clone();
+    tokio::spawn(async move {
+        let serve = axum::serve(listener, router.layer(config.cors_layer()))
+            .with_graceful_shutdown(async move { shutdown.drained().await });
+        if let Err(err) = serve.await {
+            tracing::error!("[OCR] Dedicated listener failed: {err}");
+        }
+    });
+    None
+}
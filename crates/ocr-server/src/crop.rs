@@ -0,0 +1,67 @@
+//! Cropping a text region out of a page, for embedding bubble images in Anki cards.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+
+use crate::logic::BoundingBox;
+
+/// Pixels added around the box when the request doesn't say.
+pub const DEFAULT_PADDING_PX: u32 = 8;
+
+/// Parses `x,y,width,height` in the normalized coordinates of OCR results.
+pub fn parse_box(value: &str) -> Result<BoundingBox, String> {
+    let numbers: Vec<f64> = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("box must be four numbers: {value}"))?;
+    let [x, y, width, height] = numbers[..] else {
+        return Err(format!("box must be x,y,width,height: {value}"));
+    };
+    let in_range = |value: f64| (0.0..=1.0).contains(&value);
+    if ![x, y, width, height].into_iter().all(in_range) || width <= 0.0 || height <= 0.0 {
+        return Err(format!(
+            "box must be normalized to 0..1 and non-empty: {value}"
+        ));
+    }
+    Ok(BoundingBox {
+        x,
+        y,
+        width,
+        height,
+        rotation: None,
+    })
+}
+
+/// Pixel rectangle `(left, top, width, height)` of `bbox` on a `width` x `height` image,
+/// grown by `padding` and clamped to the image. `None` when nothing is left.
+pub fn pixel_rect(
+    bbox: &BoundingBox,
+    width: u32,
+    height: u32,
+    padding: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    let padding = f64::from(padding);
+    let clamp_x = |value: f64| value.clamp(0.0, f64::from(width)) as u32;
+    let clamp_y = |value: f64| value.clamp(0.0, f64::from(height)) as u32;
+    let left = clamp_x((bbox.x * f64::from(width)).round() - padding);
+    let top = clamp_y((bbox.y * f64::from(height)).round() - padding);
+    let right = clamp_x(((bbox.x + bbox.width) * f64::from(width)).round() + padding);
+    let bottom = clamp_y(((bbox.y + bbox.height) * f64::from(height)).round() + padding);
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some((left, top, right - left, bottom - top))
+}
+
+/// Crops `bbox` (plus `padding` pixels) out of `image` and encodes it as PNG.
+pub fn crop_png(image: &DynamicImage, bbox: &BoundingBox, padding: u32) -> anyhow::Result<Vec<u8>> {
+    let (left, top, width, height) = pixel_rect(bbox, image.width(), image.height(), padding)
+        .ok_or_else(|| anyhow::anyhow!("Box lies outside the image"))?;
+    let mut png = Vec::new();
+    image
+        .crop_imm(left, top, width, height)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(png)
+}
@@ -0,0 +1,114 @@
+//! Background pruning of `ocr_cache` down to a configured size.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::state::AppState;
+
+const MAX_ENTRIES_ENV: &str = "MANATAN_OCR_CACHE_MAX_ENTRIES";
+const MAX_BYTES_ENV: &str = "MANATAN_OCR_CACHE_MAX_BYTES";
+const INTERVAL_SECS_ENV: &str = "MANATAN_OCR_CACHE_EVICTION_INTERVAL_SECS";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Upper bounds for the OCR cache. Unset limits are not enforced.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct CacheLimits {
+    pub max_entries: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl CacheLimits {
+    pub fn from_env() -> Self {
+        Self {
+            max_entries: env_u64(MAX_ENTRIES_ENV),
+            max_bytes: env_u64(MAX_BYTES_ENV),
+        }
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.max_entries.is_none() && self.max_bytes.is_none()
+    }
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    manatan_config::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|value| *value > 0)
+}
+
+/// Outcome of a single eviction pass.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct EvictionStats {
+    pub ran_at: i64,
+    pub evicted_entries: u64,
+    pub evicted_bytes: u64,
+    pub remaining_entries: u64,
+    pub remaining_bytes: u64,
+}
+
+/// Running totals since startup, reported by the status endpoint.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct EvictionSummary {
+    pub limits: CacheLimits,
+    pub runs: u64,
+    pub total_evicted_entries: u64,
+    pub total_evicted_bytes: u64,
+    pub last_run: Option<EvictionStats>,
+}
+
+/// Runs an eviction pass every `MANATAN_OCR_CACHE_EVICTION_INTERVAL_SECS` when a limit is set.
+pub fn spawn_eviction_task(state: &AppState) {
+    let limits = state.cache_limits;
+    if limits.is_unbounded() {
+        return;
+    }
+
+    let interval = env_u64(INTERVAL_SECS_ENV)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_INTERVAL);
+    tracing::info!(
+        "[Cache] Evicting least-recently-used entries every {}s (max entries: {:?}, max bytes: {:?})",
+        interval.as_secs(),
+        limits.max_entries,
+        limits.max_bytes
+    );
+
+    let state = state.clone();
+    manatan_tasks::spawn("ocr.cache-eviction", move || {
+        let state = state.clone();
+        async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let pass_state = state.clone();
+                let pass =
+                    tokio::task::spawn_blocking(move || pass_state.evict_lru_entries(limits));
+                let stats = match pass.await {
+                    Ok(stats) => stats,
+                    Err(err) => {
+                        tracing::warn!("[Cache] Eviction pass panicked: {err}");
+                        continue;
+                    }
+                };
+
+                if stats.evicted_entries > 0 {
+                    tracing::info!(
+                        "[Cache] Evicted {} entries ({} bytes); {} entries ({} bytes) remain",
+                        stats.evicted_entries,
+                        stats.evicted_bytes,
+                        stats.remaining_entries,
+                        stats.remaining_bytes
+                    );
+                }
+
+                let mut summary = state.eviction_summary.write().expect("lock poisoned");
+                summary.runs += 1;
+                summary.total_evicted_entries += stats.evicted_entries;
+                summary.total_evicted_bytes += stats.evicted_bytes;
+                summary.last_run = Some(stats);
+            }
+        }
+    });
+}
@@ -0,0 +1,302 @@
+//! Streaming cache export and the matching import parser.
+//!
+//! Exports are written row by row from the database cursor to the response body, either as
+//! NDJSON (one [`ExportRecord`] per line) or as the legacy `{cache_key: entry}` JSON object,
+//! optionally gzip-compressed. Imports accept any of those shapes.
+
+use std::io::{self, Read, Write};
+
+use axum::body::Bytes;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::{
+    logic::OcrResult,
+    state::{AppState, CacheEntry},
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Largest decompressed import accepted, so a small gzip bomb can't exhaust memory.
+pub const MAX_IMPORT_BYTES: u64 = 512 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub cache_key: String,
+    pub context: String,
+    pub data: Vec<OcrResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    /// Chapters the page belongs to, so imports can restore chapter status.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chapter_keys: Vec<String>,
+}
+
+/// Restricts an export or import to one series or chapter.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ExportFilter {
+    /// Chapter key prefix, e.g. `lang/japanese/api/v1/manga/12/`.
+    pub chapter_prefix: Option<String>,
+    /// Suwayomi manga ID; matches `/manga/{id}/` anywhere in the key.
+    pub manga_id: Option<String>,
+}
+
+impl ExportFilter {
+    pub fn is_empty(&self) -> bool {
+        self.chapter_prefix.is_none() && self.manga_id.is_none()
+    }
+
+    /// SQL `LIKE` pattern for cache and chapter keys, or `None` to match everything.
+    /// Wildcards in the filter are escaped with `\`, for use with `ESCAPE '\'`.
+    pub fn like_pattern(&self) -> Option<String> {
+        let escape = |value: &str| {
+            value
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        };
+        if let Some(prefix) = &self.chapter_prefix {
+            return Some(format!("{}%", escape(prefix)));
+        }
+        self.manga_id
+            .as_ref()
+            .map(|id| format!("%/manga/{}/%", escape(id.trim())))
+    }
+
+    fn matches_key(&self, key: &str) -> bool {
+        if let Some(prefix) = &self.chapter_prefix {
+            return key.starts_with(prefix.as_str());
+        }
+        match &self.manga_id {
+            Some(id) => key.contains(&format!("/manga/{}/", id.trim())),
+            None => true,
+        }
+    }
+
+    pub fn matches(&self, record: &ExportRecord) -> bool {
+        self.is_empty()
+            || self.matches_key(&record.cache_key)
+            || record.chapter_keys.iter().any(|key| self.matches_key(key))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Ndjson,
+    /// The pre-streaming `{cache_key: {context, data}}` object.
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportCompression {
+    #[default]
+    None,
+    Gzip,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// What to do with imported records whose cache key already exists. Manually corrected
+/// entries are never overwritten, see `ImportReport::skipped_edited`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStrategy {
+    #[default]
+    SkipExisting,
+    Overwrite,
+    /// Overwrite only when the record's `created_at` is newer than the stored entry.
+    NewestWins,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub added: usize,
+    pub overwritten: usize,
+    pub skipped_existing: usize,
+    pub skipped_older: usize,
+    /// Existing entries kept because they were corrected by hand.
+    pub skipped_edited: usize,
+    pub failed: usize,
+}
+
+/// `io::Write` adapter that forwards buffered chunks to an async body stream.
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "export client disconnected"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+/// Serializes records in the requested format.
+struct RecordWriter<W: Write> {
+    out: W,
+    format: ExportFormat,
+    written: usize,
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn new(mut out: W, format: ExportFormat) -> io::Result<Self> {
+        if format == ExportFormat::Json {
+            out.write_all(b"{")?;
+        }
+        Ok(Self {
+            out,
+            format,
+            written: 0,
+        })
+    }
+
+    fn write(&mut self, record: &ExportRecord) -> io::Result<()> {
+        match self.format {
+            ExportFormat::Ndjson => {
+                serde_json::to_writer(&mut self.out, record)?;
+                self.out.write_all(b"\n")?;
+            }
+            ExportFormat::Json => {
+                if self.written > 0 {
+                    self.out.write_all(b",")?;
+                }
+                serde_json::to_writer(&mut self.out, &record.cache_key)?;
+                self.out.write_all(b":")?;
+                serde_json::to_writer(
+                    &mut self.out,
+                    &serde_json::json!({ "context": record.context, "data": record.data }),
+                )?;
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        if self.format == ExportFormat::Json {
+            self.out.write_all(b"}")?;
+        }
+        Ok(self.out)
+    }
+}
+
+fn write_export<W: Write>(
+    state: &AppState,
+    out: W,
+    format: ExportFormat,
+    filter: &ExportFilter,
+) -> io::Result<W> {
+    let mut writer = RecordWriter::new(out, format)?;
+    state.export_cache_rows(filter, |record| writer.write(record))?;
+    writer.finish()
+}
+
+/// Starts exporting on a blocking thread and returns the body chunks as they are produced.
+pub fn spawn_export(
+    state: AppState,
+    format: ExportFormat,
+    compression: ExportCompression,
+    filter: ExportFilter,
+) -> mpsc::Receiver<io::Result<Bytes>> {
+    let (sender, receiver) = mpsc::channel(8);
+    tokio::task::spawn_blocking(move || {
+        let channel = ChannelWriter {
+            sender: sender.clone(),
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        };
+        let result = match compression {
+            ExportCompression::None => write_export(&state, channel, format, &filter)
+                .and_then(|mut channel| channel.flush()),
+            ExportCompression::Gzip => {
+                let encoder = GzEncoder::new(channel, Compression::default());
+                write_export(&state, encoder, format, &filter)
+                    .and_then(|encoder| encoder.finish())
+                    .and_then(|mut channel| channel.flush())
+            }
+        };
+        if let Err(err) = result {
+            tracing::warn!("Cache export aborted: {err}");
+            let _ = sender.blocking_send(Err(err));
+        }
+    });
+    receiver
+}
+
+/// Parses an uploaded export: gzip or plain, NDJSON or the legacy JSON object.
+pub fn parse_import(body: &[u8]) -> anyhow::Result<Vec<ExportRecord>> {
+    let mut decompressed = Vec::new();
+    let body = if body.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(body)
+            .take(MAX_IMPORT_BYTES + 1)
+            .read_to_end(&mut decompressed)?;
+        if decompressed.len() as u64 > MAX_IMPORT_BYTES {
+            anyhow::bail!("Import is larger than {MAX_IMPORT_BYTES} bytes uncompressed");
+        }
+        decompressed.as_slice()
+    } else {
+        body
+    };
+
+    if let Ok(legacy) =
+        serde_json::from_slice::<std::collections::HashMap<String, CacheEntry>>(body)
+    {
+        return Ok(legacy
+            .into_iter()
+            .map(|(cache_key, entry)| ExportRecord {
+                cache_key,
+                context: entry.context,
+                data: entry.data,
+                created_at: None,
+                chapter_keys: Vec::new(),
+            })
+            .collect());
+    }
+
+    let mut records = Vec::new();
+    for (index, line) in body.split(|byte| *byte == b'\n').enumerate() {
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let record = serde_json::from_slice::<ExportRecord>(line)
+            .map_err(|err| anyhow::anyhow!("Invalid export record on line {}: {err}", index + 1))?;
+        records.push(record);
+    }
+    Ok(records)
+}
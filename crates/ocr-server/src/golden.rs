@@ -0,0 +1,271 @@
+//! Golden-page regression harness for the decode → OCR → merge pipeline.
+//!
+//! A fixture is a page image `<name>.<ext>`, the backend response recorded for it as
+//! `<name>.raw.json` (the `RawChunk`s of `logic::get_raw_ocr_data`) and the expected
+//! page as `<name>.golden.json`. Replaying a fixture decodes the image, checks that the
+//! recording was made for it, and runs the chunks through `logic::merge_chunks`, the same
+//! merge, normalization and reading order as `/ocr`. The resulting text and boxes must
+//! match the golden lines, so merge changes that move or rejoin text show up as a diff.
+//!
+//! The bundled fixtures live in `tests/fixtures/golden`; `MANATAN_OCR_GOLDEN_DIR` points
+//! the harness at another set. With the `golden` feature, `POST /internal/golden` replays
+//! them on a running server, and `?update=1` records new golden files.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    language::OcrLanguage,
+    logic::{self, OcrResult, RawChunk},
+    merge::MergeConfig,
+};
+
+const GOLDEN_DIR_ENV: &str = "MANATAN_OCR_GOLDEN_DIR";
+const RAW_SUFFIX: &str = ".raw.json";
+const GOLDEN_SUFFIX: &str = ".golden.json";
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "avif", "gif", "bmp"];
+/// Largest accepted drift of a box edge, as a fraction of the page.
+pub const BOX_TOLERANCE: f64 = 0.002;
+
+/// The expected page of a fixture.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GoldenPage {
+    #[serde(default)]
+    pub language: OcrLanguage,
+    pub lines: Vec<GoldenLine>,
+}
+
+/// One result in reading order, with its box as fractions of the page.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GoldenLine {
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<String>,
+}
+
+impl GoldenLine {
+    pub fn from_result(result: &OcrResult) -> Self {
+        let b = &result.tight_bounding_box;
+        Self {
+            text: result.text.clone(),
+            x: b.x,
+            y: b.y,
+            width: b.width,
+            height: b.height,
+            orientation: result.forced_orientation.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GoldenCase {
+    pub name: String,
+    pub image: PathBuf,
+    pub raw: PathBuf,
+    pub golden: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseStatus {
+    Passed,
+    /// The golden file was missing or `update` was set, and has been written.
+    Recorded,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CaseReport {
+    pub name: String,
+    pub status: CaseStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub mismatches: Vec<String>,
+}
+
+/// The fixtures to replay: `MANATAN_OCR_GOLDEN_DIR`, or the bundled set.
+pub fn fixtures_dir() -> PathBuf {
+    manatan_config::var(GOLDEN_DIR_ENV)
+        .ok()
+        .map(|dir| dir.trim().to_string())
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden"))
+}
+
+/// Images in `dir` with a recorded response next to them, sorted by name.
+pub fn discover(dir: &Path) -> Vec<GoldenCase> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut cases: Vec<GoldenCase> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| {
+                    IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                })
+        })
+        .filter_map(|image| {
+            let name = image.file_stem()?.to_str()?.to_string();
+            let raw = dir.join(format!("{name}{RAW_SUFFIX}"));
+            let golden = dir.join(format!("{name}{GOLDEN_SUFFIX}"));
+            raw.is_file().then_some(GoldenCase {
+                name,
+                image,
+                raw,
+                golden,
+            })
+        })
+        .collect();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    cases
+}
+
+/// Runs the recorded response of `case` through the pipeline.
+pub fn replay(
+    case: &GoldenCase,
+    language: OcrLanguage,
+    merge_config: &MergeConfig,
+) -> anyhow::Result<Vec<OcrResult>> {
+    let image_bytes =
+        fs::read(&case.image).with_context(|| format!("reading {}", case.image.display()))?;
+    let image = logic::decode_image(&image_bytes)?;
+    let raw = fs::read(&case.raw).with_context(|| format!("reading {}", case.raw.display()))?;
+    let chunks: Vec<RawChunk> =
+        serde_json::from_slice(&raw).with_context(|| format!("parsing {}", case.raw.display()))?;
+
+    let (width, height) = (image.width(), image.height());
+    for chunk in &chunks {
+        if (chunk.full_width, chunk.full_height) != (width, height) {
+            bail!(
+                "{} was recorded for a {}x{} image, {} is {width}x{height}",
+                case.raw.display(),
+                chunk.full_width,
+                chunk.full_height,
+                case.image.display()
+            );
+        }
+    }
+    Ok(logic::merge_chunks(chunks, merge_config, language))
+}
+
+/// Differences between the golden lines and the replayed ones; empty when they match.
+pub fn compare(expected: &[GoldenLine], actual: &[GoldenLine], tolerance: f64) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if expected.len() != actual.len() {
+        mismatches.push(format!(
+            "expected {} lines, got {}",
+            expected.len(),
+            actual.len()
+        ));
+    }
+    for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+        if expected.text != actual.text {
+            mismatches.push(format!(
+                "line {index}: text {:?} became {:?}",
+                expected.text, actual.text
+            ));
+        }
+        let edges = [
+            ("x", expected.x, actual.x),
+            ("y", expected.y, actual.y),
+            ("width", expected.width, actual.width),
+            ("height", expected.height, actual.height),
+        ];
+        for (edge, expected_value, actual_value) in edges {
+            if (expected_value - actual_value).abs() > tolerance {
+                mismatches.push(format!(
+                    "line {index} ({:?}): {edge} {expected_value:.4} became {actual_value:.4}",
+                    expected.text
+                ));
+            }
+        }
+        if expected.orientation != actual.orientation {
+            mismatches.push(format!(
+                "line {index} ({:?}): orientation {:?} became {:?}",
+                expected.text, expected.orientation, actual.orientation
+            ));
+        }
+    }
+    for (index, extra) in actual.iter().enumerate().skip(expected.len()) {
+        mismatches.push(format!("line {index}: unexpected {:?}", extra.text));
+    }
+    mismatches
+}
+
+/// Replays `case` against its golden file. Without one, or with `update`, the replayed
+/// page is written as the new golden file instead.
+pub fn run_case(case: &GoldenCase, update: bool) -> CaseReport {
+    let report = |status, mismatches| CaseReport {
+        name: case.name.clone(),
+        status,
+        mismatches,
+    };
+    let golden: Option<GoldenPage> = match fs::read(&case.golden) {
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(golden) => Some(golden),
+            Err(err) if !update => {
+                return report(
+                    CaseStatus::Failed,
+                    vec![format!("parsing {}: {err}", case.golden.display())],
+                );
+            }
+            Err(_) => None,
+        },
+        Err(_) => None,
+    };
+    let language = golden
+        .as_ref()
+        .map(|golden| golden.language)
+        .unwrap_or_default();
+
+    let results = match replay(case, language, &MergeConfig::default()) {
+        Ok(results) => results,
+        Err(err) => return report(CaseStatus::Failed, vec![format!("{err:#}")]),
+    };
+    let lines: Vec<GoldenLine> = results.iter().map(GoldenLine::from_result).collect();
+
+    match golden {
+        Some(golden) if !update => {
+            let mismatches = compare(&golden.lines, &lines, BOX_TOLERANCE);
+            let status = if mismatches.is_empty() {
+                CaseStatus::Passed
+            } else {
+                CaseStatus::Failed
+            };
+            report(status, mismatches)
+        }
+        _ => {
+            let page = GoldenPage { language, lines };
+            let written = serde_json::to_string_pretty(&page)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(fs::write(&case.golden, json + "\n")?));
+            match written {
+                Ok(()) => report(CaseStatus::Recorded, Vec::new()),
+                Err(err) => report(
+                    CaseStatus::Failed,
+                    vec![format!("writing {}: {err}", case.golden.display())],
+                ),
+            }
+        }
+    }
+}
+
+/// Replays every fixture in `dir`.
+pub fn run_dir(dir: &Path, update: bool) -> Vec<CaseReport> {
+    discover(dir)
+        .iter()
+        .map(|case| run_case(case, update))
+        .collect()
+}
@@ -2,20 +2,42 @@ use std::sync::atomic::Ordering;
 
 use axum::{
     Json,
-    extract::{Query, State},
-    http::StatusCode,
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
 };
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 use serde::Deserialize;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use tokio::sync::broadcast;
 use tracing::{info, warn};
 
 use crate::{
-    jobs,
+    archive,
+    auth::SuwayomiAuth,
+    backend::OcrBackend,
+    concurrency::{self, ConcurrencySettings, ConcurrencyUpdate},
+    crop,
+    export::{self, ExportCompression, ExportFilter, ExportFormat, ImportStrategy},
+    jobs::{ChapterJob, EnqueueOutcome, JobEvent, JobPriority, JobSummary},
     language::OcrLanguage,
-    logic,
+    local,
+    logic::{self, OcrResult},
+    manga::{self, MangaJob},
+    merge::{self, FuriganaMode, MergeConfig, MergeOverrides, OrientationBias},
+    postprocess::{ReplacementRule, RuleInput},
+    prefetch,
+    proxy::ProxyMode,
+    request_id,
+    retry::{BackoffStrategy, RetryOverrides, RetryPolicy},
+    shutdown::PageGuard,
     state::{AppState, CacheEntry},
+    structure, translate,
 };
 
 #[derive(Deserialize)]
@@ -23,18 +45,215 @@ pub struct OcrRequest {
     pub url: String,
     pub user: Option<String>,
     pub pass: Option<String>,
+    /// Raw `Authorization` header for Suwayomi, e.g. `Bearer <token>`, see `auth`.
+    pub authorization: Option<String>,
     #[serde(default, rename = "base_url", alias = "baseUrl")]
     pub base_url: Option<String>,
     #[serde(default = "default_context")]
     pub context: String,
     pub add_space_on_merge: Option<bool>,
     pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+    /// `none`, `suwayomi` or a proxy URL, see `proxy`.
+    pub proxy: Option<ProxyMode>,
+    pub max_attempts: Option<u32>,
+    pub retry_backoff: Option<BackoffStrategy>,
+    pub retry_delay_ms: Option<u64>,
+    pub attempt_timeout_ms: Option<u64>,
+    pub merge_enabled: Option<bool>,
+    pub font_size_ratio: Option<f64>,
+    pub merge_gap_scale: Option<f64>,
+    pub max_merge_gap: Option<f64>,
+    /// `orientation=vertical|horizontal|auto`. Like the other merge options, anything but
+    /// the default is cached apart from the default results.
+    #[serde(alias = "orientation")]
+    pub orientation_bias: Option<OrientationBias>,
+    pub furigana: Option<FuriganaMode>,
+    pub respect_bubbles: Option<bool>,
+    /// Hide results whose recognition confidence is below this value.
+    pub min_confidence: Option<f32>,
+    /// `1` adds the nested blocks → lines → words view next to the flat results.
+    pub structure: Option<String>,
+    /// Attach a machine `translation` to each result, see `translate`.
+    pub translate: Option<bool>,
+    /// `1` skips the cache, reprocesses the page and replaces the stored entry, even a
+    /// manually corrected one (which stays in the history).
+    pub force: Option<String>,
+    /// Pages after this one to OCR in the background, see `prefetch`. Needs `base_url`.
+    pub prefetch: Option<usize>,
+}
+
+/// One page to OCR and cache, detached from the request that asked for it.
+#[derive(Clone)]
+struct PageOcr {
+    url: String,
+    auth: SuwayomiAuth,
+    proxy: Option<ProxyMode>,
+    context: String,
+    merge_config: MergeConfig,
+    retry: RetryPolicy,
+    language: OcrLanguage,
+    backend: OcrBackend,
+}
+
+/// OCRs `page` and caches the result. Concurrent misses for the same page share one
+/// run; forced runs only share with each other, since a normal run may answer from the
+/// cache. Returns whether an existing run was joined.
+async fn run_page_ocr(
+    state: &AppState,
+    cache_key: String,
+    page: PageOcr,
+    force: bool,
+) -> (Result<Vec<OcrResult>, String>, bool) {
+    let flight_key = if force {
+        format!("{cache_key}#force")
+    } else {
+        cache_key.clone()
+    };
+    let work_state = state.clone();
+    state
+        .ocr_in_flight
+        .run(&flight_key, move || async move {
+            // The previous run for this key may have finished after our cache check.
+            if !force && let Some(entry) = work_state.get_cache_entry(&cache_key) {
+                return Ok(entry.data);
+            }
+            let data = logic::fetch_and_process(
+                &page.url,
+                &page.auth,
+                &page.merge_config,
+                page.language,
+                page.backend,
+                page.proxy.as_ref(),
+                &page.retry,
+                Some(&work_state),
+                force,
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+
+            info!("OCR Handler: Writing cache entry to DB...");
+            let entry = CacheEntry {
+                context: page.context,
+                data: data.clone(),
+            };
+            if force {
+                work_state.replace_cache_entry(&cache_key, &entry).await;
+            } else {
+                work_state.insert_cache_entry(&cache_key, &entry).await;
+            }
+            info!("OCR Handler: Cache write complete.");
+            Ok(data)
+        })
+        .await
+}
+
+/// Reads ahead `count` pages after `page` in the background, see `prefetch`. Pages are
+/// processed one at a time and go through `run_page_ocr`, so a reader who turns the
+/// page before its prefetch finishes waits for that run instead of starting another.
+fn spawn_prefetch(
+    state: &AppState,
+    base_url: &str,
+    chapter_key: &str,
+    page: &PageOcr,
+    count: usize,
+) {
+    if count == 0 {
+        return;
+    }
+    let state = state.clone();
+    let base_url = base_url.to_string();
+    let chapter_key = chapter_key.to_string();
+    let page = page.clone();
+    tokio::spawn(concurrency::in_background(async move {
+        let page_count = match state.get_chapter_pages(&chapter_key) {
+            Some(page_count) if page_count > 0 => page_count,
+            _ => resolve_chapter_length(&state, &base_url, &chapter_key, &page.auth).await,
+        };
+        for url in prefetch::following_pages(&page.url, page_count, count) {
+            if state.shutting_down.load(Ordering::SeqCst) {
+                return;
+            }
+            state.job_queue.wait_while_paused().await;
+            let cache_key =
+                logic::page_cache_key(&url, page.language, page.backend, &page.merge_config);
+            if state.has_cache_entry(&cache_key) {
+                continue;
+            }
+            let _in_flight = PageGuard::new(&state);
+            let next = PageOcr {
+                url: url.clone(),
+                ..page.clone()
+            };
+            match run_page_ocr(&state, cache_key.clone(), next, false).await {
+                (Ok(_), _) => {
+                    state.insert_chapter_cache(&chapter_key, &cache_key).await;
+                    info!("[Prefetch] Cached {url}");
+                }
+                (Err(err), _) => {
+                    // Later pages likely fail the same way.
+                    warn!("[Prefetch] Failed {url}: {err}");
+                    return;
+                }
+            }
+        }
+    }));
+}
+
+/// Query flags given as `1` or `true`.
+fn is_flag_set(value: Option<&str>) -> bool {
+    matches!(value, Some("1" | "true"))
 }
 
 fn default_context() -> String {
     "No Context".to_string()
 }
 
+/// Applies the response-only options of an `/ocr` request to a page's results.
+fn ocr_response(
+    data: Vec<OcrResult>,
+    min_confidence: Option<f32>,
+    with_structure: bool,
+    language: OcrLanguage,
+) -> Response {
+    let mut results = logic::filter_min_confidence(data, min_confidence);
+    // Entries cached before reading order was computed.
+    if results.iter().any(|result| result.order.is_none()) {
+        results = merge::sort_reading_order(results, language);
+    }
+    let blocks = with_structure.then(|| structure::build_blocks(&results, language));
+    structure::strip_line_structure(&mut results);
+    match blocks {
+        Some(blocks) => {
+            Json(serde_json::json!({ "results": results, "blocks": blocks })).into_response()
+        }
+        None => Json(results).into_response(),
+    }
+}
+
+/// Adds translations to `data` (caching them with the entry) when the request asks for
+/// them, and strips cached ones when it doesn't. Failures leave the OCR text usable.
+async fn apply_translation(
+    state: &AppState,
+    cache_key: &str,
+    data: &mut [OcrResult],
+    language: OcrLanguage,
+    requested: bool,
+) {
+    if !requested {
+        translate::strip_translations(data);
+        return;
+    }
+    let Some(translator) = state.translator.as_deref() else {
+        warn!("Translation requested for {cache_key}, but no provider is configured");
+        return;
+    };
+    match translate::translate_results(translator, data, language).await {
+        Ok(true) => state.update_cache_data(cache_key, data).await,
+        Ok(false) => {}
+        Err(err) => warn!("Translation failed for {cache_key}: {err}"),
+    }
+}
 
 // --- Handlers ---
 
@@ -46,102 +265,432 @@ pub async fn status_handler(State(state): State<AppState>) -> Json<serde_json::V
         "requests_processed": state.requests_processed.load(Ordering::Relaxed),
         "items_in_cache": cache_size,
         "active_jobs": state.active_jobs.load(Ordering::Relaxed),
+        "ocr_in_flight": state.ocr_in_flight.len(),
+        "backend_calls": state.concurrency.backend_in_use(),
+        "jobs_paused": state.job_queue.is_paused(),
+        "concurrency": state.concurrency.settings(),
+        "cache_eviction": *state.eviction_summary.read().expect("lock poisoned"),
+        "cache_ttl": &*state.cache_ttl,
+        "degraded": state.backend_health.any_degraded(),
+        "backends": state.backend_health.snapshot(),
     }))
 }
 
+/// Liveness: the process is up and serving requests.
+pub async fn health_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+/// Readiness: 503 until the database is reachable and startup has finished. Reports
+/// `degraded` while a backend keeps failing, so clients can warn before OCR calls fail.
+pub async fn ready_handler(State(state): State<AppState>) -> Response {
+    let database = state.db_reachable();
+    let initialized = state.initialized.load(Ordering::Relaxed);
+    let (code, status) = if !database || !initialized {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    } else if state.backend_health.any_degraded() {
+        (StatusCode::OK, "degraded")
+    } else {
+        (StatusCode::OK, "ready")
+    };
+    let body = serde_json::json!({
+        "status": status,
+        "database": database,
+        "initialized": initialized,
+        "backends": state.backend_health.snapshot(),
+    });
+    (code, Json(body)).into_response()
+}
+
 pub async fn ocr_handler(
     State(state): State<AppState>,
     Query(params): Query<OcrRequest>,
-) -> Result<Json<Vec<crate::logic::OcrResult>>, (StatusCode, String)> {
+) -> Result<Response, (StatusCode, String)> {
     let language = params.language.unwrap_or_default();
-    let cache_key = logic::get_cache_key(&params.url, Some(language));
+    let with_structure = is_flag_set(params.structure.as_deref());
+    let with_translation = params.translate.unwrap_or(false);
+    let force = is_flag_set(params.force.as_deref());
+    let backend = params.backend.unwrap_or_default();
+    let merge_config = MergeConfig {
+        add_space_on_merge: params.add_space_on_merge,
+        ..MergeConfig::default()
+    }
+    .with_overrides(&MergeOverrides {
+        merge_enabled: params.merge_enabled,
+        font_size_ratio: params.font_size_ratio,
+        merge_gap_scale: params.merge_gap_scale,
+        max_merge_gap: params.max_merge_gap,
+        orientation_bias: params.orientation_bias,
+        furigana: params.furigana,
+        respect_bubbles: params.respect_bubbles,
+    });
+    let cache_key = logic::page_cache_key(&params.url, language, backend, &merge_config);
     let chapter_key = params
         .base_url
         .as_ref()
         .map(|base| logic::get_cache_key(base, Some(language)));
     info!("OCR Handler: Incoming request for cache_key={}", cache_key);
 
+    let retry = state.retry_policy.with_overrides(RetryOverrides {
+        max_attempts: params.max_attempts,
+        retry_backoff: params.retry_backoff,
+        retry_delay_ms: params.retry_delay_ms,
+        attempt_timeout_ms: params.attempt_timeout_ms,
+    });
+    let page = PageOcr {
+        url: params.url.clone(),
+        auth: SuwayomiAuth::new(
+            params.user.clone(),
+            params.pass.clone(),
+            params.authorization.clone(),
+        ),
+        proxy: params.proxy.clone(),
+        context: params.context.clone(),
+        merge_config,
+        retry,
+        language,
+        backend,
+    };
+    // Also on cache hits, so the read-ahead stays ahead of the reader.
+    if let Some(chapter_key) = chapter_key.as_deref()
+        && let Some(base_url) = params.base_url.as_deref()
+    {
+        spawn_prefetch(
+            &state,
+            base_url,
+            chapter_key,
+            &page,
+            prefetch::pages_ahead(params.prefetch),
+        );
+    }
+
     info!("OCR Handler: Checking cache...");
-    if let Some(entry) = state.get_cache_entry(&cache_key) {
+    let cached = if force {
+        info!("OCR Handler: Forced reprocess for cache_key={}", cache_key);
+        None
+    } else if let Some(entry) = state.get_cache_entry(&cache_key) {
+        Some(entry)
+    } else {
+        state.claim_unnamespaced_entry(&cache_key).await
+    };
+    if let Some(entry) = cached {
         info!("OCR Handler: Cache HIT for cache_key={}", cache_key);
         if let Some(chapter_key) = chapter_key.as_deref() {
-            state.insert_chapter_cache(chapter_key, &cache_key);
+            state.insert_chapter_cache(chapter_key, &cache_key).await;
         }
         state.requests_processed.fetch_add(1, Ordering::Relaxed);
-        return Ok(Json(entry.data));
+        let mut data = entry.data;
+        apply_translation(&state, &cache_key, &mut data, language, with_translation).await;
+        return Ok(ocr_response(
+            data,
+            params.min_confidence,
+            with_structure,
+            language,
+        ));
     }
 
     // Back-compat: older versions included sourceId in the cache key.
     // Try to find a matching entry and promote it to the normalized key.
-    if let Some((_legacy_key, legacy_entry)) = state.get_cache_entry_sourceid_variant(&cache_key) {
+    if let Some((_legacy_key, legacy_entry)) = state
+        .get_cache_entry_sourceid_variant(&cache_key)
+        .filter(|_| !force)
+    {
         info!(
             "OCR Handler: Cache HIT via sourceId variant for cache_key={}",
             cache_key
         );
         if let Some(chapter_key) = chapter_key.as_deref() {
-            state.insert_chapter_cache(chapter_key, &cache_key);
+            state.insert_chapter_cache(chapter_key, &cache_key).await;
         }
-        state.insert_cache_entry(&cache_key, &legacy_entry);
+        state.insert_cache_entry(&cache_key, &legacy_entry).await;
         state.requests_processed.fetch_add(1, Ordering::Relaxed);
-        return Ok(Json(legacy_entry.data));
+        let mut data = legacy_entry.data;
+        apply_translation(&state, &cache_key, &mut data, language, with_translation).await;
+        return Ok(ocr_response(
+            data,
+            params.min_confidence,
+            with_structure,
+            language,
+        ));
     }
     info!(
         "OCR Handler: Cache MISS for cache_key={}. Starting processing.",
         cache_key
     );
 
-    let result = logic::fetch_and_process(
-        &params.url,
-        params.user.clone(),
-        params.pass.clone(),
-        params.add_space_on_merge,
-        language,
-    )
-    .await;
+    let (result, joined) = run_page_ocr(&state, cache_key.clone(), page, force).await;
+    if joined {
+        info!(
+            "OCR Handler: Shared in-flight processing for cache_key={}",
+            cache_key
+        );
+    }
 
     match result {
-        Ok(data) => {
+        Ok(mut data) => {
             state.requests_processed.fetch_add(1, Ordering::Relaxed);
             info!(
                 "OCR Handler: Processing successful for cache_key={}",
                 cache_key
             );
-
-            info!("OCR Handler: Writing cache entry to DB...");
-            state.insert_cache_entry(
-                &cache_key,
-                &CacheEntry {
-                    context: params.context,
-                    data: data.clone(),
-                },
-            );
-            info!("OCR Handler: Cache write complete.");
+            apply_translation(&state, &cache_key, &mut data, language, with_translation).await;
 
             if let Some(chapter_key) = chapter_key.as_deref() {
-                state.insert_chapter_cache(chapter_key, &cache_key);
+                state.insert_chapter_cache(chapter_key, &cache_key).await;
             }
 
-            Ok(Json(data))
+            Ok(ocr_response(
+                data,
+                params.min_confidence,
+                with_structure,
+                language,
+            ))
         }
         Err(e) => {
             warn!(
                 "OCR Handler: Processing FAILED for cache_key={}: {}",
                 cache_key, e
             );
-            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+/// Raw lines from another OCR engine, see `merge::merge_external_lines`.
+#[derive(Deserialize)]
+pub struct MergeRequest {
+    pub width: u32,
+    pub height: u32,
+    pub lines: Vec<OcrResult>,
+    /// Boxes are fractions of the image instead of pixels.
+    #[serde(default)]
+    pub normalized: bool,
+    pub language: Option<OcrLanguage>,
+    pub add_space_on_merge: Option<bool>,
+    #[serde(flatten)]
+    pub merge: MergeOverrides,
+}
+
+pub async fn merge_handler(
+    Json(req): Json<MergeRequest>,
+) -> Result<Json<Vec<OcrResult>>, (StatusCode, String)> {
+    if req.width == 0 || req.height == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "width and height must be positive".to_string(),
+        ));
+    }
+    let merge_config = MergeConfig {
+        add_space_on_merge: req.add_space_on_merge,
+        language: req.language.unwrap_or_default(),
+        ..MergeConfig::default()
+    }
+    .with_overrides(&req.merge);
+    Ok(Json(merge::merge_external_lines(
+        req.lines,
+        req.width,
+        req.height,
+        req.normalized,
+        &merge_config,
+    )))
+}
+
+#[derive(Deserialize)]
+pub struct CropQuery {
+    pub url: String,
+    /// `x,y,width,height`, normalized like `tightBoundingBox`.
+    #[serde(rename = "box")]
+    pub bbox: String,
+    /// Pixels added on each side, default `crop::DEFAULT_PADDING_PX`.
+    pub padding: Option<u32>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub authorization: Option<String>,
+}
+
+/// Returns the boxed region of a page as PNG, e.g. a speech bubble for an Anki card.
+pub async fn crop_handler(
+    Query(query): Query<CropQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let bbox = crop::parse_box(&query.bbox).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    let padding = query.padding.unwrap_or(crop::DEFAULT_PADDING_PX);
+    let auth = SuwayomiAuth::new(query.user, query.pass, query.authorization);
+    let image_bytes = logic::fetch_image_bytes(&query.url, &auth)
+        .await
+        .map_err(|err| (StatusCode::BAD_GATEWAY, err.to_string()))?;
+
+    let png = tokio::task::spawn_blocking(move || {
+        let image = logic::decode_image(&image_bytes)?;
+        crop::crop_png(&image, &bbox, padding)
+    })
+    .await
+    .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .map_err(|err| (StatusCode::UNPROCESSABLE_ENTITY, err.to_string()))?;
+
+    Ok(([(header::CONTENT_TYPE, "image/png")], png).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct LocalOcrRequest {
+    /// Path relative to `MANATAN_OCR_LOCAL_DIR`.
+    pub path: String,
+    pub add_space_on_merge: Option<bool>,
+    pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+    #[serde(flatten)]
+    pub merge: MergeOverrides,
+    pub min_confidence: Option<f32>,
+    #[serde(default)]
+    pub structure: bool,
+}
+
+/// OCRs an image from the configured local directory.
+pub async fn ocr_local_handler(
+    State(state): State<AppState>,
+    Json(req): Json<LocalOcrRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(root) = local::local_dir() else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "No local directory configured".to_string(),
+        ));
+    };
+    let path = local::resolve(&root, &req.path).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    let language = req.language.unwrap_or_default();
+    let merge_config = MergeConfig {
+        add_space_on_merge: req.add_space_on_merge,
+        ..MergeConfig::default()
+    }
+    .with_overrides(&req.merge);
+
+    match local::ocr_file(
+        &state,
+        &root,
+        &path,
+        &merge_config,
+        language,
+        req.backend.unwrap_or_default(),
+    )
+    .await
+    {
+        Ok(data) => Ok(ocr_response(
+            data,
+            req.min_confidence,
+            req.structure,
+            language,
+        )),
+        Err(err) => {
+            warn!("Local OCR failed for {}: {err:?}", path.display());
+            Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
         }
     }
 }
 
+#[derive(Deserialize)]
+pub struct ArchiveQuery {
+    /// Archive path relative to `MANATAN_OCR_LOCAL_DIR`; otherwise the body is the archive.
+    pub path: Option<String>,
+    pub context: Option<String>,
+    pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+}
+
+/// Queues every image of a CBZ/ZIP archive, uploaded as the body or read from the local
+/// directory, as one chapter.
+pub async fn ocr_archive_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ArchiveQuery>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    queue_archive(&state, query, body, archive::read_zip_pages).await
+}
+
+/// Like `ocr_archive_handler`, for the images of an EPUB in spine order.
+pub async fn ocr_epub_handler(
+    State(state): State<AppState>,
+    Query(query): Query<ArchiveQuery>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    queue_archive(&state, query, body, archive::read_epub_pages).await
+}
+
+async fn queue_archive(
+    state: &AppState,
+    query: ArchiveQuery,
+    body: Bytes,
+    read_pages: fn(&[u8], &str) -> anyhow::Result<Vec<archive::ArchivePage>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let (base_url, bytes) = match query.path.as_deref() {
+        Some(relative) => {
+            let root = local::local_dir().ok_or((
+                StatusCode::NOT_FOUND,
+                "No local directory configured".to_string(),
+            ))?;
+            let path =
+                local::resolve(&root, relative).map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+            let bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+            (
+                local::local_url(path.strip_prefix(&root).unwrap_or(&path)),
+                bytes,
+            )
+        }
+        None if body.is_empty() => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Upload an archive as the body or pass path".to_string(),
+            ));
+        }
+        None => (archive::upload_base_url(&body), body.to_vec()),
+    };
+
+    let pages_base_url = base_url.clone();
+    let pages = tokio::task::spawn_blocking(move || read_pages(&bytes, &pages_base_url))
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid archive: {err}")))?;
+    if pages.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "No images found in archive".to_string(),
+        ));
+    }
+
+    let page_urls: Vec<String> = pages.iter().map(|page| page.url.clone()).collect();
+    let started = archive::spawn_archive_job(
+        state,
+        base_url.clone(),
+        pages,
+        query.context.unwrap_or_else(|| base_url.clone()),
+        query.language.unwrap_or_default(),
+        query.backend.unwrap_or_default(),
+    );
+    Ok(Json(serde_json::json!({
+        "status": if started { "queued" } else { "already_processing" },
+        "base_url": base_url,
+        "pages": page_urls,
+    })))
+}
+
 #[derive(Deserialize)]
 pub struct JobRequest {
     pub base_url: String,
     pub user: Option<String>,
     pub pass: Option<String>,
+    /// Raw `Authorization` header for Suwayomi, e.g. `Bearer <token>`, see `auth`.
+    pub authorization: Option<String>,
     pub context: String,
     pub pages: Option<Vec<String>>,
     pub add_space_on_merge: Option<bool>,
     pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+    pub proxy: Option<ProxyMode>,
+    pub priority: Option<JobPriority>,
+    #[serde(flatten)]
+    pub retry: RetryOverrides,
+    #[serde(flatten)]
+    pub merge: MergeOverrides,
 }
 
 #[derive(Deserialize)]
@@ -149,6 +698,7 @@ pub struct ChapterStatusQuery {
     pub base_url: String,
     pub user: Option<String>,
     pub pass: Option<String>,
+    pub authorization: Option<String>,
     pub language: Option<OcrLanguage>,
 }
 
@@ -164,102 +714,144 @@ pub struct ChapterStatusBatchRequest {
     pub chapters: Vec<ChapterStatusBatchItem>,
     pub user: Option<String>,
     pub pass: Option<String>,
+    pub authorization: Option<String>,
     pub language: Option<OcrLanguage>,
 }
 
-async fn chapter_status(state: &AppState, req: JobRequest) -> Json<serde_json::Value> {
-    let language = req.language.unwrap_or_default();
-    let job_key = logic::get_cache_key(&req.base_url, Some(language));
+/// Status of a running or queued chapter, `None` when it is neither.
+fn active_chapter_status(
+    state: &AppState,
+    job_key: &str,
+    pages: Option<&[String]>,
+) -> Option<serde_json::Value> {
     let progress = {
         state
             .active_chapter_jobs
             .read()
             .expect("lock poisoned")
-            .get(&job_key)
+            .get(job_key)
             .cloned()
     };
 
     if let Some(p) = progress {
-        return Json(serde_json::json!({
+        return Some(serde_json::json!({
             "status": "processing",
             "progress": p.current,
             "total": p.total
         }));
     }
 
-    let mut cached_count = 0usize;
-    let mut total_expected = 0usize;
-    if let Some(page_list) = req.pages.as_ref() {
-        if page_list.is_empty() {
-            return Json(serde_json::json!({
-                "status": "idle",
-                "cached_count": 0,
-                "total_expected": 0
-            }));
-        }
-        let mut cached_keys = Vec::new();
-        for page in page_list {
-            let cache_key = logic::get_cache_key(page, Some(language));
-            if state.has_cache_entry(&cache_key)
-                || state.has_cache_entry_prefix(&format!("{cache_key}?sourceId="))
-                || state.has_cache_entry_prefix(&format!("{cache_key}&sourceId="))
-            {
-                cached_count += 1;
-                cached_keys.push(cache_key);
-            }
-        }
-        if cached_count > 0 {
-            total_expected = page_list.len();
-            state.set_chapter_pages(&job_key, total_expected);
-            for cache_key in cached_keys {
-                state.insert_chapter_cache(&job_key, &cache_key);
-            }
-        }
+    if state.job_queue.is_queued(job_key) {
+        return Some(serde_json::json!({
+            "status": "processing",
+            "progress": 0,
+            "total": pages.map_or(0, <[String]>::len),
+            "queued": true
+        }));
+    }
+    None
+}
+
+fn cached_chapter_status(cached_count: usize, total_expected: usize) -> serde_json::Value {
+    let status = if total_expected > 0 && cached_count >= total_expected {
+        "processed"
     } else {
-        cached_count = state.count_chapter_cache(&job_key);
-        if cached_count > 0 {
-            if let Some((page_count, _)) = state.get_chapter_progress(&job_key) {
-                total_expected = page_count;
-            } else if let Some(page_count) = state.get_chapter_pages(&job_key) {
-                total_expected = page_count;
-            }
-        }
+        "idle"
+    };
+    serde_json::json!({
+        "status": status,
+        "cached_count": cached_count,
+        "total_expected": total_expected
+    })
+}
+
+/// Cached pages of a chapter whose page list the client supplied, recording them so
+/// later checks without a list can use `chapter_cache`. Returns `(cached, total)`.
+async fn count_listed_pages(
+    state: &AppState,
+    job_key: &str,
+    pages: &[String],
+    page_keys: Vec<String>,
+    cached_keys: &HashSet<String>,
+) -> (usize, usize) {
+    let cached: Vec<String> = page_keys
+        .into_iter()
+        .filter(|cache_key| cached_keys.contains(cache_key))
+        .collect();
+    if cached.is_empty() {
+        return (0, 0);
     }
+    state
+        .record_chapter_pages(job_key, pages.len(), &cached)
+        .await;
+    (cached.len(), pages.len())
+}
 
-    // If we have cached pages but don't yet know how many pages exist in the chapter,
-    // resolve the total page count via the REST chapter pages endpoint and persist it.
-    // This commonly happens when pages were OCR'd on-demand (per-page) rather than via
-    // a preprocess job that supplies the full page list.
-    if cached_count > 0 && total_expected == 0 {
-        match logic::resolve_total_pages_from_graphql(&req.base_url, req.user.clone(), req.pass.clone()).await {
-            Ok(page_count) if page_count > 0 => {
-                total_expected = page_count;
-                state.set_chapter_pages(&job_key, total_expected);
-            }
-            Ok(_) => {}
-            Err(err) => {
-                warn!(
-                    base_url = req.base_url,
-                    error = %err,
-                    "failed to resolve total pages for chapter"
-                );
-            }
+// If we have cached pages but don't yet know how many pages exist in the chapter,
+// resolve the total page count (GraphQL pageCount, else REST pages) and persist it.
+// This commonly happens when pages were OCR'd on-demand (per-page) rather than via
+// a preprocess job that supplies the full page list.
+async fn resolve_chapter_length(
+    state: &AppState,
+    base_url: &str,
+    job_key: &str,
+    auth: &SuwayomiAuth,
+) -> usize {
+    match logic::resolve_total_pages_from_graphql(base_url, auth).await {
+        Ok(page_count) if page_count > 0 => {
+            state.set_chapter_pages(job_key, page_count).await;
+            page_count
+        }
+        Ok(_) => 0,
+        Err(err) => {
+            warn!(
+                base_url = base_url,
+                error = %err,
+                "failed to resolve total pages for chapter"
+            );
+            0
         }
     }
+}
 
-    if total_expected > 0 && cached_count >= total_expected {
-        return Json(serde_json::json!({
-            "status": "processed",
-            "cached_count": cached_count,
-            "total_expected": total_expected
-        }));
+async fn chapter_status(state: &AppState, req: JobRequest) -> Json<serde_json::Value> {
+    let language = req.language.unwrap_or_default();
+    let backend = req.backend.unwrap_or_default();
+    let merge_config = MergeConfig {
+        add_space_on_merge: req.add_space_on_merge,
+        ..MergeConfig::default()
+    }
+    .with_overrides(&req.merge);
+    let job_key = logic::get_cache_key(&req.base_url, Some(language));
+    if let Some(status) = active_chapter_status(state, &job_key, req.pages.as_deref()) {
+        return Json(status);
     }
 
-    Json(serde_json::json!({
-        "status": "idle",
-        "cached_count": cached_count,
-        "total_expected": total_expected
-    }))
+    let (cached_count, mut total_expected) = match req.pages.as_deref() {
+        Some(page_list) => {
+            let page_keys: Vec<String> = page_list
+                .iter()
+                .map(|page| logic::page_cache_key(page, language, backend, &merge_config))
+                .collect();
+            let cached_keys = state.cached_page_keys(&page_keys);
+            count_listed_pages(state, &job_key, page_list, page_keys, &cached_keys).await
+        }
+        None => {
+            let cached_count = state.count_chapter_cache(&job_key);
+            let total_expected = if cached_count > 0 {
+                state.get_chapter_pages(&job_key).unwrap_or(0)
+            } else {
+                0
+            };
+            (cached_count, total_expected)
+        }
+    };
+
+    if cached_count > 0 && total_expected == 0 {
+        let auth = SuwayomiAuth::new(req.user, req.pass, req.authorization);
+        total_expected = resolve_chapter_length(state, &req.base_url, &job_key, &auth).await;
+    }
+    Json(cached_chapter_status(cached_count, total_expected))
 }
 
 pub async fn is_chapter_preprocessed_handler(
@@ -279,94 +871,406 @@ pub async fn is_chapter_preprocessed_get_handler(
             base_url: req.base_url,
             user: req.user,
             pass: req.pass,
+            authorization: req.authorization,
             context: "Check Status".to_string(),
             pages: None,
             add_space_on_merge: None,
             language: req.language,
+            backend: None,
+            proxy: None,
+            priority: None,
+            retry: RetryOverrides::default(),
+            merge: MergeOverrides::default(),
         },
     )
     .await
 }
 
+/// Status of many chapters at once. Cache lookups for all of them share a couple of
+/// set-based queries; only chapters of unknown length go to Suwayomi.
 pub async fn is_chapters_preprocessed_handler(
     State(state): State<AppState>,
     Json(req): Json<ChapterStatusBatchRequest>,
 ) -> Json<HashMap<String, serde_json::Value>> {
-    let results = Arc::new(Mutex::new(HashMap::new()));
-    let user = req.user.clone();
-    let pass = req.pass.clone();
-    let default_language = req.language;
-
-    let concurrency_limit = 4;
-    futures::stream::iter(req.chapters)
-        .for_each_concurrent(concurrency_limit, |item| {
-            let state = state.clone();
-            let results = results.clone();
-            let user = user.clone();
-            let pass = pass.clone();
+    let mut results = HashMap::new();
+    let mut pending = Vec::new();
+    for item in req.chapters {
+        let language = item.language.or(req.language).unwrap_or_default();
+        let job_key = logic::get_cache_key(&item.base_url, Some(language));
+        match active_chapter_status(&state, &job_key, item.pages.as_deref()) {
+            Some(status) => {
+                results.insert(item.base_url, status);
+            }
+            None => pending.push((item, job_key, language)),
+        }
+    }
+
+    let page_keys: Vec<Vec<String>> = pending
+        .iter()
+        .map(|(item, _, language)| {
+            item.pages
+                .iter()
+                .flatten()
+                .map(|page| logic::get_cache_key(page, Some(*language)))
+                .collect()
+        })
+        .collect();
+    let cached_keys = state.cached_page_keys(&page_keys.concat());
+    let unlisted: Vec<String> = pending
+        .iter()
+        .filter(|(item, ..)| item.pages.is_none())
+        .map(|(_, job_key, _)| job_key.clone())
+        .collect();
+    let chapter_statuses = state.chapter_cache_statuses(&unlisted);
+
+    let mut unknown_length = Vec::new();
+    for ((item, job_key, _), page_keys) in pending.into_iter().zip(page_keys) {
+        let (cached_count, total_expected) = match item.pages.as_deref() {
+            Some(pages) => {
+                count_listed_pages(&state, &job_key, pages, page_keys, &cached_keys).await
+            }
+            None => {
+                let status = chapter_statuses.get(&job_key).copied().unwrap_or_default();
+                (status.cached_pages, status.page_count.unwrap_or(0))
+            }
+        };
+        if cached_count > 0 && total_expected == 0 {
+            unknown_length.push((item.base_url, job_key, cached_count));
+        } else {
+            results.insert(
+                item.base_url,
+                cached_chapter_status(cached_count, total_expected),
+            );
+        }
+    }
+
+    let concurrency_limit = state.concurrency.status();
+    let auth = SuwayomiAuth::new(req.user, req.pass, req.authorization);
+    let resolved: Vec<(String, serde_json::Value)> = futures::stream::iter(unknown_length)
+        .map(|(base_url, job_key, cached_count)| {
+            let (state, auth) = (&state, &auth);
             async move {
-                let language = item.language.or(default_language);
-                let Json(value) = chapter_status(
-                    &state,
-                    JobRequest {
-                        base_url: item.base_url.clone(),
-                        user: user.clone(),
-                        pass: pass.clone(),
-                        context: "Batch Status".to_string(),
-                        pages: item.pages,
-                        add_space_on_merge: None,
-                        language,
-                    },
+                let total_expected = resolve_chapter_length(state, &base_url, &job_key, auth).await;
+                (
+                    base_url,
+                    cached_chapter_status(cached_count, total_expected),
                 )
-                .await;
-                let mut locked = results.lock().expect("lock poisoned");
-                locked.insert(item.base_url, value);
             }
         })
+        .buffer_unordered(concurrency_limit)
+        .collect()
         .await;
+    results.extend(resolved);
+
+    Json(results)
+}
+
+pub async fn get_concurrency_handler(State(state): State<AppState>) -> Json<ConcurrencySettings> {
+    Json(state.concurrency.settings())
+}
 
-    let out = results.lock().expect("lock poisoned").clone();
-    Json(out)
+pub async fn update_concurrency_handler(
+    State(state): State<AppState>,
+    Json(update): Json<ConcurrencyUpdate>,
+) -> Result<Json<ConcurrencySettings>, (StatusCode, String)> {
+    let settings = state
+        .concurrency
+        .apply(update)
+        .map_err(|err| (StatusCode::BAD_REQUEST, err))?;
+    info!(
+        "Concurrency limits set to {} page(s) per job, {} status lookup(s), {} backend call(s)",
+        settings.page_concurrency, settings.status_concurrency, settings.backend_concurrency
+    );
+    Ok(Json(settings))
 }
 
 pub async fn preprocess_handler(
     State(state): State<AppState>,
     Json(req): Json<JobRequest>,
 ) -> Json<serde_json::Value> {
+    Json(enqueue_chapter(&state, req))
+}
+
+fn enqueue_chapter(state: &AppState, req: JobRequest) -> serde_json::Value {
     let language = req.language.unwrap_or_default();
     let pages = match req.pages {
         Some(p) => p,
-        None => return Json(serde_json::json!({ "error": "No pages provided" })),
+        None => return serde_json::json!({ "error": "No pages provided" }),
     };
 
+    let chapter_key = logic::get_cache_key(&req.base_url, Some(language));
     let is_processing = {
         state
             .active_chapter_jobs
             .read()
             .expect("lock poisoned")
-            .contains_key(&logic::get_cache_key(&req.base_url, Some(language)))
+            .contains_key(&chapter_key)
     };
 
     if is_processing {
-        return Json(serde_json::json!({ "status": "already_processing" }));
-    }
-
-    let state_clone = state.clone();
-    tokio::spawn(async move {
-        jobs::run_chapter_job(
-            state_clone,
-            req.base_url,
-            pages,
-            req.user,
-            req.pass,
-            req.context,
-            req.add_space_on_merge,
-            language,
-        )
-        .await;
-    });
+        return serde_json::json!({ "status": "already_processing" });
+    }
+
+    let job = ChapterJob {
+        base_url: req.base_url,
+        pages,
+        auth: SuwayomiAuth::new(req.user, req.pass, req.authorization),
+        context: req.context,
+        add_space_on_merge: req.add_space_on_merge,
+        language,
+        backend: req.backend.unwrap_or_default(),
+        proxy: req.proxy,
+        retry: state.retry_policy.with_overrides(req.retry),
+        merge: req.merge,
+        request_id: request_id::current(),
+    };
+    let priority = req.priority.unwrap_or_default();
+    state.save_chapter_job(&chapter_key, priority, &job);
+    match state.job_queue.enqueue(chapter_key.clone(), priority, job) {
+        EnqueueOutcome::Queued(id) => serde_json::json!({ "status": "queued", "job_id": id }),
+        EnqueueOutcome::AlreadyQueued(id) => {
+            serde_json::json!({ "status": "already_queued", "job_id": id })
+        }
+        EnqueueOutcome::Full => {
+            state.delete_chapter_job(&chapter_key);
+            serde_json::json!({ "error": "Job queue is full" })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PreprocessBatchItem {
+    pub base_url: String,
+    pub pages: Vec<String>,
+    pub context: Option<String>,
+    pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+    pub priority: Option<JobPriority>,
+}
+
+#[derive(Deserialize)]
+pub struct PreprocessBatchRequest {
+    pub chapters: Vec<PreprocessBatchItem>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub authorization: Option<String>,
+    #[serde(default = "default_context")]
+    pub context: String,
+    pub add_space_on_merge: Option<bool>,
+    pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+    pub proxy: Option<ProxyMode>,
+    pub priority: Option<JobPriority>,
+    #[serde(flatten)]
+    pub retry: RetryOverrides,
+    #[serde(flatten)]
+    pub merge: MergeOverrides,
+}
+
+/// Queues several chapters in the given order. Per-chapter fields override the batch defaults.
+pub async fn preprocess_batch_handler(
+    State(state): State<AppState>,
+    Json(req): Json<PreprocessBatchRequest>,
+) -> Json<serde_json::Value> {
+    let jobs: Vec<serde_json::Value> = req
+        .chapters
+        .into_iter()
+        .map(|item| {
+            let base_url = item.base_url.clone();
+            let mut result = enqueue_chapter(
+                &state,
+                JobRequest {
+                    base_url: item.base_url,
+                    user: req.user.clone(),
+                    pass: req.pass.clone(),
+                    authorization: req.authorization.clone(),
+                    context: item.context.unwrap_or_else(|| req.context.clone()),
+                    pages: Some(item.pages),
+                    add_space_on_merge: req.add_space_on_merge,
+                    language: item.language.or(req.language),
+                    backend: item.backend.or(req.backend),
+                    proxy: req.proxy.clone(),
+                    priority: item.priority.or(req.priority),
+                    retry: req.retry,
+                    merge: req.merge,
+                },
+            );
+            result["base_url"] = serde_json::Value::String(base_url);
+            result
+        })
+        .collect();
+
+    Json(serde_json::json!({ "jobs": jobs }))
+}
+
+#[derive(Deserialize)]
+pub struct MangaJobRequest {
+    pub manga_url: String,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub authorization: Option<String>,
+    #[serde(default = "default_context")]
+    pub context: String,
+    pub add_space_on_merge: Option<bool>,
+    pub language: Option<OcrLanguage>,
+    pub backend: Option<OcrBackend>,
+    pub proxy: Option<ProxyMode>,
+    #[serde(flatten)]
+    pub retry: RetryOverrides,
+    #[serde(flatten)]
+    pub merge: MergeOverrides,
+}
+
+/// Preprocesses every chapter of a manga, one chapter at a time.
+pub async fn preprocess_manga_handler(
+    State(state): State<AppState>,
+    Json(req): Json<MangaJobRequest>,
+) -> Json<serde_json::Value> {
+    if logic::path_segment_after(&req.manga_url, "manga").is_none() {
+        return Json(serde_json::json!({ "error": "Could not parse manga ID from manga_url" }));
+    }
+
+    let job = MangaJob {
+        manga_url: req.manga_url,
+        auth: SuwayomiAuth::new(req.user, req.pass, req.authorization),
+        context: req.context,
+        add_space_on_merge: req.add_space_on_merge,
+        language: req.language.unwrap_or_default(),
+        backend: req.backend.unwrap_or_default(),
+        proxy: req.proxy,
+        retry: state.retry_policy.with_overrides(req.retry),
+        merge: req.merge,
+        request_id: request_id::current(),
+    };
+    if manga::spawn_manga_job(&state, job) {
+        Json(serde_json::json!({ "status": "started" }))
+    } else {
+        Json(serde_json::json!({ "status": "already_processing" }))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MangaStatusQuery {
+    pub manga_url: String,
+    pub language: Option<OcrLanguage>,
+}
+
+pub async fn manga_status_handler(
+    State(state): State<AppState>,
+    Query(req): Query<MangaStatusQuery>,
+) -> Json<serde_json::Value> {
+    let manga_key = logic::get_cache_key(&req.manga_url, Some(req.language.unwrap_or_default()));
+    let progress = state
+        .active_manga_jobs
+        .read()
+        .expect("lock poisoned")
+        .get(&manga_key)
+        .cloned();
+
+    match progress {
+        Some(p) => Json(serde_json::json!({
+            "status": "processing",
+            "progress": p.current,
+            "total": p.total
+        })),
+        None => Json(serde_json::json!({ "status": "idle" })),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PreprocessEventsQuery {
+    pub base_url: String,
+    pub language: Option<OcrLanguage>,
+}
+
+fn sse_event(event: &JobEvent) -> Event {
+    Event::default()
+        .event(event.name())
+        .json_data(event)
+        .unwrap_or_else(|_| Event::default().event(event.name()))
+}
+
+/// Streams progress of a chapter job as Server-Sent Events.
+///
+/// Sends a `status` snapshot first, then `page` / `page_failed` events as pages finish,
+/// and closes after `completed`. If the chapter is neither running nor queued the stream
+/// closes right after the snapshot.
+pub async fn preprocess_events_handler(
+    State(state): State<AppState>,
+    Query(req): Query<PreprocessEventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let language = req.language.unwrap_or_default();
+    let chapter_key = logic::get_cache_key(&req.base_url, Some(language));
+
+    // Subscribe before taking the snapshot so no event falls in between.
+    let receiver = state.job_events.subscribe();
+    let progress = state
+        .active_chapter_jobs
+        .read()
+        .expect("lock poisoned")
+        .get(&chapter_key)
+        .cloned();
+    let queued = state.job_queue.is_queued(&chapter_key);
+
+    let snapshot = match progress {
+        Some(p) => serde_json::json!({
+            "status": "processing",
+            "progress": p.current,
+            "total": p.total
+        }),
+        None if queued => serde_json::json!({ "status": "queued" }),
+        None => serde_json::json!({ "status": "idle" }),
+    };
+    let snapshot = Event::default()
+        .event("status")
+        .json_data(snapshot)
+        .unwrap_or_else(|_| Event::default().event("status"));
+    let follow = progress.is_some() || queued;
+
+    let updates = futures::stream::unfold(
+        (receiver, chapter_key, follow),
+        |(mut receiver, chapter_key, follow)| async move {
+            if !follow {
+                return None;
+            }
+            loop {
+                match receiver.recv().await {
+                    Ok(event) if event.chapter_key() == chapter_key => {
+                        let done = matches!(event, JobEvent::Completed { .. });
+                        return Some((Ok(sse_event(&event)), (receiver, chapter_key, !done)));
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
 
-    Json(serde_json::json!({ "status": "started" }))
+    Sse::new(futures::stream::once(async move { Ok(snapshot) }).chain(updates))
+        .keep_alive(KeepAlive::default())
+}
+
+pub async fn list_jobs_handler(State(state): State<AppState>) -> Json<Vec<JobSummary>> {
+    Json(state.job_queue.list())
+}
+
+fn job_queue_state(state: &AppState) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "status": if state.job_queue.is_paused() { "paused" } else { "running" },
+        "queued": state.job_queue.pending_len(),
+    }))
+}
+
+pub async fn pause_jobs_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    state.job_queue.pause();
+    info!("Job workers paused");
+    job_queue_state(&state)
+}
+
+pub async fn resume_jobs_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    state.job_queue.resume();
+    info!("Job workers resumed");
+    job_queue_state(&state)
 }
 
 #[derive(Deserialize)]
@@ -384,8 +1288,10 @@ pub async fn delete_chapter_handler(
     let chapter_key = logic::get_cache_key(&req.base_url, Some(language));
     let delete_data = req.delete_data.unwrap_or(true);
 
-    // If a job is currently tracked, drop the progress entry.
-    // This doesn't cancel the underlying task, but keeps status checks consistent.
+    // Drop queued work for the chapter. If a job is already running, drop its progress entry;
+    // this doesn't cancel the underlying task, but keeps status checks consistent.
+    state.job_queue.remove_pending(&chapter_key);
+    state.delete_chapter_job(&chapter_key);
     {
         let mut locked = state.active_chapter_jobs.write().expect("lock poisoned");
         locked.remove(&chapter_key);
@@ -403,21 +1309,310 @@ pub async fn delete_chapter_handler(
     }))
 }
 
+/// The cache key given directly, or derived from a page URL and language.
+fn resolve_cache_key(
+    cache_key: Option<String>,
+    url: Option<String>,
+    language: Option<OcrLanguage>,
+) -> Result<String, (StatusCode, String)> {
+    match (cache_key, url) {
+        (Some(cache_key), _) => Ok(cache_key),
+        (None, Some(url)) => Ok(logic::get_cache_key(
+            &url,
+            Some(language.unwrap_or_default()),
+        )),
+        (None, None) => Err((
+            StatusCode::BAD_REQUEST,
+            "cache_key or url is required".to_string(),
+        )),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CacheEntryPatch {
+    /// Either a cache key, or a page URL plus language to derive it from.
+    pub cache_key: Option<String>,
+    pub url: Option<String>,
+    pub language: Option<OcrLanguage>,
+    /// The full corrected result list; boxes may be edited, merged or split freely.
+    pub data: Option<Vec<logic::OcrResult>>,
+    pub context: Option<String>,
+    /// `true` drops the user-edited mark instead of applying `data`.
+    #[serde(default)]
+    pub revert: bool,
+}
+
+/// Applies a manual OCR correction to a cached page.
+pub async fn patch_cache_entry_handler(
+    State(state): State<AppState>,
+    Json(req): Json<CacheEntryPatch>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let cache_key = resolve_cache_key(req.cache_key, req.url, req.language)?;
+
+    if req.revert {
+        let reverted = state.clear_user_edited(&cache_key);
+        return Ok(Json(serde_json::json!({
+            "status": if reverted { "reverted" } else { "not_found" },
+            "cache_key": cache_key,
+        })));
+    }
+
+    let Some(data) = req.data else {
+        return Err((StatusCode::BAD_REQUEST, "data is required".to_string()));
+    };
+    let entry = CacheEntry {
+        context: req
+            .context
+            .unwrap_or_else(|| "Manual Correction".to_string()),
+        data,
+    };
+    if !state.set_user_edited_entry(&cache_key, &entry).await {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to store correction".to_string(),
+        ));
+    }
+
+    info!("Stored manual OCR correction for cache_key={cache_key}");
+    Ok(Json(serde_json::json!({
+        "status": "updated",
+        "cache_key": cache_key,
+        "user_edited": true,
+        "lines": entry.data.len(),
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub cache_key: Option<String>,
+    pub url: Option<String>,
+    pub language: Option<OcrLanguage>,
+}
+
+#[derive(Deserialize)]
+pub struct HistoryRevertRequest {
+    pub cache_key: Option<String>,
+    pub url: Option<String>,
+    pub language: Option<OcrLanguage>,
+    /// `id` of the version to restore, from `GET /cache/history`.
+    pub version: i64,
+}
+
+/// Lists the earlier versions of a cached page, newest first.
+pub async fn cache_history_handler(
+    State(state): State<AppState>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let cache_key = resolve_cache_key(query.cache_key, query.url, query.language)?;
+    let versions = state.list_history(&cache_key);
+    Ok(Json(serde_json::json!({
+        "cache_key": cache_key,
+        "versions": versions,
+    })))
+}
+
+/// Restores an earlier version of a cached page; the replaced one joins the history.
+pub async fn revert_cache_history_handler(
+    State(state): State<AppState>,
+    Json(req): Json<HistoryRevertRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let cache_key = resolve_cache_key(req.cache_key, req.url, req.language)?;
+    if !state.revert_to_version(&cache_key, req.version).await {
+        return Err((
+            StatusCode::NOT_FOUND,
+            format!("No version {} for {cache_key}", req.version),
+        ));
+    }
+    info!("Reverted cache_key={cache_key} to version {}", req.version);
+    Ok(Json(serde_json::json!({
+        "status": "reverted",
+        "cache_key": cache_key,
+        "version": req.version,
+    })))
+}
+
+pub async fn list_postprocess_rules_handler(
+    State(state): State<AppState>,
+) -> Json<Vec<ReplacementRule>> {
+    Json(state.list_postprocess_rules())
+}
+
+pub async fn create_postprocess_rule_handler(
+    State(state): State<AppState>,
+    Json(rule): Json<RuleInput>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    rule.validate()
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid pattern: {err}")))?;
+    let Some(id) = state.insert_postprocess_rule(&rule) else {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to store rule".to_string(),
+        ));
+    };
+    info!("Added post-processing rule {id}: {}", rule.pattern);
+    Ok(Json(serde_json::json!({ "status": "created", "id": id })))
+}
+
+pub async fn update_postprocess_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(rule): Json<RuleInput>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    rule.validate()
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid pattern: {err}")))?;
+    if !state.update_postprocess_rule(id, &rule) {
+        return Err((StatusCode::NOT_FOUND, format!("No rule with id {id}")));
+    }
+    Ok(Json(serde_json::json!({ "status": "updated", "id": id })))
+}
+
+pub async fn delete_postprocess_rule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    if !state.delete_postprocess_rule(id) {
+        return Err((StatusCode::NOT_FOUND, format!("No rule with id {id}")));
+    }
+    Ok(Json(serde_json::json!({ "status": "deleted", "id": id })))
+}
+
 pub async fn purge_cache_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
     state.clear_cache();
     Json(serde_json::json!({ "status": "cleared" }))
 }
 
+/// Vacuums and analyzes the cache database; see `AppState::run_maintenance`.
+pub async fn cache_maintenance_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let report = tokio::task::spawn_blocking(move || state.run_maintenance())
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .map_err(|err| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Cache maintenance failed: {err}"),
+            )
+        })?;
+    Ok(Json(serde_json::json!(report)))
+}
+
+#[cfg(feature = "golden")]
+#[derive(Deserialize)]
+pub struct GoldenQuery {
+    /// Record the replayed pages as the new golden files.
+    pub update: Option<String>,
+}
+
+/// Replays the golden fixtures, see `golden`. Responds 409 when a page regressed.
+#[cfg(feature = "golden")]
+pub async fn golden_handler(
+    Query(params): Query<GoldenQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    use crate::golden::{self, CaseStatus};
+
+    let update = is_flag_set(params.update.as_deref());
+    let dir = golden::fixtures_dir();
+    let cases = tokio::task::spawn_blocking(move || golden::run_dir(&dir, update))
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    let failed = cases
+        .iter()
+        .filter(|case| case.status == CaseStatus::Failed)
+        .count();
+    let status = if failed == 0 {
+        StatusCode::OK
+    } else {
+        StatusCode::CONFLICT
+    };
+    let body = serde_json::json!({ "total": cases.len(), "failed": failed, "cases": cases });
+    Ok((status, Json(body)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub compression: ExportCompression,
+    pub chapter_prefix: Option<String>,
+    pub manga_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    pub chapter_prefix: Option<String>,
+    pub manga_id: Option<String>,
+    #[serde(default)]
+    pub strategy: ImportStrategy,
+}
+
+/// Streams the cache as a download, without buffering it in memory.
 pub async fn export_cache_handler(
     State(state): State<AppState>,
-) -> Json<std::collections::HashMap<String, CacheEntry>> {
-    Json(state.export_cache())
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let filter = ExportFilter {
+        chapter_prefix: query.chapter_prefix,
+        manga_id: query.manga_id,
+    };
+    let receiver = export::spawn_export(state, query.format, query.compression, filter);
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    });
+
+    let (content_type, file_name) = match query.compression {
+        ExportCompression::None => (
+            query.format.content_type(),
+            format!("ocr-cache.{}", query.format.file_extension()),
+        ),
+        ExportCompression::Gzip => (
+            "application/gzip",
+            format!("ocr-cache.{}.gz", query.format.file_extension()),
+        ),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            ),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response()
 }
 
+/// Imports an export in any supported shape (NDJSON or JSON object, optionally gzipped).
+/// `chapter_prefix` / `manga_id` import only the matching part of the upload, and
+/// `strategy` decides what happens to keys that already exist.
 pub async fn import_cache_handler(
     State(state): State<AppState>,
-    Json(data): Json<std::collections::HashMap<String, CacheEntry>>,
-) -> Json<serde_json::Value> {
-    let added = state.import_cache(data);
-    Json(serde_json::json!({ "message": "Import successful", "added": added }))
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let filter = ExportFilter {
+        chapter_prefix: query.chapter_prefix,
+        manga_id: query.manga_id,
+    };
+    let mut records = export::parse_import(&body)
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid import: {err}")))?;
+    let total = records.len();
+    records.retain(|record| filter.matches(record));
+    let skipped_by_filter = total - records.len();
+
+    let report = state.import_cache(records, query.strategy);
+    Ok(Json(serde_json::json!({
+        "message": "Import successful",
+        "strategy": query.strategy,
+        "added": report.added,
+        "overwritten": report.overwritten,
+        "skipped_existing": report.skipped_existing,
+        "skipped_older": report.skipped_older,
+        "skipped_edited": report.skipped_edited,
+        "failed": report.failed,
+        "skipped_by_filter": skipped_by_filter,
+    })))
 }
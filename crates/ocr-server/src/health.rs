@@ -0,0 +1,133 @@
+//! Per-backend success/failure tracking behind `/ready` and the status endpoint.
+//!
+//! A backend is degraded once it has failed `MANATAN_OCR_DEGRADED_AFTER` times in a row
+//! (default 5); its next success clears the streak. [`HealthProbe`] reports the same
+//! to an embedding server, e.g. for the gateway's `/healthz`.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, atomic::Ordering},
+};
+
+use serde::Serialize;
+
+use crate::{
+    backend::OcrBackend,
+    state::{AppState, now_unix},
+};
+
+const DEGRADED_AFTER_ENV: &str = "MANATAN_OCR_DEGRADED_AFTER";
+const DEFAULT_DEGRADED_AFTER: u32 = 5;
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BackendStatus {
+    pub consecutive_failures: u32,
+    pub degraded: bool,
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+pub struct BackendHealth {
+    degraded_after: u32,
+    backends: Mutex<HashMap<OcrBackend, BackendStatus>>,
+}
+
+impl BackendHealth {
+    pub fn from_env() -> Self {
+        let degraded_after = manatan_config::var(DEGRADED_AFTER_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u32>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_DEGRADED_AFTER);
+        Self {
+            degraded_after,
+            backends: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_success(&self, backend: OcrBackend) {
+        let mut backends = self.backends.lock().expect("lock poisoned");
+        let status = backends.entry(backend).or_default();
+        status.consecutive_failures = 0;
+        status.degraded = false;
+        status.last_success_at = Some(now_unix());
+    }
+
+    pub fn record_failure(&self, backend: OcrBackend, error: &anyhow::Error) {
+        let mut backends = self.backends.lock().expect("lock poisoned");
+        let status = backends.entry(backend).or_default();
+        status.consecutive_failures += 1;
+        status.degraded = status.consecutive_failures >= self.degraded_after;
+        status.last_failure_at = Some(now_unix());
+        status.last_error = Some(error.to_string());
+        if status.consecutive_failures == self.degraded_after {
+            tracing::warn!(
+                "[Health] {} degraded after {} consecutive failures: {error}",
+                backend.as_str(),
+                status.consecutive_failures
+            );
+        }
+    }
+
+    /// Status of every backend used since startup, keyed by backend name.
+    pub fn snapshot(&self) -> HashMap<&'static str, BackendStatus> {
+        self.backends
+            .lock()
+            .expect("lock poisoned")
+            .iter()
+            .map(|(backend, status)| (backend.as_str(), status.clone()))
+            .collect()
+    }
+
+    pub fn any_degraded(&self) -> bool {
+        self.backends
+            .lock()
+            .expect("lock poisoned")
+            .values()
+            .any(|status| status.degraded)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OcrHealth {
+    /// `ok`, `degraded` while a backend keeps failing, or `unavailable`.
+    pub status: &'static str,
+    pub database: bool,
+    pub initialized: bool,
+    pub shutting_down: bool,
+    /// Backends used since startup; reachability as seen by recent OCR calls.
+    pub backends: HashMap<&'static str, BackendStatus>,
+}
+
+#[derive(Clone)]
+pub struct HealthProbe {
+    state: AppState,
+}
+
+impl HealthProbe {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub fn check(&self) -> OcrHealth {
+        let state = &self.state;
+        let database = state.db_reachable();
+        let initialized = state.initialized.load(Ordering::Relaxed);
+        let shutting_down = state.shutting_down.load(Ordering::SeqCst);
+        let status = if !database || !initialized || shutting_down {
+            "unavailable"
+        } else if state.backend_health.any_degraded() {
+            "degraded"
+        } else {
+            "ok"
+        };
+        OcrHealth {
+            status,
+            database,
+            initialized,
+            shutting_down,
+            backends: state.backend_health.snapshot(),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+//! Earlier versions of cached pages.
+//!
+//! Whenever a page's results are replaced, by reprocessing or by a manual correction,
+//! the previous version moves to `ocr_history`. `MANATAN_OCR_HISTORY_LIMIT` versions are
+//! kept per page (default 5, `0` keeps none). `GET /cache/history` lists them and
+//! `POST /cache/history/revert` restores one.
+
+use std::sync::LazyLock;
+
+use serde::Serialize;
+
+use crate::logic::OcrResult;
+
+const HISTORY_LIMIT_ENV: &str = "MANATAN_OCR_HISTORY_LIMIT";
+const DEFAULT_HISTORY_LIMIT: usize = 5;
+
+static HISTORY_LIMIT: LazyLock<usize> = LazyLock::new(|| {
+    manatan_config::var(HISTORY_LIMIT_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+});
+
+/// Versions kept per page.
+pub fn history_limit() -> usize {
+    *HISTORY_LIMIT
+}
+
+/// A replaced version of a page.
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryVersion {
+    pub id: i64,
+    pub context: String,
+    pub user_edited: bool,
+    /// When this version was produced.
+    pub processed_at: i64,
+    /// When it was replaced.
+    pub archived_at: i64,
+    pub data: Vec<OcrResult>,
+}
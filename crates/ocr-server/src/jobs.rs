@@ -1,98 +1,530 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicUsize, Ordering},
+use std::{
+    collections::HashMap,
+    panic::AssertUnwindSafe,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
 };
 
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Notify, watch};
+use tracing::Instrument;
 
 use crate::{
+    auth::SuwayomiAuth,
+    backend::OcrBackend,
+    concurrency,
     language::OcrLanguage,
-    state::{AppState, JobProgress},
+    merge::{MergeConfig, MergeOverrides},
+    proxy::ProxyMode,
+    request_id,
+    retry::RetryPolicy,
+    shutdown::PageGuard,
+    state::{AppState, JobProgress, now_unix},
 };
 
-pub async fn run_chapter_job(
-    state: AppState,
-    base_url: String,
-    pages: Vec<String>,
-    user: Option<String>,
-    pass: Option<String>,
-    context: String,
-    add_space_on_merge: Option<bool>,
-    language: OcrLanguage,
-) {
-    let total = pages.len();
-    let job_id = crate::logic::get_cache_key(&base_url, Some(language));
+const JOB_WORKERS_ENV: &str = "MANATAN_OCR_JOB_WORKERS";
+const JOB_QUEUE_LIMIT_ENV: &str = "MANATAN_OCR_JOB_QUEUE_LIMIT";
+const DEFAULT_JOB_QUEUE_LIMIT: usize = 64;
+
+/// Scheduling priority of a chapter job. Higher priorities are dequeued first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    /// Chapters the reader is likely to open next.
+    Prefetch,
+    /// The chapter the reader is currently looking at.
+    #[default]
+    Current,
+}
+
+/// Everything needed to OCR one chapter.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChapterJob {
+    pub base_url: String,
+    pub pages: Vec<String>,
+    /// Kept in memory only, so a resumed job goes without credentials.
+    #[serde(skip_serializing, default)]
+    pub auth: SuwayomiAuth,
+    pub context: String,
+    pub add_space_on_merge: Option<bool>,
+    pub language: OcrLanguage,
+    pub backend: OcrBackend,
+    #[serde(default)]
+    pub proxy: Option<ProxyMode>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub merge: MergeOverrides,
+    /// ID of the request that queued the job, attached to its log lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// Progress notifications broadcast while chapter jobs run.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobEvent {
+    Page {
+        chapter_key: String,
+        url: String,
+        current: usize,
+        processed: usize,
+        total: usize,
+    },
+    PageFailed {
+        chapter_key: String,
+        url: String,
+        error: String,
+        attempts: u32,
+    },
+    Completed {
+        chapter_key: String,
+        processed: usize,
+        total: usize,
+    },
+}
+
+impl JobEvent {
+    pub fn chapter_key(&self) -> &str {
+        match self {
+            JobEvent::Page { chapter_key, .. }
+            | JobEvent::PageFailed { chapter_key, .. }
+            | JobEvent::Completed { chapter_key, .. } => chapter_key,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobEvent::Page { .. } => "page",
+            JobEvent::PageFailed { .. } => "page_failed",
+            JobEvent::Completed { .. } => "completed",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+}
+
+/// Public view of a queued or running job, as returned by `GET /jobs`.
+#[derive(Clone, Debug, Serialize)]
+pub struct JobSummary {
+    pub id: u64,
+    pub chapter_key: String,
+    pub base_url: String,
+    pub context: String,
+    pub priority: JobPriority,
+    pub status: JobStatus,
+    pub pages: usize,
+    pub queued_at: i64,
+    pub started_at: Option<i64>,
+}
+
+struct QueuedJob {
+    summary: JobSummary,
+    job: ChapterJob,
+}
+
+#[derive(Default)]
+struct QueueInner {
+    pending: Vec<QueuedJob>,
+    running: HashMap<u64, JobSummary>,
+}
+
+pub enum EnqueueOutcome {
+    Queued(u64),
+    AlreadyQueued(u64),
+    Full,
+}
+
+/// Bounded priority queue feeding the chapter job workers.
+pub struct JobQueue {
+    inner: Mutex<QueueInner>,
+    notify: Notify,
+    next_id: AtomicU64,
+    capacity: usize,
+    paused: watch::Sender<bool>,
+}
+
+impl JobQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Mutex::new(QueueInner::default()),
+            notify: Notify::new(),
+            next_id: AtomicU64::new(1),
+            capacity,
+            paused: watch::Sender::new(false),
+        }
+    }
 
-    {
+    pub fn from_env() -> Self {
+        let capacity = manatan_config::var(JOB_QUEUE_LIMIT_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<usize>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(DEFAULT_JOB_QUEUE_LIMIT);
+        Self::new(capacity)
+    }
+
+    /// Queues a chapter. Re-queuing a chapter that is already waiting keeps its place but
+    /// raises its priority if the new request is more urgent.
+    pub fn enqueue(
+        &self,
+        chapter_key: String,
+        priority: JobPriority,
+        job: ChapterJob,
+    ) -> EnqueueOutcome {
+        let mut inner = self.inner.lock().expect("lock poisoned");
+        if let Some(existing) = inner
+            .pending
+            .iter_mut()
+            .find(|queued| queued.summary.chapter_key == chapter_key)
+        {
+            existing.summary.priority = existing.summary.priority.max(priority);
+            return EnqueueOutcome::AlreadyQueued(existing.summary.id);
+        }
+
+        if inner.pending.len() >= self.capacity {
+            return EnqueueOutcome::Full;
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        inner.pending.push(QueuedJob {
+            summary: JobSummary {
+                id,
+                chapter_key,
+                base_url: job.base_url.clone(),
+                context: job.context.clone(),
+                priority,
+                status: JobStatus::Queued,
+                pages: job.pages.len(),
+                queued_at: now_unix(),
+                started_at: None,
+            },
+            job,
+        });
+        drop(inner);
+
+        self.notify.notify_one();
+        EnqueueOutcome::Queued(id)
+    }
+
+    pub fn is_queued(&self, chapter_key: &str) -> bool {
+        self.inner
+            .lock()
+            .expect("lock poisoned")
+            .pending
+            .iter()
+            .any(|queued| queued.summary.chapter_key == chapter_key)
+    }
+
+    /// Drops a chapter that has not started yet. Returns whether anything was removed.
+    pub fn remove_pending(&self, chapter_key: &str) -> bool {
+        let mut inner = self.inner.lock().expect("lock poisoned");
+        let before = inner.pending.len();
+        inner
+            .pending
+            .retain(|queued| queued.summary.chapter_key != chapter_key);
+        inner.pending.len() != before
+    }
+
+    /// Running jobs first, then pending jobs in the order they will be picked up.
+    pub fn list(&self) -> Vec<JobSummary> {
+        let inner = self.inner.lock().expect("lock poisoned");
+        let mut running: Vec<JobSummary> = inner.running.values().cloned().collect();
+        running.sort_by_key(|summary| summary.id);
+
+        let mut pending: Vec<JobSummary> = inner
+            .pending
+            .iter()
+            .map(|queued| queued.summary.clone())
+            .collect();
+        pending.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.id.cmp(&b.id)));
+
+        running.extend(pending);
+        running
+    }
+
+    fn take_next(&self) -> Option<(u64, ChapterJob)> {
+        let mut inner = self.inner.lock().expect("lock poisoned");
+        let index = inner
+            .pending
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| {
+                a.summary
+                    .priority
+                    .cmp(&b.summary.priority)
+                    .then(b.summary.id.cmp(&a.summary.id))
+            })
+            .map(|(index, _)| index)?;
+
+        let QueuedJob { mut summary, job } = inner.pending.remove(index);
+        let id = summary.id;
+        summary.status = JobStatus::Running;
+        summary.started_at = Some(now_unix());
+        inner.running.insert(id, summary);
+        Some((id, job))
+    }
+
+    /// Stops workers from starting new jobs or pages. Queued work is kept.
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.inner.lock().expect("lock poisoned").pending.len()
+    }
+
+    pub async fn wait_while_paused(&self) {
+        let mut paused = self.paused.subscribe();
+        let _ = paused.wait_for(|paused| !*paused).await;
+    }
+
+    async fn next(&self) -> (u64, ChapterJob) {
+        loop {
+            self.wait_while_paused().await;
+
+            // Register interest before checking, so an enqueue in between isn't missed.
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            if let Some(next) = self.take_next() {
+                return next;
+            }
+            notified.await;
+        }
+    }
+
+    fn finish(&self, id: u64) {
+        self.inner
+            .lock()
+            .expect("lock poisoned")
+            .running
+            .remove(&id);
+    }
+}
+
+fn worker_count() -> usize {
+    manatan_config::var(JOB_WORKERS_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(if cfg!(target_os = "android") { 1 } else { 2 })
+}
+
+/// Re-queues chapter jobs that were interrupted by a shutdown or crash.
+/// Pages cached before the interruption are skipped when the job runs again.
+pub fn resume_persisted_jobs(state: &AppState) {
+    for (chapter_key, priority, job) in state.load_chapter_jobs() {
+        // Drops the credentials older versions stored with the job
+        state.save_chapter_job(&chapter_key, priority, &job);
+        let done = state.count_chapter_cache(&chapter_key);
+        let total = job.pages.len();
+        match state.job_queue.enqueue(chapter_key.clone(), priority, job) {
+            EnqueueOutcome::Queued(_) | EnqueueOutcome::AlreadyQueued(_) => {
+                tracing::info!("[Job] Resuming {chapter_key} ({done}/{total} pages done)");
+            }
+            EnqueueOutcome::Full => {
+                tracing::warn!("[Job] Queue full, not resuming {chapter_key}");
+            }
+        }
+    }
+}
+
+/// Marks a dequeued job as finished when dropped.
+struct FinishOnDrop<'a>(&'a AppState, u64);
+
+impl Drop for FinishOnDrop<'_> {
+    fn drop(&mut self) {
+        self.0.job_queue.finish(self.1);
+    }
+}
+
+/// Undoes a running job's bookkeeping when dropped, so a panicking job isn't left
+/// counted as active.
+struct RunningJob<'a> {
+    state: &'a AppState,
+    job_id: String,
+}
+
+impl<'a> RunningJob<'a> {
+    fn start(state: &'a AppState, job_id: String, total: usize) -> Self {
         state
             .active_chapter_jobs
             .write()
             .expect("lock poisoned")
             .insert(job_id.clone(), JobProgress { current: 0, total });
+        state.active_jobs.fetch_add(1, Ordering::Relaxed);
+        Self { state, job_id }
+    }
+}
+
+impl Drop for RunningJob<'_> {
+    fn drop(&mut self) {
+        self.state.active_jobs.fetch_sub(1, Ordering::Relaxed);
+        self.state
+            .active_chapter_jobs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.job_id);
+    }
+}
+
+/// Spawns the workers that drain `state.job_queue`, supervised by `manatan_tasks` so a
+/// panicking job doesn't take its worker down for good.
+pub fn spawn_workers(state: &AppState) {
+    let workers = worker_count();
+    tracing::info!("[Job] Starting {workers} chapter job worker(s)");
+
+    for worker in 0..workers {
+        let state = state.clone();
+        manatan_tasks::spawn(format!("ocr.chapter-worker-{worker}"), move || {
+            let state = state.clone();
+            async move {
+                loop {
+                    let (id, job) = state.job_queue.next().await;
+                    // Frees the queue slot even if the job panics
+                    let _finish = FinishOnDrop(&state, id);
+                    let span = request_id::job_span("chapter", job.request_id.as_deref());
+                    let job_id = crate::logic::get_cache_key(&job.base_url, Some(job.language));
+                    let run = AssertUnwindSafe(run_chapter_job(state.clone(), job)).catch_unwind();
+                    if concurrency::in_background(run).instrument(span).await.is_err() {
+                        // Replaying it on every startup would likely panic again
+                        tracing::error!("[Job {job_id}] Panicked, dropping the job");
+                        state.delete_chapter_job(&job_id);
+                    }
+                }
+            }
+        });
     }
+}
 
-    state.active_jobs.fetch_add(1, Ordering::Relaxed);
+pub async fn run_chapter_job(state: AppState, job: ChapterJob) {
+    let ChapterJob {
+        base_url,
+        pages,
+        auth,
+        context,
+        add_space_on_merge,
+        language,
+        backend,
+        proxy,
+        retry,
+        merge,
+        request_id: _,
+    } = job;
+    let total = pages.len();
+    let job_id = crate::logic::get_cache_key(&base_url, Some(language));
+    // None defaults to Smart Detection for space merging
+    let merge_config = MergeConfig {
+        add_space_on_merge,
+        ..MergeConfig::default()
+    }
+    .with_overrides(&merge);
+
+    let running = RunningJob::start(&state, job_id.clone(), total);
     tracing::info!("[Job] Started for {} ({} pages)", context, total);
 
     let completed_counter = Arc::new(AtomicUsize::new(0));
     let processed_counter = Arc::new(AtomicUsize::new(0));
     let stream = futures::stream::iter(pages.into_iter());
 
-    // Change from 6 to 2 or 3 for Android stability
-    let concurrency_limit = if cfg!(target_os = "android") { 2 } else { 6 };
+    let concurrency_limit = state.concurrency.page();
 
     stream
         .for_each_concurrent(concurrency_limit, |url| {
             let state = state.clone();
             let job_id = job_id.clone();
-            let user = user.clone();
-            let pass = pass.clone();
+            let auth = auth.clone();
+            let proxy = proxy.clone();
             let context = context.clone();
             let completed_counter = completed_counter.clone();
             let processed_counter = processed_counter.clone();
+            let merge_config = &merge_config;
 
             let page_id = url.split('/').next_back().unwrap_or("unknown").to_string();
 
             async move {
-                let cache_key = crate::logic::get_cache_key(&url, Some(language));
-                let exists = state.has_cache_entry(&cache_key);
+                state.job_queue.wait_while_paused().await;
+
+                let cache_key =
+                    crate::logic::page_cache_key(&url, language, backend, merge_config);
+                let exists = state.has_cache_entry(&cache_key)
+                    || state.claim_unnamespaced_entry(&cache_key).await.is_some();
                 if exists {
-                    state.insert_chapter_cache(&job_id, &cache_key);
+                    state.insert_chapter_cache(&job_id, &cache_key).await;
                     processed_counter.fetch_add(1, Ordering::Relaxed);
                     tracing::info!("[Page {page_id}] Skip (Cached)");
                 } else {
                     tracing::info!("[Page {page_id}] Starting fetch_and_process (Async)...");
+                    let _in_flight = PageGuard::new(&state);
 
-                    // None defaults to Smart Detection for space merging
                     match crate::logic::fetch_and_process(
                         &url,
-                        user,
-                        pass,
-                        add_space_on_merge,
+                        &auth,
+                        merge_config,
                         language,
+                        backend,
+                        proxy.as_ref(),
+                        &retry,
+                        Some(&state),
+                        false,
                     )
                     .await
                     {
                         Ok(res) => {
-                            state.insert_cache_entry(
-                                &cache_key,
-                                &crate::state::CacheEntry {
-                                    context: context.clone(),
-                                    data: res,
-                                },
-                            );
-                            state.insert_chapter_cache(&job_id, &cache_key);
+                            state
+                                .insert_cache_entry(
+                                    &cache_key,
+                                    &crate::state::CacheEntry {
+                                        context: context.clone(),
+                                        data: res,
+                                    },
+                                )
+                                .await;
+                            state.insert_chapter_cache(&job_id, &cache_key).await;
                             processed_counter.fetch_add(1, Ordering::Relaxed);
                         }
                         Err(err) => {
                             tracing::warn!("[Page {page_id}] Failed: {err:?}");
+                            state.publish_job_event(JobEvent::PageFailed {
+                                chapter_key: job_id.clone(),
+                                url: url.clone(),
+                                error: err.source.to_string(),
+                                attempts: err.attempts,
+                            });
                         }
                     }
                 }
 
                 let current = completed_counter.fetch_add(1, Ordering::Relaxed) + 1;
                 let processed_count = processed_counter.load(Ordering::Relaxed);
-                state.set_chapter_progress(&job_id, total, processed_count);
+                state
+                    .set_chapter_progress(&job_id, total, processed_count)
+                    .await;
+                state.publish_job_event(JobEvent::Page {
+                    chapter_key: job_id.clone(),
+                    url,
+                    current,
+                    processed: processed_count,
+                    total,
+                });
 
                 {
                     if let Some(prog) = state
@@ -110,17 +542,17 @@ pub async fn run_chapter_job(
 
     tracing::info!("[Job {job_id}] Finalize...");
     let processed_count = processed_counter.load(Ordering::Relaxed);
-    state.set_chapter_progress(&job_id, total, processed_count);
-
-    state.active_jobs.fetch_sub(1, Ordering::Relaxed);
+    state
+        .set_chapter_progress(&job_id, total, processed_count)
+        .await;
 
-    {
-        state
-            .active_chapter_jobs
-            .write()
-            .expect("lock poisoned")
-            .remove(&job_id);
-    }
+    drop(running);
+    state.delete_chapter_job(&job_id);
 
+    state.publish_job_event(JobEvent::Completed {
+        chapter_key: job_id.clone(),
+        processed: processed_count,
+        total,
+    });
     tracing::info!("[Job {job_id}] Finished for {}", context);
 }
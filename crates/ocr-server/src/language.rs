@@ -95,6 +95,54 @@ impl OcrLanguage {
         }
     }
 
+    /// BCP-47 tag used by platform OCR engines.
+    pub fn bcp47_tag(&self) -> &'static str {
+        match self {
+            OcrLanguage::Japanese => "ja-JP",
+            OcrLanguage::English => "en-US",
+            OcrLanguage::Chinese => "zh-Hans",
+            OcrLanguage::Korean => "ko-KR",
+            OcrLanguage::Arabic => "ar",
+            OcrLanguage::Spanish => "es",
+            OcrLanguage::French => "fr",
+            OcrLanguage::German => "de",
+            OcrLanguage::Portuguese => "pt",
+            OcrLanguage::Bulgarian => "bg",
+            OcrLanguage::Czech => "cs",
+            OcrLanguage::Danish => "da",
+            OcrLanguage::Greek => "el",
+            OcrLanguage::Estonian => "et",
+            OcrLanguage::Persian => "fa",
+            OcrLanguage::Finnish => "fi",
+            OcrLanguage::Hebrew => "he",
+            OcrLanguage::Hindi => "hi",
+            OcrLanguage::Hungarian => "hu",
+            OcrLanguage::Indonesian => "id",
+            OcrLanguage::Italian => "it",
+            OcrLanguage::Latin => "la",
+            OcrLanguage::Lao => "lo",
+            OcrLanguage::Latvian => "lv",
+            OcrLanguage::Georgian => "ka",
+            OcrLanguage::Kannada => "kn",
+            OcrLanguage::Khmer => "km",
+            OcrLanguage::Mongolian => "mn",
+            OcrLanguage::Maltese => "mt",
+            OcrLanguage::Dutch => "nl",
+            OcrLanguage::Norwegian => "nb",
+            OcrLanguage::Polish => "pl",
+            OcrLanguage::Romanian => "ro",
+            OcrLanguage::Russian => "ru",
+            OcrLanguage::Swedish => "sv",
+            OcrLanguage::Thai => "th",
+            OcrLanguage::Tagalog => "tl",
+            OcrLanguage::Turkish => "tr",
+            OcrLanguage::Ukrainian => "uk",
+            OcrLanguage::Vietnamese => "vi",
+            OcrLanguage::Welsh => "cy",
+            OcrLanguage::Cantonese => "yue-Hant",
+        }
+    }
+
     pub fn prefers_vertical(&self) -> bool {
         matches!(
             self,
@@ -1,29 +1,107 @@
+pub mod archive;
+pub mod auth;
+pub mod backend;
+pub mod bubble;
+pub mod cache_ttl;
+pub mod concurrency;
+pub mod config;
+pub mod crop;
+pub mod eviction;
+pub mod export;
+pub mod golden;
 pub mod handlers;
+pub mod health;
+pub mod history;
 pub mod jobs;
 pub mod language;
+pub mod local;
 pub mod logic;
+pub mod manga;
 pub mod merge;
+pub mod namespace;
+pub mod normalize;
+pub mod postprocess;
+pub mod prefetch;
+pub mod proxy;
+pub mod request_id;
+pub mod retry;
+pub mod shutdown;
+pub mod single_flight;
 pub mod state;
+pub mod structure;
+pub mod translate;
 
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    sync::{Arc, atomic::Ordering},
+};
 
 use axum::{
     Router,
     extract::DefaultBodyLimit,
-    routing::{get, post},
+    middleware,
+    routing::{get, patch, post, put},
 };
+use config::OcrServerConfig;
+use shutdown::ShutdownHandle;
 use state::AppState;
+use tower_http::compression::{
+    CompressionLayer,
+    predicate::{DefaultPredicate, NotForContentType, Predicate},
+};
 
 /// Creates the OCR Router.
 pub fn create_router(cache_dir: PathBuf) -> Router {
-    let state = AppState::new(cache_dir);
+    create_router_with_shutdown(cache_dir).0
+}
 
-    // Spawn the job worker if you want strict concurrency,
-    // or we just spawn tasks per request (handled in handlers).
+/// Creates the OCR Router, along with the handle that drains its jobs on shutdown.
+pub fn create_router_with_shutdown(cache_dir: PathBuf) -> (Router, ShutdownHandle) {
+    let (router, handles) = create_router_with_handles(cache_dir);
+    (router, handles.shutdown)
+}
+
+/// What an embedding server keeps of the OCR server besides its router.
+pub struct Handles {
+    pub shutdown: ShutdownHandle,
+    pub health: health::HealthProbe,
+}
 
-    Router::new()
+/// Resumes the persisted jobs and loads the local backends, then marks the server
+/// initialized, so `/ready` answers 503 until it can actually take work.
+fn spawn_startup(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        jobs::resume_persisted_jobs(&state);
+        manga::resume_persisted_manga_jobs(&state);
+        backend::warm_up().await;
+        state.initialized.store(true, Ordering::Relaxed);
+        tracing::info!("[OCR] Ready");
+    });
+}
+
+pub fn create_router_with_handles(cache_dir: PathBuf) -> (Router, Handles) {
+    let state = AppState::new(cache_dir);
+    jobs::spawn_workers(&state);
+    eviction::spawn_eviction_task(&state);
+    local::spawn_watcher(&state);
+    spawn_startup(&state);
+    let handles = Handles {
+        shutdown: ShutdownHandle::new(state.clone()),
+        health: health::HealthProbe::new(state.clone()),
+    };
+    let config = Arc::new(OcrServerConfig::from_env());
+
+    let routes = Router::new()
         .route("/", get(handlers::status_handler))
+        .route("/health", get(handlers::health_handler))
+        .route("/ready", get(handlers::ready_handler))
         .route("/ocr", get(handlers::ocr_handler))
+        .route("/ocr/local", post(handlers::ocr_local_handler))
+        .route("/ocr/archive", post(handlers::ocr_archive_handler))
+        .route("/ocr/epub", post(handlers::ocr_epub_handler))
+        .route("/crop", get(handlers::crop_handler))
+        .route("/merge", post(handlers::merge_handler))
         .route(
             "/is-chapter-preprocessed",
             get(handlers::is_chapter_preprocessed_get_handler)
@@ -34,10 +112,69 @@ pub fn create_router(cache_dir: PathBuf) -> Router {
             post(handlers::is_chapters_preprocessed_handler),
         )
         .route("/preprocess-chapter", post(handlers::preprocess_handler))
+        .route(
+            "/preprocess/batch",
+            post(handlers::preprocess_batch_handler),
+        )
+        .route(
+            "/preprocess/manga",
+            get(handlers::manga_status_handler).post(handlers::preprocess_manga_handler),
+        )
+        .route(
+            "/preprocess/events",
+            get(handlers::preprocess_events_handler),
+        )
+        .route("/jobs", get(handlers::list_jobs_handler))
+        .route("/jobs/pause", post(handlers::pause_jobs_handler))
+        .route("/jobs/resume", post(handlers::resume_jobs_handler))
+        .route(
+            "/settings/concurrency",
+            get(handlers::get_concurrency_handler).put(handlers::update_concurrency_handler),
+        )
         .route("/delete-chapter", post(handlers::delete_chapter_handler))
         .route("/purge-cache", post(handlers::purge_cache_handler))
+        .route("/cache/entry", patch(handlers::patch_cache_entry_handler))
+        .route("/cache/history", get(handlers::cache_history_handler))
+        .route(
+            "/cache/history/revert",
+            post(handlers::revert_cache_history_handler),
+        )
+        .route(
+            "/cache/maintenance",
+            post(handlers::cache_maintenance_handler),
+        )
+        .route(
+            "/postprocess/rules",
+            get(handlers::list_postprocess_rules_handler)
+                .post(handlers::create_postprocess_rule_handler),
+        )
+        .route(
+            "/postprocess/rules/{id}",
+            put(handlers::update_postprocess_rule_handler)
+                .delete(handlers::delete_postprocess_rule_handler),
+        )
         .route("/export-cache", get(handlers::export_cache_handler))
-        .route("/import-cache", post(handlers::import_cache_handler))
+        .route("/import-cache", post(handlers::import_cache_handler));
+    #[cfg(feature = "golden")]
+    let routes = routes.route("/internal/golden", post(handlers::golden_handler));
+
+    // Gzipped cache exports are already compressed; SSE is excluded by the default predicate.
+    let compression = CompressionLayer::new().compress_when(
+        DefaultPredicate::new().and(NotForContentType::const_new("application/gzip")),
+    );
+
+    let router = manatan_auth::protect(routes)
+        .layer(compression)
         .layer(DefaultBodyLimit::max(50 * 1024 * 1024)) // 50MB limit for imports
-        .with_state(state)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            shutdown::reject_while_draining,
+        ))
+        .layer(middleware::from_fn_with_state(
+            config,
+            config::enforce_allowed_origins,
+        ))
+        .layer(middleware::from_fn(request_id::assign))
+        .with_state(state);
+    (router, handles)
 }
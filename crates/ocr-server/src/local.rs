@@ -0,0 +1,232 @@
+//! OCR for images in a local directory, for scans read outside Suwayomi.
+//!
+//! `MANATAN_OCR_LOCAL_DIR` names the directory; `POST /ocr/local` takes paths relative
+//! to it. With `MANATAN_OCR_LOCAL_WATCH` set, the directory is rescanned every
+//! `MANATAN_OCR_LOCAL_WATCH_SECS` seconds (default 30) and images that are not cached yet
+//! are processed in the background; one that fails is retried on the next scans, up to
+//! 3 attempts. Results are cached under `local/<relative path>`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Duration,
+};
+
+use crate::{
+    backend::OcrBackend,
+    concurrency,
+    language::OcrLanguage,
+    logic::{self, OcrResult},
+    merge::MergeConfig,
+    state::{AppState, CacheEntry},
+};
+
+const LOCAL_DIR_ENV: &str = "MANATAN_OCR_LOCAL_DIR";
+const WATCH_ENV: &str = "MANATAN_OCR_LOCAL_WATCH";
+const WATCH_SECS_ENV: &str = "MANATAN_OCR_LOCAL_WATCH_SECS";
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_WATCH_ATTEMPTS: u32 = 3;
+const LOCAL_CONTEXT: &str = "Local";
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "avif", "gif", "bmp"];
+
+/// The configured directory, if it exists.
+pub fn local_dir() -> Option<PathBuf> {
+    let dir = manatan_config::var(LOCAL_DIR_ENV).ok()?;
+    let dir = dir.trim();
+    if dir.is_empty() {
+        return None;
+    }
+    match Path::new(dir).canonicalize() {
+        Ok(dir) => Some(dir),
+        Err(err) => {
+            tracing::warn!("[Local] Ignoring {LOCAL_DIR_ENV}={dir}: {err}");
+            None
+        }
+    }
+}
+
+/// Cache key URL for a file, using `/` separators on every platform.
+pub fn local_url(relative: &Path) -> String {
+    let parts: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    format!("local/{}", parts.join("/"))
+}
+
+/// Resolves `relative` inside `root`, rejecting absolute paths and anything that
+/// escapes the directory, including through symlinks.
+pub fn resolve(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative);
+    let plain = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !plain {
+        return Err("path must be relative and stay inside the local directory".to_string());
+    }
+    let path = root
+        .join(relative)
+        .canonicalize()
+        .map_err(|err| format!("{}: {err}", relative.display()))?;
+    if !path.starts_with(root) {
+        return Err("path must be relative and stay inside the local directory".to_string());
+    }
+    Ok(path)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            IMAGE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+/// OCRs `path` (inside `root`), returning the cached result when there is one.
+pub async fn ocr_file(
+    state: &AppState,
+    root: &Path,
+    path: &Path,
+    merge_config: &MergeConfig,
+    language: OcrLanguage,
+    backend: OcrBackend,
+) -> anyhow::Result<Vec<OcrResult>> {
+    let url = local_url(path.strip_prefix(root).unwrap_or(path));
+    let cache_key = logic::page_cache_key(&url, language, backend, merge_config);
+    if let Some(entry) = state.get_cache_entry(&cache_key) {
+        return Ok(entry.data);
+    }
+
+    let image_bytes = tokio::fs::read(path).await?;
+    let data = logic::process_image_bytes(
+        &image_bytes,
+        &url,
+        merge_config,
+        language,
+        backend,
+        None,
+        Some(state),
+        false,
+    )
+    .await?;
+    state
+        .insert_cache_entry(
+            &cache_key,
+            &CacheEntry {
+                context: LOCAL_CONTEXT.to_string(),
+                data: data.clone(),
+            },
+        )
+        .await;
+    state.requests_processed.fetch_add(1, Ordering::Relaxed);
+    Ok(data)
+}
+
+fn collect_images(dir: &Path, images: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => collect_images(&path, images),
+            Ok(file_type) if file_type.is_file() && is_image(&path) => images.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Starts the directory watcher when both the directory and `MANATAN_OCR_LOCAL_WATCH`
+/// are set.
+pub fn spawn_watcher(state: &AppState) {
+    let watch = manatan_config::var(WATCH_ENV)
+        .map(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "0" | "false"))
+        .unwrap_or(false);
+    let Some(root) = local_dir().filter(|_| watch) else {
+        return;
+    };
+    let interval = manatan_config::var(WATCH_SECS_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_WATCH_INTERVAL);
+    tracing::info!(
+        "[Local] Watching {} every {}s",
+        root.display(),
+        interval.as_secs()
+    );
+
+    let state = state.clone();
+    manatan_tasks::spawn("ocr.local-watch", move || {
+        let state = state.clone();
+        let root = root.clone();
+        concurrency::in_background(async move {
+            let language = OcrLanguage::default();
+            let merge_config = MergeConfig::default();
+            // Cached files are skipped without a cache lookup on later scans
+            let mut done: HashSet<PathBuf> = HashSet::new();
+            let mut failures: HashMap<PathBuf, u32> = HashMap::new();
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                state.job_queue.wait_while_paused().await;
+
+                let scan_root = root.clone();
+                let Ok(mut images) = tokio::task::spawn_blocking(move || {
+                    let mut images = Vec::new();
+                    collect_images(&scan_root, &mut images);
+                    images
+                })
+                .await
+                else {
+                    continue;
+                };
+                images.sort();
+
+                for path in images {
+                    if state.shutting_down.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let gave_up = failures
+                        .get(&path)
+                        .is_some_and(|attempts| *attempts >= MAX_WATCH_ATTEMPTS);
+                    if gave_up || done.contains(&path) {
+                        continue;
+                    }
+                    let url = local_url(path.strip_prefix(&root).unwrap_or(&path));
+                    if state.has_cache_entry(&logic::get_cache_key(&url, Some(language))) {
+                        done.insert(path);
+                        continue;
+                    }
+                    match ocr_file(
+                        &state,
+                        &root,
+                        &path,
+                        &merge_config,
+                        language,
+                        OcrBackend::default(),
+                    )
+                    .await
+                    {
+                        Ok(_) => {
+                            tracing::info!("[Local] Processed {}", path.display());
+                            failures.remove(&path);
+                            done.insert(path);
+                        }
+                        Err(err) => {
+                            let attempts = failures.entry(path.clone()).or_default();
+                            *attempts += 1;
+                            tracing::warn!(
+                                "[Local] Failed {} (try {attempts}/{MAX_WATCH_ATTEMPTS}): {err:?}",
+                                path.display()
+                            );
+                        }
+                    }
+                }
+            }
+        })
+    });
+}
@@ -1,14 +1,29 @@
-use std::{io::Cursor, time::Duration};
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::{LazyLock, Mutex},
+};
 
 use anyhow::anyhow;
-use chrome_lens_ocr::LensClient;
-use image::{DynamicImage, GenericImageView, ImageBuffer, ImageFormat, ImageReader};
+use image::{
+    AnimationDecoder, DynamicImage, Frames, GenericImageView, ImageBuffer, ImageFormat,
+    ImageReader,
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+};
 use reqwest::header::ACCEPT;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
+    auth::SuwayomiAuth,
+    backend::{BackendSession, OcrBackend},
+    bubble, concurrency,
     language::OcrLanguage,
     merge::{self, MergeConfig},
+    namespace, normalize, postprocess,
+    proxy::{self, ProxyMode},
+    retry::{RetryError, RetryPolicy},
+    state::AppState,
 };
 
 // --- REST Structs ---
@@ -35,26 +50,22 @@ struct ProxySettingsRaw {
 }
 
 #[derive(Clone, Debug)]
-struct ProxySettings {
-    socks_proxy_enabled: bool,
-    socks_proxy_version: i32,
-    socks_proxy_host: String,
-    socks_proxy_port: String,
-    socks_proxy_username: Option<String>,
-    socks_proxy_password: Option<String>,
+pub(crate) struct ProxySettings {
+    pub(crate) socks_proxy_enabled: bool,
+    pub(crate) socks_proxy_version: i32,
+    pub(crate) socks_proxy_host: String,
+    pub(crate) socks_proxy_port: String,
+    pub(crate) socks_proxy_username: Option<String>,
+    pub(crate) socks_proxy_password: Option<String>,
 }
 
-async fn get_proxy_settings(
-    user: Option<String>,
-    pass: Option<String>,
+pub(crate) async fn get_proxy_settings(
+    auth: &SuwayomiAuth,
 ) -> anyhow::Result<Option<ProxySettings>> {
     let client = reqwest::Client::new();
     let settings_url = "http://127.0.0.1:4568/api/v1/settings";
-    let mut request = client.get(settings_url).header(ACCEPT, "application/json");
-    if let Some(username) = user {
-        request = request.basic_auth(username, pass);
-    }
-    let response = request.send().await?;
+    let request = client.get(settings_url).header(ACCEPT, "application/json");
+    let response = auth.apply(request).send().await?;
     let status = response.status();
     if !status.is_success() {
         let body = response
@@ -87,12 +98,186 @@ async fn get_proxy_settings(
     Ok(Some(settings))
 }
 
+/// First Suwayomi release whose GraphQL API exposes `chapters { pageCount }`.
+const GRAPHQL_MIN_VERSION: (u32, u32, u32) = (0, 7, 0);
+
+const SERVER_VERSION_QUERY: &str = "query { aboutServer { version } }";
+const PAGE_COUNT_QUERY: &str = "query ($mangaId: Int!, $sourceOrder: Int!) {
+    chapters(condition: { mangaId: $mangaId, sourceOrder: $sourceOrder }) {
+        nodes { pageCount }
+    }
+}";
+
+/// Whether each Suwayomi API base supports the page count query, probed once per base.
+static GRAPHQL_SUPPORT: LazyLock<Mutex<HashMap<String, bool>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Deserialize)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AboutServerData {
+    about_server: AboutServer,
+}
+
+#[derive(Deserialize)]
+struct AboutServer {
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct ChaptersData {
+    chapters: ChapterNodes,
+}
+
+#[derive(Deserialize)]
+struct ChapterNodes {
+    nodes: Vec<ChapterPageCount>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChapterPageCount {
+    page_count: i64,
+}
+
+/// Parses a Suwayomi version such as `v1.0.0-r1535` into `(major, minor, patch)`.
+pub fn parse_server_version(version: &str) -> Option<(u32, u32, u32)> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let version = version.split(['-', '+', ' ']).next()?;
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+async fn graphql_request<T: serde::de::DeserializeOwned>(
+    api_base: &str,
+    query: &str,
+    variables: serde_json::Value,
+    auth: &SuwayomiAuth,
+) -> anyhow::Result<T> {
+    let client = reqwest::Client::new();
+    let request = client
+        .post(format!("{api_base}/api/graphql"))
+        .header(ACCEPT, "application/json")
+        .json(&serde_json::json!({ "query": query, "variables": variables }));
+    let response = auth.apply(request).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "[Failed to read body]".to_string());
+        return Err(anyhow!(
+            "GraphQL request failed (Status: {status}). Body: {body}"
+        ));
+    }
+    let response: GraphqlResponse<T> = response
+        .json()
+        .await
+        .map_err(|err| anyhow!("Error decoding GraphQL response: {err}"))?;
+    if let Some(error) = response.errors.first() {
+        return Err(anyhow!("GraphQL error: {}", error.message));
+    }
+    response
+        .data
+        .ok_or_else(|| anyhow!("GraphQL response has no data"))
+}
+
+/// Probes the server version; servers that are too old or don't answer use REST.
+async fn supports_graphql_page_count(api_base: &str, auth: &SuwayomiAuth) -> bool {
+    if let Some(supported) = GRAPHQL_SUPPORT.lock().expect("lock poisoned").get(api_base) {
+        return *supported;
+    }
+    let supported = match graphql_request::<AboutServerData>(
+        api_base,
+        SERVER_VERSION_QUERY,
+        serde_json::Value::Null,
+        auth,
+    )
+    .await
+    {
+        Ok(data) => {
+            let version = data.about_server.version;
+            let supported =
+                parse_server_version(&version).is_some_and(|parsed| parsed >= GRAPHQL_MIN_VERSION);
+            tracing::info!("Suwayomi {version} at {api_base}, GraphQL page count: {supported}");
+            supported
+        }
+        Err(err) => {
+            tracing::info!("Suwayomi version probe failed at {api_base}, using REST: {err}");
+            false
+        }
+    };
+    GRAPHQL_SUPPORT
+        .lock()
+        .expect("lock poisoned")
+        .insert(api_base.to_string(), supported);
+    supported
+}
+
+/// Reads the chapter's `pageCount`. `None` when Suwayomi hasn't fetched the page list
+/// yet, which it reports as a non-positive count.
+async fn graphql_page_count(
+    api_base: &str,
+    manga_id: i64,
+    chapter_index: i64,
+    auth: &SuwayomiAuth,
+) -> anyhow::Result<Option<usize>> {
+    let data: ChaptersData = graphql_request(
+        api_base,
+        PAGE_COUNT_QUERY,
+        serde_json::json!({ "mangaId": manga_id, "sourceOrder": chapter_index }),
+        auth,
+    )
+    .await?;
+    Ok(data
+        .chapters
+        .nodes
+        .first()
+        .filter(|chapter| chapter.page_count > 0)
+        .map(|chapter| chapter.page_count as usize))
+}
+
+/// Resolves a chapter's page count through GraphQL on servers that support it, falling
+/// back to the REST page list, which also makes Suwayomi fetch the pages.
 pub async fn resolve_total_pages_from_graphql(
     chapter_base_url: &str,
-    user: Option<String>,
-    pass: Option<String>,
+    auth: &SuwayomiAuth,
 ) -> anyhow::Result<usize> {
-    resolve_total_pages_from_rest(chapter_base_url, user, pass).await
+    let api_base = derive_api_base(chapter_base_url);
+    let ids = path_segment_after(chapter_base_url, "manga")
+        .and_then(|id| id.parse::<i64>().ok())
+        .zip(
+            path_segment_after(chapter_base_url, "chapter")
+                .and_then(|index| index.parse::<i64>().ok()),
+        );
+    if let Some((manga_id, chapter_index)) = ids
+        && supports_graphql_page_count(&api_base, auth).await
+    {
+        match graphql_page_count(&api_base, manga_id, chapter_index, auth).await {
+            Ok(Some(page_count)) => return Ok(page_count),
+            Ok(None) => {}
+            Err(err) => {
+                tracing::warn!(
+                    "GraphQL page count failed for {chapter_base_url}, using REST: {err}"
+                );
+            }
+        }
+    }
+    resolve_total_pages_from_rest(chapter_base_url, auth).await
 }
 
 #[derive(Deserialize)]
@@ -100,7 +285,19 @@ struct RestPageList {
     pages: Vec<String>,
 }
 
-fn derive_api_base(chapter_base_url: &str) -> String {
+/// Splits a Suwayomi URL path into the sub-path a reverse proxy serves it under (empty
+/// at the root) and the rest, which starts at `/api/` or `/manga/`.
+pub fn split_base_path(path: &str) -> (&str, &str) {
+    let start = ["/api/", "/manga/"]
+        .iter()
+        .find_map(|marker| path.find(marker))
+        .unwrap_or(path.len());
+    path.split_at(start)
+}
+
+/// Scheme, host and sub-path of the Suwayomi serving `chapter_base_url`, e.g.
+/// `https://host/suwayomi` for `https://host/suwayomi/api/v1/manga/1/chapter/2/page/`.
+pub fn derive_api_base(chapter_base_url: &str) -> String {
     if let Ok(parsed) = reqwest::Url::parse(chapter_base_url) {
         let scheme = parsed.scheme();
         let host = parsed.host_str().unwrap_or("127.0.0.1");
@@ -108,7 +305,14 @@ fn derive_api_base(chapter_base_url: &str) -> String {
             .port()
             .map(|value| format!(":{}", value))
             .unwrap_or_default();
-        format!("{}://{}{}", scheme, host, port)
+        let (base_path, _) = split_base_path(parsed.path());
+        format!(
+            "{}://{}{}{}",
+            scheme,
+            host,
+            port,
+            base_path.trim_end_matches('/')
+        )
     } else {
         "http://127.0.0.1:4568".to_string()
     }
@@ -116,10 +320,9 @@ fn derive_api_base(chapter_base_url: &str) -> String {
 
 pub async fn resolve_total_pages_from_rest(
     chapter_base_url: &str,
-    user: Option<String>,
-    pass: Option<String>,
+    auth: &SuwayomiAuth,
 ) -> anyhow::Result<usize> {
-    let path = get_cache_key(chapter_base_url, None);
+    let path = get_cache_key_in(chapter_base_url, None, None);
     let parts: Vec<&str> = path.split('/').collect();
     let manga_id_str = parts
         .iter()
@@ -139,11 +342,8 @@ pub async fn resolve_total_pages_from_rest(
     );
 
     let client = reqwest::Client::new();
-    let mut request = client.get(url).header(ACCEPT, "application/json");
-    if let Some(username) = user {
-        request = request.basic_auth(username, pass);
-    }
-    let response = request.send().await?;
+    let request = client.get(url).header(ACCEPT, "application/json");
+    let response = auth.apply(request).send().await?;
     let status = response.status();
     if !status.is_success() {
         let body = response
@@ -161,6 +361,90 @@ pub async fn resolve_total_pages_from_rest(
     Ok(list.pages.len())
 }
 
+#[derive(Deserialize)]
+pub struct RestChapter {
+    pub index: i64,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Lists a manga's chapters through the Suwayomi REST API, ordered by chapter index.
+pub async fn fetch_manga_chapters_from_rest(
+    api_base: &str,
+    manga_id: &str,
+    auth: &SuwayomiAuth,
+) -> anyhow::Result<Vec<RestChapter>> {
+    let url = format!("{api_base}/api/v1/manga/{manga_id}/chapters");
+    let client = reqwest::Client::new();
+    let request = client.get(url).header(ACCEPT, "application/json");
+    let response = auth.apply(request).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "[Failed to read body]".to_string());
+        return Err(anyhow!(
+            "REST request failed (Status: {status}). Body: {body}"
+        ));
+    }
+    let mut chapters: Vec<RestChapter> = response
+        .json()
+        .await
+        .map_err(|err| anyhow!("Error decoding chapters REST response: {err}"))?;
+    chapters.sort_by_key(|chapter| chapter.index);
+    Ok(chapters)
+}
+
+/// Resolves the absolute page URLs of a chapter through the Suwayomi REST API.
+pub async fn fetch_chapter_pages_from_rest(
+    api_base: &str,
+    manga_id: &str,
+    chapter_index: i64,
+    auth: &SuwayomiAuth,
+) -> anyhow::Result<Vec<String>> {
+    let url = format!("{api_base}/api/v1/manga/{manga_id}/chapter/{chapter_index}/pages");
+    let client = reqwest::Client::new();
+    let request = client.get(url).header(ACCEPT, "application/json");
+    let response = auth.apply(request).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "[Failed to read body]".to_string());
+        return Err(anyhow!(
+            "REST request failed (Status: {status}). Body: {body}"
+        ));
+    }
+    let list: RestPageList = response
+        .json()
+        .await
+        .map_err(|err| anyhow!("Error decoding REST response: {err}"))?;
+    Ok(list
+        .pages
+        .into_iter()
+        .map(|page| {
+            if page.starts_with("http") {
+                page
+            } else {
+                format!("{api_base}{page}")
+            }
+        })
+        .collect())
+}
+
+/// Returns the path segment following `name`, e.g. the manga ID for `name = "manga"`.
+pub fn path_segment_after(url: &str, name: &str) -> Option<String> {
+    let path = get_cache_key_in(url, None, None);
+    let mut parts = path.split('/');
+    parts.find(|part| *part == name)?;
+    parts
+        .next()
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct OcrResult {
     pub text: String,
@@ -173,6 +457,72 @@ pub struct OcrResult {
 
     #[serde(rename = "forcedOrientation", skip_serializing_if = "Option::is_none")]
     pub forced_orientation: Option<String>,
+
+    /// Recognition confidence in `0.0..=1.0`, when the backend reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+
+    /// Backend paragraph this line belongs to, unique within a page.
+    #[serde(rename = "blockId", default, skip_serializing_if = "Option::is_none")]
+    pub block_id: Option<u32>,
+
+    /// Source lines of a merged result, kept for `structure::build_blocks`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<OcrResult>,
+
+    /// Furigana read above or beside this text, when `furigana=ruby` is requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ruby: Vec<RubyText>,
+
+    /// Position in reading order on the page, for "next text box" navigation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<u32>,
+
+    /// Speech bubble the line was found in, used only while merging.
+    #[serde(skip)]
+    pub bubble_id: Option<u32>,
+
+    /// Pixel size of the source image, for converting the normalized boxes back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_width: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_height: Option<u32>,
+
+    /// The source image was animated (GIF, WebP or APNG) and only its first frame was read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animated: Option<bool>,
+
+    /// Machine translation of `text`, see `translate`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RubyText {
+    pub text: String,
+
+    #[serde(rename = "tightBoundingBox")]
+    pub tight_bounding_box: BoundingBox,
+}
+
+/// Drops results whose confidence is below `min_confidence`.
+/// Results without a confidence score are always kept.
+pub fn filter_min_confidence(
+    results: Vec<OcrResult>,
+    min_confidence: Option<f32>,
+) -> Vec<OcrResult> {
+    let Some(min_confidence) = min_confidence else {
+        return results;
+    };
+    results
+        .into_iter()
+        .filter(|result| {
+            result
+                .confidence
+                .is_none_or(|confidence| confidence >= min_confidence)
+        })
+        .collect()
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -185,8 +535,42 @@ pub struct BoundingBox {
     pub rotation: Option<f64>,
 }
 
-/// Helper to strip the scheme/host/query from the URL for caching purposes.
+/// Helper to strip the scheme/host/query from the URL for caching purposes. Keys are
+/// namespaced as configured, see `namespace`.
 pub fn get_cache_key(url: &str, language: Option<OcrLanguage>) -> String {
+    get_cache_key_in(url, language, namespace::current().for_url(url).as_deref())
+}
+
+/// Cache key of a page OCR'd with `backend` and `merge_config`. Results of another
+/// backend or merge tuning than the default get their own key, e.g.
+/// `...#manga-ocr,furigana=ruby`, so they neither replace nor answer for each other and
+/// don't share a run; keys of the default settings stay as they were.
+pub fn page_cache_key(
+    url: &str,
+    language: OcrLanguage,
+    backend: OcrBackend,
+    merge_config: &MergeConfig,
+) -> String {
+    let key = get_cache_key(url, Some(language));
+    match cache_variant(backend, merge_config) {
+        Some(variant) => format!("{key}#{variant}"),
+        None => key,
+    }
+}
+
+/// The non-default backend and merge tuning of `page_cache_key`, if any.
+fn cache_variant(backend: OcrBackend, merge_config: &MergeConfig) -> Option<String> {
+    let backend = (backend != OcrBackend::default()).then(|| backend.as_str().to_string());
+    let variant: Vec<String> = backend.into_iter().chain(merge_config.cache_variant()).collect();
+    (!variant.is_empty()).then(|| variant.join(","))
+}
+
+/// `get_cache_key` with an explicit namespace; `None` gives the plain URL path.
+pub fn get_cache_key_in(
+    url: &str,
+    language: Option<OcrLanguage>,
+    namespace: Option<&str>,
+) -> String {
     let raw = if let Ok(parsed) = reqwest::Url::parse(url) {
         let mut path = parsed.path().to_string();
         if let Some(query) = parsed.query() {
@@ -212,7 +596,7 @@ pub fn get_cache_key(url: &str, language: Option<OcrLanguage>) -> String {
         url.to_string()
     };
 
-    if let Some(language) = language {
+    let key = if let Some(language) = language {
         let trimmed = raw.trim_start_matches('/');
         if trimmed.is_empty() {
             format!("lang/{}/", language.as_str())
@@ -221,15 +605,22 @@ pub fn get_cache_key(url: &str, language: Option<OcrLanguage>) -> String {
         }
     } else {
         raw
-    }
+    };
+    namespace::apply(namespace, key)
 }
 
-fn post_process_text(text: String, language: OcrLanguage) -> String {
-    if language.prefers_no_space() {
+pub(crate) fn post_process_text(text: String, language: OcrLanguage) -> String {
+    let text = if language.prefers_no_space() {
         text.replace(char::is_whitespace, "")
     } else {
         text
-    }
+    };
+    let text = if language.is_japanese() && normalize::is_enabled() {
+        normalize::normalize_japanese(&text)
+    } else {
+        text
+    };
+    postprocess::apply_rules(text, language)
 }
 
 fn decode_avif_custom(bytes: &[u8]) -> anyhow::Result<DynamicImage> {
@@ -290,41 +681,73 @@ fn decode_avif_custom(bytes: &[u8]) -> anyhow::Result<DynamicImage> {
     }
 }
 
+/// Fetches and OCRs a page. With `state` set, images whose bytes were already OCR'd
+/// under another URL reuse that result instead of being processed again (unless
+/// `force`), and backend failures count towards `/ready`'s degraded state.
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_and_process(
     url: &str,
-    user: Option<String>,
-    pass: Option<String>,
-    add_space_on_merge: Option<bool>,
+    auth: &SuwayomiAuth,
+    merge_config: &MergeConfig,
     language: OcrLanguage,
-) -> anyhow::Result<Vec<OcrResult>> {
+    backend: OcrBackend,
+    proxy: Option<&ProxyMode>,
+    retry: &RetryPolicy,
+    state: Option<&AppState>,
+    force: bool,
+) -> Result<Vec<OcrResult>, RetryError> {
     let mut last_error = anyhow!("Unknown error");
+    let max_attempts = retry.max_attempts.max(1);
 
-    for attempt_number in 1..=3 {
-        match fetch_and_process_internal(
+    for attempt_number in 1..=max_attempts {
+        let attempt = fetch_and_process_internal(
             url,
-            user.clone(),
-            pass.clone(),
-            add_space_on_merge,
+            auth,
+            merge_config,
             language,
-        )
-        .await
-        {
+            backend,
+            proxy,
+            state,
+            force,
+        );
+        let outcome = match retry.attempt_timeout() {
+            Some(limit) => tokio::time::timeout(limit, attempt)
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("Attempt timed out after {limit:?}"))),
+            None => attempt.await,
+        };
+
+        match outcome {
             Ok(result) => return Ok(result),
             Err(error) => {
                 last_error = error;
                 tracing::warn!(
-                    "Attempt {} failed for {}: {:?}",
+                    "Attempt {}/{} failed for {}: {:?}",
                     attempt_number,
+                    max_attempts,
                     url,
                     last_error
                 );
-                tokio::time::sleep(Duration::from_secs(attempt_number)).await;
+                if attempt_number < max_attempts {
+                    tokio::time::sleep(retry.delay_after(attempt_number)).await;
+                }
             }
         }
     }
-    Err(last_error)
+    Err(RetryError {
+        attempts: max_attempts,
+        source: last_error,
+    })
 }
 
+/// `true` or `1` splits double-page spreads at the gutter before OCR, see
+/// `spread_columns`. Off by default: wide single pages and panels would be split too.
+const SPLIT_SPREADS_ENV: &str = "MANATAN_OCR_SPLIT_SPREADS";
+const CHUNK_OVERLAP_PX_ENV: &str = "MANATAN_OCR_CHUNK_OVERLAP_PX";
+const DEFAULT_CHUNK_OVERLAP_PX: u32 = 200;
+/// Images at least this much wider than tall are treated as double-page spreads.
+const SPREAD_MIN_ASPECT_RATIO: f64 = 1.2;
+
 // --- Data Structure for Test Caching ---
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -332,6 +755,9 @@ pub struct RawChunk {
     pub lines: Vec<OcrResult>,
     pub width: u32,
     pub height: u32,
+    /// Left edge of the chunk in the full image; non-zero for the right half of a spread.
+    #[serde(default)]
+    pub global_x: u32,
     pub global_y: u32,
     pub full_width: u32,
     pub full_height: u32,
@@ -343,247 +769,428 @@ pub async fn get_raw_ocr_data(
     user: Option<String>,
     pass: Option<String>,
     language: OcrLanguage,
+    backend: OcrBackend,
 ) -> anyhow::Result<Vec<RawChunk>> {
+    let proxy_url = proxy::resolve(None, &SuwayomiAuth::new(user, pass, None)).await;
+    get_raw_ocr_data_with_proxy(image_bytes, proxy_url.as_deref(), language, backend).await
+}
+
+/// Frames of a GIF, animated WebP or APNG; `None` for other images.
+fn animation_frames(image_bytes: &[u8]) -> Option<Frames<'_>> {
+    let cursor = Cursor::new(image_bytes);
+    match image::guess_format(image_bytes).ok()? {
+        ImageFormat::Gif => Some(GifDecoder::new(cursor).ok()?.into_frames()),
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(cursor).ok()?;
+            decoder.has_animation().then(|| decoder.into_frames())
+        }
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor).ok()?;
+            if !decoder.is_apng().ok()? {
+                return None;
+            }
+            Some(decoder.apng().ok()?.into_frames())
+        }
+        _ => None,
+    }
+}
+
+/// Whether the image has more than one frame. Only the first one is OCRed.
+pub fn is_animated(image_bytes: &[u8]) -> bool {
+    animation_frames(image_bytes).is_some_and(|frames| frames.take(2).count() > 1)
+}
+
+/// Decodes any format the `image` crate supports, plus AVIF through `avif-decode`.
+/// Animated images decode to their first frame.
+pub fn decode_image(image_bytes: &[u8]) -> anyhow::Result<DynamicImage> {
+    if let Some(frame) = animation_frames(image_bytes).and_then(|mut frames| frames.next()) {
+        let frame = frame.map_err(|err| anyhow!("Failed to decode first frame: {err:?}"))?;
+        return Ok(DynamicImage::ImageRgba8(frame.into_buffer()));
+    }
+
     let reader = ImageReader::new(Cursor::new(image_bytes))
         .with_guessed_format()
         .map_err(|err| anyhow!("Failed with_guessed_format: {err:?}"))?;
 
-    let decoded_image = if reader.format() == Some(ImageFormat::Avif) {
-        decode_avif_custom(image_bytes)?
+    if reader.format() == Some(ImageFormat::Avif) {
+        decode_avif_custom(image_bytes)
     } else {
         reader
             .decode()
-            .map_err(|err| anyhow!("Failed decode: {err:?}"))?
-    };
+            .map_err(|err| anyhow!("Failed decode: {err:?}"))
+    }
+}
+
+/// Merges the lines of each chunk, converts the boxes to fractions of the full image and
+/// sorts the page into reading order. Shared by `process_image_bytes` and the `golden`
+/// harness, which replays recorded chunks.
+pub fn merge_chunks(
+    raw_chunks: Vec<RawChunk>,
+    merge_config: &MergeConfig,
+    language: OcrLanguage,
+) -> Vec<OcrResult> {
+    let mut final_results = Vec::new();
+    let mut merge_config = merge_config.clone();
+    merge_config.language = language;
+    merge_config.keep_source_lines = true;
+
+    for chunk in raw_chunks {
+        let merged_lines = merge::auto_merge(chunk.lines, chunk.width, chunk.height, &merge_config);
+
+        // Adjust Coordinates: Chunk Pixels -> Global Pixels -> Global Normalized
+        let normalize = |bbox: &mut BoundingBox| {
+            let global_pixel_x = bbox.x + (chunk.global_x as f64);
+            let global_pixel_y = bbox.y + (chunk.global_y as f64);
+            bbox.x = global_pixel_x / chunk.full_width as f64;
+            bbox.width /= chunk.full_width as f64;
+            bbox.y = global_pixel_y / chunk.full_height as f64;
+            bbox.height /= chunk.full_height as f64;
+        };
+
+        for mut result in merged_lines {
+            normalize(&mut result.tight_bounding_box);
+            result.full_width = Some(chunk.full_width);
+            result.full_height = Some(chunk.full_height);
+            for line in &mut result.lines {
+                normalize(&mut line.tight_bounding_box);
+                for ruby in &mut line.ruby {
+                    normalize(&mut ruby.tight_bounding_box);
+                }
+            }
+            for ruby in &mut result.ruby {
+                normalize(&mut ruby.tight_bounding_box);
+            }
+            final_results.push(result);
+        }
+    }
+
+    merge::sort_reading_order(final_results, language)
+}
+
+/// `get_raw_ocr_data` with an already resolved proxy.
+pub async fn get_raw_ocr_data_with_proxy(
+    image_bytes: &[u8],
+    proxy_url: Option<&str>,
+    language: OcrLanguage,
+    backend: OcrBackend,
+) -> anyhow::Result<Vec<RawChunk>> {
+    let decoded_image = decode_image(image_bytes)?;
 
     let full_image_width = decoded_image.width();
     let full_image_height = decoded_image.height();
     let chunk_height_limit = 3000;
+    let chunk_step = chunk_height_limit - chunk_overlap_px().min(chunk_height_limit / 2);
 
     let mut raw_chunks = Vec::new();
 
-    // Fetch proxy settings
-    let proxy_settings = get_proxy_settings(user.clone(), pass.clone())
-        .await
-        .ok()
-        .flatten();
-
-    // Create LensClient with optional proxy
-    let lens_client = if let Some(ref proxy) = proxy_settings {
-        if proxy.socks_proxy_enabled && !proxy.socks_proxy_host.is_empty() {
-            // Build proxy URL with authentication if provided
-            let proxy_url = if let (Some(username), Some(password)) =
-                (&proxy.socks_proxy_username, &proxy.socks_proxy_password)
-            {
-                if !username.is_empty() && !password.is_empty() {
-                    format!(
-                        "socks{}://{}:{}@{}:{}",
-                        proxy.socks_proxy_version,
-                        username,
-                        password,
-                        proxy.socks_proxy_host,
-                        proxy.socks_proxy_port
-                    )
-                } else {
-                    format!(
-                        "socks{}://{}:{}",
-                        proxy.socks_proxy_version, proxy.socks_proxy_host, proxy.socks_proxy_port
-                    )
-                }
-            } else {
-                format!(
-                    "socks{}://{}:{}",
-                    proxy.socks_proxy_version, proxy.socks_proxy_host, proxy.socks_proxy_port
+    let session = BackendSession::connect(backend, language, proxy_url).await?;
+
+    let mut block_offset = 0;
+    for (column_x, column_width) in spread_columns(&decoded_image) {
+        let mut current_y_position = 0;
+        while current_y_position < full_image_height {
+            let current_chunk_height =
+                std::cmp::min(chunk_height_limit, full_image_height - current_y_position);
+            if current_chunk_height == 0 {
+                break;
+            }
+
+            let chunk_image = decoded_image
+                .view(
+                    column_x,
+                    current_y_position,
+                    column_width,
+                    current_chunk_height,
                 )
-            };
+                .to_image();
+            let mut flat_ocr_lines = session.recognize(&chunk_image, language).await?;
+            bubble::assign_bubbles(&chunk_image, &mut flat_ocr_lines);
 
-            tracing::info!(
-                "Using SOCKS{} proxy for Google Lens: {}:{}",
-                proxy.socks_proxy_version,
-                proxy.socks_proxy_host,
-                proxy.socks_proxy_port
-            );
+            // Backends number paragraphs per chunk; make the IDs unique within the page.
+            let mut next_block_offset = block_offset;
+            for line in &mut flat_ocr_lines {
+                if let Some(block_id) = line.block_id.as_mut() {
+                    *block_id += block_offset;
+                    next_block_offset = next_block_offset.max(*block_id + 1);
+                }
+            }
+            block_offset = next_block_offset;
 
-            LensClient::new_with_proxy(None, Some(&proxy_url))
-                .map_err(|e| anyhow!("Failed to create LensClient with proxy: {}", e))?
-        } else {
-            LensClient::new(None)
+            raw_chunks.push(RawChunk {
+                lines: flat_ocr_lines,
+                width: column_width,
+                height: current_chunk_height,
+                global_x: column_x,
+                global_y: current_y_position,
+                full_width: full_image_width,
+                full_height: full_image_height,
+            });
+
+            if current_y_position + current_chunk_height >= full_image_height {
+                break;
+            }
+            current_y_position += chunk_step;
         }
-    } else {
-        LensClient::new(None)
-    };
+    }
 
-    let mut current_y_position = 0;
-    while current_y_position < full_image_height {
-        let current_chunk_height =
-            std::cmp::min(chunk_height_limit, full_image_height - current_y_position);
-        if current_chunk_height == 0 {
-            break;
+    dedup_chunk_overlaps(&mut raw_chunks);
+    Ok(raw_chunks)
+}
+
+fn chunk_overlap_px() -> u32 {
+    manatan_config::var(CHUNK_OVERLAP_PX_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .unwrap_or(DEFAULT_CHUNK_OVERLAP_PX)
+}
+
+/// Removes lines seen twice where consecutive chunks overlap. Of two matching lines,
+/// the taller one is kept, since the other was most likely cut by a chunk edge.
+fn dedup_chunk_overlaps(chunks: &mut [RawChunk]) {
+    for index in 1..chunks.len() {
+        let (before, after) = chunks.split_at_mut(index);
+        let upper = &mut before[index - 1];
+        let lower = &mut after[0];
+        let overlap_end = upper.global_y + upper.height;
+        if upper.global_x != lower.global_x || lower.global_y >= overlap_end {
+            continue;
         }
 
-        let chunk_image = decoded_image
-            .view(
-                0,
-                current_y_position,
-                full_image_width,
-                current_chunk_height,
-            )
-            .to_image();
-        let mut image_buffer = Cursor::new(Vec::new());
-        chunk_image
-            .write_to(&mut image_buffer, ImageFormat::Png)
-            .map_err(|err| anyhow!("Failed write_to: {err:?}"))?;
-        let chunk_png_bytes = image_buffer.into_inner();
-
-        let lens_response = lens_client
-            .process_image_bytes(&chunk_png_bytes, Some("jp"))
-            .await
-            .map_err(|err| anyhow!("Failed process_image_bytes: {err:?}"))?;
-
-        let mut flat_ocr_lines = Vec::new();
-        for paragraph in lens_response.paragraphs {
-            for line in paragraph.lines {
-                if let Some(geometry) = line.geometry {
-                    let clean_text = post_process_text(line.text, language);
-                    if clean_text.trim().is_empty() {
-                        continue;
-                    }
-
-                    let rotation = geometry.rotation_z as f64;
-                    let cx = (geometry.center_x * full_image_width as f32) as f64;
-                    let cy = (geometry.center_y * current_chunk_height as f32) as f64;
-                    let w = (geometry.width * full_image_width as f32) as f64;
-                    let h = (geometry.height * current_chunk_height as f32) as f64;
-
-                    let hw = w / 2.0;
-                    let hh = h / 2.0;
-                    let cos_a = rotation.cos();
-                    let sin_a = rotation.sin();
-
-                    let corners = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)];
-
-                    let mut min_x = f64::INFINITY;
-                    let mut max_x = f64::NEG_INFINITY;
-                    let mut min_y = f64::INFINITY;
-                    let mut max_y = f64::NEG_INFINITY;
-
-                    for (lx, ly) in corners {
-                        let rx = lx * cos_a - ly * sin_a + cx;
-                        let ry = lx * sin_a + ly * cos_a + cy;
-                        min_x = min_x.min(rx);
-                        max_x = max_x.max(rx);
-                        min_y = min_y.min(ry);
-                        max_y = max_y.max(ry);
-                    }
-
-                    let aabb_w = max_x - min_x;
-                    let aabb_h = max_y - min_y;
-
-                    let is_vertical = if language.prefers_vertical() {
-                        if rotation.abs() > 0.1 {
-                            (rotation.abs() - std::f32::consts::FRAC_PI_2 as f64).abs() < 0.5
-                        } else {
-                            aabb_w <= aabb_h
-                        }
-                    } else {
-                        false
-                    };
-
-                    flat_ocr_lines.push(OcrResult {
-                        text: clean_text,
-                        is_merged: Some(false),
-                        forced_orientation: Some(if is_vertical {
-                            "vertical".into()
-                        } else {
-                            "horizontal".into()
-                        }),
-                        tight_bounding_box: BoundingBox {
-                            x: min_x,
-                            y: min_y,
-                            width: aabb_w,
-                            height: aabb_h,
-                            rotation: None,
-                        },
-                    });
+        let global = |chunk: &RawChunk, line: &OcrResult| {
+            let bbox = &line.tight_bounding_box;
+            let y = bbox.y + chunk.global_y as f64;
+            (bbox.x, y, bbox.x + bbox.width, y + bbox.height)
+        };
+
+        let mut drop_upper = vec![false; upper.lines.len()];
+        let mut drop_lower = vec![false; lower.lines.len()];
+        for (lower_index, lower_line) in lower.lines.iter().enumerate() {
+            let (lx0, ly0, lx1, ly1) = global(lower, lower_line);
+            if ly0 >= overlap_end as f64 {
+                continue;
+            }
+            let counterpart = upper
+                .lines
+                .iter()
+                .enumerate()
+                .find(|(upper_index, upper_line)| {
+                    let (ux0, uy0, ux1, uy1) = global(upper, upper_line);
+                    let x_overlap = lx1.min(ux1) - lx0.max(ux0);
+                    let min_width = (lx1 - lx0).min(ux1 - ux0);
+                    !drop_upper[*upper_index]
+                        && x_overlap > min_width * 0.5
+                        && ly1.min(uy1) > ly0.max(uy0)
+                });
+            if let Some((upper_index, upper_line)) = counterpart {
+                if lower_line.tight_bounding_box.height > upper_line.tight_bounding_box.height {
+                    drop_upper[upper_index] = true;
+                } else {
+                    drop_lower[lower_index] = true;
                 }
             }
         }
 
-        raw_chunks.push(RawChunk {
-            lines: flat_ocr_lines,
-            width: full_image_width,
-            height: current_chunk_height,
-            global_y: current_y_position,
-            full_width: full_image_width,
-            full_height: full_image_height,
-        });
+        let mut keep = drop_upper.into_iter().map(|drop| !drop);
+        upper.lines.retain(|_| keep.next().unwrap_or(true));
+        let mut keep = drop_lower.into_iter().map(|drop| !drop);
+        lower.lines.retain(|_| keep.next().unwrap_or(true));
+    }
+}
 
-        current_y_position += chunk_height_limit;
+fn split_spreads_enabled() -> bool {
+    manatan_config::var(SPLIT_SPREADS_ENV)
+        .is_ok_and(|value| matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true"))
+}
+
+/// Horizontal `(x, width)` ranges to OCR separately. Double-page spreads are split at
+/// the gutter, taken as the brightest column near the middle, so text columns next to
+/// the spine are not cut by chunking or merged across pages.
+fn spread_columns(image: &DynamicImage) -> Vec<(u32, u32)> {
+    let (width, height) = image.dimensions();
+    if !split_spreads_enabled() || (width as f64) < (height as f64) * SPREAD_MIN_ASPECT_RATIO {
+        return vec![(0, width)];
     }
 
-    Ok(raw_chunks)
+    let search_start = width * 2 / 5;
+    let search_width = (width / 5).max(1);
+    let band = image.view(search_start, 0, search_width, height).to_image();
+    let band = image::imageops::grayscale(&band);
+
+    let mut column_sums = vec![0u64; search_width as usize];
+    for (x, _, pixel) in band.enumerate_pixels() {
+        column_sums[x as usize] += pixel.0[0] as u64;
+    }
+    let center = search_width as i64 / 2;
+    let gutter_offset = column_sums
+        .iter()
+        .enumerate()
+        .max_by(|(a_x, a_sum), (b_x, b_sum)| {
+            a_sum
+                .cmp(b_sum)
+                // Prefer the column closest to the middle on ties.
+                .then_with(|| {
+                    (*b_x as i64 - center)
+                        .abs()
+                        .cmp(&(*a_x as i64 - center).abs())
+                })
+        })
+        .map(|(x, _)| x as u32)
+        .unwrap_or(search_width / 2);
+
+    let gutter = search_start + gutter_offset;
+    tracing::info!("Splitting {width}x{height} spread at x={gutter}");
+    vec![(0, gutter), (gutter, width - gutter)]
+}
+
+/// Content hash used to find identical images served under different URLs. Like
+/// `page_cache_key`, it tells apart results of another backend or merge tuning.
+pub fn image_hash_key(
+    image_bytes: &[u8],
+    language: OcrLanguage,
+    backend: OcrBackend,
+    merge_config: &MergeConfig,
+) -> String {
+    let digest = Sha256::digest(image_bytes);
+    match cache_variant(backend, merge_config) {
+        Some(variant) => format!("{}:{digest:x}#{variant}", language.as_str()),
+        None => format!("{}:{digest:x}", language.as_str()),
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn fetch_and_process_internal(
     url: &str,
-    user: Option<String>,
-    pass: Option<String>,
-    add_space_on_merge: Option<bool>,
+    auth: &SuwayomiAuth,
+    merge_config: &MergeConfig,
     language: OcrLanguage,
+    backend: OcrBackend,
+    proxy: Option<&ProxyMode>,
+    state: Option<&AppState>,
+    force: bool,
 ) -> anyhow::Result<Vec<OcrResult>> {
-    // 0. Force URL to Localhost
-    let target_url = match reqwest::Url::parse(url) {
+    // 1. Fetch
+    let image_bytes = fetch_image_bytes(url, auth).await?;
+    let proxy_url = proxy::resolve(proxy, auth).await;
+
+    process_image_bytes(
+        &image_bytes,
+        url,
+        merge_config,
+        language,
+        backend,
+        proxy_url.as_deref(),
+        state,
+        force,
+    )
+    .await
+}
+
+/// `url` on the local Suwayomi, which is reached directly: without the host, and
+/// without the sub-path of a reverse proxy in front of it.
+pub fn local_image_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
         Ok(mut parsed) => {
             let _ = parsed.set_scheme("http");
             let _ = parsed.set_host(Some("127.0.0.1"));
             let _ = parsed.set_port(Some(4568));
+            let (base_path, api_path) = split_base_path(parsed.path());
+            if !base_path.is_empty() && api_path.starts_with("/api/") {
+                let api_path = api_path.to_string();
+                parsed.set_path(&api_path);
+            }
             parsed.to_string()
         }
         Err(_) => url.to_string(),
-    };
-
-    // 1. Fetch
-    let client = reqwest::Client::new();
-    let mut request = client.get(&target_url);
-    if let Some(username) = &user {
-        request = request.basic_auth(username, pass.as_ref());
     }
-    let response = request
+}
+
+/// Downloads a page image from the local Suwayomi, whatever host `url` names, or
+/// through Suwayomi's SOCKS proxy or `MANATAN_OCR_IMAGE_PROXY` when one is set.
+pub async fn fetch_image_bytes(url: &str, auth: &SuwayomiAuth) -> anyhow::Result<Vec<u8>> {
+    let image_proxy = proxy::image_proxy(auth).await;
+    fetch_image_bytes_via(url, auth, image_proxy.as_deref()).await
+}
+
+/// Downloads a page image from `url`'s own host through `image_proxy`, or from the local
+/// Suwayomi without one.
+pub async fn fetch_image_bytes_via(
+    url: &str,
+    auth: &SuwayomiAuth,
+    image_proxy: Option<&str>,
+) -> anyhow::Result<Vec<u8>> {
+    let target_url = match image_proxy {
+        Some(_) => url.to_string(),
+        None => local_image_url(url),
+    };
+    let client = proxy::image_client(image_proxy)?;
+    let response = auth
+        .apply(client.get(&target_url))
         .send()
         .await?
         .error_for_status()
         .map_err(|err| anyhow!("Failed error_for_status (URL: {target_url}): {err:?}"))?;
-    let image_bytes = response.bytes().await?.to_vec();
-
-    // 2. Decode & OCR (Wrapped) - now passes user/pass for proxy settings
-    let raw_chunks = get_raw_ocr_data(&image_bytes, user, pass, language).await?;
-
-    // 3. Merge & Normalize
-    let mut final_results = Vec::new();
-    let mut merge_config = MergeConfig::default();
-    merge_config.add_space_on_merge = add_space_on_merge;
-    merge_config.language = language;
-
-    for chunk in raw_chunks {
-        let merged_lines = merge::auto_merge(chunk.lines, chunk.width, chunk.height, &merge_config);
-
-        for mut result in merged_lines {
-            // Adjust Coordinates: Chunk Pixels -> Global Pixels -> Global Normalized
-            let chunk_pixel_x = result.tight_bounding_box.x;
-            let chunk_pixel_y = result.tight_bounding_box.y;
-            let chunk_pixel_width = result.tight_bounding_box.width;
-            let chunk_pixel_height = result.tight_bounding_box.height;
+    Ok(response.bytes().await?.to_vec())
+}
 
-            let global_pixel_y = chunk_pixel_y + (chunk.global_y as f64);
+/// OCRs, merges and normalizes an image that is already in memory. `url` is the key the
+/// results will be cached under; with `state` set, identical images are deduplicated
+/// (unless `force`) and backend health is recorded, as in `fetch_and_process`.
+#[allow(clippy::too_many_arguments)]
+pub async fn process_image_bytes(
+    image_bytes: &[u8],
+    url: &str,
+    merge_config: &MergeConfig,
+    language: OcrLanguage,
+    backend: OcrBackend,
+    proxy_url: Option<&str>,
+    state: Option<&AppState>,
+    force: bool,
+) -> anyhow::Result<Vec<OcrResult>> {
+    let hash_key = state.map(|_| image_hash_key(image_bytes, language, backend, merge_config));
+    if let (Some(state), Some(hash_key), false) = (state, hash_key.as_deref(), force) {
+        if let Some(results) = state.get_results_by_image_hash(hash_key) {
+            tracing::info!("Reusing OCR results of an identical image for {url}");
+            return Ok(results);
+        }
+    }
 
-            result.tight_bounding_box.x = chunk_pixel_x / chunk.full_width as f64;
-            result.tight_bounding_box.width = chunk_pixel_width / chunk.full_width as f64;
-            result.tight_bounding_box.y = global_pixel_y / chunk.full_height as f64;
-            result.tight_bounding_box.height = chunk_pixel_height / chunk.full_height as f64;
+    // 2. Decode & OCR (Wrapped)
+    let backend_permit = match state {
+        Some(state) => Some(
+            state
+                .concurrency
+                .acquire_backend(concurrency::current_priority())
+                .await,
+        ),
+        None => None,
+    };
+    let raw_chunks = get_raw_ocr_data_with_proxy(image_bytes, proxy_url, language, backend).await;
+    drop(backend_permit);
+    if let Some(state) = state {
+        match &raw_chunks {
+            Ok(_) => state.backend_health.record_success(backend),
+            Err(err) => state.backend_health.record_failure(backend, err),
+        }
+    }
+    let raw_chunks = raw_chunks?;
 
-            final_results.push(result);
+    // 3. Merge & Normalize
+    let mut final_results = merge_chunks(raw_chunks, merge_config, language);
+    if is_animated(image_bytes) {
+        tracing::warn!("{url} is animated; only its first frame was OCRed");
+        for result in &mut final_results {
+            result.animated = Some(true);
         }
     }
 
+    if let (Some(state), Some(hash_key)) = (state, hash_key.as_deref()) {
+        state
+            .record_image_hash(
+                hash_key,
+                &page_cache_key(url, language, backend, merge_config),
+            )
+            .await;
+    }
+
     Ok(final_results)
 }
@@ -0,0 +1,241 @@
+//! Whole-manga preprocessing.
+//!
+//! A manga job walks every chapter of a manga in order and feeds them one at a time
+//! through the chapter job queue. Chapters whose `chapter_pages` row shows every page
+//! processed are skipped, so an interrupted manga job picks up where it left off.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::Instrument;
+
+use crate::{
+    auth::SuwayomiAuth,
+    backend::OcrBackend,
+    jobs::{ChapterJob, EnqueueOutcome, JobEvent, JobPriority},
+    language::OcrLanguage,
+    logic,
+    merge::MergeOverrides,
+    proxy::ProxyMode,
+    request_id,
+    retry::RetryPolicy,
+    state::{AppState, JobProgress},
+};
+
+const QUEUE_FULL_BACKOFF: Duration = Duration::from_secs(5);
+const COMPLETION_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MangaJob {
+    /// Any Suwayomi URL containing `/manga/{id}`.
+    pub manga_url: String,
+    /// Kept in memory only, so a resumed job goes without credentials.
+    #[serde(skip_serializing, default)]
+    pub auth: SuwayomiAuth,
+    pub context: String,
+    pub add_space_on_merge: Option<bool>,
+    pub language: OcrLanguage,
+    pub backend: OcrBackend,
+    #[serde(default)]
+    pub proxy: Option<ProxyMode>,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub merge: MergeOverrides,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl MangaJob {
+    pub fn key(&self) -> String {
+        logic::get_cache_key(&self.manga_url, Some(self.language))
+    }
+}
+
+/// Re-spawns manga jobs that were interrupted by a shutdown or crash.
+pub fn resume_persisted_manga_jobs(state: &AppState) {
+    for job in state.load_manga_jobs() {
+        tracing::info!("[Manga] Resuming {}", job.manga_url);
+        spawn_manga_job(state, job);
+    }
+}
+
+/// Starts a manga job unless one is already running for the same manga.
+/// Returns `false` if it was already running.
+pub fn spawn_manga_job(state: &AppState, job: MangaJob) -> bool {
+    let manga_key = job.key();
+    {
+        let mut active = state.active_manga_jobs.write().expect("lock poisoned");
+        if active.contains_key(&manga_key) {
+            return false;
+        }
+        active.insert(
+            manga_key.clone(),
+            JobProgress {
+                current: 0,
+                total: 0,
+            },
+        );
+    }
+    state.save_manga_job(&manga_key, &job);
+
+    let state = state.clone();
+    let span = request_id::job_span("manga", job.request_id.as_deref());
+    tokio::spawn(
+        async move {
+            let finished = run_manga_job(&state, &manga_key, job).await;
+            if finished {
+                state.delete_manga_job(&manga_key);
+            }
+            state
+                .active_manga_jobs
+                .write()
+                .expect("lock poisoned")
+                .remove(&manga_key);
+        }
+        .instrument(span),
+    );
+    true
+}
+
+/// Returns whether the job ran to the end. Jobs that could not list their chapters
+/// stay persisted and are retried on the next startup.
+async fn run_manga_job(state: &AppState, manga_key: &str, job: MangaJob) -> bool {
+    let Some(manga_id) = logic::path_segment_after(&job.manga_url, "manga") else {
+        tracing::warn!("[Manga] Could not parse manga ID from {}", job.manga_url);
+        return true;
+    };
+    let api_base = logic::derive_api_base(&job.manga_url);
+
+    let chapters =
+        match logic::fetch_manga_chapters_from_rest(&api_base, &manga_id, &job.auth).await {
+            Ok(chapters) => chapters,
+            Err(err) => {
+                tracing::warn!("[Manga {manga_id}] Failed to list chapters: {err:?}");
+                return false;
+            }
+        };
+
+    let total = chapters.len();
+    tracing::info!("[Manga {manga_id}] Started ({total} chapters)");
+
+    for (position, chapter) in chapters.iter().enumerate() {
+        set_progress(state, manga_key, position, total);
+
+        let base_url = format!(
+            "{api_base}/api/v1/manga/{manga_id}/chapter/{}/page/",
+            chapter.index
+        );
+        let chapter_key = logic::get_cache_key(&base_url, Some(job.language));
+
+        if let Some((page_count, processed_count)) = state.get_chapter_progress(&chapter_key) {
+            if page_count > 0 && processed_count >= page_count {
+                continue;
+            }
+        }
+
+        let pages = match logic::fetch_chapter_pages_from_rest(
+            &api_base,
+            &manga_id,
+            chapter.index,
+            &job.auth,
+        )
+        .await
+        {
+            Ok(pages) if !pages.is_empty() => pages,
+            Ok(_) => continue,
+            Err(err) => {
+                tracing::warn!(
+                    "[Manga {manga_id}] Failed to list pages of chapter {}: {err:?}",
+                    chapter.index
+                );
+                continue;
+            }
+        };
+
+        let context = if chapter.name.is_empty() {
+            job.context.clone()
+        } else {
+            format!("{} - {}", job.context, chapter.name)
+        };
+        let chapter_job = ChapterJob {
+            base_url,
+            pages,
+            auth: job.auth.clone(),
+            context,
+            add_space_on_merge: job.add_space_on_merge,
+            language: job.language,
+            backend: job.backend,
+            proxy: job.proxy.clone(),
+            retry: job.retry,
+            merge: job.merge,
+            request_id: job.request_id.clone(),
+        };
+        run_chapter_and_wait(state, chapter_key, chapter_job).await;
+    }
+
+    set_progress(state, manga_key, total, total);
+    tracing::info!("[Manga {manga_id}] Finished");
+    true
+}
+
+fn set_progress(state: &AppState, manga_key: &str, current: usize, total: usize) {
+    if let Some(progress) = state
+        .active_manga_jobs
+        .write()
+        .expect("lock poisoned")
+        .get_mut(manga_key)
+    {
+        *progress = JobProgress { current, total };
+    }
+}
+
+fn chapter_pending(state: &AppState, chapter_key: &str) -> bool {
+    state.job_queue.is_queued(chapter_key)
+        || state
+            .active_chapter_jobs
+            .read()
+            .expect("lock poisoned")
+            .contains_key(chapter_key)
+}
+
+/// Queues a chapter at prefetch priority and waits until its job has finished.
+async fn run_chapter_and_wait(state: &AppState, chapter_key: String, job: ChapterJob) {
+    let mut events = state.job_events.subscribe();
+
+    let already_running = state
+        .active_chapter_jobs
+        .read()
+        .expect("lock poisoned")
+        .contains_key(&chapter_key);
+    if !already_running {
+        state.save_chapter_job(&chapter_key, JobPriority::Prefetch, &job);
+        loop {
+            match state
+                .job_queue
+                .enqueue(chapter_key.clone(), JobPriority::Prefetch, job.clone())
+            {
+                EnqueueOutcome::Queued(_) | EnqueueOutcome::AlreadyQueued(_) => break,
+                EnqueueOutcome::Full => tokio::time::sleep(QUEUE_FULL_BACKOFF).await,
+            }
+        }
+    }
+
+    loop {
+        match tokio::time::timeout(COMPLETION_POLL_INTERVAL, events.recv()).await {
+            Ok(Ok(JobEvent::Completed {
+                chapter_key: done, ..
+            })) if done == chapter_key => return,
+            Ok(Ok(_)) => {}
+            Ok(Err(broadcast::error::RecvError::Closed)) => return,
+            // Missed events, or nothing for a while: check whether the chapter is still
+            // around, e.g. it may have been deleted from the queue.
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) | Err(_) => {
+                if !chapter_pending(state, &chapter_key) {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -2,10 +2,11 @@ use std::cmp::Ordering;
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     language::OcrLanguage,
-    logic::{BoundingBox, OcrResult},
+    logic::{BoundingBox, OcrResult, RubyText},
 };
 
 lazy_static! {
@@ -15,12 +16,47 @@ lazy_static! {
     static ref KATAKANA_REGEX: Regex = Regex::new(r"[\p{Katakana}]").unwrap();
 }
 
+/// Reading direction of the page's lines. `vertical` and `horizontal` override the
+/// per-line guess of the backend and the merge heuristics, for titles they misclassify.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrientationBias {
+    /// Decide per line from the backend hint, the box shape and the language.
+    #[default]
+    Auto,
+    Vertical,
+    Horizontal,
+}
+
+/// What happens to furigana detected next to a kanji line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FuriganaMode {
+    /// Remove it from the results.
+    #[default]
+    Drop,
+    /// Attach it to the parent line as `ruby`.
+    Ruby,
+    /// Leave it in the results like any other line.
+    Keep,
+}
+
 #[derive(Clone)]
 pub struct MergeConfig {
     pub enabled: bool,
     pub font_size_ratio: f64,
     pub add_space_on_merge: Option<bool>,
     pub language: OcrLanguage,
+    /// Keep the source lines of merged results in `OcrResult::lines`.
+    pub keep_source_lines: bool,
+    /// Scales the distance thresholds between lines. Above 1 merges more eagerly.
+    pub gap_scale: f64,
+    /// Never merge lines further apart than this, in multiples of the font size.
+    pub max_merge_gap: Option<f64>,
+    pub orientation_bias: OrientationBias,
+    pub furigana: FuriganaMode,
+    /// Only merge lines that lie in the same detected speech bubble.
+    pub respect_bubbles: bool,
 }
 
 impl Default for MergeConfig {
@@ -30,10 +66,88 @@ impl Default for MergeConfig {
             font_size_ratio: 3.0,
             add_space_on_merge: None,
             language: OcrLanguage::default(),
+            keep_source_lines: false,
+            gap_scale: 1.0,
+            max_merge_gap: None,
+            orientation_bias: OrientationBias::Auto,
+            furigana: FuriganaMode::Drop,
+            respect_bubbles: true,
         }
     }
 }
 
+/// Per-request merge tuning, e.g. tighter for dense manga or looser for sparse webtoons.
+/// Unset or invalid fields keep the defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MergeOverrides {
+    /// `false` returns the unmerged backend lines.
+    pub merge_enabled: Option<bool>,
+    pub font_size_ratio: Option<f64>,
+    pub merge_gap_scale: Option<f64>,
+    pub max_merge_gap: Option<f64>,
+    #[serde(alias = "orientation")]
+    pub orientation_bias: Option<OrientationBias>,
+    pub furigana: Option<FuriganaMode>,
+    pub respect_bubbles: Option<bool>,
+}
+
+impl MergeConfig {
+    pub fn with_overrides(mut self, overrides: &MergeOverrides) -> Self {
+        let positive = |value: Option<f64>| value.filter(|value| value.is_finite() && *value > 0.0);
+        if let Some(enabled) = overrides.merge_enabled {
+            self.enabled = enabled;
+        }
+        if let Some(ratio) = positive(overrides.font_size_ratio) {
+            self.font_size_ratio = ratio.max(1.0);
+        }
+        if let Some(scale) = positive(overrides.merge_gap_scale) {
+            self.gap_scale = scale;
+        }
+        if let Some(max_gap) = positive(overrides.max_merge_gap) {
+            self.max_merge_gap = Some(max_gap);
+        }
+        if let Some(bias) = overrides.orientation_bias {
+            self.orientation_bias = bias;
+        }
+        if let Some(furigana) = overrides.furigana {
+            self.furigana = furigana;
+        }
+        if let Some(respect_bubbles) = overrides.respect_bubbles {
+            self.respect_bubbles = respect_bubbles;
+        }
+        self
+    }
+
+    /// The tuning that differs from the defaults, e.g. `furigana=ruby,ratio=2.5`, or
+    /// `None` for the default tuning. Goes into the cache key, see `logic::page_cache_key`.
+    pub fn cache_variant(&self) -> Option<String> {
+        let default = Self::default();
+        let mut parts = Vec::new();
+        if self.enabled != default.enabled {
+            parts.push("unmerged".to_string());
+        }
+        if self.font_size_ratio != default.font_size_ratio {
+            parts.push(format!("ratio={}", self.font_size_ratio));
+        }
+        if self.gap_scale != default.gap_scale {
+            parts.push(format!("gap={}", self.gap_scale));
+        }
+        if let Some(max_gap) = self.max_merge_gap {
+            parts.push(format!("max-gap={max_gap}"));
+        }
+        if self.orientation_bias != default.orientation_bias {
+            parts.push(format!("orientation={:?}", self.orientation_bias).to_lowercase());
+        }
+        if self.furigana != default.furigana {
+            parts.push(format!("furigana={:?}", self.furigana).to_lowercase());
+        }
+        if self.respect_bubbles != default.respect_bubbles {
+            parts.push("ignore-bubbles".to_string());
+        }
+        (!parts.is_empty()).then(|| parts.join(","))
+    }
+}
+
 // --- Geometry Helpers ---
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -183,6 +297,7 @@ fn filter_bad_boxes(
     }
 
     // 3. Furigana Check (Japanese only)
+    let mut furigana_parent: Vec<Option<usize>> = vec![None; n];
     if config.language.is_japanese() {
         for i in 0..n {
             if !keep[i] {
@@ -239,18 +354,42 @@ fn filter_bad_boxes(
                     && y_gap_h < proximity_limit
                     && x_overlap_h > 0.0;
 
-                if is_vertical_furigana || is_horizontal_furigana {
-                    keep[j] = false;
+                if (is_vertical_furigana || is_horizontal_furigana) && furigana_parent[j].is_none()
+                {
+                    furigana_parent[j] = Some(i);
+                    if config.furigana != FuriganaMode::Keep {
+                        keep[j] = false;
+                    }
                 }
             }
         }
     }
 
+    let mut ruby: Vec<Vec<RubyText>> = vec![Vec::new(); n];
+    if config.furigana == FuriganaMode::Ruby {
+        for (j, parent) in furigana_parent.iter().enumerate() {
+            if let Some(i) = *parent {
+                ruby[i].push(RubyText {
+                    text: lines[j].text.clone(),
+                    tight_bounding_box: lines[j].tight_bounding_box.clone(),
+                });
+            }
+        }
+    }
+
     lines
         .into_iter()
+        .zip(ruby)
         .enumerate()
         .filter(|(i, _)| keep[*i])
-        .map(|(_, l)| l)
+        .map(|(_, (mut l, mut ruby))| {
+            ruby.sort_by(|a, b| {
+                let (ba, bb) = (&a.tight_bounding_box, &b.tight_bounding_box);
+                ba.y.total_cmp(&bb.y).then(ba.x.total_cmp(&bb.x))
+            });
+            l.ruby.extend(ruby);
+            l
+        })
         .collect()
 }
 
@@ -258,6 +397,7 @@ fn filter_bad_boxes(
 
 struct ProcessedLine {
     is_vertical: bool,
+    bubble_id: Option<u32>,
     font_size: f64,
     length_main: f64,
     min_main: f64,
@@ -295,6 +435,14 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
         return false;
     }
 
+    let in_different_bubbles = a
+        .bubble_id
+        .zip(b.bubble_id)
+        .is_some_and(|(bubble_a, bubble_b)| bubble_a != bubble_b);
+    if config.respect_bubbles && in_different_bubbles {
+        return false;
+    }
+
     let max_font = a.font_size.max(b.font_size);
     let min_font = a.font_size.min(b.font_size);
     let font_ratio = max_font / min_font;
@@ -320,6 +468,13 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
 
     // --- REFINED TIERED STRATEGY (INVERTED LOGIC) ---
 
+    if config
+        .max_merge_gap
+        .is_some_and(|max_gap| gap_cross > base_metric * max_gap)
+    {
+        return false;
+    }
+
     // 1. TOUCHING: Merge anything that touches horizontally.
     if gap_cross < base_metric * 0.2 {
         return true;
@@ -363,7 +518,7 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
         }
     }
 
-    if gap_cross > base_metric * allowed_gap {
+    if gap_cross > base_metric * allowed_gap * config.gap_scale {
         return false;
     }
 
@@ -372,7 +527,7 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
         let gap_main = 0.0f64
             .max(b.min_main - a.max_main)
             .max(a.min_main - b.max_main);
-        if gap_main > base_metric * 0.6 {
+        if gap_main > base_metric * 0.6 * config.gap_scale {
             return false;
         }
     }
@@ -380,8 +535,26 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
     true
 }
 
-pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -> Vec<OcrResult> {
+/// Labels every line with a forced orientation; merged lines get it from `auto_merge`.
+fn force_orientation(lines: &mut [OcrResult], bias: OrientationBias) {
+    let orientation = match bias {
+        OrientationBias::Vertical => "vertical",
+        OrientationBias::Horizontal => "horizontal",
+        OrientationBias::Auto => return,
+    };
+    for line in lines {
+        line.forced_orientation = Some(orientation.to_string());
+    }
+}
+
+pub fn auto_merge(
+    mut lines: Vec<OcrResult>,
+    w: u32,
+    h: u32,
+    config: &MergeConfig,
+) -> Vec<OcrResult> {
     if !config.enabled || lines.is_empty() {
+        force_orientation(&mut lines, config.orientation_bias);
         return lines;
     }
 
@@ -395,15 +568,18 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
             let lens_is_vertical = l.forced_orientation.as_deref() == Some("vertical");
             let char_count = l.text.chars().count();
 
-            let is_v = if prefers_vertical {
-                if char_count == 1 {
-                    b.height > b.width * 0.8
-                } else {
-                    let is_physically_vertical = b.height > b.width;
-                    lens_is_vertical || is_physically_vertical
+            let is_v = match config.orientation_bias {
+                OrientationBias::Vertical => true,
+                OrientationBias::Horizontal => false,
+                OrientationBias::Auto if prefers_vertical => {
+                    if char_count == 1 {
+                        b.height > b.width * 0.8
+                    } else {
+                        let is_physically_vertical = b.height > b.width;
+                        lens_is_vertical || is_physically_vertical
+                    }
                 }
-            } else {
-                lens_is_vertical && b.height > b.width * 1.1
+                OrientationBias::Auto => lens_is_vertical && b.height > b.width * 1.1,
             };
 
             let (min_main, max_main, min_cross, max_cross) = if is_v {
@@ -414,6 +590,7 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
 
             ProcessedLine {
                 is_vertical: is_v,
+                bubble_id: l.bubble_id,
                 font_size: if is_v { b.width } else { b.height },
                 length_main: if is_v { b.height } else { b.width },
                 min_main,
@@ -520,6 +697,31 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
         }
         let (cx, cy, w, h, _rot) = calculate_aabb(&points);
 
+        // Character-weighted mean over the lines that report a confidence.
+        let (weighted_confidence, weighted_chars) = group_lines
+            .iter()
+            .filter_map(|l| {
+                l.confidence
+                    .map(|confidence| (confidence, l.text.chars().count().max(1) as f32))
+            })
+            .fold((0.0, 0.0), |(sum, chars), (confidence, count)| {
+                (sum + confidence * count, chars + count)
+            });
+        let confidence = (weighted_chars > 0.0).then(|| weighted_confidence / weighted_chars);
+
+        let block_id = group_lines[0]
+            .block_id
+            .filter(|block_id| group_lines.iter().all(|l| l.block_id == Some(*block_id)));
+        let ruby = group_lines
+            .iter()
+            .flat_map(|l| l.ruby.iter().cloned())
+            .collect();
+        let source_lines = if config.keep_source_lines {
+            group_lines.iter().copied().cloned().collect()
+        } else {
+            Vec::new()
+        };
+
         results.push(OcrResult {
             text: text_content,
             tight_bounding_box: BoundingBox {
@@ -535,7 +737,95 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
             } else {
                 "horizontal".into()
             }),
+            confidence,
+            block_id,
+            lines: source_lines,
+            ruby,
+            order: None,
+            bubble_id: None,
+            full_width: None,
+            full_height: None,
+            animated: None,
+            translation: None,
         });
     }
     results
 }
+
+/// Merges lines from another OCR engine for `POST /merge`. Boxes are pixels of a
+/// `width`×`height` image, or fractions of it with `normalized`; results come back
+/// normalized and in reading order, like `/ocr`.
+pub fn merge_external_lines(
+    mut lines: Vec<OcrResult>,
+    width: u32,
+    height: u32,
+    normalized: bool,
+    config: &MergeConfig,
+) -> Vec<OcrResult> {
+    let (w, h) = (f64::from(width), f64::from(height));
+    if normalized {
+        for line in &mut lines {
+            let b = &mut line.tight_bounding_box;
+            (b.x, b.y, b.width, b.height) = (b.x * w, b.y * h, b.width * w, b.height * h);
+        }
+    }
+    lines.retain(|line| {
+        let b = &line.tight_bounding_box;
+        [b.x, b.y, b.width, b.height]
+            .iter()
+            .all(|value| value.is_finite())
+    });
+
+    let to_fractions = |b: &mut BoundingBox| {
+        (b.x, b.y, b.width, b.height) = (b.x / w, b.y / h, b.width / w, b.height / h);
+    };
+    let mut results = auto_merge(lines, width, height, config);
+    for result in &mut results {
+        to_fractions(&mut result.tight_bounding_box);
+        for line in &mut result.lines {
+            to_fractions(&mut line.tight_bounding_box);
+        }
+        for ruby in &mut result.ruby {
+            to_fractions(&mut ruby.tight_bounding_box);
+        }
+        result.full_width = Some(width);
+        result.full_height = Some(height);
+    }
+    sort_reading_order(results, config.language)
+}
+
+/// Sorts page results into reading order and numbers them through `OcrResult::order`.
+///
+/// Results are grouped into horizontal bands of vertically overlapping boxes, read top to
+/// bottom. Within a band, vertical-text languages read right to left (by right edge),
+/// others left to right.
+pub fn sort_reading_order(mut results: Vec<OcrResult>, language: OcrLanguage) -> Vec<OcrResult> {
+    let right_to_left = language.prefers_vertical();
+    results.sort_by(|a, b| a.tight_bounding_box.y.total_cmp(&b.tight_bounding_box.y));
+
+    let mut band = 0usize;
+    let mut band_bottom = f64::NEG_INFINITY;
+    let mut keyed: Vec<(usize, f64, OcrResult)> = Vec::with_capacity(results.len());
+    for result in results {
+        let b = &result.tight_bounding_box;
+        let overlap = band_bottom - b.y;
+        if !keyed.is_empty() && overlap < b.height * 0.5 {
+            band += 1;
+            band_bottom = b.y + b.height;
+        } else {
+            band_bottom = band_bottom.max(b.y + b.height);
+        }
+        let key = if right_to_left { -(b.x + b.width) } else { b.x };
+        keyed.push((band, key, result));
+    }
+
+    keyed.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    keyed
+        .into_iter()
+        .enumerate()
+        .map(|(order, (_, _, mut result))| {
+            result.order = Some(order as u32);
+            result
+        })
+        .collect()
+}
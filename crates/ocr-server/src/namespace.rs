@@ -0,0 +1,84 @@
+//! Cache key namespaces, for one cache shared by several Suwayomi servers or sources.
+//!
+//! Page keys are URL paths, so two servers that both serve
+//! `/api/v1/manga/1/chapter/1/page/0` would share one entry. `MANATAN_OCR_CACHE_NAMESPACE`
+//! prefixes every key with `ns/<name>/`:
+//! - `host`: a server fingerprint, the `host:port` of each URL. Keys of URLs without a
+//!   host (local files, uploads) stay unprefixed.
+//! - any other value: that name, e.g. a source id, for every key.
+//!
+//! Entries cached before the namespace was set are migrated: with a fixed name they are
+//! renamed once at startup, with `host` a lookup that misses claims the unprefixed entry
+//! for the same path, so the first server to ask for a page keeps its cached text. Keys
+//! of an earlier namespace are left where they are.
+
+use std::sync::LazyLock;
+
+const NAMESPACE_ENV: &str = "MANATAN_OCR_CACHE_NAMESPACE";
+/// Prefix of namespaced keys; plain keys start with `lang/` or `/`.
+pub const NAMESPACE_PREFIX: &str = "ns/";
+
+static CURRENT: LazyLock<CacheNamespace> = LazyLock::new(|| {
+    manatan_config::var(NAMESPACE_ENV)
+        .map(|value| CacheNamespace::parse(&value))
+        .unwrap_or_default()
+});
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CacheNamespace {
+    /// Keys are URL paths only.
+    #[default]
+    None,
+    /// One namespace per server, taken from each URL.
+    Host,
+    /// The same namespace for every key.
+    Fixed(String),
+}
+
+impl CacheNamespace {
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim().trim_matches('/');
+        if value.is_empty() {
+            Self::None
+        } else if value.eq_ignore_ascii_case("host") {
+            Self::Host
+        } else {
+            Self::Fixed(value.replace('/', "_"))
+        }
+    }
+
+    /// The namespace for keys of `url`, if any.
+    pub fn for_url(&self, url: &str) -> Option<String> {
+        match self {
+            Self::None => None,
+            Self::Fixed(name) => Some(name.clone()),
+            Self::Host => {
+                let parsed = reqwest::Url::parse(url).ok()?;
+                let host = parsed.host_str()?;
+                Some(match parsed.port() {
+                    Some(port) => format!("{host}:{port}"),
+                    None => host.to_string(),
+                })
+            }
+        }
+    }
+}
+
+/// The namespace configured through `MANATAN_OCR_CACHE_NAMESPACE`.
+pub fn current() -> &'static CacheNamespace {
+    &CURRENT
+}
+
+/// Prefixes `key` with `namespace`; `strip` undoes it.
+pub fn apply(namespace: Option<&str>, key: String) -> String {
+    match namespace {
+        Some(namespace) => format!("{NAMESPACE_PREFIX}{namespace}/{key}"),
+        None => key,
+    }
+}
+
+/// The key `key` had before namespacing, `None` for keys without a namespace.
+pub fn strip(key: &str) -> Option<&str> {
+    let (_, rest) = key.strip_prefix(NAMESPACE_PREFIX)?.split_once('/')?;
+    Some(rest)
+}
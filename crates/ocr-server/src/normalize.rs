@@ -0,0 +1,104 @@
+//! Corrections for characters Lens commonly confuses in Japanese manga.
+//!
+//! Applied to every recognized Japanese line before it is merged and cached, unless
+//! `MANATAN_OCR_JA_NORMALIZE` is set to `0` or `false`.
+
+use std::sync::LazyLock;
+
+use unicode_normalization::UnicodeNormalization;
+
+const JA_NORMALIZE_ENV: &str = "MANATAN_OCR_JA_NORMALIZE";
+
+static ENABLED: LazyLock<bool> = LazyLock::new(|| {
+    manatan_config::var(JA_NORMALIZE_ENV)
+        .map(|value| !matches!(value.trim().to_ascii_lowercase().as_str(), "0" | "false"))
+        .unwrap_or(true)
+});
+
+/// Marks read as a long vowel when they follow katakana (`一`, `|`, dashes). Not after
+/// hiragana, where `一` is usually the kanji (`もう一度`, `この一つ`).
+const LONG_VOWEL_LOOKALIKES: &[char] = &['一', '|', '丨', '-', '‐', '—', '―', '─'];
+
+/// Kanji that look like katakana, with the katakana they are usually meant to be.
+const KATAKANA_LOOKALIKES: &[(char, char)] = &[
+    ('口', 'ロ'),
+    ('力', 'カ'),
+    ('工', 'エ'),
+    ('夕', 'タ'),
+    ('卜', 'ト'),
+    ('二', 'ニ'),
+    ('八', 'ハ'),
+];
+
+/// Longest run of Latin letters treated as noise when surrounded by kana.
+const MAX_STRAY_LATIN: usize = 2;
+
+pub fn is_enabled() -> bool {
+    *ENABLED
+}
+
+fn is_hiragana(ch: char) -> bool {
+    ('\u{3041}'..='\u{309F}').contains(&ch)
+}
+
+fn is_katakana(ch: char) -> bool {
+    ('\u{30A0}'..='\u{30FF}').contains(&ch)
+}
+
+fn is_kana(ch: char) -> bool {
+    is_hiragana(ch) || is_katakana(ch)
+}
+
+fn is_kanji(ch: char) -> bool {
+    ('\u{4E00}'..='\u{9FFF}').contains(&ch)
+}
+
+/// NFKC, then long-vowel, katakana look-alike and stray Latin fixes.
+pub fn normalize_japanese(text: &str) -> String {
+    let chars: Vec<char> = text.nfkc().collect();
+    let mut out: Vec<char> = Vec::with_capacity(chars.len());
+
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        let prev = out.last().copied();
+        let next = chars.get(index + 1).copied();
+
+        // Short Latin runs inside kana, e.g. "あlい", are misread strokes.
+        if ch.is_ascii_alphabetic() && prev.is_some_and(is_kana) {
+            let run = chars[index..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphabetic())
+                .count();
+            if run <= MAX_STRAY_LATIN && chars.get(index + run).copied().is_some_and(is_kana) {
+                index += run;
+                continue;
+            }
+        }
+
+        // A kanji or numeral after it makes it part of a word or number, e.g. `テスト一回`.
+        if LONG_VOWEL_LOOKALIKES.contains(&ch)
+            && prev.is_some_and(is_katakana)
+            && !next.is_some_and(|next| is_kanji(next) || next.is_numeric())
+        {
+            out.push('ー');
+            index += 1;
+            continue;
+        }
+
+        if let Some(&(_, katakana)) = KATAKANA_LOOKALIKES.iter().find(|(kanji, _)| *kanji == ch) {
+            let touches_katakana = prev.is_some_and(is_katakana) || next.is_some_and(is_katakana);
+            let touches_kanji = prev.is_some_and(is_kanji) || next.is_some_and(is_kanji);
+            if touches_katakana && !touches_kanji {
+                out.push(katakana);
+                index += 1;
+                continue;
+            }
+        }
+
+        out.push(ch);
+        index += 1;
+    }
+
+    out.into_iter().collect()
+}
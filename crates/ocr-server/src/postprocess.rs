@@ -0,0 +1,95 @@
+//! User-defined regex replacement rules applied to recognized text.
+//!
+//! Rules live in the `postprocess_rules` table and are compiled into a process-wide list
+//! whenever they change, so `logic::post_process_text` can apply them without a database
+//! round trip per line.
+
+use std::sync::{LazyLock, RwLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::language::OcrLanguage;
+
+static COMPILED_RULES: LazyLock<RwLock<Vec<CompiledRule>>> =
+    LazyLock::new(|| RwLock::new(Vec::new()));
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplacementRule {
+    pub id: i64,
+    /// `None` applies the rule to every language.
+    pub language: Option<OcrLanguage>,
+    pub pattern: String,
+    pub replacement: String,
+    pub enabled: bool,
+    pub created_at: i64,
+}
+
+/// Body of create and update requests.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RuleInput {
+    pub language: Option<OcrLanguage>,
+    pub pattern: String,
+    #[serde(default)]
+    pub replacement: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl RuleInput {
+    /// Checks that the pattern compiles, returning the regex error otherwise.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.pattern.is_empty() {
+            return Err("pattern must not be empty".to_string());
+        }
+        Regex::new(&self.pattern)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+}
+
+struct CompiledRule {
+    language: Option<OcrLanguage>,
+    regex: Regex,
+    replacement: String,
+}
+
+/// Replaces the active rule set. Disabled rules and invalid patterns are skipped.
+pub fn set_rules(rules: &[ReplacementRule]) {
+    let compiled = rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| match Regex::new(&rule.pattern) {
+            Ok(regex) => Some(CompiledRule {
+                language: rule.language,
+                regex,
+                replacement: rule.replacement.clone(),
+            }),
+            Err(err) => {
+                tracing::warn!("Skipping post-processing rule {}: {err}", rule.id);
+                None
+            }
+        })
+        .collect();
+    *COMPILED_RULES.write().expect("lock poisoned") = compiled;
+}
+
+/// Applies the active rules for `language`, in creation order.
+pub fn apply_rules(text: String, language: OcrLanguage) -> String {
+    let rules = COMPILED_RULES.read().expect("lock poisoned");
+    rules
+        .iter()
+        .filter(|rule| {
+            rule.language
+                .is_none_or(|rule_language| rule_language == language)
+        })
+        .fold(text, |text, rule| {
+            rule.regex
+                .replace_all(&text, rule.replacement.as_str())
+                .into_owned()
+        })
+}
@@ -0,0 +1,51 @@
+//! Read-ahead for page-by-page reading.
+//!
+//! `/ocr` with `prefetch=K` (or `MANATAN_OCR_PREFETCH_PAGES` as the default) and a
+//! `base_url` queues the next `K` pages of the chapter in the background, so turning the
+//! page hits the cache. Pages are only predicted for Suwayomi page URLs
+//! (`.../page/<n>`) of chapters whose page count is known or can be resolved.
+
+use std::sync::LazyLock;
+
+const PREFETCH_PAGES_ENV: &str = "MANATAN_OCR_PREFETCH_PAGES";
+/// Upper bound for `prefetch`, so one request can't queue a whole volume.
+pub const MAX_PREFETCH_PAGES: usize = 10;
+
+static DEFAULT_PAGES: LazyLock<usize> = LazyLock::new(|| {
+    manatan_config::var(PREFETCH_PAGES_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(MAX_PREFETCH_PAGES)
+});
+
+/// Pages to read ahead for a request, `0` when prefetching is off.
+pub fn pages_ahead(requested: Option<usize>) -> usize {
+    requested.unwrap_or(*DEFAULT_PAGES).min(MAX_PREFETCH_PAGES)
+}
+
+/// URLs of the `count` pages after `url` in a chapter of `page_count` pages. Empty when
+/// `url` is not a `.../page/<n>` URL. The query string is kept.
+pub fn following_pages(url: &str, page_count: usize, count: usize) -> Vec<String> {
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url, None),
+    };
+    let path = path.trim_end_matches('/');
+    let Some((prefix, index)) = path.rsplit_once('/') else {
+        return Vec::new();
+    };
+    let Ok(index) = index.parse::<usize>() else {
+        return Vec::new();
+    };
+    if !prefix.ends_with("/page") {
+        return Vec::new();
+    }
+    let end = page_count.min(index.saturating_add(count).saturating_add(1));
+    (index + 1..end)
+        .map(|next| match query {
+            Some(query) => format!("{prefix}/{next}?{query}"),
+            None => format!("{prefix}/{next}"),
+        })
+        .collect()
+}
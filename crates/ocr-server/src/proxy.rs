@@ -0,0 +1,184 @@
+//! Proxy selection for outgoing OCR traffic.
+//!
+//! By default the SOCKS proxy configured in Suwayomi is used. Its settings are read from
+//! the Suwayomi REST API and cached for `MANATAN_OCR_PROXY_CACHE_SECS` (default 60), so
+//! a chapter job doesn't query them once per page. `MANATAN_OCR_PROXY` replaces the
+//! lookup: `none` disables proxying without contacting Suwayomi (for deployments
+//! without it), any other value is used as the proxy URL. Requests can pass `proxy`
+//! with the same syntax to override both.
+//!
+//! Page images normally come straight from the local Suwayomi. When Suwayomi has its
+//! SOCKS proxy enabled, or else `MANATAN_OCR_IMAGE_PROXY` is set (any proxy URL reqwest
+//! accepts), they are instead fetched from the host the page URL names, through that
+//! proxy, so a fully proxied setup makes no direct requests. Loopback hosts are still
+//! reached directly. A `MANATAN_OCR_PROXY` other than `suwayomi` skips the Suwayomi
+//! lookup here too.
+
+use std::{
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    auth::SuwayomiAuth,
+    logic::{self, ProxySettings},
+};
+
+const PROXY_ENV: &str = "MANATAN_OCR_PROXY";
+const CACHE_SECS_ENV: &str = "MANATAN_OCR_PROXY_CACHE_SECS";
+const IMAGE_PROXY_ENV: &str = "MANATAN_OCR_IMAGE_PROXY";
+const LOOPBACK_HOSTS: &str = "localhost,127.0.0.1,::1";
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+static ENV_MODE: LazyLock<Option<ProxyMode>> = LazyLock::new(|| {
+    manatan_config::var(PROXY_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .map(ProxyMode::from)
+});
+
+static CACHE_TTL: LazyLock<Duration> = LazyLock::new(|| {
+    manatan_config::var(CACHE_SECS_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_CACHE_TTL)
+});
+
+static SUWAYOMI_CACHE: LazyLock<Mutex<Option<CachedLookup>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Where the proxy for a request comes from.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ProxyMode {
+    /// Use Suwayomi's SOCKS proxy settings.
+    #[default]
+    Suwayomi,
+    /// Connect directly.
+    Disabled,
+    /// Use this proxy URL, e.g. `socks5://127.0.0.1:1080`.
+    Url(String),
+}
+
+impl From<String> for ProxyMode {
+    fn from(value: String) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "suwayomi" => Self::Suwayomi,
+            "none" | "off" | "false" | "0" => Self::Disabled,
+            _ => Self::Url(value.trim().to_string()),
+        }
+    }
+}
+
+impl From<ProxyMode> for String {
+    fn from(mode: ProxyMode) -> Self {
+        match mode {
+            ProxyMode::Suwayomi => "suwayomi".to_string(),
+            ProxyMode::Disabled => "none".to_string(),
+            ProxyMode::Url(url) => url,
+        }
+    }
+}
+
+struct CachedLookup {
+    auth: SuwayomiAuth,
+    fetched_at: Instant,
+    proxy_url: Option<String>,
+}
+
+impl ProxySettings {
+    /// The proxy URL, or `None` when Suwayomi has no SOCKS proxy enabled.
+    pub(crate) fn url(&self) -> Option<String> {
+        if !self.socks_proxy_enabled || self.socks_proxy_host.is_empty() {
+            return None;
+        }
+        let credentials = match (&self.socks_proxy_username, &self.socks_proxy_password) {
+            (Some(username), Some(password)) => format!("{username}:{password}@"),
+            _ => String::new(),
+        };
+        Some(format!(
+            "socks{}://{credentials}{}:{}",
+            self.socks_proxy_version, self.socks_proxy_host, self.socks_proxy_port
+        ))
+    }
+}
+
+/// Strips `user:password@` from a proxy URL for logging.
+pub(crate) fn redact_credentials(proxy_url: &str) -> String {
+    match (proxy_url.split_once("://"), proxy_url.rsplit_once('@')) {
+        (Some((scheme, _)), Some((_, host))) => format!("{scheme}://{host}"),
+        _ => proxy_url.to_string(),
+    }
+}
+
+/// Resolves the proxy URL to use, preferring `request_mode` over `MANATAN_OCR_PROXY`.
+pub async fn resolve(request_mode: Option<&ProxyMode>, auth: &SuwayomiAuth) -> Option<String> {
+    let mode = request_mode
+        .or(ENV_MODE.as_ref())
+        .cloned()
+        .unwrap_or_default();
+    match mode {
+        ProxyMode::Disabled => None,
+        ProxyMode::Url(url) => Some(url),
+        ProxyMode::Suwayomi => suwayomi_proxy_url(auth).await,
+    }
+}
+
+async fn suwayomi_proxy_url(auth: &SuwayomiAuth) -> Option<String> {
+    {
+        let cache = SUWAYOMI_CACHE.lock().expect("lock poisoned");
+        if let Some(cached) = cache.as_ref()
+            && cached.auth == *auth
+            && cached.fetched_at.elapsed() < *CACHE_TTL
+        {
+            return cached.proxy_url.clone();
+        }
+    }
+
+    // Failed lookups are cached too, so an unreachable Suwayomi isn't retried per page.
+    let proxy_url = match logic::get_proxy_settings(auth).await {
+        Ok(settings) => settings.and_then(|settings| settings.url()),
+        Err(err) => {
+            tracing::warn!("Failed to read Suwayomi proxy settings: {err}");
+            None
+        }
+    };
+    *SUWAYOMI_CACHE.lock().expect("lock poisoned") = Some(CachedLookup {
+        auth: auth.clone(),
+        fetched_at: Instant::now(),
+        proxy_url: proxy_url.clone(),
+    });
+    proxy_url
+}
+
+/// The proxy for page image fetches: Suwayomi's SOCKS proxy if enabled, else
+/// `MANATAN_OCR_IMAGE_PROXY` if set.
+pub async fn image_proxy(auth: &SuwayomiAuth) -> Option<String> {
+    let suwayomi = match ENV_MODE.as_ref() {
+        None | Some(ProxyMode::Suwayomi) => suwayomi_proxy_url(auth).await,
+        Some(_) => None,
+    };
+    suwayomi.or_else(|| {
+        manatan_config::var(IMAGE_PROXY_ENV)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    })
+}
+
+/// HTTP client for fetching page images, see the module docs.
+pub fn image_client(image_proxy: Option<&str>) -> anyhow::Result<reqwest::Client> {
+    let Some(proxy_url) = image_proxy else {
+        return Ok(reqwest::Client::new());
+    };
+    let proxy = reqwest::Proxy::all(proxy_url.trim())
+        .map_err(|err| {
+            anyhow::anyhow!(
+                "Invalid image proxy {}: {err}",
+                redact_credentials(proxy_url)
+            )
+        })?
+        .no_proxy(reqwest::NoProxy::from_string(LOOPBACK_HOSTS));
+    Ok(reqwest::Client::builder().proxy(proxy).build()?)
+}
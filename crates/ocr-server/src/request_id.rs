@@ -0,0 +1,83 @@
+//! Request IDs for correlating client reports with server logs.
+//!
+//! Every request runs inside a `request` tracing span carrying its ID, and the ID is
+//! echoed back in the `X-Request-Id` response header. Clients may supply their own ID in
+//! the same header. Jobs queued by a request keep the ID, so their logs carry it too.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied ID that is reused as-is.
+const MAX_CLIENT_ID_LEN: usize = 64;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// The ID of the request being handled by the current task, if any.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+fn generate() -> String {
+    // The start time keeps IDs from repeating across restarts.
+    let started = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let sequence = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    format!("{:08x}-{sequence:06x}", started as u32)
+}
+
+fn client_id(request: &Request) -> Option<String> {
+    let value = request.headers().get(&REQUEST_ID_HEADER)?.to_str().ok()?;
+    let value = value.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_CLIENT_ID_LEN
+        && value
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'));
+    valid.then(|| value.to_string())
+}
+
+/// Middleware assigning the request ID, see the module docs.
+pub async fn assign(request: Request, next: Next) -> Response {
+    let id = client_id(&request).unwrap_or_else(generate);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %id,
+        method = %request.method(),
+        path = %request.uri().path(),
+    );
+
+    let mut response = CURRENT
+        .scope(id.clone(), next.run(request))
+        .instrument(span)
+        .await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Span for work queued by a request, e.g. a chapter job run by a worker.
+pub fn job_span(kind: &'static str, request_id: Option<&str>) -> tracing::Span {
+    match request_id {
+        Some(request_id) => tracing::info_span!("job", kind, request_id),
+        None => tracing::info_span!("job", kind),
+    }
+}
@@ -0,0 +1,135 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+const RETRY_ATTEMPTS_ENV: &str = "MANATAN_OCR_RETRY_ATTEMPTS";
+const RETRY_BACKOFF_ENV: &str = "MANATAN_OCR_RETRY_BACKOFF";
+const RETRY_DELAY_MS_ENV: &str = "MANATAN_OCR_RETRY_DELAY_MS";
+const ATTEMPT_TIMEOUT_MS_ENV: &str = "MANATAN_OCR_ATTEMPT_TIMEOUT_MS";
+
+/// How the delay between attempts grows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackoffStrategy {
+    /// `delay`, `delay`, `delay`, ...
+    Constant,
+    /// `delay`, `2 * delay`, `3 * delay`, ...
+    #[default]
+    Linear,
+    /// `delay`, `2 * delay`, `4 * delay`, ...
+    Exponential,
+}
+
+impl BackoffStrategy {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "constant" => Some(Self::Constant),
+            "linear" => Some(Self::Linear),
+            "exponential" => Some(Self::Exponential),
+            _ => None,
+        }
+    }
+}
+
+/// Retry behaviour of `logic::fetch_and_process`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff: BackoffStrategy,
+    pub base_delay_ms: u64,
+    /// Upper bound for a single attempt (fetch + OCR). `None` waits indefinitely.
+    pub attempt_timeout_ms: Option<u64>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: BackoffStrategy::Linear,
+            base_delay_ms: 1000,
+            attempt_timeout_ms: None,
+        }
+    }
+}
+
+/// Per-request overrides; unset fields keep the server-wide policy.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct RetryOverrides {
+    pub max_attempts: Option<u32>,
+    pub retry_backoff: Option<BackoffStrategy>,
+    pub retry_delay_ms: Option<u64>,
+    pub attempt_timeout_ms: Option<u64>,
+}
+
+fn env_u64(key: &str) -> Option<u64> {
+    manatan_config::var(key)
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_attempts: env_u64(RETRY_ATTEMPTS_ENV)
+                .map(|value| value.clamp(1, u32::MAX as u64) as u32)
+                .unwrap_or(defaults.max_attempts),
+            backoff: manatan_config::var(RETRY_BACKOFF_ENV)
+                .ok()
+                .and_then(|value| BackoffStrategy::parse(&value))
+                .unwrap_or(defaults.backoff),
+            base_delay_ms: env_u64(RETRY_DELAY_MS_ENV).unwrap_or(defaults.base_delay_ms),
+            attempt_timeout_ms: env_u64(ATTEMPT_TIMEOUT_MS_ENV).filter(|value| *value > 0),
+        }
+    }
+
+    pub fn with_overrides(self, overrides: RetryOverrides) -> Self {
+        Self {
+            max_attempts: overrides
+                .max_attempts
+                .map(|value| value.max(1))
+                .unwrap_or(self.max_attempts),
+            backoff: overrides.retry_backoff.unwrap_or(self.backoff),
+            base_delay_ms: overrides.retry_delay_ms.unwrap_or(self.base_delay_ms),
+            attempt_timeout_ms: match overrides.attempt_timeout_ms {
+                Some(0) => None,
+                Some(value) => Some(value),
+                None => self.attempt_timeout_ms,
+            },
+        }
+    }
+
+    /// Delay to wait after the given (1-based) failed attempt.
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let factor = match self.backoff {
+            BackoffStrategy::Constant => 1,
+            BackoffStrategy::Linear => attempt as u64,
+            BackoffStrategy::Exponential => 1u64 << attempt.saturating_sub(1).min(16),
+        };
+        Duration::from_millis(self.base_delay_ms.saturating_mul(factor))
+    }
+
+    pub fn attempt_timeout(&self) -> Option<Duration> {
+        self.attempt_timeout_ms.map(Duration::from_millis)
+    }
+}
+
+/// The last error of a retried operation, along with how many attempts were made.
+#[derive(Debug)]
+pub struct RetryError {
+    pub attempts: u32,
+    pub source: anyhow::Error,
+}
+
+impl fmt::Display for RetryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.attempts == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} (after {} attempt{plural})",
+            self.source, self.attempts
+        )
+    }
+}
+
+impl std::error::Error for RetryError {}
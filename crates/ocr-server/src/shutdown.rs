@@ -0,0 +1,139 @@
+//! Graceful shutdown for chapter jobs.
+//!
+//! Draining rejects new requests with 503, pauses the job queue so no new page starts,
+//! waits up to `MANATAN_OCR_SHUTDOWN_DEADLINE_SECS` (default 20) for pages already being
+//! OCR'd, aborts the Lens requests still outstanding, then writes each running chapter's
+//! progress to `chapter_pages` and checkpoints the write-ahead log into the database
+//! file. Interrupted jobs stay in `chapter_jobs` and resume on the next start.
+
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use tokio::sync::watch;
+
+use crate::state::AppState;
+
+const SHUTDOWN_DEADLINE_ENV: &str = "MANATAN_OCR_SHUTDOWN_DEADLINE_SECS";
+const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(20);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returned by `create_router_with_shutdown`; call `drain` before the process exits.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    state: AppState,
+    deadline: Duration,
+    drained: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new(state: AppState) -> Self {
+        let deadline = manatan_config::var(SHUTDOWN_DEADLINE_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SHUTDOWN_DEADLINE);
+        Self {
+            state,
+            deadline,
+            drained: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    /// Resolves once the first `drain` has finished.
+    pub async fn drained(&self) {
+        let mut drained = self.drained.subscribe();
+        let _ = drained.wait_for(|drained| *drained).await;
+    }
+
+    /// Stops new work and waits for in-flight pages, see the module docs.
+    /// Calling it again after the first drain is a no-op.
+    pub async fn drain(&self) {
+        let state = &self.state;
+        if state.shutting_down.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        state.job_queue.pause();
+        tracing::info!(
+            "[Shutdown] Draining OCR jobs ({} page(s) in flight)",
+            state.in_flight_pages.load(Ordering::SeqCst)
+        );
+
+        let started = Instant::now();
+        while state.in_flight_pages.load(Ordering::SeqCst) > 0 && started.elapsed() < self.deadline
+        {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+        let unfinished = state.in_flight_pages.load(Ordering::SeqCst);
+        if unfinished > 0 {
+            tracing::warn!(
+                "[Shutdown] Deadline of {:?} reached with {unfinished} page(s) still in flight, aborting Lens requests",
+                self.deadline
+            );
+        }
+        // Also fails `/ocr` requests still waiting on Lens, so the server can close.
+        crate::backend::lens::abort_requests();
+
+        let running: Vec<(String, usize)> = state
+            .active_chapter_jobs
+            .read()
+            .expect("lock poisoned")
+            .iter()
+            .map(|(chapter_key, progress)| (chapter_key.clone(), progress.total))
+            .collect();
+        for (chapter_key, total) in &running {
+            let processed = state.count_chapter_cache(chapter_key);
+            state
+                .set_chapter_progress(chapter_key, *total, processed)
+                .await;
+        }
+        tracing::info!("[Shutdown] Checkpointed {} chapter job(s)", running.len());
+
+        if let Err(err) = state.checkpoint_wal() {
+            tracing::warn!("[Shutdown] Failed to checkpoint the OCR database: {err}");
+        }
+        self.drained.send_replace(true);
+    }
+}
+
+/// Middleware rejecting requests once draining has started. Health checks still answer.
+pub async fn reject_while_draining(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let is_health_check = path.ends_with("/health") || path.ends_with("/ready");
+    if state.shutting_down.load(Ordering::SeqCst) && !is_health_check {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "OCR server is shutting down",
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Counts a page as in flight until dropped.
+pub(crate) struct PageGuard<'a>(&'a AppState);
+
+impl<'a> PageGuard<'a> {
+    pub(crate) fn new(state: &'a AppState) -> Self {
+        state.in_flight_pages.fetch_add(1, Ordering::SeqCst);
+        Self(state)
+    }
+}
+
+impl Drop for PageGuard<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight_pages.fetch_sub(1, Ordering::SeqCst);
+    }
+}
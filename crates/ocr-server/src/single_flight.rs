@@ -0,0 +1,67 @@
+//! Coalescing of concurrent work on the same key.
+//!
+//! When two clients ask for the same uncached page at once, the second one awaits the
+//! first one's OCR instead of starting its own. The shared work keeps running as long
+//! as any caller is still waiting for it.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+
+type InFlight<T> = Arc<Mutex<HashMap<String, Shared<BoxFuture<'static, T>>>>>;
+
+pub struct SingleFlight<T> {
+    in_flight: InFlight<T>,
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    /// Runs the future built by `work`, or joins the one already running for `key`.
+    /// Returns its output and whether it came from another caller's run.
+    pub async fn run<F>(&self, key: &str, work: impl FnOnce() -> F) -> (T, bool)
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let (shared, joined) = {
+            let mut in_flight = self.in_flight.lock().expect("lock poisoned");
+            match in_flight.get(key) {
+                Some(shared) => (shared.clone(), true),
+                None => {
+                    let registry = self.in_flight.clone();
+                    let owned_key = key.to_string();
+                    let work = work();
+                    let shared = async move {
+                        let output = work.await;
+                        registry.lock().expect("lock poisoned").remove(&owned_key);
+                        output
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(key.to_string(), shared.clone());
+                    (shared, false)
+                }
+            }
+        };
+        (shared.await, joined)
+    }
+
+    /// Number of keys currently being processed.
+    pub fn len(&self) -> usize {
+        self.in_flight.lock().expect("lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -1,8 +1,11 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
-    sync::{atomic::AtomicUsize, Arc, RwLock},
-    time::{SystemTime, UNIX_EPOCH},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicUsize},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use r2d2::Pool;
@@ -11,7 +14,24 @@ use rusqlite::{params, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use tracing::{info, warn};
 
-use crate::logic::OcrResult;
+use tokio::sync::broadcast;
+
+use crate::{
+    cache_ttl::CacheTtl,
+    concurrency::ConcurrencyLimits,
+    eviction::{CacheLimits, EvictionStats, EvictionSummary},
+    export::{ExportFilter, ExportRecord, ImportReport, ImportStrategy},
+    health::BackendHealth,
+    history::{self, HistoryVersion},
+    jobs::{ChapterJob, JobEvent, JobPriority, JobQueue},
+    logic::OcrResult,
+    manga::MangaJob,
+    namespace::{self, CacheNamespace},
+    postprocess::{self, ReplacementRule, RuleInput},
+    retry::RetryPolicy,
+    single_flight::SingleFlight,
+    translate::Translator,
+};
 
 #[derive(Clone, Copy, Serialize, Debug)]
 pub struct JobProgress {
@@ -26,6 +46,26 @@ pub struct AppState {
     pub active_jobs: Arc<AtomicUsize>,
     pub requests_processed: Arc<AtomicUsize>,
     pub active_chapter_jobs: Arc<RwLock<HashMap<String, JobProgress>>>,
+    /// Manga jobs in progress; `current` / `total` count chapters.
+    pub active_manga_jobs: Arc<RwLock<HashMap<String, JobProgress>>>,
+    pub job_queue: Arc<JobQueue>,
+    pub job_events: broadcast::Sender<JobEvent>,
+    pub retry_policy: RetryPolicy,
+    pub concurrency: Arc<ConcurrencyLimits>,
+    pub cache_limits: CacheLimits,
+    pub cache_ttl: Arc<CacheTtl>,
+    pub eviction_summary: Arc<RwLock<EvictionSummary>>,
+    pub backend_health: Arc<BackendHealth>,
+    /// Set once persisted jobs have been resumed and the local backends have loaded.
+    pub initialized: Arc<AtomicBool>,
+    /// Set when `ShutdownHandle::drain` starts.
+    pub shutting_down: Arc<AtomicBool>,
+    /// Job pages currently being fetched and OCR'd.
+    pub in_flight_pages: Arc<AtomicUsize>,
+    /// `/ocr` misses being processed, keyed by cache key; the error is a message.
+    pub ocr_in_flight: Arc<SingleFlight<Result<Vec<OcrResult>, String>>>,
+    /// Set when a translation provider is configured.
+    pub translator: Option<Arc<Translator>>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -34,8 +74,49 @@ pub struct CacheEntry {
     pub data: Vec<OcrResult>,
 }
 
+/// Row counts behind a chapter's status, see `AppState::chapter_cache_statuses`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChapterCacheStatus {
+    /// Pages recorded in `chapter_cache`.
+    pub cached_pages: usize,
+    /// Known length of the chapter, from `chapter_pages`.
+    pub page_count: Option<usize>,
+}
+
+/// Outcome of `AppState::run_maintenance`. Sizes include the write-ahead log.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct MaintenanceReport {
+    pub full_vacuum: bool,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub reclaimed_bytes: u64,
+    pub duration_ms: u64,
+}
+
 pub type DbPool = Pool<SqliteConnectionManager>;
 
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const BUSY_RETRY_ATTEMPTS: u32 = 3;
+/// `PRAGMA auto_vacuum` value for incremental mode.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+/// Prefix of zstd-compressed `ocr_cache.data` blobs. Blobs without it are plain JSON
+/// written by older versions, which never starts with a NUL byte.
+const ZSTD_BLOB_MARKER: &[u8] = b"\0zst";
+const ZSTD_LEVEL: i32 = 3;
+
+/// Columns holding cache, chapter or manga keys, renamed by `migrate_cache_namespace`.
+const NAMESPACED_COLUMNS: &[(&str, &str)] = &[
+    ("ocr_cache", "cache_key"),
+    ("chapter_cache", "chapter_key"),
+    ("chapter_cache", "cache_key"),
+    ("chapter_pages", "chapter_key"),
+    ("chapter_jobs", "chapter_key"),
+    ("image_hashes", "cache_key"),
+    ("ocr_history", "cache_key"),
+    ("manga_jobs", "manga_key"),
+];
+
 // Struct for the legacy persistent state (cache and metadata)
 #[derive(Serialize, Deserialize, Default)]
 struct PersistentState {
@@ -50,13 +131,19 @@ impl AppState {
         }
 
         let db_path = cache_dir.join("ocr-cache.db");
-        let manager = SqliteConnectionManager::file(&db_path);
+        // busy_timeout is per connection, so it is set on every pooled connection.
+        let manager = SqliteConnectionManager::file(&db_path).with_init(|conn| {
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            conn.execute_batch("PRAGMA synchronous = NORMAL;")
+        });
         let pool = Pool::new(manager).expect("Failed to create OCR DB pool");
         let mut conn = pool.get().expect("Failed to get OCR DB connection");
 
+        // auto_vacuum only takes effect on a new database; older ones are converted by
+        // the first run_maintenance.
         conn.execute_batch(
-            "PRAGMA journal_mode = DELETE;
-             PRAGMA synchronous = NORMAL;
+            "PRAGMA auto_vacuum = INCREMENTAL;
+             PRAGMA journal_mode = WAL;
 
              CREATE TABLE IF NOT EXISTS metadata (
                 key TEXT PRIMARY KEY,
@@ -70,7 +157,8 @@ impl AppState {
                 created_at INTEGER NOT NULL,
                 last_processed_at INTEGER NOT NULL,
                 last_accessed_at INTEGER NOT NULL,
-                access_count INTEGER NOT NULL
+                access_count INTEGER NOT NULL,
+                user_edited INTEGER NOT NULL DEFAULT 0
              );
 
              CREATE INDEX IF NOT EXISTS idx_ocr_cache_accessed
@@ -95,7 +183,48 @@ impl AppState {
              );
 
              CREATE INDEX IF NOT EXISTS idx_chapter_pages_accessed
-                ON chapter_pages(last_accessed_at);",
+                ON chapter_pages(last_accessed_at);
+
+             CREATE TABLE IF NOT EXISTS chapter_jobs (
+                chapter_key TEXT PRIMARY KEY,
+                priority TEXT NOT NULL,
+                job TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS image_hashes (
+                image_hash TEXT PRIMARY KEY,
+                cache_key TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS postprocess_rules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                language TEXT,
+                pattern TEXT NOT NULL,
+                replacement TEXT NOT NULL,
+                enabled INTEGER NOT NULL DEFAULT 1,
+                created_at INTEGER NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS manga_jobs (
+                manga_key TEXT PRIMARY KEY,
+                job TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS ocr_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                cache_key TEXT NOT NULL,
+                context TEXT NOT NULL,
+                data BLOB NOT NULL,
+                user_edited INTEGER NOT NULL,
+                processed_at INTEGER NOT NULL,
+                archived_at INTEGER NOT NULL
+             );
+
+             CREATE INDEX IF NOT EXISTS idx_ocr_history_key
+                ON ocr_history(cache_key);",
         )
         .expect("Failed to initialize OCR cache database");
 
@@ -103,20 +232,65 @@ impl AppState {
             "ALTER TABLE chapter_pages ADD COLUMN processed_count INTEGER NOT NULL DEFAULT 0",
             [],
         );
+        let _ = conn.execute(
+            "ALTER TABLE ocr_cache ADD COLUMN user_edited INTEGER NOT NULL DEFAULT 0",
+            [],
+        );
 
         migrate_legacy_cache(&mut conn, &cache_dir);
+        migrate_cache_namespace(&mut conn);
+
+        #[cfg(feature = "manga-ocr")]
+        crate::backend::manga_ocr::set_default_model_dir(
+            cache_dir.join("models").join("manga-ocr"),
+        );
+
+        let cache_limits = CacheLimits::from_env();
 
-        Self {
+        let state = Self {
             pool,
             cache_dir,
             active_jobs: Arc::new(AtomicUsize::new(0)),
             requests_processed: Arc::new(AtomicUsize::new(0)),
             active_chapter_jobs: Arc::new(RwLock::new(HashMap::new())),
-        }
+            active_manga_jobs: Arc::new(RwLock::new(HashMap::new())),
+            job_queue: Arc::new(JobQueue::from_env()),
+            job_events: broadcast::channel(256).0,
+            retry_policy: RetryPolicy::from_env(),
+            concurrency: Arc::new(ConcurrencyLimits::from_env()),
+            cache_limits,
+            cache_ttl: Arc::new(CacheTtl::from_env()),
+            eviction_summary: Arc::new(RwLock::new(EvictionSummary {
+                limits: cache_limits,
+                ..Default::default()
+            })),
+            backend_health: Arc::new(BackendHealth::from_env()),
+            initialized: Arc::new(AtomicBool::new(false)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            in_flight_pages: Arc::new(AtomicUsize::new(0)),
+            ocr_in_flight: Arc::new(SingleFlight::default()),
+            translator: Translator::from_env().map(Arc::new),
+        };
+        state.reload_postprocess_rules();
+        state
+    }
+
+    /// Broadcasts a job event. Events are dropped when nobody is subscribed.
+    pub fn publish_job_event(&self, event: JobEvent) {
+        let _ = self.job_events.send(event);
     }
 }
 
 impl AppState {
+    /// Whether a pooled connection can run a query.
+    pub fn db_reachable(&self) -> bool {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for db_reachable");
+            return false;
+        };
+        conn.query_row("SELECT 1", [], |_| Ok(())).is_ok()
+    }
+
     pub fn cache_len(&self) -> usize {
         let Ok(conn) = self.pool.get() else {
             warn!("Failed to get DB connection for cache_len");
@@ -134,9 +308,10 @@ impl AppState {
             warn!("Failed to get DB connection for has_cache_entry");
             return false;
         };
+        let min_processed_at = self.cache_ttl.min_processed_at(cache_key, now_unix());
         conn.query_row(
-            "SELECT 1 FROM ocr_cache WHERE cache_key = ? LIMIT 1",
-            params![cache_key],
+            "SELECT 1 FROM ocr_cache WHERE cache_key = ? AND (last_processed_at >= ? OR user_edited = 1) LIMIT 1",
+            params![cache_key, min_processed_at],
             |_| Ok(()),
         )
         .optional()
@@ -150,9 +325,10 @@ impl AppState {
             return false;
         };
         let like_pattern = format!("{}%", prefix);
+        let min_processed_at = self.cache_ttl.min_processed_at(prefix, now_unix());
         conn.query_row(
-            "SELECT 1 FROM ocr_cache WHERE cache_key LIKE ? LIMIT 1",
-            params![like_pattern],
+            "SELECT 1 FROM ocr_cache WHERE cache_key LIKE ? AND (last_processed_at >= ? OR user_edited = 1) LIMIT 1",
+            params![like_pattern, min_processed_at],
             |_| Ok(()),
         )
         .optional()
@@ -160,16 +336,38 @@ impl AppState {
         .unwrap_or(false)
     }
 
-    pub fn insert_chapter_cache(&self, chapter_key: &str, cache_key: &str) {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for insert_chapter_cache");
-            return;
-        };
-        let now = now_unix();
-        let _ = conn.execute(
-            "INSERT OR IGNORE INTO chapter_cache (chapter_key, cache_key, created_at) VALUES (?, ?, ?)",
-            params![chapter_key, cache_key, now],
-        );
+    /// Runs a database write on the blocking pool, where `retry_on_busy` can wait out a
+    /// busy database without stalling an async worker. A panic counts as a failed write.
+    async fn blocking<T>(&self, op: impl FnOnce(&AppState) -> T + Send + 'static) -> T
+    where
+        T: Default + Send + 'static,
+    {
+        let state = self.clone();
+        tokio::task::spawn_blocking(move || op(&state))
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Database write panicked: {err}");
+                T::default()
+            })
+    }
+
+    pub async fn insert_chapter_cache(&self, chapter_key: &str, cache_key: &str) {
+        let (chapter_key, cache_key) = (chapter_key.to_string(), cache_key.to_string());
+        self.blocking(move |state| {
+            let (chapter_key, cache_key) = (chapter_key.as_str(), cache_key.as_str());
+            let Ok(conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for insert_chapter_cache");
+                return;
+            };
+            let now = now_unix();
+            let _ = retry_on_busy(|| {
+                conn.execute(
+                    "INSERT OR IGNORE INTO chapter_cache (chapter_key, cache_key, created_at) VALUES (?, ?, ?)",
+                    params![chapter_key, cache_key, now],
+                )
+            });
+        })
+        .await
     }
 
     pub fn count_chapter_cache(&self, chapter_key: &str) -> usize {
@@ -186,20 +384,100 @@ impl AppState {
         .unwrap_or(0)
     }
 
+    /// The keys from `cache_keys` that have a fresh cache entry, counting `?sourceId=` /
+    /// `&sourceId=` variants like `has_cache_entry_prefix`. Runs as a single join for
+    /// the whole batch.
+    pub fn cached_page_keys(&self, cache_keys: &[String]) -> HashSet<String> {
+        if cache_keys.is_empty() {
+            return HashSet::new();
+        }
+        let Ok(mut conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for cached_page_keys");
+            return HashSet::new();
+        };
+        query_cached_page_keys(&mut conn, &self.cache_ttl, cache_keys).unwrap_or_else(|err| {
+            warn!("Failed to query cached page keys: {err}");
+            HashSet::new()
+        })
+    }
+
+    /// Cached page counts and known chapter lengths for many chapters in one query.
+    /// Chapters without any row are omitted.
+    pub fn chapter_cache_statuses(
+        &self,
+        chapter_keys: &[String],
+    ) -> HashMap<String, ChapterCacheStatus> {
+        if chapter_keys.is_empty() {
+            return HashMap::new();
+        }
+        let Ok(mut conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for chapter_cache_statuses");
+            return HashMap::new();
+        };
+        query_chapter_cache_statuses(&mut conn, chapter_keys).unwrap_or_else(|err| {
+            warn!("Failed to query chapter cache statuses: {err}");
+            HashMap::new()
+        })
+    }
+
+    /// `set_chapter_pages` plus `insert_chapter_cache` for each key, in one transaction.
+    pub async fn record_chapter_pages(
+        &self,
+        chapter_key: &str,
+        page_count: usize,
+        cache_keys: &[String],
+    ) {
+        let (chapter_key, cache_keys) = (chapter_key.to_string(), cache_keys.to_vec());
+        self.blocking(move |state| {
+            let (chapter_key, cache_keys) = (chapter_key.as_str(), cache_keys.as_slice());
+            let Ok(mut conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for record_chapter_pages");
+                return;
+            };
+            let now = now_unix();
+            let result = retry_on_busy(|| {
+                let tx = conn.transaction()?;
+                tx.execute(
+                    "INSERT INTO chapter_pages (chapter_key, page_count, processed_count, created_at, last_accessed_at)
+                     VALUES (?, ?, 0, ?, ?)
+                     ON CONFLICT(chapter_key) DO UPDATE SET
+                        page_count = excluded.page_count,
+                        last_accessed_at = excluded.last_accessed_at",
+                    params![chapter_key, page_count as i64, now, now],
+                )?;
+                {
+                    let mut insert = tx.prepare(
+                        "INSERT OR IGNORE INTO chapter_cache (chapter_key, cache_key, created_at) VALUES (?, ?, ?)",
+                    )?;
+                    for cache_key in cache_keys {
+                        insert.execute(params![chapter_key, cache_key, now])?;
+                    }
+                }
+                tx.commit()
+            });
+            if let Err(err) = result {
+                warn!("Failed to record pages for {chapter_key}: {err}");
+            }
+        })
+        .await
+    }
+
     pub fn get_cache_entry(&self, cache_key: &str) -> Option<CacheEntry> {
         let Ok(conn) = self.pool.get() else {
             warn!("Failed to get DB connection for get_cache_entry");
             return None;
         };
 
+        let min_processed_at = self.cache_ttl.min_processed_at(cache_key, now_unix());
         let entry = conn
             .query_row(
-                "SELECT context, data FROM ocr_cache WHERE cache_key = ?",
-                params![cache_key],
+                "SELECT context, data FROM ocr_cache
+                 WHERE cache_key = ? AND (last_processed_at >= ? OR user_edited = 1)",
+                params![cache_key, min_processed_at],
                 |row| {
                     let context: String = row.get(0)?;
                     let data_blob: Vec<u8> = row.get(1)?;
-                    let data = serde_json::from_slice(&data_blob).unwrap_or_default();
+                    let data = decode_cache_data(&data_blob);
                     Ok(CacheEntry { context, data })
                 },
             )
@@ -219,6 +497,44 @@ impl AppState {
         entry
     }
 
+    /// Takes over the entry cached under `cache_key` without its namespace, for caches
+    /// that predate `MANATAN_OCR_CACHE_NAMESPACE=host`. Returns the claimed entry.
+    pub async fn claim_unnamespaced_entry(&self, cache_key: &str) -> Option<CacheEntry> {
+        let cache_key = cache_key.to_string();
+        self.blocking(move |state| {
+            let cache_key = cache_key.as_str();
+            let legacy_key = namespace::strip(cache_key)?;
+            let Ok(mut conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for claim_unnamespaced_entry");
+                return None;
+            };
+            let claimed = retry_on_busy(|| {
+                let tx = conn.transaction()?;
+                let renamed = tx.execute(
+                    "UPDATE OR IGNORE ocr_cache SET cache_key = ? WHERE cache_key = ?",
+                    params![cache_key, legacy_key],
+                )?;
+                if renamed > 0 {
+                    for table in ["image_hashes", "ocr_history"] {
+                        tx.execute(
+                            &format!("UPDATE {table} SET cache_key = ? WHERE cache_key = ?"),
+                            params![cache_key, legacy_key],
+                        )?;
+                    }
+                }
+                tx.commit()?;
+                Ok(renamed > 0)
+            })
+            .unwrap_or(false);
+            if !claimed {
+                return None;
+            }
+            info!("Claimed cached OCR of {legacy_key} for {cache_key}");
+            state.get_cache_entry(cache_key)
+        })
+        .await
+    }
+
     pub fn get_cache_entry_sourceid_variant(
         &self,
         cache_key: &str,
@@ -230,16 +546,20 @@ impl AppState {
 
         let like_q = format!("{}?sourceId=%", cache_key);
         let like_amp = format!("{}&sourceId=%", cache_key);
+        let min_processed_at = self.cache_ttl.min_processed_at(cache_key, now_unix());
 
         let row = conn
             .query_row(
-                "SELECT cache_key, context, data FROM ocr_cache WHERE cache_key LIKE ? OR cache_key LIKE ? LIMIT 1",
-                params![like_q, like_amp],
+                "SELECT cache_key, context, data FROM ocr_cache
+                 WHERE (cache_key LIKE ? OR cache_key LIKE ?)
+                   AND (last_processed_at >= ? OR user_edited = 1)
+                 LIMIT 1",
+                params![like_q, like_amp, min_processed_at],
                 |row| {
                     let key: String = row.get(0)?;
                     let context: String = row.get(1)?;
                     let data_blob: Vec<u8> = row.get(2)?;
-                    let data = serde_json::from_slice(&data_blob).unwrap_or_default();
+                    let data = decode_cache_data(&data_blob);
                     Ok((key, CacheEntry { context, data }))
                 },
             )
@@ -259,33 +579,349 @@ impl AppState {
         row
     }
 
-    pub fn insert_cache_entry(&self, cache_key: &str, entry: &CacheEntry) {
+    pub async fn insert_cache_entry(&self, cache_key: &str, entry: &CacheEntry) {
+        self.store_cache_entry(cache_key, entry, false).await;
+    }
+
+    /// `insert_cache_entry` that also replaces user-corrected data, which moves to the
+    /// history, and clears the user-edited mark. Used by forced reprocessing.
+    pub async fn replace_cache_entry(&self, cache_key: &str, entry: &CacheEntry) {
+        self.store_cache_entry(cache_key, entry, true).await;
+    }
+
+    async fn store_cache_entry(
+        &self,
+        cache_key: &str,
+        entry: &CacheEntry,
+        replace_user_edited: bool,
+    ) {
+        let (cache_key, entry) = (cache_key.to_string(), entry.clone());
+        self.blocking(move |state| {
+            let (cache_key, entry) = (cache_key.as_str(), &entry);
+            let Ok(mut conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for insert_cache_entry");
+                return;
+            };
+            let now = now_unix();
+            let data_blob = encode_cache_data(&entry.data);
+            let _ = retry_on_busy(|| {
+                let tx = conn.transaction()?;
+                // User-edited rows are only archived when they are replaced below.
+                archive_current_version(&tx, cache_key, &data_blob, replace_user_edited, now)?;
+                tx.execute(
+                    "INSERT INTO ocr_cache
+                        (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
+                     VALUES (?, ?, ?, ?, ?, ?, ?)
+                     ON CONFLICT(cache_key) DO UPDATE SET
+                        context = excluded.context,
+                        data = excluded.data,
+                        last_processed_at = excluded.last_processed_at,
+                        last_accessed_at = excluded.last_accessed_at,
+                        access_count = ocr_cache.access_count + 1,
+                        user_edited = 0
+                     WHERE ocr_cache.user_edited = 0 OR ?",
+                    params![
+                        cache_key,
+                        entry.context.as_str(),
+                        data_blob,
+                        now,
+                        now,
+                        now,
+                        1i64,
+                        replace_user_edited
+                    ],
+                )?;
+                tx.commit()
+            });
+        })
+        .await
+    }
+
+    /// Looks up cached results for an image with the given content hash.
+    pub fn get_results_by_image_hash(&self, image_hash: &str) -> Option<Vec<OcrResult>> {
+        let cache_key = {
+            let conn = self.pool.get().ok()?;
+            conn.query_row(
+                "SELECT cache_key FROM image_hashes WHERE image_hash = ?",
+                params![image_hash],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .unwrap_or(None)?
+        };
+        self.get_cache_entry(&cache_key).map(|entry| entry.data)
+    }
+
+    pub async fn record_image_hash(&self, image_hash: &str, cache_key: &str) {
+        let (image_hash, cache_key) = (image_hash.to_string(), cache_key.to_string());
+        self.blocking(move |state| {
+            let (image_hash, cache_key) = (image_hash.as_str(), cache_key.as_str());
+            let Ok(conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for record_image_hash");
+                return;
+            };
+            let _ = retry_on_busy(|| {
+                conn.execute(
+                    "INSERT INTO image_hashes (image_hash, cache_key, created_at) VALUES (?, ?, ?)
+                     ON CONFLICT(image_hash) DO UPDATE SET cache_key = excluded.cache_key",
+                    params![image_hash, cache_key, now_unix()],
+                )
+            });
+        })
+        .await
+    }
+
+    /// Replaces the data of an existing entry without touching its timestamps or
+    /// `user_edited`, e.g. to add translations.
+    pub async fn update_cache_data(&self, cache_key: &str, data: &[OcrResult]) {
+        let (cache_key, data) = (cache_key.to_string(), data.to_vec());
+        self.blocking(move |state| {
+            let (cache_key, data) = (cache_key.as_str(), data.as_slice());
+            let Ok(conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for update_cache_data");
+                return;
+            };
+            let data_blob = encode_cache_data(data);
+            let _ = retry_on_busy(|| {
+                conn.execute(
+                    "UPDATE ocr_cache SET data = ? WHERE cache_key = ?",
+                    params![data_blob, cache_key],
+                )
+            });
+        })
+        .await
+    }
+
+    /// Stores user-corrected OCR data for `cache_key` and marks it as edited, so
+    /// `insert_cache_entry`, TTL expiry and eviction leave it alone.
+    pub async fn set_user_edited_entry(&self, cache_key: &str, entry: &CacheEntry) -> bool {
+        let (cache_key, entry) = (cache_key.to_string(), entry.clone());
+        self.blocking(move |state| {
+            let (cache_key, entry) = (cache_key.as_str(), &entry);
+            let Ok(mut conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for set_user_edited_entry");
+                return false;
+            };
+            let now = now_unix();
+            let data_blob = encode_cache_data(&entry.data);
+            retry_on_busy(|| {
+                let tx = conn.transaction()?;
+                archive_current_version(&tx, cache_key, &data_blob, true, now)?;
+                tx.execute(
+                    "INSERT INTO ocr_cache
+                        (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count, user_edited)
+                     VALUES (?, ?, ?, ?, ?, ?, 1, 1)
+                     ON CONFLICT(cache_key) DO UPDATE SET
+                        data = excluded.data,
+                        last_processed_at = excluded.last_processed_at,
+                        last_accessed_at = excluded.last_accessed_at,
+                        user_edited = 1",
+                    params![cache_key, entry.context.as_str(), data_blob, now, now, now],
+                )?;
+                tx.commit()
+            })
+            .is_ok()
+        })
+        .await
+    }
+
+    /// Earlier versions of `cache_key`, newest first.
+    pub fn list_history(&self, cache_key: &str) -> Vec<HistoryVersion> {
         let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for insert_cache_entry");
-            return;
+            warn!("Failed to get DB connection for list_history");
+            return Vec::new();
         };
-        let now = now_unix();
-        let data_blob = serde_json::to_vec(&entry.data).unwrap_or_default();
-        let _ = conn.execute(
-            "INSERT INTO ocr_cache
-                (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
-             VALUES (?, ?, ?, ?, ?, ?, ?)
-             ON CONFLICT(cache_key) DO UPDATE SET
-                context = excluded.context,
-                data = excluded.data,
-                last_processed_at = excluded.last_processed_at,
-                last_accessed_at = excluded.last_accessed_at,
-                access_count = ocr_cache.access_count + 1",
-            params![
-                cache_key,
-                entry.context.as_str(),
-                data_blob,
-                now,
-                now,
-                now,
-                1i64
-            ],
-        );
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT id, context, data, user_edited, processed_at, archived_at
+             FROM ocr_history WHERE cache_key = ? ORDER BY id DESC",
+        ) else {
+            return Vec::new();
+        };
+        stmt.query_map(params![cache_key], |row| {
+            let data_blob: Vec<u8> = row.get(2)?;
+            Ok(HistoryVersion {
+                id: row.get(0)?,
+                context: row.get(1)?,
+                data: decode_cache_data(&data_blob),
+                user_edited: row.get(3)?,
+                processed_at: row.get(4)?,
+                archived_at: row.get(5)?,
+            })
+        })
+        .map(|rows| rows.flatten().collect())
+        .unwrap_or_default()
+    }
+
+    /// Makes history version `id` of `cache_key` current again. The version being
+    /// replaced is archived in turn. Returns `false` if there is no such version.
+    pub async fn revert_to_version(&self, cache_key: &str, id: i64) -> bool {
+        let cache_key = cache_key.to_string();
+        self.blocking(move |state| {
+            let cache_key = cache_key.as_str();
+            let Ok(mut conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for revert_to_version");
+                return false;
+            };
+            let now = now_unix();
+            let result = retry_on_busy(|| {
+                let tx = conn.transaction()?;
+                let version = tx
+                    .query_row(
+                        "SELECT context, data, user_edited FROM ocr_history
+                         WHERE id = ? AND cache_key = ?",
+                        params![id, cache_key],
+                        |row| {
+                            Ok((
+                                row.get::<_, String>(0)?,
+                                row.get::<_, Vec<u8>>(1)?,
+                                row.get::<_, bool>(2)?,
+                            ))
+                        },
+                    )
+                    .optional()?;
+                let Some((context, data_blob, user_edited)) = version else {
+                    return Ok(false);
+                };
+                tx.execute("DELETE FROM ocr_history WHERE id = ?", params![id])?;
+                archive_current_version(&tx, cache_key, &data_blob, true, now)?;
+                tx.execute(
+                    "INSERT INTO ocr_cache
+                        (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count, user_edited)
+                     VALUES (?, ?, ?, ?, ?, ?, 1, ?)
+                     ON CONFLICT(cache_key) DO UPDATE SET
+                        context = excluded.context,
+                        data = excluded.data,
+                        last_processed_at = excluded.last_processed_at,
+                        last_accessed_at = excluded.last_accessed_at,
+                        user_edited = excluded.user_edited",
+                    params![cache_key, context, data_blob, now, now, now, user_edited],
+                )?;
+                tx.commit()?;
+                Ok(true)
+            });
+            result.unwrap_or_else(|err| {
+                warn!("Failed to revert {cache_key} to version {id}: {err}");
+                false
+            })
+        })
+        .await
+    }
+
+    /// Clears the user-edited flag so the next reprocess may overwrite the entry.
+    pub fn clear_user_edited(&self, cache_key: &str) -> bool {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for clear_user_edited");
+            return false;
+        };
+        conn.execute(
+            "UPDATE ocr_cache SET user_edited = 0 WHERE cache_key = ?",
+            params![cache_key],
+        )
+        .map(|rows| rows > 0)
+        .unwrap_or(false)
+    }
+
+    /// Deletes least-recently-used cache entries (and their chapter links) until the
+    /// cache fits within `limits`. Entry size is the stored data plus context length.
+    pub fn evict_lru_entries(&self, limits: CacheLimits) -> EvictionStats {
+        let mut stats = EvictionStats {
+            ran_at: now_unix(),
+            ..Default::default()
+        };
+        let Ok(mut conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for evict_lru_entries");
+            return stats;
+        };
+
+        let (entries, bytes) = conn
+            .query_row(
+                "SELECT COUNT(*), COALESCE(SUM(LENGTH(data) + LENGTH(context)), 0) FROM ocr_cache",
+                [],
+                |row| Ok((row.get::<_, i64>(0)? as u64, row.get::<_, i64>(1)? as u64)),
+            )
+            .unwrap_or((0, 0));
+        stats.remaining_entries = entries;
+        stats.remaining_bytes = bytes;
+
+        let over_limit = |entries: u64, bytes: u64| {
+            limits.max_entries.is_some_and(|max| entries > max)
+                || limits.max_bytes.is_some_and(|max| bytes > max)
+        };
+        if !over_limit(entries, bytes) {
+            return stats;
+        }
+
+        let tx = match conn.transaction() {
+            Ok(tx) => tx,
+            Err(err) => {
+                warn!("Failed to start eviction transaction: {err}");
+                return stats;
+            }
+        };
+
+        let mut victims = Vec::<(String, u64)>::new();
+        {
+            let mut stmt = match tx.prepare(
+                "SELECT cache_key, LENGTH(data) + LENGTH(context) FROM ocr_cache
+                 WHERE user_edited = 0
+                 ORDER BY last_accessed_at ASC, access_count ASC",
+            ) {
+                Ok(stmt) => stmt,
+                Err(err) => {
+                    warn!("Failed to prepare eviction select: {err}");
+                    return stats;
+                }
+            };
+            let Ok(rows) = stmt.query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as u64))
+            }) else {
+                return stats;
+            };
+
+            let (mut entries, mut bytes) = (entries, bytes);
+            for (cache_key, size) in rows.flatten() {
+                if !over_limit(entries, bytes) {
+                    break;
+                }
+                entries -= 1;
+                bytes = bytes.saturating_sub(size);
+                victims.push((cache_key, size));
+            }
+        }
+
+        let mut evicted_entries = 0u64;
+        let mut evicted_bytes = 0u64;
+        for (cache_key, size) in &victims {
+            let deleted = tx
+                .execute(
+                    "DELETE FROM ocr_cache WHERE cache_key = ?",
+                    params![cache_key],
+                )
+                .unwrap_or(0);
+            if deleted > 0 {
+                let _ = tx.execute(
+                    "DELETE FROM chapter_cache WHERE cache_key = ?",
+                    params![cache_key],
+                );
+                let _ = tx.execute(
+                    "DELETE FROM ocr_history WHERE cache_key = ?",
+                    params![cache_key],
+                );
+                evicted_entries += 1;
+                evicted_bytes += size;
+            }
+        }
+
+        if let Err(err) = tx.commit() {
+            warn!("Failed to commit eviction transaction: {err}");
+            return stats;
+        }
+
+        stats.evicted_entries = evicted_entries;
+        stats.evicted_bytes = evicted_bytes;
+        stats.remaining_entries = entries - evicted_entries;
+        stats.remaining_bytes = bytes.saturating_sub(evicted_bytes);
+        stats
     }
 
     pub fn clear_cache(&self) {
@@ -296,6 +932,8 @@ impl AppState {
         let _ = conn.execute("DELETE FROM ocr_cache", []);
         let _ = conn.execute("DELETE FROM chapter_cache", []);
         let _ = conn.execute("DELETE FROM chapter_pages", []);
+        let _ = conn.execute("DELETE FROM image_hashes", []);
+        let _ = conn.execute("DELETE FROM ocr_history", []);
     }
 
     pub fn delete_chapter_ocr(
@@ -363,6 +1001,10 @@ impl AppState {
                     )
                     .unwrap_or(0);
                 ocr_cache_rows += deleted as usize;
+                let _ = tx.execute(
+                    "DELETE FROM ocr_history WHERE cache_key = ? OR cache_key LIKE ? OR cache_key LIKE ?",
+                    params![cache_key, like_q, like_amp],
+                );
             }
         }
 
@@ -374,39 +1016,59 @@ impl AppState {
         (chapter_cache_rows, chapter_pages_rows, ocr_cache_rows)
     }
 
-    pub fn export_cache(&self) -> HashMap<String, CacheEntry> {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for export_cache");
-            return HashMap::new();
-        };
-        let mut out = HashMap::new();
-        let mut stmt = match conn.prepare("SELECT cache_key, context, data FROM ocr_cache") {
-            Ok(stmt) => stmt,
-            Err(err) => {
-                warn!("Failed to prepare export_cache: {err}");
-                return out;
-            }
-        };
-
-        if let Ok(rows) = stmt.query_map([], |row| {
-            let key: String = row.get(0)?;
-            let context: String = row.get(1)?;
-            let data_blob: Vec<u8> = row.get(2)?;
-            let data = serde_json::from_slice(&data_blob).unwrap_or_default();
-            Ok((key, CacheEntry { context, data }))
-        }) {
-            for row in rows.flatten() {
-                out.insert(row.0, row.1);
-            }
+    /// Feeds every cache row to `write`, straight from the DB cursor.
+    pub fn export_cache_rows(
+        &self,
+        filter: &ExportFilter,
+        mut write: impl FnMut(&ExportRecord) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        let to_io = |err: rusqlite::Error| std::io::Error::other(err.to_string());
+        let conn = self
+            .pool
+            .get()
+            .map_err(|err| std::io::Error::other(err.to_string()))?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT o.cache_key, o.context, o.data, o.created_at,
+                        GROUP_CONCAT(c.chapter_key, char(10))
+                 FROM ocr_cache o
+                 LEFT JOIN chapter_cache c ON c.cache_key = o.cache_key
+                 WHERE ?1 IS NULL
+                    OR o.cache_key LIKE ?1 ESCAPE '\\'
+                    OR o.cache_key IN (
+                        SELECT cache_key FROM chapter_cache WHERE chapter_key LIKE ?1 ESCAPE '\\'
+                    )
+                 GROUP BY o.cache_key",
+            )
+            .map_err(to_io)?;
+        let mut rows = stmt.query(params![filter.like_pattern()]).map_err(to_io)?;
+
+        while let Some(row) = rows.next().map_err(to_io)? {
+            let data_blob: Vec<u8> = row.get(2).map_err(to_io)?;
+            let chapter_keys: Option<String> = row.get(4).map_err(to_io)?;
+            let record = ExportRecord {
+                cache_key: row.get(0).map_err(to_io)?,
+                context: row.get(1).map_err(to_io)?,
+                data: decode_cache_data(&data_blob),
+                created_at: row.get(3).map_err(to_io)?,
+                chapter_keys: chapter_keys
+                    .map(|keys| keys.lines().map(str::to_string).collect())
+                    .unwrap_or_default(),
+            };
+            write(&record)?;
         }
-
-        out
+        Ok(())
     }
 
-    pub fn import_cache(&self, data: HashMap<String, CacheEntry>) -> usize {
+    pub fn import_cache(
+        &self,
+        records: Vec<ExportRecord>,
+        strategy: ImportStrategy,
+    ) -> ImportReport {
+        let mut report = ImportReport::default();
         let Ok(mut conn) = self.pool.get() else {
             warn!("Failed to get DB connection for import_cache");
-            return 0;
+            return report;
         };
 
         let now = now_unix();
@@ -414,25 +1076,85 @@ impl AppState {
             Ok(tx) => tx,
             Err(err) => {
                 warn!("Failed to start import transaction: {err}");
-                return 0;
+                return report;
             }
         };
-        let mut added = 0;
-        for (key, entry) in data {
-            let data_blob = serde_json::to_vec(&entry.data).unwrap_or_default();
-            if let Ok(changes) = tx.execute(
-                "INSERT OR IGNORE INTO ocr_cache
+        for record in records {
+            for chapter_key in &record.chapter_keys {
+                let _ = tx.execute(
+                    "INSERT OR IGNORE INTO chapter_cache (chapter_key, cache_key, created_at) VALUES (?, ?, ?)",
+                    params![chapter_key, record.cache_key, now],
+                );
+            }
+
+            let existing = tx
+                .query_row(
+                    "SELECT created_at, user_edited FROM ocr_cache WHERE cache_key = ?",
+                    params![record.cache_key],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, bool>(1)?)),
+                )
+                .optional()
+                .unwrap_or(None);
+            let existing_created_at = match existing {
+                Some((_, true)) if strategy != ImportStrategy::SkipExisting => {
+                    report.skipped_edited += 1;
+                    continue;
+                }
+                existing => existing.map(|(created_at, _)| created_at),
+            };
+
+            let overwrite = match (existing_created_at, strategy) {
+                (None, _) => false,
+                (Some(_), ImportStrategy::SkipExisting) => {
+                    report.skipped_existing += 1;
+                    continue;
+                }
+                (Some(_), ImportStrategy::Overwrite) => true,
+                // Records without a timestamp (legacy exports) never win.
+                (Some(existing), ImportStrategy::NewestWins) => {
+                    if record
+                        .created_at
+                        .is_some_and(|created_at| created_at > existing)
+                    {
+                        true
+                    } else {
+                        report.skipped_older += 1;
+                        continue;
+                    }
+                }
+            };
+
+            let data_blob = encode_cache_data(&record.data);
+            let created_at = record.created_at.unwrap_or(now);
+            let result = tx.execute(
+                "INSERT INTO ocr_cache
                     (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
-                 VALUES (?, ?, ?, ?, ?, ?, ?)",
-                params![key, entry.context, data_blob, now, now, now, 1i64],
-            ) {
-                if changes > 0 {
-                    added += 1;
+                 VALUES (?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(cache_key) DO UPDATE SET
+                    context = excluded.context,
+                    data = excluded.data,
+                    created_at = excluded.created_at,
+                    last_processed_at = excluded.last_processed_at
+                 WHERE ocr_cache.user_edited = 0",
+                params![record.cache_key, record.context, data_blob, created_at, now, now, 1i64],
+            );
+            match result {
+                Ok(_) if overwrite => report.overwritten += 1,
+                Ok(_) => report.added += 1,
+                Err(err) => {
+                    warn!("Failed to import cache entry {}: {err}", record.cache_key);
+                    report.failed += 1;
                 }
             }
         }
-        let _ = tx.commit();
-        added
+        if let Err(err) = tx.commit() {
+            warn!("Failed to commit import transaction: {err}");
+            return ImportReport {
+                failed: report.added + report.overwritten + report.failed,
+                ..ImportReport::default()
+            };
+        }
+        report
     }
 
     pub fn get_chapter_pages(&self, chapter_key: &str) -> Option<usize> {
@@ -490,46 +1212,534 @@ impl AppState {
             .map(|(page_count, processed_count)| (page_count as usize, processed_count as usize))
     }
 
-    pub fn set_chapter_pages(&self, chapter_key: &str, page_count: usize) {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for set_chapter_pages");
-            return;
-        };
-        let now = now_unix();
-        let _ = conn.execute(
-            "INSERT INTO chapter_pages (chapter_key, page_count, processed_count, created_at, last_accessed_at)
-             VALUES (?, ?, 0, ?, ?)
-             ON CONFLICT(chapter_key) DO UPDATE SET
-                page_count = excluded.page_count,
-                last_accessed_at = excluded.last_accessed_at",
-            params![chapter_key, page_count as i64, now, now],
-        );
+    pub async fn set_chapter_pages(&self, chapter_key: &str, page_count: usize) {
+        let chapter_key = chapter_key.to_string();
+        self.blocking(move |state| {
+            let chapter_key = chapter_key.as_str();
+            let Ok(conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for set_chapter_pages");
+                return;
+            };
+            let now = now_unix();
+            let _ = retry_on_busy(|| {
+                conn.execute(
+                    "INSERT INTO chapter_pages (chapter_key, page_count, processed_count, created_at, last_accessed_at)
+                     VALUES (?, ?, 0, ?, ?)
+                     ON CONFLICT(chapter_key) DO UPDATE SET
+                        page_count = excluded.page_count,
+                        last_accessed_at = excluded.last_accessed_at",
+                    params![chapter_key, page_count as i64, now, now],
+                )
+            });
+        })
+        .await
     }
 
-    pub fn set_chapter_progress(
+    pub async fn set_chapter_progress(
         &self,
         chapter_key: &str,
         page_count: usize,
         processed_count: usize,
     ) {
+        let chapter_key = chapter_key.to_string();
+        self.blocking(move |state| {
+            let chapter_key = chapter_key.as_str();
+            let Ok(conn) = state.pool.get() else {
+                warn!("Failed to get DB connection for set_chapter_progress");
+                return;
+            };
+            let now = now_unix();
+            let _ = retry_on_busy(|| {
+                conn.execute(
+                    "INSERT INTO chapter_pages (chapter_key, page_count, processed_count, created_at, last_accessed_at)
+                     VALUES (?, ?, ?, ?, ?)
+                     ON CONFLICT(chapter_key) DO UPDATE SET
+                        page_count = excluded.page_count,
+                        processed_count = excluded.processed_count,
+                        last_accessed_at = excluded.last_accessed_at",
+                    params![chapter_key, page_count as i64, processed_count as i64, now, now],
+                )
+            });
+        })
+        .await
+    }
+
+    /// Records a chapter job so it can be resumed if the server stops before it finishes.
+    pub fn save_chapter_job(&self, chapter_key: &str, priority: JobPriority, job: &ChapterJob) {
         let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for set_chapter_progress");
+            warn!("Failed to get DB connection for save_chapter_job");
+            return;
+        };
+        let (Ok(priority), Ok(job)) =
+            (serde_json::to_string(&priority), serde_json::to_string(job))
+        else {
+            warn!("Failed to serialize chapter job for {chapter_key}");
             return;
         };
-        let now = now_unix();
         let _ = conn.execute(
-            "INSERT INTO chapter_pages (chapter_key, page_count, processed_count, created_at, last_accessed_at)
-             VALUES (?, ?, ?, ?, ?)
+            "INSERT INTO chapter_jobs (chapter_key, priority, job, created_at)
+             VALUES (?, ?, ?, ?)
              ON CONFLICT(chapter_key) DO UPDATE SET
-                page_count = excluded.page_count,
-                processed_count = excluded.processed_count,
-                last_accessed_at = excluded.last_accessed_at",
-            params![chapter_key, page_count as i64, processed_count as i64, now, now],
+                priority = excluded.priority,
+                job = excluded.job",
+            params![chapter_key, priority, job, now_unix()],
+        );
+    }
+
+    pub fn delete_chapter_job(&self, chapter_key: &str) {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for delete_chapter_job");
+            return;
+        };
+        let _ = conn.execute(
+            "DELETE FROM chapter_jobs WHERE chapter_key = ?",
+            params![chapter_key],
+        );
+    }
+
+    /// Chapter jobs that were queued or running when the server last stopped, oldest first.
+    pub fn load_chapter_jobs(&self) -> Vec<(String, JobPriority, ChapterJob)> {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for load_chapter_jobs");
+            return Vec::new();
+        };
+        let mut stmt = match conn
+            .prepare("SELECT chapter_key, priority, job FROM chapter_jobs ORDER BY created_at")
+        {
+            Ok(stmt) => stmt,
+            Err(err) => {
+                warn!("Failed to prepare chapter_jobs query: {err}");
+                return Vec::new();
+            }
+        };
+        let rows = match stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        }) {
+            Ok(rows) => rows,
+            Err(err) => {
+                warn!("Failed to read chapter_jobs: {err}");
+                return Vec::new();
+            }
+        };
+
+        let mut jobs = Vec::new();
+        for (chapter_key, priority, job) in rows.flatten() {
+            let priority = serde_json::from_str(&priority).unwrap_or_default();
+            match serde_json::from_str::<ChapterJob>(&job) {
+                Ok(job) => jobs.push((chapter_key, priority, job)),
+                Err(err) => warn!("Dropping unreadable chapter job {chapter_key}: {err}"),
+            }
+        }
+        jobs
+    }
+
+    pub fn save_manga_job(&self, manga_key: &str, job: &MangaJob) {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for save_manga_job");
+            return;
+        };
+        let Ok(job) = serde_json::to_string(job) else {
+            warn!("Failed to serialize manga job for {manga_key}");
+            return;
+        };
+        let _ = conn.execute(
+            "INSERT INTO manga_jobs (manga_key, job, created_at)
+             VALUES (?, ?, ?)
+             ON CONFLICT(manga_key) DO UPDATE SET job = excluded.job",
+            params![manga_key, job, now_unix()],
+        );
+    }
+
+    pub fn delete_manga_job(&self, manga_key: &str) {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for delete_manga_job");
+            return;
+        };
+        let _ = conn.execute(
+            "DELETE FROM manga_jobs WHERE manga_key = ?",
+            params![manga_key],
+        );
+    }
+
+    pub fn load_manga_jobs(&self) -> Vec<MangaJob> {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for load_manga_jobs");
+            return Vec::new();
+        };
+        let mut stmt =
+            match conn.prepare("SELECT manga_key, job FROM manga_jobs ORDER BY created_at") {
+                Ok(stmt) => stmt,
+                Err(err) => {
+                    warn!("Failed to prepare manga_jobs query: {err}");
+                    return Vec::new();
+                }
+            };
+        let rows = match stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        }) {
+            Ok(rows) => rows,
+            Err(err) => {
+                warn!("Failed to read manga_jobs: {err}");
+                return Vec::new();
+            }
+        };
+
+        let mut jobs = Vec::new();
+        for (manga_key, job) in rows.flatten() {
+            match serde_json::from_str::<MangaJob>(&job) {
+                Ok(job) => jobs.push(job),
+                Err(err) => warn!("Dropping unreadable manga job {manga_key}: {err}"),
+            }
+        }
+        jobs
+    }
+
+    pub fn list_postprocess_rules(&self) -> Vec<ReplacementRule> {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for list_postprocess_rules");
+            return Vec::new();
+        };
+        let mut stmt = match conn.prepare(
+            "SELECT id, language, pattern, replacement, enabled, created_at
+             FROM postprocess_rules ORDER BY id",
+        ) {
+            Ok(stmt) => stmt,
+            Err(err) => {
+                warn!("Failed to prepare postprocess_rules query: {err}");
+                return Vec::new();
+            }
+        };
+        let rows = stmt.query_map([], |row| {
+            let language: Option<String> = row.get(1)?;
+            Ok(ReplacementRule {
+                id: row.get(0)?,
+                language: language.and_then(|language| {
+                    serde_json::from_value(serde_json::Value::String(language)).ok()
+                }),
+                pattern: row.get(2)?,
+                replacement: row.get(3)?,
+                enabled: row.get::<_, i64>(4)? != 0,
+                created_at: row.get(5)?,
+            })
+        });
+        match rows {
+            Ok(rows) => rows.flatten().collect(),
+            Err(err) => {
+                warn!("Failed to read postprocess_rules: {err}");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns the new rule's ID.
+    pub fn insert_postprocess_rule(&self, rule: &RuleInput) -> Option<i64> {
+        let conn = self.pool.get().ok()?;
+        conn.execute(
+            "INSERT INTO postprocess_rules (language, pattern, replacement, enabled, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+            params![
+                rule.language.map(|language| language.as_str()),
+                rule.pattern,
+                rule.replacement,
+                rule.enabled as i64,
+                now_unix()
+            ],
+        )
+        .ok()?;
+        let id = conn.last_insert_rowid();
+        drop(conn);
+        self.reload_postprocess_rules();
+        Some(id)
+    }
+
+    /// Returns `false` if no rule has this ID.
+    pub fn update_postprocess_rule(&self, id: i64, rule: &RuleInput) -> bool {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for update_postprocess_rule");
+            return false;
+        };
+        let updated = conn
+            .execute(
+                "UPDATE postprocess_rules
+                 SET language = ?, pattern = ?, replacement = ?, enabled = ?
+                 WHERE id = ?",
+                params![
+                    rule.language.map(|language| language.as_str()),
+                    rule.pattern,
+                    rule.replacement,
+                    rule.enabled as i64,
+                    id
+                ],
+            )
+            .map(|changes| changes > 0)
+            .unwrap_or(false);
+        drop(conn);
+        self.reload_postprocess_rules();
+        updated
+    }
+
+    /// Returns `false` if no rule has this ID.
+    pub fn delete_postprocess_rule(&self, id: i64) -> bool {
+        let Ok(conn) = self.pool.get() else {
+            warn!("Failed to get DB connection for delete_postprocess_rule");
+            return false;
+        };
+        let deleted = conn
+            .execute("DELETE FROM postprocess_rules WHERE id = ?", params![id])
+            .map(|changes| changes > 0)
+            .unwrap_or(false);
+        drop(conn);
+        self.reload_postprocess_rules();
+        deleted
+    }
+
+    /// Recompiles the rules used by `logic::post_process_text`.
+    pub fn reload_postprocess_rules(&self) {
+        postprocess::set_rules(&self.list_postprocess_rules());
+    }
+
+    /// Moves the write-ahead log back into the database file.
+    pub fn checkpoint_wal(&self) -> anyhow::Result<()> {
+        let conn = self.pool.get()?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Returns space freed by deletions to the filesystem and refreshes the query
+    /// planner statistics. Databases created before incremental auto-vacuum was enabled
+    /// get one full `VACUUM` to switch them over.
+    pub fn run_maintenance(&self) -> anyhow::Result<MaintenanceReport> {
+        let conn = self.pool.get()?;
+        let started = std::time::Instant::now();
+        let bytes_before = self.db_file_size();
+
+        let auto_vacuum: i64 = conn.query_row("PRAGMA auto_vacuum", [], |row| row.get(0))?;
+        let full_vacuum = auto_vacuum != AUTO_VACUUM_INCREMENTAL;
+        if full_vacuum {
+            retry_on_busy(|| conn.execute_batch("PRAGMA auto_vacuum = INCREMENTAL; VACUUM;"))?;
+        } else {
+            retry_on_busy(|| conn.execute_batch("PRAGMA incremental_vacuum;"))?;
+        }
+        retry_on_busy(|| conn.execute_batch("ANALYZE;"))?;
+        // Moves the WAL back into the main file so the size reflects what was freed.
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+        let bytes_after = self.db_file_size();
+        let report = MaintenanceReport {
+            full_vacuum,
+            bytes_before,
+            bytes_after,
+            reclaimed_bytes: bytes_before.saturating_sub(bytes_after),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        info!(
+            "Cache maintenance: {} -> {} bytes (full vacuum: {full_vacuum})",
+            report.bytes_before, report.bytes_after
         );
+        Ok(report)
+    }
+
+    /// Size of the database and its write-ahead log on disk.
+    fn db_file_size(&self) -> u64 {
+        ["ocr-cache.db", "ocr-cache.db-wal"]
+            .iter()
+            .filter_map(|name| std::fs::metadata(self.cache_dir.join(name)).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+}
+
+/// Retries a write that failed because another connection held the lock for longer
+/// than `BUSY_TIMEOUT`. Sleeps between attempts, so only call it on the blocking pool,
+/// see `AppState::blocking`.
+fn retry_on_busy<T>(mut op: impl FnMut() -> rusqlite::Result<T>) -> rusqlite::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(rusqlite::Error::SqliteFailure(err, _))
+                if attempt < BUSY_RETRY_ATTEMPTS
+                    && matches!(
+                        err.code,
+                        rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+                    ) =>
+            {
+                std::thread::sleep(Duration::from_millis(25 * attempt as u64));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn query_cached_page_keys(
+    conn: &mut rusqlite::Connection,
+    cache_ttl: &CacheTtl,
+    cache_keys: &[String],
+) -> rusqlite::Result<HashSet<String>> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS status_page_ranges (
+            page_key TEXT NOT NULL,
+            low TEXT NOT NULL,
+            high TEXT NOT NULL
+         );
+         DELETE FROM temp.status_page_ranges;",
+    )?;
+    {
+        // Each page matches its own key or a `sourceId` variant; ranges keep the join on
+        // the primary key index instead of scanning with LIKE.
+        let mut insert = tx.prepare("INSERT INTO temp.status_page_ranges VALUES (?, ?, ?)")?;
+        for key in cache_keys {
+            insert.execute(params![key, key, key])?;
+            for separator in ['?', '&'] {
+                insert.execute(params![
+                    key,
+                    format!("{key}{separator}sourceId="),
+                    format!("{key}{separator}sourceId>")
+                ])?;
+            }
+        }
+    }
+
+    let now = now_unix();
+    let mut cached = HashSet::new();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT r.page_key, o.cache_key, o.last_processed_at, o.user_edited
+             FROM temp.status_page_ranges r
+             JOIN ocr_cache o ON o.cache_key BETWEEN r.low AND r.high",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let page_key: String = row.get(0)?;
+            let cache_key: String = row.get(1)?;
+            let last_processed_at: i64 = row.get(2)?;
+            let user_edited: bool = row.get(3)?;
+            if user_edited || last_processed_at >= cache_ttl.min_processed_at(&cache_key, now) {
+                cached.insert(page_key);
+            }
+        }
+    }
+    tx.execute("DELETE FROM temp.status_page_ranges", [])?;
+    tx.commit()?;
+    Ok(cached)
+}
+
+fn query_chapter_cache_statuses(
+    conn: &mut rusqlite::Connection,
+    chapter_keys: &[String],
+) -> rusqlite::Result<HashMap<String, ChapterCacheStatus>> {
+    let tx = conn.transaction()?;
+    tx.execute_batch(
+        "CREATE TEMP TABLE IF NOT EXISTS status_chapters (chapter_key TEXT PRIMARY KEY);
+         DELETE FROM temp.status_chapters;",
+    )?;
+    {
+        let mut insert = tx.prepare("INSERT OR IGNORE INTO temp.status_chapters VALUES (?)")?;
+        for key in chapter_keys {
+            insert.execute(params![key])?;
+        }
+    }
+
+    let mut statuses = HashMap::new();
+    {
+        let mut stmt = tx.prepare(
+            "SELECT s.chapter_key,
+                    (SELECT COUNT(*) FROM chapter_cache c WHERE c.chapter_key = s.chapter_key),
+                    (SELECT page_count FROM chapter_pages p WHERE p.chapter_key = s.chapter_key)
+             FROM temp.status_chapters s",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let chapter_key: String = row.get(0)?;
+            let cached_pages: i64 = row.get(1)?;
+            let page_count: Option<i64> = row.get(2)?;
+            if cached_pages > 0 || page_count.is_some() {
+                statuses.insert(
+                    chapter_key,
+                    ChapterCacheStatus {
+                        cached_pages: cached_pages as usize,
+                        page_count: page_count.map(|count| count as usize),
+                    },
+                );
+            }
+        }
+    }
+    let _ = tx.execute(
+        "UPDATE chapter_pages SET last_accessed_at = ?
+         WHERE chapter_key IN (SELECT chapter_key FROM temp.status_chapters)",
+        params![now_unix()],
+    );
+    tx.execute("DELETE FROM temp.status_chapters", [])?;
+    tx.commit()?;
+    Ok(statuses)
+}
+
+/// Copies the current row of `cache_key` into `ocr_history` before it is replaced by
+/// `new_data`, then drops versions beyond `history::history_limit`. Identical data and,
+/// unless `include_user_edited`, user-edited rows are not archived.
+fn archive_current_version(
+    conn: &rusqlite::Connection,
+    cache_key: &str,
+    new_data: &[u8],
+    include_user_edited: bool,
+    now: i64,
+) -> rusqlite::Result<()> {
+    let limit = history::history_limit();
+    if limit == 0 {
+        return Ok(());
+    }
+    let archived = conn.execute(
+        "INSERT INTO ocr_history
+            (cache_key, context, data, user_edited, processed_at, archived_at)
+         SELECT cache_key, context, data, user_edited, last_processed_at, ?
+         FROM ocr_cache
+         WHERE cache_key = ? AND data != ? AND (? OR user_edited = 0)",
+        params![now, cache_key, new_data, include_user_edited],
+    )?;
+    if archived > 0 {
+        conn.execute(
+            "DELETE FROM ocr_history
+             WHERE cache_key = ?1 AND id NOT IN (
+                SELECT id FROM ocr_history WHERE cache_key = ?1 ORDER BY id DESC LIMIT ?2
+             )",
+            params![cache_key, limit as i64],
+        )?;
+    }
+    Ok(())
+}
+
+/// Serializes OCR results for `ocr_cache.data`, zstd-compressed behind `ZSTD_BLOB_MARKER`.
+fn encode_cache_data(data: &[OcrResult]) -> Vec<u8> {
+    let json = serde_json::to_vec(data).unwrap_or_default();
+    match zstd::encode_all(json.as_slice(), ZSTD_LEVEL) {
+        Ok(compressed) => [ZSTD_BLOB_MARKER, compressed.as_slice()].concat(),
+        Err(err) => {
+            warn!("Failed to compress cache data, storing it uncompressed: {err}");
+            json
+        }
+    }
+}
+
+/// Reads an `ocr_cache.data` blob in either the compressed or the legacy JSON format.
+fn decode_cache_data(blob: &[u8]) -> Vec<OcrResult> {
+    let Some(compressed) = blob.strip_prefix(ZSTD_BLOB_MARKER) else {
+        return serde_json::from_slice(blob).unwrap_or_default();
+    };
+    match zstd::decode_all(compressed) {
+        Ok(json) => serde_json::from_slice(&json).unwrap_or_default(),
+        Err(err) => {
+            warn!("Failed to decompress cache data: {err}");
+            Vec::new()
+        }
     }
 }
 
-fn now_unix() -> i64 {
+pub(crate) fn now_unix() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -582,7 +1792,7 @@ fn migrate_legacy_cache(conn: &mut rusqlite::Connection, cache_dir: &Path) {
 
     let mut imported = 0;
     for (key, entry) in persistent_state.cache {
-        let data_blob = serde_json::to_vec(&entry.data).unwrap_or_default();
+        let data_blob = encode_cache_data(&entry.data);
         if let Ok(changes) = tx.execute(
             "INSERT OR IGNORE INTO ocr_cache
                 (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
@@ -614,3 +1824,52 @@ fn migrate_legacy_cache(conn: &mut rusqlite::Connection, cache_dir: &Path) {
         info!("Migrated {} legacy OCR cache entries into SQLite", imported);
     }
 }
+
+/// Moves keys without a namespace into a fixed `MANATAN_OCR_CACHE_NAMESPACE`, once per
+/// namespace. With `host` the owner of a key is unknown, so pages are claimed on first
+/// lookup instead, see `AppState::claim_unnamespaced_entry`.
+fn migrate_cache_namespace(conn: &mut rusqlite::Connection) {
+    let CacheNamespace::Fixed(name) = namespace::current() else {
+        return;
+    };
+    let migrated: Option<String> = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE key = 'cache_namespace'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .unwrap_or(None);
+    if migrated.as_deref() == Some(name.as_str()) {
+        return;
+    }
+
+    let prefix = namespace::apply(Some(name), String::new());
+    let unprefixed = format!("{}%", namespace::NAMESPACE_PREFIX);
+    let result = (|| -> rusqlite::Result<usize> {
+        let tx = conn.transaction()?;
+        let mut renamed_entries = 0;
+        for &(table, column) in NAMESPACED_COLUMNS {
+            let renamed = tx.execute(
+                &format!(
+                    "UPDATE OR IGNORE {table} SET {column} = ? || {column}
+                     WHERE {column} NOT LIKE ?"
+                ),
+                params![prefix, unprefixed],
+            )?;
+            if table == "ocr_cache" {
+                renamed_entries = renamed;
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES ('cache_namespace', ?)",
+            params![name],
+        )?;
+        tx.commit()?;
+        Ok(renamed_entries)
+    })();
+    match result {
+        Ok(renamed) => info!("Moved {renamed} OCR cache entries into namespace {name}"),
+        Err(err) => warn!("Failed to move OCR cache into namespace {name}: {err}"),
+    }
+}
@@ -0,0 +1,197 @@
+//! Nested block → line → word view of a page's OCR results.
+//!
+//! Merging flattens backend paragraphs into single results. Merged results keep their
+//! source lines (`OcrResult::lines`) and the backend paragraph they came from
+//! (`OcrResult::block_id`), which is enough to rebuild the hierarchy on request.
+//! Word boxes are estimated by splitting the line box proportionally to character count.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    language::OcrLanguage,
+    logic::{BoundingBox, OcrResult},
+};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TextBlock {
+    pub text: String,
+    #[serde(rename = "boundingBox")]
+    pub bounding_box: BoundingBox,
+    pub lines: Vec<TextLine>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TextLine {
+    pub text: String,
+    #[serde(rename = "boundingBox")]
+    pub bounding_box: BoundingBox,
+    #[serde(rename = "forcedOrientation", skip_serializing_if = "Option::is_none")]
+    pub forced_orientation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    pub words: Vec<TextWord>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TextWord {
+    pub text: String,
+    #[serde(rename = "boundingBox")]
+    pub bounding_box: BoundingBox,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum BlockKey {
+    /// A paragraph reported by the backend.
+    Backend(u32),
+    /// No paragraph info: the merged result itself is the block.
+    Result(usize),
+}
+
+/// Removes the per-line detail kept for [`build_blocks`] from a flat result list.
+pub fn strip_line_structure(results: &mut [OcrResult]) {
+    for result in results {
+        result.block_id = None;
+        result.lines.clear();
+    }
+}
+
+/// Groups the source lines of `results` into blocks, in order of first appearance.
+pub fn build_blocks(results: &[OcrResult], language: OcrLanguage) -> Vec<TextBlock> {
+    let mut order: Vec<BlockKey> = Vec::new();
+    let mut grouped: HashMap<BlockKey, Vec<&OcrResult>> = HashMap::new();
+
+    for (index, result) in results.iter().enumerate() {
+        let source_lines: Vec<&OcrResult> = if result.lines.is_empty() {
+            vec![result]
+        } else {
+            result.lines.iter().collect()
+        };
+        for line in source_lines {
+            let key = line
+                .block_id
+                .map(BlockKey::Backend)
+                .unwrap_or(BlockKey::Result(index));
+            grouped
+                .entry(key)
+                .or_insert_with(|| {
+                    order.push(key);
+                    Vec::new()
+                })
+                .push(line);
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| grouped.remove(&key))
+        .map(|lines| {
+            let lines: Vec<TextLine> = lines
+                .into_iter()
+                .map(|line| build_line(line, language))
+                .collect();
+            TextBlock {
+                text: lines
+                    .iter()
+                    .map(|line| line.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                bounding_box: union_boxes(lines.iter().map(|line| &line.bounding_box)),
+                lines,
+            }
+        })
+        .collect()
+}
+
+fn build_line(line: &OcrResult, language: OcrLanguage) -> TextLine {
+    let bbox = &line.tight_bounding_box;
+    let is_vertical = line.forced_orientation.as_deref() == Some("vertical");
+    let total_chars = line.text.chars().count().max(1) as f64;
+
+    let tokens: Vec<(usize, &str)> = if language.prefers_no_space() {
+        vec![(0, line.text.as_str())]
+    } else {
+        split_words(&line.text)
+    };
+
+    let words = tokens
+        .into_iter()
+        .map(|(start, word)| {
+            let start = start as f64 / total_chars;
+            let len = word.chars().count() as f64 / total_chars;
+            let bounding_box = if is_vertical {
+                BoundingBox {
+                    x: bbox.x,
+                    y: bbox.y + bbox.height * start,
+                    width: bbox.width,
+                    height: bbox.height * len,
+                    rotation: None,
+                }
+            } else {
+                BoundingBox {
+                    x: bbox.x + bbox.width * start,
+                    y: bbox.y,
+                    width: bbox.width * len,
+                    height: bbox.height,
+                    rotation: None,
+                }
+            };
+            TextWord {
+                text: word.to_string(),
+                bounding_box,
+            }
+        })
+        .collect();
+
+    TextLine {
+        text: line.text.clone(),
+        bounding_box: bbox.clone(),
+        forced_orientation: line.forced_orientation.clone(),
+        confidence: line.confidence,
+        words,
+    }
+}
+
+/// Whitespace-separated words with their starting character offset.
+fn split_words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (char_index, (byte_index, ch)) in text.char_indices().enumerate() {
+        match (ch.is_whitespace(), start) {
+            (false, None) => start = Some((char_index, byte_index)),
+            (true, Some((word_char, word_byte))) => {
+                words.push((word_char, &text[word_byte..byte_index]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((word_char, word_byte)) = start {
+        words.push((word_char, &text[word_byte..]));
+    }
+    words
+}
+
+fn union_boxes<'a>(boxes: impl Iterator<Item = &'a BoundingBox>) -> BoundingBox {
+    let mut min_x = f64::INFINITY;
+    let mut min_y = f64::INFINITY;
+    let mut max_x = f64::NEG_INFINITY;
+    let mut max_y = f64::NEG_INFINITY;
+    for bbox in boxes {
+        min_x = min_x.min(bbox.x);
+        min_y = min_y.min(bbox.y);
+        max_x = max_x.max(bbox.x + bbox.width);
+        max_y = max_y.max(bbox.y + bbox.height);
+    }
+    if !min_x.is_finite() {
+        return BoundingBox::default();
+    }
+    BoundingBox {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+        rotation: None,
+    }
+}
@@ -0,0 +1,292 @@
+//! Optional machine translation of OCR results, for readers still learning the language.
+//!
+//! Enabled by `MANATAN_OCR_TRANSLATE_PROVIDER` (`deepl`, `google` or `libretranslate`).
+//!
+//! - `MANATAN_OCR_TRANSLATE_API_KEY`: key for the provider. DeepL free keys (ending in
+//!   `:fx`) use the free API automatically.
+//! - `MANATAN_OCR_TRANSLATE_URL`: base URL, mostly for self-hosted LibreTranslate.
+//! - `MANATAN_OCR_TRANSLATE_TARGET`: target language code, default `en`.
+//!
+//! `/ocr?translate=true` fills in `translation` on each result and stores it with the
+//! cache entry, so a page is only translated once. The target is server-wide for the same
+//! reason.
+
+use anyhow::anyhow;
+use serde::Deserialize;
+
+use crate::{language::OcrLanguage, logic::OcrResult};
+
+const PROVIDER_ENV: &str = "MANATAN_OCR_TRANSLATE_PROVIDER";
+const API_KEY_ENV: &str = "MANATAN_OCR_TRANSLATE_API_KEY";
+const URL_ENV: &str = "MANATAN_OCR_TRANSLATE_URL";
+const TARGET_ENV: &str = "MANATAN_OCR_TRANSLATE_TARGET";
+const DEFAULT_TARGET: &str = "en";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranslationProvider {
+    DeepL,
+    Google,
+    LibreTranslate,
+}
+
+impl TranslationProvider {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "deepl" => Some(Self::DeepL),
+            "google" => Some(Self::Google),
+            "libretranslate" | "libre" => Some(Self::LibreTranslate),
+            _ => None,
+        }
+    }
+
+    fn default_base_url(self, api_key: Option<&str>) -> &'static str {
+        match self {
+            Self::DeepL if api_key.is_some_and(|key| key.ends_with(":fx")) => {
+                "https://api-free.deepl.com"
+            }
+            Self::DeepL => "https://api.deepl.com",
+            Self::Google => "https://translation.googleapis.com",
+            Self::LibreTranslate => "https://libretranslate.com",
+        }
+    }
+}
+
+pub struct Translator {
+    provider: TranslationProvider,
+    api_key: Option<String>,
+    base_url: String,
+    target: String,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslation {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct GoogleResponse {
+    data: GoogleData,
+}
+
+#[derive(Deserialize)]
+struct GoogleData {
+    translations: Vec<GoogleTranslation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleTranslation {
+    translated_text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: Vec<String>,
+}
+
+impl Translator {
+    pub fn new(
+        provider: TranslationProvider,
+        api_key: Option<String>,
+        base_url: Option<String>,
+        target: String,
+    ) -> Self {
+        let base_url = base_url
+            .unwrap_or_else(|| provider.default_base_url(api_key.as_deref()).to_string())
+            .trim_end_matches('/')
+            .to_string();
+        Self {
+            provider,
+            api_key,
+            base_url,
+            target,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// `None` unless a provider is configured.
+    pub fn from_env() -> Option<Self> {
+        let value = manatan_config::var(PROVIDER_ENV).ok()?;
+        if value.trim().is_empty() {
+            return None;
+        }
+        let Some(provider) = TranslationProvider::parse(&value) else {
+            tracing::warn!("Ignoring unknown {PROVIDER_ENV}={value}");
+            return None;
+        };
+        let read = |name: &str| {
+            manatan_config::var(name)
+                .ok()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let api_key = read(API_KEY_ENV);
+        if api_key.is_none() && provider != TranslationProvider::LibreTranslate {
+            tracing::warn!("{PROVIDER_ENV}={value} needs {API_KEY_ENV}, translation disabled");
+            return None;
+        }
+        let target = read(TARGET_ENV).unwrap_or_else(|| DEFAULT_TARGET.to_string());
+        tracing::info!("Translating OCR results to {target} with {provider:?}");
+        Some(Self::new(provider, api_key, read(URL_ENV), target))
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Whether pages in `source` need translating at all.
+    pub fn translates_from(&self, source: OcrLanguage) -> bool {
+        !language_code(source).eq_ignore_ascii_case(primary_subtag(&self.target))
+    }
+
+    /// Translates `texts` in one request, returning them in the same order.
+    pub async fn translate(
+        &self,
+        texts: &[String],
+        source: OcrLanguage,
+    ) -> anyhow::Result<Vec<String>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        let source = language_code(source);
+        let translations: Vec<String> = match self.provider {
+            TranslationProvider::DeepL => {
+                let api_key = self.api_key.as_deref().unwrap_or_default();
+                let response: DeepLResponse = self
+                    .send_json(
+                        self.client
+                            .post(format!("{}/v2/translate", self.base_url))
+                            .header("Authorization", format!("DeepL-Auth-Key {api_key}"))
+                            .json(&serde_json::json!({
+                                "text": texts,
+                                "source_lang": source.to_ascii_uppercase(),
+                                "target_lang": self.target.to_ascii_uppercase(),
+                            })),
+                    )
+                    .await?;
+                response
+                    .translations
+                    .into_iter()
+                    .map(|translation| translation.text)
+                    .collect()
+            }
+            TranslationProvider::Google => {
+                let response: GoogleResponse = self
+                    .send_json(
+                        self.client
+                            .post(format!("{}/language/translate/v2", self.base_url))
+                            .query(&[("key", self.api_key.as_deref().unwrap_or_default())])
+                            .json(&serde_json::json!({
+                                "q": texts,
+                                "source": source,
+                                "target": self.target,
+                                "format": "text",
+                            })),
+                    )
+                    .await?;
+                response
+                    .data
+                    .translations
+                    .into_iter()
+                    .map(|translation| translation.translated_text)
+                    .collect()
+            }
+            TranslationProvider::LibreTranslate => {
+                let response: LibreTranslateResponse = self
+                    .send_json(
+                        self.client
+                            .post(format!("{}/translate", self.base_url))
+                            .json(&serde_json::json!({
+                                "q": texts,
+                                "source": source,
+                                "target": self.target,
+                                "format": "text",
+                                "api_key": self.api_key,
+                            })),
+                    )
+                    .await?;
+                response.translated_text
+            }
+        };
+        if translations.len() != texts.len() {
+            return Err(anyhow!(
+                "{:?} returned {} translations for {} lines",
+                self.provider,
+                translations.len(),
+                texts.len()
+            ));
+        }
+        Ok(translations)
+    }
+
+    async fn send_json<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> anyhow::Result<T> {
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "[Failed to read body]".to_string());
+            return Err(anyhow!(
+                "{:?} request failed (Status: {status}). Body: {body}",
+                self.provider
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|err| anyhow!("Error decoding {:?} response: {err}", self.provider))
+    }
+}
+
+/// Two- or three-letter code of `language`, e.g. `ja`.
+pub fn language_code(language: OcrLanguage) -> &'static str {
+    primary_subtag(language.bcp47_tag())
+}
+
+fn primary_subtag(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+/// Translates every result that has no translation yet. Returns whether any changed.
+pub async fn translate_results(
+    translator: &Translator,
+    results: &mut [OcrResult],
+    source: OcrLanguage,
+) -> anyhow::Result<bool> {
+    let missing: Vec<usize> = results
+        .iter()
+        .enumerate()
+        .filter(|(_, result)| result.translation.is_none() && !result.text.trim().is_empty())
+        .map(|(index, _)| index)
+        .collect();
+    if missing.is_empty() || !translator.translates_from(source) {
+        return Ok(false);
+    }
+    let texts: Vec<String> = missing
+        .iter()
+        .map(|index| results[*index].text.clone())
+        .collect();
+    let translations = translator.translate(&texts, source).await?;
+    for (index, translation) in missing.into_iter().zip(translations) {
+        results[index].translation = Some(translation);
+    }
+    Ok(true)
+}
+
+/// Drops translations from results, for requests that didn't ask for them.
+pub fn strip_translations(results: &mut [OcrResult]) {
+    for result in results {
+        result.translation = None;
+    }
+}
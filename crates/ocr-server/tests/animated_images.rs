@@ -0,0 +1,47 @@
+use std::io::Cursor;
+
+use image::{Delay, Frame, ImageFormat, Rgba, RgbaImage, codecs::gif::GifEncoder};
+use manatan_ocr_server::logic;
+
+fn gif(frames: &[Rgba<u8>]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        encoder
+            .encode_frames(frames.iter().map(|color| {
+                Frame::from_parts(
+                    RgbaImage::from_pixel(8, 6, *color),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(100, 1),
+                )
+            }))
+            .expect("encode gif");
+    }
+    bytes
+}
+
+#[test]
+fn multi_frame_gif_is_animated_and_decodes_to_the_first_frame() {
+    let bytes = gif(&[Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255])]);
+
+    assert!(logic::is_animated(&bytes));
+    let image = logic::decode_image(&bytes).expect("decode");
+    assert_eq!((image.width(), image.height()), (8, 6));
+    assert_eq!(
+        image.to_rgba8().get_pixel(0, 0),
+        &Rgba([255, 255, 255, 255])
+    );
+}
+
+#[test]
+fn single_frame_images_are_not_animated() {
+    assert!(!logic::is_animated(&gif(&[Rgba([255, 255, 255, 255])])));
+
+    let mut png = Vec::new();
+    RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .expect("encode png");
+    assert!(!logic::is_animated(&png));
+    assert!(logic::decode_image(&png).is_ok());
+}
@@ -0,0 +1,75 @@
+use std::io::{Cursor, Write};
+
+use manatan_ocr_server::archive::{self, natural_cmp};
+use zip::write::SimpleFileOptions;
+
+#[test]
+fn pages_sort_by_number_value() {
+    let mut names = ["page10.png", "page2.png", "Page1.png", "page02b.png"];
+    names.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(
+        names,
+        ["Page1.png", "page2.png", "page02b.png", "page10.png"]
+    );
+}
+
+fn zip_with(files: &[(&str, &str)]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        writer
+            .start_file(*name, SimpleFileOptions::default())
+            .expect("start zip entry");
+        writer
+            .write_all(contents.as_bytes())
+            .expect("write zip entry");
+    }
+    writer.finish().expect("finish zip").into_inner()
+}
+
+#[test]
+fn epub_pages_follow_the_spine() {
+    let epub = zip_with(&[
+        (
+            "META-INF/container.xml",
+            r#"<container><rootfiles><rootfile full-path="OEBPS/content.opf"/></rootfiles></container>"#,
+        ),
+        (
+            "OEBPS/content.opf",
+            r#"<package><manifest>
+                <item id="p2" href="text/p2.xhtml" media-type="application/xhtml+xml"/>
+                <item id="p1" href="text/p1.xhtml" media-type="application/xhtml+xml"/>
+            </manifest><spine><itemref idref="p1"/><itemref idref="p2"/></spine></package>"#,
+        ),
+        (
+            "OEBPS/text/p1.xhtml",
+            r#"<body><img src="../images/b.jpg"/></body>"#,
+        ),
+        (
+            "OEBPS/text/p2.xhtml",
+            r#"<svg><image xlink:href="../images/a%20page.jpg"/></svg>"#,
+        ),
+        ("OEBPS/images/a page.jpg", "a"),
+        ("OEBPS/images/b.jpg", "b"),
+    ]);
+
+    let pages = archive::read_epub_pages(&epub, "archive/test").expect("valid epub");
+    let urls: Vec<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+    assert_eq!(
+        urls,
+        [
+            "archive/test/OEBPS/images/b.jpg",
+            "archive/test/OEBPS/images/a page.jpg"
+        ]
+    );
+}
+
+#[test]
+fn oversize_entries_are_refused() {
+    let page = "0".repeat(archive::MAX_ENTRY_BYTES as usize + 1);
+    let cbz = zip_with(&[("page1.png", &page)]);
+
+    let Err(err) = archive::read_zip_pages(&cbz, "archive/test") else {
+        panic!("entry over the limit was read");
+    };
+    assert!(err.to_string().contains("page1.png"), "{err}");
+}
@@ -0,0 +1,39 @@
+use manatan_ocr_server::logic;
+
+#[test]
+fn keeps_the_reverse_proxy_sub_path() {
+    assert_eq!(
+        logic::derive_api_base("https://example.com/suwayomi/api/v1/manga/12/chapter/3/page/"),
+        "https://example.com/suwayomi"
+    );
+    assert_eq!(
+        logic::derive_api_base("https://example.com/apps/suwayomi/manga/12"),
+        "https://example.com/apps/suwayomi"
+    );
+}
+
+#[test]
+fn root_installs_are_unchanged() {
+    assert_eq!(
+        logic::derive_api_base("http://127.0.0.1:4567/api/v1/manga/12/chapter/3/page/"),
+        "http://127.0.0.1:4567"
+    );
+    assert_eq!(
+        logic::split_base_path("/api/v1/manga/12"),
+        ("", "/api/v1/manga/12")
+    );
+}
+
+#[test]
+fn local_page_fetches_drop_the_sub_path() {
+    assert_eq!(
+        logic::local_image_url("https://example.com/suwayomi/api/v1/manga/12/chapter/3/page/0"),
+        "http://127.0.0.1:4568/api/v1/manga/12/chapter/3/page/0"
+    );
+    assert_eq!(
+        logic::local_image_url(
+            "http://192.168.1.5:4567/api/v1/manga/12/chapter/3/page/0?sourceId=7"
+        ),
+        "http://127.0.0.1:4568/api/v1/manga/12/chapter/3/page/0?sourceId=7"
+    );
+}
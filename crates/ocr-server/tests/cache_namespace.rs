@@ -0,0 +1,51 @@
+use manatan_ocr_server::{
+    language::OcrLanguage,
+    logic,
+    namespace::{self, CacheNamespace},
+};
+
+const PAGE: &str = "http://192.168.1.5:4567/api/v1/manga/12/chapter/3/page/0?sourceId=7";
+
+#[test]
+fn parses_the_namespace_setting() {
+    assert_eq!(CacheNamespace::parse(""), CacheNamespace::None);
+    assert_eq!(CacheNamespace::parse(" HOST "), CacheNamespace::Host);
+    assert_eq!(
+        CacheNamespace::parse("/mangadex/en/"),
+        CacheNamespace::Fixed("mangadex_en".to_string())
+    );
+}
+
+#[test]
+fn host_namespace_fingerprints_the_server() {
+    let host = CacheNamespace::Host;
+    assert_eq!(host.for_url(PAGE).as_deref(), Some("192.168.1.5:4567"));
+    assert_eq!(
+        host.for_url("https://suwayomi.example/api/v1/manga/1")
+            .as_deref(),
+        Some("suwayomi.example")
+    );
+    assert_eq!(host.for_url("local/scans/001.png"), None);
+}
+
+#[test]
+fn namespaced_keys_keep_the_plain_key_recoverable() {
+    let plain = logic::get_cache_key_in(PAGE, Some(OcrLanguage::Japanese), None);
+    let namespaced =
+        logic::get_cache_key_in(PAGE, Some(OcrLanguage::Japanese), Some("192.168.1.5:4567"));
+
+    assert_eq!(plain, "lang/japanese/api/v1/manga/12/chapter/3/page/0");
+    assert_eq!(
+        namespaced,
+        "ns/192.168.1.5:4567/lang/japanese/api/v1/manga/12/chapter/3/page/0"
+    );
+    assert_eq!(namespace::strip(&namespaced), Some(plain.as_str()));
+    assert_eq!(namespace::strip(&plain), None);
+}
+
+#[test]
+fn servers_no_longer_share_entries() {
+    let first = logic::get_cache_key_in(PAGE, None, Some("a:4567"));
+    let second = logic::get_cache_key_in(PAGE, None, Some("b:4567"));
+    assert_ne!(first, second);
+}
@@ -0,0 +1,86 @@
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    logic,
+    merge::{FuriganaMode, MergeConfig, MergeOverrides},
+};
+
+const PAGE: &str = "http://127.0.0.1:4567/api/v1/manga/12/chapter/3/page/0";
+
+#[test]
+fn default_backend_keeps_the_plain_key() {
+    assert_eq!(
+        logic::page_cache_key(
+            PAGE,
+            OcrLanguage::Japanese,
+            OcrBackend::default(),
+            &MergeConfig::default()
+        ),
+        logic::get_cache_key(PAGE, Some(OcrLanguage::Japanese))
+    );
+}
+
+#[test]
+fn other_backends_get_their_own_key() {
+    let key = |backend| {
+        logic::page_cache_key(
+            PAGE,
+            OcrLanguage::Japanese,
+            backend,
+            &MergeConfig::default(),
+        )
+    };
+    let lens = key(OcrBackend::Lens);
+    let manga_ocr = key(OcrBackend::MangaOcr);
+    let paddle = key(OcrBackend::PaddleOcr);
+
+    assert_eq!(
+        manga_ocr,
+        "lang/japanese/api/v1/manga/12/chapter/3/page/0#manga-ocr"
+    );
+    assert_ne!(lens, manga_ocr);
+    assert_ne!(manga_ocr, paddle);
+    // Still found by the chapter's key prefix
+    assert!(manga_ocr.starts_with(&logic::get_cache_key(
+        "http://127.0.0.1:4567/api/v1/manga/12/chapter/3",
+        Some(OcrLanguage::Japanese)
+    )));
+}
+
+#[test]
+fn merge_overrides_get_their_own_key() {
+    let tuned = MergeConfig::default().with_overrides(&MergeOverrides {
+        font_size_ratio: Some(2.5),
+        furigana: Some(FuriganaMode::Ruby),
+        respect_bubbles: Some(false),
+        ..MergeOverrides::default()
+    });
+    assert_eq!(
+        logic::page_cache_key(PAGE, OcrLanguage::Japanese, OcrBackend::MangaOcr, &tuned),
+        "lang/japanese/api/v1/manga/12/chapter/3/page/0#manga-ocr,ratio=2.5,furigana=ruby,\
+         ignore-bubbles"
+    );
+
+    // Overrides equal to the defaults change nothing
+    let restated = MergeConfig::default().with_overrides(&MergeOverrides {
+        merge_enabled: Some(true),
+        furigana: Some(FuriganaMode::Drop),
+        ..MergeOverrides::default()
+    });
+    assert_eq!(restated.cache_variant(), None);
+}
+
+#[test]
+fn image_hashes_tell_backends_and_tuning_apart() {
+    let image = b"same image bytes";
+    let default = MergeConfig::default();
+    let tuned = MergeConfig::default().with_overrides(&MergeOverrides {
+        merge_enabled: Some(false),
+        ..MergeOverrides::default()
+    });
+    let hash = |backend, config| logic::image_hash_key(image, OcrLanguage::Japanese, backend, config);
+
+    assert!(!hash(OcrBackend::Lens, &default).contains('#'));
+    assert_ne!(hash(OcrBackend::Lens, &default), hash(OcrBackend::MangaOcr, &default));
+    assert_ne!(hash(OcrBackend::Lens, &default), hash(OcrBackend::Lens, &tuned));
+}
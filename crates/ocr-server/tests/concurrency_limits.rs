@@ -0,0 +1,87 @@
+use std::{pin::pin, time::Duration};
+
+use futures::poll;
+use manatan_ocr_server::concurrency::{
+    CallPriority, ConcurrencyLimits, ConcurrencyUpdate, MAX_CONCURRENCY,
+};
+
+#[test]
+fn updates_only_the_given_limits() {
+    let limits = ConcurrencyLimits::new(6, 4, 8);
+
+    let settings = limits
+        .apply(ConcurrencyUpdate {
+            page_concurrency: Some(2),
+            status_concurrency: None,
+            backend_concurrency: None,
+        })
+        .expect("valid update");
+
+    assert_eq!(settings.page_concurrency, 2);
+    assert_eq!(settings.status_concurrency, 4);
+    assert_eq!(limits.page(), 2);
+}
+
+#[test]
+fn rejects_out_of_range_updates_without_applying_any() {
+    let limits = ConcurrencyLimits::new(6, 4, 8);
+
+    let result = limits.apply(ConcurrencyUpdate {
+        page_concurrency: Some(3),
+        status_concurrency: Some(0),
+        backend_concurrency: None,
+    });
+    assert!(result.is_err());
+    assert!(
+        limits
+            .apply(ConcurrencyUpdate {
+                page_concurrency: Some(MAX_CONCURRENCY + 1),
+                status_concurrency: None,
+                backend_concurrency: None,
+            })
+            .is_err()
+    );
+
+    assert_eq!(limits.page(), 6);
+    assert_eq!(limits.status(), 4);
+}
+
+#[tokio::test]
+async fn background_calls_yield_to_waiting_interactive_ones() {
+    let limits = ConcurrencyLimits::new(6, 4, 1);
+    let held = limits.acquire_backend(CallPriority::Background).await;
+
+    let mut background = pin!(limits.acquire_backend(CallPriority::Background));
+    let mut interactive = pin!(limits.acquire_backend(CallPriority::Interactive));
+    assert!(poll!(background.as_mut()).is_pending());
+    assert!(poll!(interactive.as_mut()).is_pending());
+
+    drop(held);
+    assert!(poll!(background.as_mut()).is_pending());
+    let permit = interactive.await;
+    assert_eq!(limits.backend_in_use(), 1);
+
+    drop(permit);
+    let _permit = tokio::time::timeout(Duration::from_secs(1), background)
+        .await
+        .expect("background call runs once the slot is free");
+}
+
+#[tokio::test]
+async fn abandoned_interactive_calls_stop_holding_off_background_ones() {
+    let limits = ConcurrencyLimits::new(6, 4, 1);
+    let held = limits.acquire_backend(CallPriority::Interactive).await;
+    {
+        let mut interactive = pin!(limits.acquire_backend(CallPriority::Interactive));
+        assert!(poll!(interactive.as_mut()).is_pending());
+    }
+    drop(held);
+
+    let _permit = tokio::time::timeout(
+        Duration::from_secs(1),
+        limits.acquire_backend(CallPriority::Background),
+    )
+    .await
+    .expect("no interactive call is waiting anymore");
+    assert_eq!(limits.backend_in_use(), 1);
+}
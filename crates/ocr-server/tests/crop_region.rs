@@ -0,0 +1,33 @@
+use image::{DynamicImage, RgbImage};
+use manatan_ocr_server::crop;
+
+#[test]
+fn parses_normalized_boxes() {
+    let bbox = crop::parse_box("0.1, 0.2,0.3,0.4").expect("valid box");
+    assert_eq!(
+        (bbox.x, bbox.y, bbox.width, bbox.height),
+        (0.1, 0.2, 0.3, 0.4)
+    );
+
+    assert!(crop::parse_box("0.1,0.2,0.3").is_err());
+    assert!(crop::parse_box("10,20,30,40").is_err());
+    assert!(crop::parse_box("0.1,0.2,0,0.4").is_err());
+}
+
+#[test]
+fn pads_and_clamps_to_the_image() {
+    let bbox = crop::parse_box("0.0,0.5,0.25,0.5").expect("valid box");
+
+    assert_eq!(crop::pixel_rect(&bbox, 200, 100, 10), Some((0, 40, 60, 60)));
+}
+
+#[test]
+fn crops_to_png() {
+    let image = DynamicImage::ImageRgb8(RgbImage::new(100, 100));
+    let bbox = crop::parse_box("0.2,0.2,0.1,0.3").expect("valid box");
+
+    let png = crop::crop_png(&image, &bbox, 0).expect("cropped");
+    let cropped = image::load_from_memory(&png).expect("valid png");
+
+    assert_eq!((cropped.width(), cropped.height()), (10, 30));
+}
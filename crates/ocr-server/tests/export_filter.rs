@@ -0,0 +1,19 @@
+use manatan_ocr_server::export::ExportFilter;
+
+#[test]
+fn like_wildcards_in_filters_are_escaped() {
+    let filter = ExportFilter {
+        chapter_prefix: Some(r"lang/japanese/api/v1/manga/1_0%\".to_string()),
+        manga_id: None,
+    };
+    assert_eq!(
+        filter.like_pattern().as_deref(),
+        Some(r"lang/japanese/api/v1/manga/1\_0\%\\%")
+    );
+
+    let filter = ExportFilter {
+        chapter_prefix: None,
+        manga_id: Some(" 12 ".to_string()),
+    };
+    assert_eq!(filter.like_pattern().as_deref(), Some("%/manga/12/%"));
+}
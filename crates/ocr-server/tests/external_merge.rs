@@ -0,0 +1,87 @@
+use manatan_ocr_server::{
+    language::OcrLanguage,
+    logic::OcrResult,
+    merge::{self, MergeConfig, MergeOverrides, OrientationBias},
+};
+
+fn line(text: &str, x: f64, y: f64, width: f64, height: f64) -> OcrResult {
+    serde_json::from_value(serde_json::json!({
+        "text": text,
+        "tightBoundingBox": { "x": x, "y": y, "width": width, "height": height },
+    }))
+    .expect("valid OcrResult")
+}
+
+fn unmerged() -> MergeConfig {
+    MergeConfig {
+        enabled: false,
+        language: OcrLanguage::English,
+        ..MergeConfig::default()
+    }
+}
+
+#[test]
+fn returns_normalized_boxes_in_reading_order() {
+    let lines = vec![
+        line("second", 100.0, 600.0, 400.0, 50.0),
+        line("first", 100.0, 100.0, 400.0, 50.0),
+    ];
+
+    let results = merge::merge_external_lines(lines, 1000, 2000, false, &unmerged());
+
+    let texts: Vec<&str> = results.iter().map(|result| result.text.as_str()).collect();
+    assert_eq!(texts, ["first", "second"]);
+    let first = &results[0];
+    assert_eq!(
+        (
+            first.tight_bounding_box.x,
+            first.tight_bounding_box.y,
+            first.tight_bounding_box.width,
+            first.tight_bounding_box.height,
+        ),
+        (0.1, 0.05, 0.4, 0.025)
+    );
+    assert_eq!(
+        (first.full_width, first.full_height),
+        (Some(1000), Some(2000))
+    );
+    assert_eq!(first.order, Some(0));
+}
+
+#[test]
+fn accepts_normalized_input() {
+    let lines = vec![line("only", 0.25, 0.5, 0.5, 0.25)];
+
+    let results = merge::merge_external_lines(lines, 800, 400, true, &unmerged());
+
+    let bbox = &results[0].tight_bounding_box;
+    assert_eq!(
+        (bbox.x, bbox.y, bbox.width, bbox.height),
+        (0.25, 0.5, 0.5, 0.25)
+    );
+}
+
+#[test]
+fn forced_orientation_overrides_the_backend_guess() {
+    let lines = vec![line("세로", 100.0, 100.0, 40.0, 30.0)];
+    let config = MergeConfig {
+        orientation_bias: OrientationBias::Vertical,
+        ..unmerged()
+    };
+
+    let results = merge::merge_external_lines(lines, 1000, 1000, false, &config);
+
+    assert_eq!(results[0].forced_orientation.as_deref(), Some("vertical"));
+}
+
+#[test]
+fn orientation_is_accepted_as_an_alias() {
+    let overrides: MergeOverrides =
+        serde_json::from_value(serde_json::json!({ "orientation": "horizontal" }))
+            .expect("valid overrides");
+
+    assert_eq!(
+        overrides.orientation_bias,
+        Some(OrientationBias::Horizontal)
+    );
+}
@@ -0,0 +1,48 @@
+use manatan_ocr_server::golden::{self, BOX_TOLERANCE, CaseStatus, GoldenLine};
+
+fn line(text: &str, x: f64, y: f64) -> GoldenLine {
+    GoldenLine {
+        text: text.to_string(),
+        x,
+        y,
+        width: 0.1,
+        height: 0.2,
+        orientation: Some("vertical".to_string()),
+    }
+}
+
+#[test]
+fn bundled_pages_match_their_golden_files() {
+    // UPDATE_GOLDEN=1 records the current pipeline output after an intended change.
+    let update = std::env::var("UPDATE_GOLDEN").is_ok();
+    let reports = golden::run_dir(&golden::fixtures_dir(), update);
+    assert!(reports.len() >= 2, "golden fixtures not found");
+
+    let failures: Vec<String> = reports
+        .iter()
+        .filter(|report| report.status == CaseStatus::Failed)
+        .map(|report| format!("{}:\n  {}", report.name, report.mismatches.join("\n  ")))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn compare_accepts_drift_within_tolerance() {
+    let expected = vec![line("あいう", 0.5, 0.1)];
+    let actual = vec![line("あいう", 0.5 + BOX_TOLERANCE / 2.0, 0.1)];
+
+    assert!(golden::compare(&expected, &actual, BOX_TOLERANCE).is_empty());
+}
+
+#[test]
+fn compare_reports_moved_boxes_and_rejoined_text() {
+    let expected = vec![line("あいう", 0.5, 0.1), line("えお", 0.2, 0.6)];
+    let actual = vec![line("あいう\nえお", 0.2, 0.1)];
+
+    let mismatches = golden::compare(&expected, &actual, BOX_TOLERANCE);
+
+    assert_eq!(mismatches.len(), 3, "{mismatches:?}");
+    assert!(mismatches[0].contains("expected 2 lines, got 1"));
+    assert!(mismatches[1].contains("text"));
+    assert!(mismatches[2].contains("x 0.5000 became 0.2000"));
+}
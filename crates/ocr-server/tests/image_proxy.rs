@@ -0,0 +1,37 @@
+use manatan_ocr_server::{auth::SuwayomiAuth, logic};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+const PAGE: &str = "http://manga.example/api/v1/manga/12/chapter/3/page/0";
+
+#[tokio::test]
+async fn page_fetches_go_through_the_image_proxy() {
+    let proxy = TcpListener::bind("127.0.0.1:0").await.expect("bind proxy");
+    let proxy_url = format!("http://{}", proxy.local_addr().expect("proxy address"));
+
+    let served = tokio::spawn(async move {
+        let (mut conn, _) = proxy.accept().await.expect("request reaches the proxy");
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+            let read = conn.read(&mut buf).await.expect("read request");
+            assert!(read > 0, "request ended early");
+            request.extend_from_slice(&buf[..read]);
+        }
+        conn.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\nconnection: close\r\n\r\npage")
+            .await
+            .expect("write response");
+        String::from_utf8_lossy(&request).into_owned()
+    });
+
+    let bytes = logic::fetch_image_bytes_via(PAGE, &SuwayomiAuth::default(), Some(&proxy_url))
+        .await
+        .expect("fetched through the proxy");
+    assert_eq!(bytes, b"page");
+
+    // The page's own host, not the local Suwayomi
+    let request = served.await.expect("proxy task");
+    assert!(request.starts_with(&format!("GET {PAGE} ")), "{request}");
+}
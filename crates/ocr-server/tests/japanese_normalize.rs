@@ -0,0 +1,34 @@
+use manatan_ocr_server::normalize::normalize_japanese;
+
+#[test]
+fn long_vowel_lookalikes_after_katakana_become_choonpu() {
+    assert_eq!(normalize_japanese("ラ一メン"), "ラーメン");
+    assert_eq!(normalize_japanese("ス|パ一"), "スーパー");
+    assert_eq!(normalize_japanese("一人"), "一人");
+}
+
+#[test]
+fn kanji_ichi_after_hiragana_is_kept() {
+    assert_eq!(normalize_japanese("もう一度"), "もう一度");
+    assert_eq!(normalize_japanese("この一つ"), "この一つ");
+    assert_eq!(normalize_japanese("ただ一人"), "ただ一人");
+}
+
+#[test]
+fn lookalikes_before_kanji_or_numerals_are_kept() {
+    assert_eq!(normalize_japanese("テスト一回"), "テスト一回");
+    assert_eq!(normalize_japanese("レベル-1"), "レベル-1");
+}
+
+#[test]
+fn kanji_lookalikes_next_to_katakana_become_katakana() {
+    assert_eq!(normalize_japanese("ク口ス"), "クロス");
+    assert_eq!(normalize_japanese("人口"), "人口");
+}
+
+#[test]
+fn nfkc_and_stray_latin() {
+    assert_eq!(normalize_japanese("ｶﾀｶﾅ１２３"), "カタカナ123");
+    assert_eq!(normalize_japanese("あlい"), "あい");
+    assert_eq!(normalize_japanese("OKです"), "OKです");
+}
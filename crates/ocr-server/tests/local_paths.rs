@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use manatan_ocr_server::local;
+
+#[test]
+fn resolve_rejects_paths_outside_the_directory() {
+    let root = std::env::temp_dir();
+    assert!(local::resolve(&root, "../etc/passwd").is_err());
+    assert!(local::resolve(&root, "/etc/passwd").is_err());
+    assert!(local::resolve(&root, "a/./../b.png").is_err());
+}
+
+#[test]
+fn local_urls_use_forward_slashes() {
+    assert_eq!(
+        local::local_url(&Path::new("series").join("ch1").join("001.png")),
+        "local/series/ch1/001.png"
+    );
+}
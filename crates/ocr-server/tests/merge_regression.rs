@@ -1,6 +1,8 @@
 use std::{fs, path::PathBuf};
 
 use manatan_ocr_server::{
+    backend::OcrBackend,
+    language::OcrLanguage,
     logic::{self, RawChunk},
     merge::{self, MergeConfig},
 };
@@ -111,9 +113,15 @@ async fn run_merge_regression_tests() {
                 } else {
                     println!("  [OCR] Running Lens OCR for {}...", test_name);
                     let image_bytes = fs::read(path).expect("Read image");
-                    let chunks = logic::get_raw_ocr_data(&image_bytes, None, None)
-                        .await
-                        .expect("Lens OCR failed");
+                    let chunks = logic::get_raw_ocr_data(
+                        &image_bytes,
+                        None,
+                        None,
+                        OcrLanguage::Japanese,
+                        OcrBackend::Lens,
+                    )
+                    .await
+                    .expect("Lens OCR failed");
 
                     let json = serde_json::to_string_pretty(&chunks).unwrap();
                     fs::write(&raw_cache_path, json).expect("Write raw cache");
@@ -129,9 +137,9 @@ async fn run_merge_regression_tests() {
                         merge::auto_merge(chunk.lines, chunk.width, chunk.height, &config);
 
                     for mut result in merged_lines {
+                        let global_pixel_x = result.tight_bounding_box.x + (chunk.global_x as f64);
                         let global_pixel_y = result.tight_bounding_box.y + (chunk.global_y as f64);
-                        result.tight_bounding_box.x =
-                            result.tight_bounding_box.x / chunk.full_width as f64;
+                        result.tight_bounding_box.x = global_pixel_x / chunk.full_width as f64;
                         result.tight_bounding_box.width =
                             result.tight_bounding_box.width / chunk.full_width as f64;
                         result.tight_bounding_box.y = global_pixel_y / chunk.full_height as f64;
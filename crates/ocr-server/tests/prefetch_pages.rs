@@ -0,0 +1,44 @@
+use manatan_ocr_server::prefetch;
+
+const PAGE: &str = "http://127.0.0.1:4567/api/v1/manga/12/chapter/3/page/";
+
+#[test]
+fn predicts_the_following_pages() {
+    assert_eq!(
+        prefetch::following_pages(&format!("{PAGE}4"), 20, 2),
+        vec![format!("{PAGE}5"), format!("{PAGE}6")]
+    );
+}
+
+#[test]
+fn stops_at_the_end_of_the_chapter() {
+    assert_eq!(
+        prefetch::following_pages(&format!("{PAGE}8"), 10, 5),
+        vec![format!("{PAGE}9")]
+    );
+    assert!(prefetch::following_pages(&format!("{PAGE}9"), 10, 5).is_empty());
+    assert!(prefetch::following_pages(&format!("{PAGE}0"), 0, 5).is_empty());
+}
+
+#[test]
+fn keeps_the_query_string() {
+    assert_eq!(
+        prefetch::following_pages(&format!("{PAGE}0?sourceId=7"), 3, 1),
+        vec![format!("{PAGE}1?sourceId=7")]
+    );
+}
+
+#[test]
+fn ignores_urls_without_a_page_index() {
+    assert!(prefetch::following_pages("http://example.com/scan/cover.png", 10, 2).is_empty());
+    assert!(prefetch::following_pages("http://example.com/chapter/3/4", 10, 2).is_empty());
+}
+
+#[test]
+fn caps_the_read_ahead() {
+    assert_eq!(prefetch::pages_ahead(Some(3)), 3);
+    assert_eq!(
+        prefetch::pages_ahead(Some(1000)),
+        prefetch::MAX_PREFETCH_PAGES
+    );
+}
@@ -0,0 +1,24 @@
+use manatan_ocr_server::proxy::ProxyMode;
+
+#[test]
+fn proxy_mode_parses_keywords_and_urls() {
+    assert_eq!(ProxyMode::from("none".to_string()), ProxyMode::Disabled);
+    assert_eq!(
+        ProxyMode::from(" Suwayomi ".to_string()),
+        ProxyMode::Suwayomi
+    );
+    assert_eq!(
+        ProxyMode::from("socks5://127.0.0.1:1080".to_string()),
+        ProxyMode::Url("socks5://127.0.0.1:1080".to_string())
+    );
+}
+
+#[test]
+fn proxy_mode_round_trips_through_json() {
+    let mode: ProxyMode = serde_json::from_str("\"off\"").expect("valid proxy mode");
+    assert_eq!(mode, ProxyMode::Disabled);
+    assert_eq!(
+        serde_json::to_string(&mode).expect("serializes"),
+        "\"none\""
+    );
+}
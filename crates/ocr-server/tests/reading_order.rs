@@ -0,0 +1,41 @@
+use manatan_ocr_server::{language::OcrLanguage, logic::OcrResult, merge};
+
+fn result(text: &str, x: f64, y: f64, width: f64, height: f64) -> OcrResult {
+    serde_json::from_value(serde_json::json!({
+        "text": text,
+        "tightBoundingBox": { "x": x, "y": y, "width": width, "height": height },
+    }))
+    .expect("valid OcrResult")
+}
+
+fn texts(results: &[OcrResult]) -> Vec<&str> {
+    results.iter().map(|result| result.text.as_str()).collect()
+}
+
+#[test]
+fn japanese_pages_read_right_to_left_then_top_to_bottom() {
+    let results = vec![
+        result("bottom", 0.4, 0.7, 0.1, 0.2),
+        result("top-left", 0.1, 0.1, 0.1, 0.3),
+        result("top-right", 0.7, 0.12, 0.1, 0.3),
+    ];
+
+    let sorted = merge::sort_reading_order(results, OcrLanguage::Japanese);
+
+    assert_eq!(texts(&sorted), ["top-right", "top-left", "bottom"]);
+    let orders: Vec<Option<u32>> = sorted.iter().map(|result| result.order).collect();
+    assert_eq!(orders, [Some(0), Some(1), Some(2)]);
+}
+
+#[test]
+fn horizontal_languages_read_left_to_right() {
+    let results = vec![
+        result("right", 0.6, 0.1, 0.3, 0.05),
+        result("left", 0.1, 0.11, 0.3, 0.05),
+        result("next row", 0.1, 0.3, 0.3, 0.05),
+    ];
+
+    let sorted = merge::sort_reading_order(results, OcrLanguage::English);
+
+    assert_eq!(texts(&sorted), ["left", "right", "next row"]);
+}
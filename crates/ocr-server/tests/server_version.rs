@@ -0,0 +1,14 @@
+use manatan_ocr_server::logic::parse_server_version;
+
+#[test]
+fn parses_release_and_preview_versions() {
+    assert_eq!(parse_server_version("v1.0.0"), Some((1, 0, 0)));
+    assert_eq!(parse_server_version("v2.1.1867-r1867"), Some((2, 1, 1867)));
+    assert_eq!(parse_server_version("0.7"), Some((0, 7, 0)));
+}
+
+#[test]
+fn rejects_unparseable_versions() {
+    assert_eq!(parse_server_version("preview"), None);
+    assert_eq!(parse_server_version(""), None);
+}
@@ -0,0 +1,43 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use manatan_ocr_server::single_flight::SingleFlight;
+
+#[tokio::test]
+async fn concurrent_calls_for_one_key_share_a_run() {
+    let flights = SingleFlight::<usize>::default();
+    let runs = Arc::new(AtomicUsize::new(0));
+    let work = || {
+        let runs = runs.clone();
+        async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            runs.fetch_add(1, Ordering::SeqCst) + 1
+        }
+    };
+
+    let ((first, first_joined), (second, second_joined)) =
+        tokio::join!(flights.run("page", work), flights.run("page", work));
+
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+    assert_eq!((first, second), (1, 1));
+    assert!(!first_joined);
+    assert!(second_joined);
+    assert!(flights.is_empty());
+}
+
+#[tokio::test]
+async fn finished_keys_run_again() {
+    let flights = SingleFlight::<&str>::default();
+
+    let (_, joined) = flights.run("page", || async { "first" }).await;
+    assert!(!joined);
+    let (output, joined) = flights.run("page", || async { "second" }).await;
+
+    assert_eq!(output, "second");
+    assert!(!joined);
+}
@@ -0,0 +1,75 @@
+use manatan_ocr_server::{auth::SuwayomiAuth, jobs::ChapterJob};
+use reqwest::header::AUTHORIZATION;
+
+fn authorization_header(auth: &SuwayomiAuth) -> Option<String> {
+    let request = auth
+        .apply(reqwest::Client::new().get("http://127.0.0.1:4568/api/v1/settings"))
+        .build()
+        .expect("valid request");
+    request
+        .headers()
+        .get(AUTHORIZATION)
+        .map(|value| value.to_str().expect("ascii header").to_string())
+}
+
+#[test]
+fn forwards_the_authorization_header() {
+    let auth = SuwayomiAuth::new(None, None, Some(" Bearer abc123 ".to_string()));
+
+    assert_eq!(
+        authorization_header(&auth).as_deref(),
+        Some("Bearer abc123")
+    );
+}
+
+#[test]
+fn prefers_the_header_over_basic_auth() {
+    let auth = SuwayomiAuth::new(
+        Some("reader".to_string()),
+        Some("secret".to_string()),
+        Some("Bearer abc123".to_string()),
+    );
+
+    assert_eq!(
+        authorization_header(&auth).as_deref(),
+        Some("Bearer abc123")
+    );
+}
+
+#[test]
+fn falls_back_to_basic_auth() {
+    let auth = SuwayomiAuth::new(Some("reader".to_string()), Some("secret".to_string()), None);
+
+    assert_eq!(
+        authorization_header(&auth).as_deref(),
+        Some("Basic cmVhZGVyOnNlY3JldA==")
+    );
+    assert_eq!(authorization_header(&SuwayomiAuth::default()), None);
+}
+
+#[test]
+fn persisted_jobs_leave_out_the_credentials() {
+    let job = ChapterJob {
+        base_url: "http://127.0.0.1:4568/api/v1/manga/1/chapter/1".to_string(),
+        pages: Vec::new(),
+        auth: SuwayomiAuth::new(
+            Some("reader".to_string()),
+            Some("secret".to_string()),
+            Some("Bearer abc123".to_string()),
+        ),
+        context: "Test".to_string(),
+        add_space_on_merge: None,
+        language: Default::default(),
+        backend: Default::default(),
+        proxy: None,
+        retry: Default::default(),
+        merge: Default::default(),
+        request_id: None,
+    };
+
+    let stored = serde_json::to_string(&job).expect("job serializes");
+    assert!(!stored.contains("secret") && !stored.contains("abc123"));
+
+    let resumed: ChapterJob = serde_json::from_str(&stored).expect("job deserializes");
+    assert_eq!(resumed.auth, SuwayomiAuth::default());
+}
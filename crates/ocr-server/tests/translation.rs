@@ -0,0 +1,53 @@
+use manatan_ocr_server::{
+    language::OcrLanguage,
+    logic::OcrResult,
+    translate::{self, TranslationProvider, Translator},
+};
+
+fn translator(target: &str) -> Translator {
+    Translator::new(
+        TranslationProvider::LibreTranslate,
+        None,
+        Some("http://127.0.0.1:1".to_string()),
+        target.to_string(),
+    )
+}
+
+#[test]
+fn parses_provider_names() {
+    assert_eq!(
+        TranslationProvider::parse(" DeepL "),
+        Some(TranslationProvider::DeepL)
+    );
+    assert_eq!(
+        TranslationProvider::parse("libretranslate"),
+        Some(TranslationProvider::LibreTranslate)
+    );
+    assert_eq!(TranslationProvider::parse("bing"), None);
+}
+
+#[test]
+fn skips_pages_already_in_the_target_language() {
+    assert_eq!(translate::language_code(OcrLanguage::Japanese), "ja");
+    assert!(translator("en").translates_from(OcrLanguage::Japanese));
+    assert!(!translator("EN-US").translates_from(OcrLanguage::English));
+}
+
+#[tokio::test]
+async fn existing_translations_are_not_requested_again() {
+    let mut results: Vec<OcrResult> = serde_json::from_value(serde_json::json!([{
+        "text": "こんにちは",
+        "tightBoundingBox": { "x": 0.1, "y": 0.1, "width": 0.1, "height": 0.1 },
+        "translation": "Hello",
+    }]))
+    .expect("valid OcrResult");
+
+    // The translator points at a closed port, so any request would fail.
+    let changed =
+        translate::translate_results(&translator("en"), &mut results, OcrLanguage::Japanese)
+            .await
+            .expect("no request needed");
+
+    assert!(!changed);
+    assert_eq!(results[0].translation.as_deref(), Some("Hello"));
+}
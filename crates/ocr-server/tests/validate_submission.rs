@@ -1,6 +1,10 @@
 use std::{collections::HashMap, fs, path::Path};
 
-use manatan_ocr_server::logic::{self, RawChunk};
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    logic::{self, RawChunk},
+};
 use serde_json::Value;
 use walkdir::WalkDir;
 
@@ -98,9 +102,15 @@ async fn validate_expected_is_subset_of_raw() {
                 } else {
                     println!("   -> Generating raw data from image...");
                     let image_bytes = fs::read(path).expect("Failed to read image");
-                    logic::get_raw_ocr_data(&image_bytes, None, None)
-                        .await
-                        .expect("Failed to perform OCR extraction")
+                    logic::get_raw_ocr_data(
+                        &image_bytes,
+                        None,
+                        None,
+                        OcrLanguage::Japanese,
+                        OcrBackend::Lens,
+                    )
+                    .await
+                    .expect("Failed to perform OCR extraction")
                 };
 
                 // 2. Extract Raw Text
@@ -0,0 +1,80 @@
+use crate::compression;
+use crate::error::SyncError;
+use crate::types::{SyncBackendType, SyncPayload};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{ErrorKind, Write};
+
+// ============================================================================
+// Sync Archive
+// ============================================================================
+//
+// A standalone copy of the sync data for offline backups and for moving between
+// backends: gzipped JSON holding the payload and where it came from. Credentials
+// and other settings are never included.
+
+pub const ARCHIVE_FORMAT: &str = "manatan-sync-archive";
+pub const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncArchive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: i64,
+    /// Device that made the export
+    pub device_id: String,
+    /// Backend the payload was read from
+    pub backend: SyncBackendType,
+    /// Etag of the payload on that backend, empty if it was only kept locally
+    #[serde(default)]
+    pub etag: String,
+    pub payload: SyncPayload,
+}
+
+impl SyncArchive {
+    pub fn new(
+        payload: SyncPayload,
+        device_id: String,
+        backend: SyncBackendType,
+        etag: String,
+    ) -> Self {
+        Self {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: chrono::Utc::now().timestamp_millis(),
+            device_id,
+            backend,
+            etag,
+            payload,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SyncError> {
+        let json_bytes = serde_json::to_vec(self)?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json_bytes)?;
+        Ok(encoder.finish()?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SyncError> {
+        let json_bytes =
+            compression::read_capped(GzDecoder::new(bytes)).map_err(|e| match e.kind() {
+                ErrorKind::FileTooLarge => SyncError::BadRequest(format!("Archive is {e}")),
+                _ => SyncError::BadRequest("Not a sync archive".to_string()),
+            })?;
+        let archive: Self = serde_json::from_slice(&json_bytes)?;
+        if archive.format != ARCHIVE_FORMAT {
+            return Err(SyncError::BadRequest("Not a sync archive".to_string()));
+        }
+        if archive.version > ARCHIVE_VERSION {
+            return Err(SyncError::BadRequest(format!(
+                "Archive version {} needs a newer Manatan",
+                archive.version
+            )));
+        }
+        Ok(archive)
+    }
+}
@@ -1,15 +1,19 @@
-use crate::backend::{AuthFlow, PushResult, SyncBackend};
+use crate::backend::{
+    AuthFlow, DeviceAuthFlow, DeviceAuthStatus, PushResult, RemoteMetadata, SyncBackend,
+};
+use crate::compression;
 use crate::error::SyncError;
-use crate::state::SyncState;
+use crate::proto;
+use crate::state::{DownloadState, SyncState};
 use crate::types::SyncPayload;
 use async_trait::async_trait;
 use base64::Engine as _;
-use flate2::read::GzDecoder;
-use flate2::write::GzEncoder;
-use flate2::Compression;
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
-use std::io::{Read, Write};
+use std::future::Future;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 
 // Re-exports from google-drive3
@@ -27,14 +31,46 @@ use google_drive3::DriveHub;
 const EMBEDDED_GDRIVE_CLIENT_ID: &str =
     "547124386971-e2bhbiav8rq299irqim61io2o02iucct.apps.googleusercontent.com";
 const GDRIVE_CLIENT_ID_ENV: &str = "MANATAN_GDRIVE_CLIENT_ID";
+const GDRIVE_CLIENT_SECRET_ENV: &str = "MANATAN_GDRIVE_CLIENT_SECRET";
+// The device flow needs a "TVs and Limited Input devices" client, which has a secret
+const GDRIVE_DEVICE_CLIENT_ID_ENV: &str = "MANATAN_GDRIVE_DEVICE_CLIENT_ID";
+const GDRIVE_DEVICE_CLIENT_SECRET_ENV: &str = "MANATAN_GDRIVE_DEVICE_CLIENT_SECRET";
 
 #[derive(Debug, Clone)]
 struct InstalledCredentials {
     client_id: String,
+    /// Secret of a configured `client_id`, only sent to Google directly
+    client_secret: Option<String>,
+    /// Whether `client_id` was configured rather than embedded. The embedded client only
+    /// works through the broker, which holds its secret
+    client_configured: bool,
+    device_client_id: String,
+    device_client_secret: Option<String>,
+}
+
+impl InstalledCredentials {
+    /// `params` for a token request straight to Google, or `None` for the embedded client
+    fn direct_params(&self, params: &[(String, String)]) -> Option<Vec<(String, String)>> {
+        if !self.client_configured {
+            return None;
+        }
+        let mut params = params.to_vec();
+        if let Some(secret) = &self.client_secret {
+            params.push(("client_secret".to_string(), secret.clone()));
+        }
+        Some(params)
+    }
+}
+
+fn env_value(name: &str) -> Option<String> {
+    manatan_config::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
 }
 
 fn load_credentials() -> InstalledCredentials {
-    let client_id = std::env::var(GDRIVE_CLIENT_ID_ENV)
+    let configured_client_id = manatan_config::var(GDRIVE_CLIENT_ID_ENV)
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty())
@@ -42,10 +78,17 @@ fn load_credentials() -> InstalledCredentials {
             option_env!("MANATAN_GDRIVE_CLIENT_ID_COMPILED")
                 .map(|value| value.trim().to_string())
                 .filter(|value| !value.is_empty())
-        })
-        .unwrap_or_else(|| EMBEDDED_GDRIVE_CLIENT_ID.to_string());
+        });
+    let client_configured = configured_client_id.is_some();
+    let client_id =
+        configured_client_id.unwrap_or_else(|| EMBEDDED_GDRIVE_CLIENT_ID.to_string());
 
     InstalledCredentials {
+        device_client_id: env_value(GDRIVE_DEVICE_CLIENT_ID_ENV)
+            .unwrap_or_else(|| client_id.clone()),
+        device_client_secret: env_value(GDRIVE_DEVICE_CLIENT_SECRET_ENV),
+        client_secret: env_value(GDRIVE_CLIENT_SECRET_ENV),
+        client_configured,
         client_id,
     }
 }
@@ -62,14 +105,31 @@ const SCOPES: &[&str] = &[
 
 const GOOGLE_OAUTH_AUTH_ENDPOINT: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const GOOGLE_OAUTH_TOKEN_ENDPOINT: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_OAUTH_DEVICE_ENDPOINT: &str = "https://oauth2.googleapis.com/device/code";
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+/// Poll interval when Google does not send one
+const DEFAULT_DEVICE_POLL_INTERVAL_SECS: u64 = 5;
+/// Access tokens are refreshed once they are this close to expiring
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
 const DEFAULT_GOOGLE_OAUTH_BROKER_ENDPOINT: &str = "https://manatan.com/auth/google";
 const GOOGLE_OAUTH_BROKER_ENDPOINT_ENV: &str = "MANATAN_GOOGLE_OAUTH_BROKER_ENDPOINT";
 const GOOGLE_OAUTH_BROKER_TOKEN_ENV: &str = "MANATAN_GOOGLE_OAUTH_BROKER_TOKEN";
+/// Set to `0` when the client id only works through the broker
+const GOOGLE_OAUTH_BROKER_FALLBACK_ENV: &str = "MANATAN_GOOGLE_OAUTH_BROKER_FALLBACK";
 const SYNC_FILE_NAME: &str = "manatan_sync.proto.gz";
 const FOLDER_MIME_TYPE: &str = "application/vnd.google-apps.folder";
+/// appProperties key holding the SHA-256 of the uncompressed payload
+const CHECKSUM_PROPERTY: &str = "payloadSha256";
+const DEVICE_ID_PROPERTY: &str = "deviceId";
+const SCHEMA_VERSION_PROPERTY: &str = "schemaVersion";
+/// Local copy of the sync file, prefixed with its etag and a newline
+const CACHE_FILE_NAME: &str = "drive-cache.bin";
+const DRIVE_FILES_ENDPOINT: &str = "https://www.googleapis.com/drive/v3/files";
+/// Bytes requested per ranged download request
+const DOWNLOAD_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
 
 fn oauth_token_endpoint() -> String {
-    if let Some(configured_endpoint) = std::env::var(GOOGLE_OAUTH_BROKER_ENDPOINT_ENV)
+    if let Some(configured_endpoint) = manatan_config::var(GOOGLE_OAUTH_BROKER_ENDPOINT_ENV)
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty())
@@ -91,8 +151,105 @@ fn oauth_token_endpoint() -> String {
     }
 }
 
+/// How the last token request reached Google
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenPath {
+    Broker,
+    Direct,
+    /// The broker was down, so Google's endpoint was used directly
+    DirectFallback,
+}
+
+fn broker_fallback_enabled() -> bool {
+    env_value(GOOGLE_OAUTH_BROKER_FALLBACK_ENV).is_none_or(|value| value != "0")
+}
+
+/// Posts a token request to the token endpoint. When the broker is unreachable or
+/// fails with a 5xx, the request goes to Google directly with `direct_params` instead,
+/// if the client can be used without the broker
+async fn send_token_request(
+    state: &SyncState,
+    params: &[(String, String)],
+    direct_params: Option<Vec<(String, String)>>,
+) -> Result<reqwest::Response, SyncError> {
+    let client = reqwest::Client::new();
+    let endpoint = oauth_token_endpoint();
+    let record = |path: TokenPath| state.set_token_path(path);
+
+    if endpoint == GOOGLE_OAUTH_TOKEN_ENDPOINT {
+        let response = client
+            .post(&endpoint)
+            .form(direct_params.as_deref().unwrap_or(params))
+            .send()
+            .await
+            .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+        record(TokenPath::Direct);
+        return Ok(response);
+    }
+
+    let mut request = client.post(&endpoint).form(params);
+    match oauth_broker_token() {
+        Some(broker_token) => {
+            request = request.bearer_auth(broker_token);
+        }
+        None => {
+            warn!("Google OAuth broker endpoint selected but no broker token is available");
+        }
+    }
+    let broker_error = match request.send().await {
+        Ok(response) if !response.status().is_server_error() => {
+            record(TokenPath::Broker);
+            return Ok(response);
+        }
+        Ok(response) => format!("broker returned {}", response.status()),
+        Err(e) => format!("broker unreachable: {e}"),
+    };
+    let Some(direct_params) = direct_params.filter(|_| broker_fallback_enabled()) else {
+        return Err(SyncError::OAuthError(format!(
+            "Token request failed: {broker_error}"
+        )));
+    };
+
+    warn!(
+        "[AUTH] OAuth {}, using Google's token endpoint directly",
+        broker_error
+    );
+    let response = client
+        .post(GOOGLE_OAUTH_TOKEN_ENDPOINT)
+        .form(&direct_params)
+        .send()
+        .await
+        .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+    record(TokenPath::DirectFallback);
+    Ok(response)
+}
+
+/// Saves a new access token and when it expires, if Google said
+fn store_access_token(
+    state: &SyncState,
+    token: &str,
+    expires_in: Option<u64>,
+) -> Result<(), SyncError> {
+    state.set_access_token(token)?;
+    if let Some(expires_in) = expires_in {
+        let expires_at = chrono::Utc::now().timestamp_millis() + (expires_in * 1000) as i64;
+        state.set_token_expires_at(expires_at)?;
+    }
+    Ok(())
+}
+
+/// Whether the stored access token is expired, close to expiring, or of unknown age
+pub fn token_needs_refresh(state: &SyncState) -> bool {
+    let Some(expires_at) = state.get_token_expires_at() else {
+        return true;
+    };
+    let margin = TOKEN_REFRESH_MARGIN.as_millis() as i64;
+    chrono::Utc::now().timestamp_millis() + margin >= expires_at
+}
+
 fn oauth_broker_token() -> Option<String> {
-    let runtime = std::env::var(GOOGLE_OAUTH_BROKER_TOKEN_ENV)
+    let runtime = manatan_config::var(GOOGLE_OAUTH_BROKER_TOKEN_ENV)
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty());
@@ -109,10 +266,179 @@ fn oauth_broker_token() -> Option<String> {
 
 type HyperConnector = HttpsConnector<HttpConnector>;
 
+// ============================================================================
+// Retry Policy
+// ============================================================================
+
+const DRIVE_MAX_ATTEMPTS: u32 = 5;
+const DRIVE_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const DRIVE_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Upper bound on a server-requested `Retry-After`
+const DRIVE_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+/// How long Drive calls are held off once retries could not get past a rate limit
+const DRIVE_COOL_DOWN: Duration = Duration::from_secs(60);
+
+/// Until when Drive calls fail fast after hitting a rate limit. Limits apply to the
+/// account, so this is shared by every backend instance.
+static THROTTLED_UNTIL: Mutex<Option<Instant>> = Mutex::new(None);
+
+enum DriveFailure {
+    /// Network errors, 5xx and rate limits; `retry_after` is the delay the server asked for
+    Transient {
+        retry_after: Option<Duration>,
+        rate_limited: bool,
+    },
+    Permanent,
+}
+
+/// Time left in the rate limit cool-down
+pub fn throttled_for() -> Option<Duration> {
+    let until = (*THROTTLED_UNTIL.lock().expect("lock poisoned"))?;
+    until.checked_duration_since(Instant::now())
+}
+
+fn start_cool_down(duration: Duration) -> SyncError {
+    let until = Instant::now() + duration;
+    let mut throttled = THROTTLED_UNTIL.lock().expect("lock poisoned");
+    if throttled.is_none_or(|current| current < until) {
+        *throttled = Some(until);
+    }
+    SyncError::Throttled {
+        retry_after_secs: duration.as_secs().max(1),
+    }
+}
+
+fn classify_drive_error(err: &google_drive3::Error) -> DriveFailure {
+    match err {
+        google_drive3::Error::HttpError(_) | google_drive3::Error::Io(_) => {
+            DriveFailure::Transient {
+                retry_after: None,
+                rate_limited: false,
+            }
+        }
+        google_drive3::Error::Failure(response) => {
+            let status = response.status();
+            if status.is_server_error() || status.as_u16() == 429 {
+                let retry_after = response
+                    .headers()
+                    .get(google_drive3::hyper::header::RETRY_AFTER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs);
+                DriveFailure::Transient {
+                    retry_after,
+                    rate_limited: status.as_u16() == 429,
+                }
+            } else {
+                DriveFailure::Permanent
+            }
+        }
+        google_drive3::Error::BadRequest(value) => {
+            let error = &value["error"];
+            let code = error["code"].as_u64().unwrap_or_default();
+            let rate_limited = error["errors"].as_array().is_some_and(|errors| {
+                errors.iter().any(|e| {
+                    matches!(
+                        e["reason"].as_str(),
+                        Some("rateLimitExceeded" | "userRateLimitExceeded")
+                    )
+                })
+            });
+            if code >= 500 || code == 429 || (code == 403 && rate_limited) {
+                DriveFailure::Transient {
+                    retry_after: None,
+                    rate_limited: code == 429 || rate_limited,
+                }
+            } else {
+                DriveFailure::Permanent
+            }
+        }
+        _ => DriveFailure::Permanent,
+    }
+}
+
+/// Runs a Drive call, retrying transient failures with exponential backoff. A rate
+/// limit that outlasts the retries starts a cool-down during which calls fail fast
+/// with `Throttled`.
+async fn with_drive_retry<T, F, Fut>(operation: &str, mut call: F) -> Result<T, SyncError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, google_drive3::Error>>,
+{
+    if let Some(remaining) = throttled_for() {
+        return Err(SyncError::Throttled {
+            retry_after_secs: remaining.as_secs().max(1),
+        });
+    }
+
+    let mut backoff = DRIVE_INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let err = match call().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+
+        let (retry_after, rate_limited) = match classify_drive_error(&err) {
+            DriveFailure::Transient {
+                retry_after,
+                rate_limited,
+            } => (retry_after, rate_limited),
+            DriveFailure::Permanent => return Err(SyncError::DriveError(err.to_string())),
+        };
+        if rate_limited
+            && (attempt >= DRIVE_MAX_ATTEMPTS
+                || retry_after.is_some_and(|delay| delay > DRIVE_MAX_RETRY_AFTER))
+        {
+            let cool_down = retry_after.unwrap_or(DRIVE_COOL_DOWN).max(DRIVE_COOL_DOWN);
+            warn!(
+                "[DRIVE] {} rate limited, holding off Drive calls for {:?}",
+                operation, cool_down
+            );
+            return Err(start_cool_down(cool_down));
+        }
+        if attempt >= DRIVE_MAX_ATTEMPTS {
+            return Err(SyncError::DriveError(err.to_string()));
+        }
+        let delay = retry_after.map_or(backoff, |delay| delay.min(DRIVE_MAX_RETRY_AFTER));
+        warn!(
+            "[DRIVE] {} failed (attempt {}/{}), retrying in {:?}: {}",
+            operation, attempt, DRIVE_MAX_ATTEMPTS, delay, err
+        );
+        tokio::time::sleep(delay).await;
+
+        backoff = (backoff * 2).min(DRIVE_MAX_BACKOFF);
+        attempt += 1;
+    }
+}
+
 // ============================================================================
 // Google Drive Backend
 // ============================================================================
 
+/// Hex SHA-256 of an uncompressed payload
+fn payload_checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// The sync file as listed by Drive
+struct RemoteSyncFile {
+    id: String,
+    /// md5Checksum, used as the etag
+    etag: String,
+    checksum: Option<String>,
+    size: Option<u64>,
+    /// appProperties written by the device that pushed it
+    device_id: Option<String>,
+    schema_version: Option<u32>,
+    modified_at: Option<i64>,
+}
+
+enum RangeChunk {
+    Partial { bytes: Vec<u8>, total: u64 },
+    Whole(Vec<u8>),
+}
+
 pub struct GoogleDriveBackend {
     state: SyncState,
     credentials: InstalledCredentials,
@@ -159,30 +485,14 @@ impl GoogleDriveBackend {
             return Err(SyncError::NotAuthenticated);
         };
 
-        let client = reqwest::Client::new();
         let params = vec![
             ("refresh_token".to_string(), refresh_token),
             ("client_id".to_string(), self.credentials.client_id.clone()),
             ("grant_type".to_string(), "refresh_token".to_string()),
         ];
 
-        let endpoint = oauth_token_endpoint();
-        let mut request = client.post(&endpoint).form(&params);
-        if endpoint != GOOGLE_OAUTH_TOKEN_ENDPOINT {
-            match oauth_broker_token() {
-                Some(broker_token) => {
-                    request = request.bearer_auth(broker_token);
-                }
-                None => {
-                    warn!("Google OAuth broker endpoint selected but no broker token is available");
-                }
-            }
-        }
-
-        let response = request
-            .send()
-            .await
-            .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+        let direct_params = self.credentials.direct_params(&params);
+        let response = send_token_request(&self.state, &params, direct_params).await?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -192,6 +502,7 @@ impl GoogleDriveBackend {
         #[derive(Deserialize)]
         struct RefreshResponse {
             access_token: String,
+            expires_in: Option<u64>,
         }
 
         let refreshed: RefreshResponse = response
@@ -199,7 +510,7 @@ impl GoogleDriveBackend {
             .await
             .map_err(|e| SyncError::OAuthError(e.to_string()))?;
 
-        self.state.set_access_token(&refreshed.access_token)?;
+        store_access_token(&self.state, &refreshed.access_token, refreshed.expires_in)?;
         Ok(())
     }
 
@@ -208,17 +519,54 @@ impl GoogleDriveBackend {
     }
 
     async fn get_or_create_folder(&self) -> Result<String, SyncError> {
-        let hub = self.get_hub()?;
         let config = self.state.get_sync_config();
 
         if config.google_drive_folder_type == crate::types::GoogleDriveFolderType::AppData {
             return Ok("appDataFolder".to_string());
         }
 
-        let folder_name = config.google_drive_folder;
-        let query = format!("name = '{}' and mimeType = '{}' and trashed = false", folder_name, FOLDER_MIME_TYPE);
+        // "Backups/Manatan" is the Manatan folder inside the Backups folder
+        let mut parent: Option<String> = None;
+        for segment in config
+            .google_drive_folder
+            .split('/')
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+        {
+            let folder_id = self
+                .get_or_create_child_folder(segment, parent.as_deref())
+                .await?;
+            parent = Some(folder_id);
+        }
+        match parent {
+            Some(folder_id) => Ok(folder_id),
+            None => Err(SyncError::BadRequest(
+                "The Google Drive folder name is empty".to_string(),
+            )),
+        }
+    }
+
+    /// The folder called `folder_name` in `parent`, created if missing. Without a
+    /// parent any folder of that name matches, as top-level folders always did.
+    async fn get_or_create_child_folder(
+        &self,
+        folder_name: &str,
+        parent: Option<&str>,
+    ) -> Result<String, SyncError> {
+        let hub = self.get_hub()?;
+        let escaped_name = folder_name.replace('\\', "\\\\").replace('\'', "\\'");
+        let mut query = format!(
+            "name = '{}' and mimeType = '{}' and trashed = false",
+            escaped_name, FOLDER_MIME_TYPE
+        );
+        if let Some(parent) = parent {
+            query.push_str(&format!(" and '{parent}' in parents"));
+        }
 
-        let (_, file_list) = hub.files().list().q(&query).spaces("drive").doit().await.map_err(|e| SyncError::DriveError(e.to_string()))?;
+        let (_, file_list) = with_drive_retry("Folder lookup", || {
+            hub.files().list().q(&query).spaces("drive").doit()
+        })
+        .await?;
 
         if let Some(files) = file_list.files {
             if let Some(folder) = files.first() {
@@ -228,17 +576,27 @@ impl GoogleDriveBackend {
             }
         }
 
+        info!("[DRIVE] Creating folder: {}", folder_name);
         let folder = File {
-            name: Some(folder_name),
+            name: Some(folder_name.to_string()),
             mime_type: Some(FOLDER_MIME_TYPE.to_string()),
+            parents: parent.map(|parent| vec![parent.to_string()]),
             ..Default::default()
         };
 
-        let (_, created_file) = hub.files().create(folder).upload(std::io::Cursor::new(Vec::<u8>::new()), "application/vnd.google-apps.folder".parse().unwrap()).await.map_err(|e| SyncError::DriveError(e.to_string()))?;
+        let folder_mime: mime::Mime = FOLDER_MIME_TYPE
+            .parse()
+            .map_err(|_| SyncError::DriveError("Invalid folder MIME type".to_string()))?;
+        let (_, created_file) = with_drive_retry("Folder creation", || {
+            hub.files()
+                .create(folder.clone())
+                .upload(std::io::Cursor::new(Vec::<u8>::new()), folder_mime.clone())
+        })
+        .await?;
         created_file.id.ok_or_else(|| SyncError::DriveError("Failed to get folder ID".to_string()))
     }
 
-    async fn find_sync_file(&self, folder_id: &str) -> Result<Option<(String, String)>, SyncError> {
+    async fn find_sync_file(&self, folder_id: &str) -> Result<Option<RemoteSyncFile>, SyncError> {
         let hub = self.get_hub()?;
         let config = self.state.get_sync_config();
         let spaces = if config.google_drive_folder_type == crate::types::GoogleDriveFolderType::AppData { "appDataFolder" } else { "drive" };
@@ -248,69 +606,402 @@ impl GoogleDriveBackend {
             format!("name = '{}' and '{}' in parents and trashed = false", SYNC_FILE_NAME, folder_id)
         };
 
-        let (_, file_list) = hub.files().list().q(&query).spaces(spaces).param("fields", "files(id,name,md5Checksum,appProperties)").doit().await.map_err(|e| SyncError::DriveError(e.to_string()))?;
+        let (_, file_list) = with_drive_retry("Sync file lookup", || {
+            hub.files()
+                .list()
+                .q(&query)
+                .spaces(spaces)
+                .param(
+                    "fields",
+                    "files(id,name,md5Checksum,size,modifiedTime,appProperties)",
+                )
+                .doit()
+        })
+        .await?;
 
         if let Some(files) = file_list.files {
             if let Some(file) = files.first() {
-                return Ok(Some((file.id.clone().unwrap_or_default(), file.md5_checksum.clone().unwrap_or_default())));
+                let property = |key: &str| {
+                    file.app_properties
+                        .as_ref()
+                        .and_then(|properties| properties.get(key))
+                        .cloned()
+                };
+                return Ok(Some(RemoteSyncFile {
+                    id: file.id.clone().unwrap_or_default(),
+                    etag: file.md5_checksum.clone().unwrap_or_default(),
+                    checksum: property(CHECKSUM_PROPERTY),
+                    size: file.size.and_then(|size| u64::try_from(size).ok()),
+                    device_id: property(DEVICE_ID_PROPERTY),
+                    schema_version: property(SCHEMA_VERSION_PROPERTY)
+                        .and_then(|version| version.parse().ok()),
+                    modified_at: file.modified_time.map(|time| time.timestamp_millis()),
+                }));
             }
         }
         Ok(None)
     }
 
-    async fn download_file(&self, file_id: &str) -> Result<Vec<u8>, SyncError> {
+    fn cache_path(&self) -> std::path::PathBuf {
+        self.state.data_dir.join(CACHE_FILE_NAME)
+    }
+
+    /// The sync file as last downloaded or uploaded, if that was version `etag`
+    fn read_cache(&self, etag: &str) -> Option<Vec<u8>> {
+        let cached = std::fs::read(self.cache_path()).ok()?;
+        let split = cached.iter().position(|&b| b == b'\n')?;
+        (&cached[..split] == etag.as_bytes()).then(|| cached[split + 1..].to_vec())
+    }
+
+    /// Keeps a copy of the sync file so an unchanged one isn't downloaded again
+    fn write_cache(&self, etag: &str, bytes: &[u8]) {
+        let mut cached = Vec::with_capacity(etag.len() + 1 + bytes.len());
+        cached.extend_from_slice(etag.as_bytes());
+        cached.push(b'\n');
+        cached.extend_from_slice(bytes);
+        if let Err(e) = std::fs::write(self.cache_path(), cached) {
+            warn!("[DRIVE] Failed to cache the sync file: {}", e);
+        }
+    }
+
+    /// Fails with `InsufficientStorage` when the account has less than `needed` bytes
+    /// free. Accounts without a limit, or a failed quota lookup, never block a push.
+    async fn check_storage_quota(&self, needed: u64) -> Result<(), SyncError> {
+        if needed == 0 {
+            return Ok(());
+        }
         let hub = self.get_hub()?;
-        let (response, _) = hub.files().get(file_id).param("alt", "media").doit().await.map_err(|e| SyncError::DriveError(e.to_string()))?;
-        
-        use http_body_util::BodyExt;
-        let body_bytes = response.into_body().collect().await.map_err(|e| SyncError::DriveError(e.to_string()))?.to_bytes();
-        info!("Downloaded {} bytes from Google Drive", body_bytes.len());
-        Ok(body_bytes.to_vec())
+        let about = match with_drive_retry("Quota lookup", || {
+            hub.about().get().param("fields", "storageQuota").doit()
+        })
+        .await
+        {
+            Ok((_, about)) => about,
+            Err(e) => {
+                warn!("[DRIVE] Could not check the storage quota: {}", e);
+                return Ok(());
+            }
+        };
+
+        let Some(quota) = about.storage_quota else {
+            return Ok(());
+        };
+        let Some(limit) = quota.limit else {
+            return Ok(());
+        };
+        let available = u64::try_from(limit - quota.usage.unwrap_or_default()).unwrap_or(0);
+        if available < needed {
+            warn!(
+                "[DRIVE] Not enough storage: {} bytes needed, {} available",
+                needed, available
+            );
+            return Err(SyncError::InsufficientStorage { needed, available });
+        }
+        Ok(())
     }
 
-    async fn exchange_code_for_tokens(&self, code: &str, redirect_uri: &str, code_verifier: &str) -> Result<(String, String), SyncError> {
+    /// Downloads the sync file in ranged chunks. Received bytes are kept on disk, so a
+    /// download cut off mid-way resumes where it stopped instead of from zero.
+    async fn download_file(&self, file_id: &str, etag: &str) -> Result<Vec<u8>, SyncError> {
+        let part_path = self.state.data_dir.join(format!("download-{file_id}.part"));
+        let part_len = std::fs::metadata(&part_path).map(|m| m.len()).ok();
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut download = match self.state.get_download_state(file_id) {
+            // Only resume the same file version, with its received bytes intact
+            Some(download)
+                if download.etag == etag && part_len == Some(download.downloaded_bytes) =>
+            {
+                info!(
+                    "[DRIVE] Resuming download at {}/{} bytes",
+                    download.downloaded_bytes, download.total_size
+                );
+                download
+            }
+            _ => {
+                let _ = std::fs::remove_file(&part_path);
+                DownloadState {
+                    file_id: file_id.to_string(),
+                    etag: etag.to_string(),
+                    total_size: 0,
+                    downloaded_bytes: 0,
+                    started_at: now,
+                    last_chunk_at: now,
+                }
+            }
+        };
+
         let client = reqwest::Client::new();
-        let params = vec![
-            ("code".to_string(), code.to_string()),
-            ("client_id".to_string(), self.credentials.client_id.clone()),
-            ("redirect_uri".to_string(), redirect_uri.to_string()),
-            ("grant_type".to_string(), "authorization_code".to_string()),
-            ("code_verifier".to_string(), code_verifier.to_string()),
-        ];
+        let url = format!("{DRIVE_FILES_ENDPOINT}/{file_id}?alt=media");
+        let mut complete =
+            download.total_size > 0 && download.downloaded_bytes >= download.total_size;
+        while !complete {
+            let start = download.downloaded_bytes;
+            let end = start + DOWNLOAD_CHUNK_SIZE - 1;
+            let chunk = with_drive_retry("Download", || {
+                self.download_range(&client, &url, start, end)
+            })
+            .await?;
 
-        let endpoint = oauth_token_endpoint();
-        let mut request = client.post(&endpoint).form(&params);
-        if endpoint != GOOGLE_OAUTH_TOKEN_ENDPOINT {
-            match oauth_broker_token() {
-                Some(broker_token) => {
-                    request = request.bearer_auth(broker_token);
+            match chunk {
+                RangeChunk::Partial { bytes, total } => {
+                    if bytes.is_empty() && start < total {
+                        return Err(SyncError::DriveError(
+                            "Download stopped making progress".to_string(),
+                        ));
+                    }
+                    let mut part = std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&part_path)?;
+                    part.write_all(&bytes)?;
+                    download.downloaded_bytes += bytes.len() as u64;
+                    download.total_size = total;
                 }
-                None => {
-                    warn!("Google OAuth broker endpoint selected but no broker token is available");
+                RangeChunk::Whole(bytes) => {
+                    // The server ignored the range and sent the whole file
+                    std::fs::write(&part_path, &bytes)?;
+                    download.downloaded_bytes = bytes.len() as u64;
+                    download.total_size = bytes.len() as u64;
                 }
             }
+            download.last_chunk_at = chrono::Utc::now().timestamp_millis();
+            self.state.set_download_state(&download)?;
+            complete = download.downloaded_bytes >= download.total_size;
+            info!(
+                "[DRIVE] Downloaded {}/{} bytes",
+                download.downloaded_bytes, download.total_size
+            );
         }
 
-        let response = request
+        let body_bytes = std::fs::read(&part_path)?;
+        let _ = std::fs::remove_file(&part_path);
+        self.state.clear_download_state(file_id)?;
+        info!("Downloaded {} bytes from Google Drive", body_bytes.len());
+        Ok(body_bytes)
+    }
+
+    /// Requests bytes `start..=end` of a file. Errors are shaped like the Drive client's
+    /// so the retry policy classifies them the same way.
+    async fn download_range(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<RangeChunk, google_drive3::Error> {
+        let access_token = self.state.get_access_token().unwrap_or_default();
+        let response = client
+            .get(url)
+            .bearer_auth(access_token)
+            .header(reqwest::header::RANGE, format!("bytes={start}-{end}"))
             .send()
             .await
-            .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+            .map_err(|e| google_drive3::Error::Io(std::io::Error::other(e)))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // Nothing left past `start`
+            return Ok(RangeChunk::Partial {
+                bytes: Vec::new(),
+                total: start,
+            });
+        }
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(google_drive3::Error::BadRequest(serde_json::json!({
+                "error": { "code": status.as_u16(), "message": message }
+            })));
+        }
+
+        // Content-Range: bytes 0-4194303/12345678
+        let total = response
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit('/').next())
+            .and_then(|value| value.parse::<u64>().ok());
+        let partial = status == reqwest::StatusCode::PARTIAL_CONTENT;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| google_drive3::Error::Io(std::io::Error::other(e)))?
+            .to_vec();
+
+        Ok(match (partial, total) {
+            (true, Some(total)) => RangeChunk::Partial { bytes, total },
+            _ => RangeChunk::Whole(bytes),
+        })
+    }
+
+    async fn exchange_code_for_tokens(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<(String, String, Option<u64>), SyncError> {
+        let params = vec![
+            ("code".to_string(), code.to_string()),
+            ("client_id".to_string(), self.credentials.client_id.clone()),
+            ("redirect_uri".to_string(), redirect_uri.to_string()),
+            ("grant_type".to_string(), "authorization_code".to_string()),
+            ("code_verifier".to_string(), code_verifier.to_string()),
+        ];
+
+        let direct_params = self.credentials.direct_params(&params);
+        let response = send_token_request(&self.state, &params, direct_params).await?;
         if !response.status().is_success() {
             return Err(SyncError::OAuthError(format!("Token exchange failed: {}", response.text().await.unwrap_or_default())));
         }
 
         #[derive(Deserialize)]
-        struct TokenResponse { access_token: String, refresh_token: Option<String> }
-        let token_response: TokenResponse = response.json().await.map_err(|e| SyncError::OAuthError(e.to_string()))?;
-        let refresh_token = token_response.refresh_token.ok_or_else(|| SyncError::OAuthError("No refresh token".to_string()))?;
-        Ok((token_response.access_token, refresh_token))
+        struct TokenResponse {
+            access_token: String,
+            refresh_token: Option<String>,
+            expires_in: Option<u64>,
+        }
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+        let refresh_token = token_response
+            .refresh_token
+            .ok_or_else(|| SyncError::OAuthError("No refresh token".to_string()))?;
+        Ok((
+            token_response.access_token,
+            refresh_token,
+            token_response.expires_in,
+        ))
     }
 
+    /// Refreshes the access token unless it is known to stay valid for a while
     async fn do_refresh_token(&mut self) -> Result<(), SyncError> {
+        if self.hub.is_some() && !token_needs_refresh(&self.state) {
+            return Ok(());
+        }
         self.refresh_access_token().await?;
         self.setup_hub().await?;
         Ok(())
     }
+
+    /// Starts Google's device authorization flow, for clients that cannot receive a
+    /// redirect (TVs, headless servers). The user enters the code at the verification
+    /// URL on another device while the client calls `poll_auth_device`.
+    pub async fn start_auth_device(&self) -> Result<DeviceAuthFlow, SyncError> {
+        let scopes = SCOPES.join(" ");
+        let params = [
+            ("client_id", self.credentials.device_client_id.as_str()),
+            ("scope", scopes.as_str()),
+        ];
+
+        let response = reqwest::Client::new()
+            .post(GOOGLE_OAUTH_DEVICE_ENDPOINT)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(SyncError::OAuthError(format!(
+                "Device authorization failed: {error_text}"
+            )));
+        }
+
+        #[derive(Deserialize)]
+        struct DeviceCodeResponse {
+            device_code: String,
+            user_code: String,
+            #[serde(alias = "verification_uri")]
+            verification_url: String,
+            expires_in: u64,
+            interval: Option<u64>,
+        }
+
+        let device: DeviceCodeResponse = response
+            .json()
+            .await
+            .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+        self.state.set_auth_device_code(&device.device_code)?;
+        info!("[AUTH] Device authorization started: {}", device.user_code);
+
+        Ok(DeviceAuthFlow {
+            user_code: device.user_code,
+            verification_url: device.verification_url,
+            expires_in: device.expires_in,
+            interval: device.interval.unwrap_or(DEFAULT_DEVICE_POLL_INTERVAL_SECS),
+        })
+    }
+
+    /// Checks once whether the user approved the device authorization, and stores the
+    /// tokens when they have
+    pub async fn poll_auth_device(&mut self) -> Result<DeviceAuthStatus, SyncError> {
+        let device_code = self.state.get_auth_device_code().ok_or_else(|| {
+            SyncError::OAuthError("No device authorization in progress".to_string())
+        })?;
+
+        let client_id = self.credentials.device_client_id.clone();
+        let mut params = vec![
+            ("client_id".to_string(), client_id),
+            ("device_code".to_string(), device_code),
+            ("grant_type".to_string(), DEVICE_CODE_GRANT_TYPE.to_string()),
+        ];
+        if let Some(secret) = &self.credentials.device_client_secret {
+            params.push(("client_secret".to_string(), secret.clone()));
+        }
+
+        // Device clients always have a secret, so without one only the broker can help
+        let direct_params = self
+            .credentials
+            .device_client_secret
+            .is_some()
+            .then(|| params.clone());
+        let response = send_token_request(&self.state, &params, direct_params).await?;
+
+        if response.status().is_success() {
+            #[derive(Deserialize)]
+            struct TokenResponse {
+                access_token: String,
+                refresh_token: Option<String>,
+                expires_in: Option<u64>,
+            }
+            let tokens: TokenResponse = response
+                .json()
+                .await
+                .map_err(|e| SyncError::OAuthError(e.to_string()))?;
+            let refresh_token = tokens
+                .refresh_token
+                .ok_or_else(|| SyncError::OAuthError("No refresh token".to_string()))?;
+
+            store_access_token(&self.state, &tokens.access_token, tokens.expires_in)?;
+            self.state.set_refresh_token(&refresh_token)?;
+            self.state.clear_auth_device_code()?;
+            self.setup_hub().await?;
+            info!("Successfully authenticated with Google Drive (device flow)");
+            return Ok(DeviceAuthStatus::Complete);
+        }
+
+        #[derive(Deserialize)]
+        struct DeviceErrorResponse {
+            error: String,
+        }
+        let error_text = response.text().await.unwrap_or_default();
+        let error = serde_json::from_str::<DeviceErrorResponse>(&error_text)
+            .map(|response| response.error)
+            .unwrap_or_default();
+
+        match error.as_str() {
+            "authorization_pending" => Ok(DeviceAuthStatus::Pending { slow_down: false }),
+            "slow_down" => Ok(DeviceAuthStatus::Pending { slow_down: true }),
+            "access_denied" | "expired_token" => {
+                self.state.clear_auth_device_code()?;
+                Err(SyncError::OAuthError(format!(
+                    "Device authorization ended: {error}"
+                )))
+            }
+            _ => Err(SyncError::OAuthError(format!(
+                "Device token poll failed: {error_text}"
+            ))),
+        }
+    }
 }
 
 #[async_trait]
@@ -319,25 +1010,65 @@ impl SyncBackend for GoogleDriveBackend {
         let folder_id = self.get_or_create_folder().await?;
         info!("[DRIVE] Using folder: {}", folder_id);
 
-        let Some((file_id, etag)) = self.find_sync_file(&folder_id).await? else {
+        let Some(RemoteSyncFile {
+            id: file_id,
+            etag,
+            checksum,
+            ..
+        }) = self.find_sync_file(&folder_id).await?
+        else {
             info!("[DRIVE] No sync file found");
             return Ok(None);
         };
 
         info!("[DRIVE] Found sync file: {}, etag: {}", file_id, etag);
-        let body_bytes = self.download_file(&file_id).await?;
-        
-        let mut decoder = GzDecoder::new(&body_bytes[..]);
-        let mut decompressed = Vec::new();
-        match decoder.read_to_end(&mut decompressed) {
-            Ok(size) => info!("[DRIVE] Decompressed to: {} bytes", size),
+        let cached = match self.state.get_last_etag() {
+            Some(last_etag) if last_etag == etag => self.read_cache(&etag),
+            _ => None,
+        };
+        let body_bytes = match cached {
+            Some(bytes) => {
+                info!("[DRIVE] Sync file unchanged since the last sync, already up to date");
+                bytes
+            }
+            None => {
+                let bytes = self.download_file(&file_id, &etag).await?;
+                self.write_cache(&etag, &bytes);
+                bytes
+            }
+        };
+
+        let decompressed = match compression::decompress(&body_bytes) {
+            Ok(decompressed) => {
+                info!("[DRIVE] Decompressed to: {} bytes", decompressed.len());
+                self.state
+                    .stats
+                    .record_download(body_bytes.len(), decompressed.len());
+                decompressed
+            }
             Err(e) => {
                 error!("[DRIVE] Failed to decompress: {}", e);
-                return Err(SyncError::IoError(e));
+                return Err(SyncError::RemoteCorrupted(format!(
+                    "the sync file could not be decompressed: {e}"
+                )));
             }
         };
 
-        let payload: SyncPayload = serde_json::from_slice(&decompressed).map_err(SyncError::SerializationError)?;
+        // Files pushed before checksums were stored have none to verify
+        if let Some(expected) = checksum {
+            let actual = payload_checksum(&decompressed);
+            if actual != expected {
+                error!(
+                    "[DRIVE] Checksum mismatch: expected {}, got {}",
+                    expected, actual
+                );
+                return Err(SyncError::RemoteCorrupted(
+                    "the sync file does not match its checksum".to_string(),
+                ));
+            }
+        }
+
+        let payload = proto::decode_payload(&decompressed)?;
         Ok(Some((payload, etag)))
     }
 
@@ -346,35 +1077,71 @@ impl SyncBackend for GoogleDriveBackend {
         let existing_file = self.find_sync_file(&folder_id).await?;
         let config = self.state.get_sync_config();
 
-        let json_bytes = serde_json::to_vec(data).map_err(SyncError::SerializationError)?;
-        
-        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-        encoder.write_all(&json_bytes).map_err(SyncError::IoError)?;
-        let compressed = encoder.finish().map_err(SyncError::IoError)?;
-        
-        let reduction = if json_bytes.len() > 0 { 
-            (100.0 - (compressed.len() as f64 / json_bytes.len() as f64 * 100.0)) as i32 
-        } else { 0 };
-        info!("[DRIVE] Compressed: {} -> {} bytes ({}% reduction)", json_bytes.len(), compressed.len(), reduction);
+        let encoded = proto::encode_payload(data);
+
+        let compressed = compression::compress(&encoded, &config.compression, config.zstd_level)
+            .map_err(SyncError::IoError)?;
+
+        let reduction = if encoded.len() > 0 {
+            (100.0 - (compressed.len() as f64 / encoded.len() as f64 * 100.0)) as i32
+        } else {
+            0
+        };
+        info!(
+            "[DRIVE] Compressed ({:?}): {} -> {} bytes ({}% reduction)",
+            config.compression,
+            encoded.len(),
+            compressed.len(),
+            reduction
+        );
 
         let hub = self.get_hub()?;
-        let cursor = std::io::Cursor::new(compressed);
         let device_id = self.state.get_device_id();
-        let mime: mime::Mime = "application/gzip".parse().unwrap();
+        let mime: mime::Mime = compression::mime_type(&config.compression).parse().unwrap();
 
         let mut file_metadata = File::default();
-        file_metadata.app_properties = Some([("deviceId".to_string(), device_id)].into_iter().collect());
+        file_metadata.app_properties = Some(
+            [
+                (DEVICE_ID_PROPERTY.to_string(), device_id),
+                (
+                    SCHEMA_VERSION_PROPERTY.to_string(),
+                    data.schema_version.to_string(),
+                ),
+                (CHECKSUM_PROPERTY.to_string(), payload_checksum(&encoded)),
+            ]
+            .into_iter()
+            .collect(),
+        );
 
-        if let Some((file_id, current_etag)) = existing_file {
+        if let Some(RemoteSyncFile {
+            id: file_id,
+            etag: current_etag,
+            size,
+            ..
+        }) = existing_file
+        {
             if let Some(expected_etag) = etag {
                 if expected_etag != current_etag {
                     return Ok(PushResult::Conflict { remote_etag: current_etag });
                 }
             }
+            // The new version replaces the old one's storage
+            let needed = (compressed.len() as u64).saturating_sub(size.unwrap_or_default());
+            self.check_storage_quota(needed).await?;
             
             info!("[DRIVE] Uploading via resumable update...");
-            let (_, result) = hub.files().update(file_metadata, &file_id).upload_resumable(cursor, mime).await.map_err(|e| SyncError::DriveError(e.to_string()))?;
-            Ok(PushResult::Success { etag: result.md5_checksum.unwrap_or_default() })
+            let (_, result) = with_drive_retry("Upload", || {
+                hub.files()
+                    .update(file_metadata.clone(), &file_id)
+                    .upload_resumable(std::io::Cursor::new(compressed.clone()), mime.clone())
+            })
+            .await?;
+            self.state
+                .stats
+                .record_upload(compressed.len(), encoded.len());
+            let new_etag = result.md5_checksum.unwrap_or_default();
+            self.write_cache(&new_etag, &compressed);
+            Ok(PushResult::Success { etag: new_etag })
         } else {
             file_metadata.name = Some(SYNC_FILE_NAME.to_string());
             if config.google_drive_folder_type == crate::types::GoogleDriveFolderType::AppData {
@@ -382,10 +1149,21 @@ impl SyncBackend for GoogleDriveBackend {
             } else {
                 file_metadata.parents = Some(vec![folder_id.clone()]);
             }
+            self.check_storage_quota(compressed.len() as u64).await?;
             
             info!("[DRIVE] Uploading via resumable create...");
-            let (_, result) = hub.files().create(file_metadata).upload_resumable(cursor, mime).await.map_err(|e| SyncError::DriveError(e.to_string()))?;
-            Ok(PushResult::Success { etag: result.md5_checksum.unwrap_or_default() })
+            let (_, result) = with_drive_retry("Upload", || {
+                hub.files()
+                    .create(file_metadata.clone())
+                    .upload_resumable(std::io::Cursor::new(compressed.clone()), mime.clone())
+            })
+            .await?;
+            self.state
+                .stats
+                .record_upload(compressed.len(), encoded.len());
+            let new_etag = result.md5_checksum.unwrap_or_default();
+            self.write_cache(&new_etag, &compressed);
+            Ok(PushResult::Success { etag: new_etag })
         }
     }
 
@@ -415,6 +1193,80 @@ impl SyncBackend for GoogleDriveBackend {
         Ok(user_info.email)
     }
 
+    async fn remote_metadata(&self) -> Result<Option<RemoteMetadata>, SyncError> {
+        let folder_id = self.get_or_create_folder().await?;
+        Ok(self
+            .find_sync_file(&folder_id)
+            .await?
+            .map(|file| RemoteMetadata {
+                device_id: file.device_id,
+                schema_version: file.schema_version,
+                checksum: file.checksum,
+                modified_at: file.modified_at,
+                size: file.size,
+            }))
+    }
+
+    async fn poll_remote_changes(&self) -> Result<bool, SyncError> {
+        let hub = self.get_hub()?;
+        let Some(mut page_token) = self.state.get_drive_changes_token() else {
+            // First poll: only changes from now on count
+            let (_, start) = with_drive_retry("Changes start token", || {
+                hub.changes().get_start_page_token().doit()
+            })
+            .await?;
+            if let Some(token) = start.start_page_token {
+                self.state.set_drive_changes_token(&token)?;
+            }
+            return Ok(false);
+        };
+
+        let config = self.state.get_sync_config();
+        let spaces =
+            if config.google_drive_folder_type == crate::types::GoogleDriveFolderType::AppData {
+                "appDataFolder"
+            } else {
+                "drive"
+            };
+        let last_etag = self.state.get_last_etag();
+        let mut changed = false;
+        loop {
+            let (_, list) = with_drive_retry("Changes lookup", || {
+                hub.changes()
+                    .list(&page_token)
+                    .spaces(spaces)
+                    .param(
+                        "fields",
+                        "nextPageToken,newStartPageToken,changes(file(name,md5Checksum))",
+                    )
+                    .doit()
+            })
+            .await?;
+
+            for change in list.changes.unwrap_or_default() {
+                let Some(file) = change.file else {
+                    continue;
+                };
+                // This device's own pushes show up as changes too
+                if file.name.as_deref() == Some(SYNC_FILE_NAME)
+                    && file.md5_checksum.is_some()
+                    && file.md5_checksum != last_etag
+                {
+                    changed = true;
+                }
+            }
+
+            if let Some(next_page_token) = list.next_page_token {
+                page_token = next_page_token;
+                continue;
+            }
+            if let Some(token) = list.new_start_page_token {
+                self.state.set_drive_changes_token(&token)?;
+            }
+            return Ok(changed);
+        }
+    }
+
     fn start_auth(&self, redirect_uri: &str) -> Result<AuthFlow, SyncError> {
         let state = uuid::Uuid::new_v4().to_string();
         let code_verifier = format!(
@@ -447,10 +1299,10 @@ impl SyncBackend for GoogleDriveBackend {
             .state
             .get_auth_code_verifier()
             .ok_or_else(|| SyncError::OAuthError("Missing PKCE verifier".to_string()))?;
-        let (access_token, refresh_token) = self
+        let (access_token, refresh_token, expires_in) = self
             .exchange_code_for_tokens(code, redirect_uri, &code_verifier)
             .await?;
-        self.state.set_access_token(&access_token)?;
+        store_access_token(&self.state, &access_token, expires_in)?;
         self.state.set_refresh_token(&refresh_token)?;
         self.state.clear_auth_state()?;
         self.state.clear_auth_code_verifier()?;
@@ -1,4 +1,7 @@
 pub mod google_drive;
+pub mod peer;
+pub mod registry;
+pub mod sftp;
 
 use crate::error::SyncError;
 use crate::types::SyncPayload;
@@ -19,6 +22,29 @@ pub struct AuthFlow {
     pub state: String,
 }
 
+/// Device authorization flow information, shown to the user
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceAuthFlow {
+    pub user_code: String,
+    pub verification_url: String,
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    pub interval: u64,
+}
+
+/// Result of polling a device authorization
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeviceAuthStatus {
+    /// Not approved yet; `slow_down` asks the client to poll 5 seconds less often
+    #[serde(rename_all = "camelCase")]
+    Pending {
+        slow_down: bool,
+    },
+    Complete,
+}
+
 /// Authentication status
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -29,6 +55,19 @@ pub struct AuthStatus {
     pub last_sync: Option<i64>,
 }
 
+/// What the backend stores about the remote sync file, besides the payload
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteMetadata {
+    /// Device that pushed the current version
+    pub device_id: Option<String>,
+    pub schema_version: Option<u32>,
+    /// SHA-256 of the uncompressed payload
+    pub checksum: Option<String>,
+    pub modified_at: Option<i64>,
+    pub size: Option<u64>,
+}
+
 /// Trait for sync storage backends
 #[async_trait]
 pub trait SyncBackend: Send + Sync {
@@ -40,6 +79,17 @@ pub trait SyncBackend: Send + Sync {
     /// Uses etag for optimistic locking (If-Match)
     async fn push(&self, data: &SyncPayload, etag: Option<&str>) -> Result<PushResult, SyncError>;
 
+    /// Metadata of the remote sync file, for backends that keep any
+    async fn remote_metadata(&self) -> Result<Option<RemoteMetadata>, SyncError> {
+        Ok(None)
+    }
+
+    /// Whether another device pushed since the last call, for backends that can tell
+    /// without downloading the sync file
+    async fn poll_remote_changes(&self) -> Result<bool, SyncError> {
+        Ok(false)
+    }
+
     /// Check if authenticated
     async fn is_authenticated(&self) -> bool;
 
@@ -0,0 +1,212 @@
+use crate::backend::{AuthFlow, PushResult, SyncBackend};
+use crate::error::SyncError;
+use crate::stats::SyncStats;
+use crate::types::{SyncConfig, SyncPayload};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MATCH};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::info;
+
+// ============================================================================
+// Peer-to-peer Backend
+// ============================================================================
+//
+// Syncs with another Manatan server over HTTP instead of a cloud provider. The peer
+// keeps the sync payload under `/api/sync/peer`, or `/api/v1/sync/peer` on a gateway,
+// whichever answers; `pull` reads it with its ETag and
+// `push` replaces it with `If-Match`, so two devices pushing at once conflict like
+// they would on Google Drive. Both sides share a token: the peer's
+// `peer_serve_token` is this device's `peer_token`.
+
+/// Paths of the peer endpoints on a Manatan server: the app's, then the gateway's
+pub const PEER_API_PATHS: &[&str] = &["/api/sync/peer", "/api/v1/sync/peer"];
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// ETag of a stored payload: the SHA-256 of its JSON
+pub fn payload_etag(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Quotes an ETag for a header value
+pub fn quote_etag(etag: &str) -> String {
+    format!("\"{etag}\"")
+}
+
+/// Strips the quotes (and weak marker) from an ETag header value
+pub fn unquote_etag(value: &str) -> &str {
+    value.trim().trim_start_matches("W/").trim_matches('"')
+}
+
+pub struct PeerBackend {
+    client: reqwest::Client,
+    base_url: String,
+    token: String,
+    stats: Arc<SyncStats>,
+    /// Index into `PEER_API_PATHS` of the path the peer last answered on
+    api_path: AtomicUsize,
+}
+
+impl PeerBackend {
+    pub fn from_config(config: &SyncConfig) -> Result<Self, SyncError> {
+        let base_url = config.peer_url.trim().trim_end_matches('/').to_string();
+        let token = config.peer_token.trim().to_string();
+        if base_url.is_empty() || token.is_empty() {
+            return Err(SyncError::NotAuthenticated);
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| SyncError::PeerError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            base_url,
+            token,
+            stats: Arc::default(),
+            api_path: AtomicUsize::new(0),
+        })
+    }
+
+    /// Reports transfers to `stats`
+    pub fn with_stats(mut self, stats: Arc<SyncStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Sends the request `build` makes for the endpoint of `action`. On a 404 the other
+    /// known paths are tried, and the one that answers is used from then on
+    async fn send(
+        &self,
+        action: &str,
+        build: impl Fn(String) -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, SyncError> {
+        let first = self.api_path.load(Ordering::Relaxed);
+        let mut index = first;
+        loop {
+            let url = format!("{}{}/{action}", self.base_url, PEER_API_PATHS[index]);
+            let response = build(url)
+                .bearer_auth(&self.token)
+                .send()
+                .await
+                .map_err(|e| SyncError::PeerError(e.to_string()))?;
+            let next = (index + 1) % PEER_API_PATHS.len();
+            if response.status() != StatusCode::NOT_FOUND || next == first {
+                self.api_path.store(index, Ordering::Relaxed);
+                return Ok(response);
+            }
+            index = next;
+        }
+    }
+
+    fn response_etag(response: &reqwest::Response) -> String {
+        response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| unquote_etag(value).to_string())
+            .unwrap_or_default()
+    }
+
+    async fn error_for(response: reqwest::Response) -> SyncError {
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED {
+            return SyncError::PeerError(
+                "The peer rejected the token. Check that it matches the peer's serve token."
+                    .to_string(),
+            );
+        }
+        let body = response.text().await.unwrap_or_default();
+        SyncError::PeerError(format!("Peer responded with {status}: {body}"))
+    }
+}
+
+#[async_trait]
+impl SyncBackend for PeerBackend {
+    async fn pull(&self) -> Result<Option<(SyncPayload, String)>, SyncError> {
+        info!("[PEER] Pulling from {}", self.base_url);
+        let response = self.send("pull", |url| self.client.get(url)).await?;
+
+        if response.status() == StatusCode::NO_CONTENT {
+            info!("[PEER] Peer has no sync data yet");
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(Self::error_for(response).await);
+        }
+
+        let etag = Self::response_etag(&response);
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| SyncError::PeerError(e.to_string()))?;
+        self.stats.record_download(bytes.len(), bytes.len());
+        let payload: SyncPayload = serde_json::from_slice(&bytes)?;
+        Ok(Some((payload, etag)))
+    }
+
+    async fn push(&self, data: &SyncPayload, etag: Option<&str>) -> Result<PushResult, SyncError> {
+        info!("[PEER] Pushing to {}", self.base_url);
+        let body = serde_json::to_vec(data)?;
+        let body_len = body.len();
+        let response = self
+            .send("push", |url| {
+                let request = self
+                    .client
+                    .post(url)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(body.clone());
+                match etag {
+                    Some(etag) => request.header(IF_MATCH, quote_etag(etag)),
+                    None => request,
+                }
+            })
+            .await?;
+
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            return Ok(PushResult::Conflict {
+                remote_etag: Self::response_etag(&response),
+            });
+        }
+        if !response.status().is_success() {
+            return Err(Self::error_for(response).await);
+        }
+
+        self.stats.record_upload(body_len, body_len);
+        Ok(PushResult::Success {
+            etag: Self::response_etag(&response),
+        })
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        !self.base_url.is_empty() && !self.token.is_empty()
+    }
+
+    async fn get_user_info(&self) -> Result<Option<String>, SyncError> {
+        Ok(None)
+    }
+
+    fn start_auth(&self, _redirect_uri: &str) -> Result<AuthFlow, SyncError> {
+        Err(SyncError::BadRequest(
+            "Peer sync uses a shared token instead of OAuth".to_string(),
+        ))
+    }
+
+    async fn complete_auth(&mut self, _code: &str, _redirect_uri: &str) -> Result<(), SyncError> {
+        Err(SyncError::BadRequest(
+            "Peer sync uses a shared token instead of OAuth".to_string(),
+        ))
+    }
+
+    async fn disconnect(&mut self) -> Result<(), SyncError> {
+        Ok(())
+    }
+
+    async fn refresh_token(&mut self) -> Result<(), SyncError> {
+        Ok(())
+    }
+}
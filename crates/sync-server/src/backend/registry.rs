@@ -0,0 +1,122 @@
+use crate::backend::SyncBackend;
+use crate::backend::google_drive::GoogleDriveBackend;
+use crate::backend::peer::PeerBackend;
+use crate::backend::sftp::SftpBackend;
+use crate::error::SyncError;
+use crate::state::SyncState;
+use crate::types::SyncBackendType;
+use std::collections::HashMap;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use tracing::{debug, info};
+
+// ============================================================================
+// Backend Registry
+// ============================================================================
+//
+// Holds at most one loaded backend per `SyncBackendType`. The active backend is the
+// one `SyncConfig.backend` names, loaded on first use, so switching providers is a
+// config change. Google Drive keeps its OAuth session here between requests; the
+// backends built from config fields (peer, SFTP) are dropped when the config changes
+// so the next operation picks up the new settings.
+
+pub type BackendMap = HashMap<SyncBackendType, Box<dyn SyncBackend>>;
+
+/// Backends that can be selected
+pub const AVAILABLE_BACKENDS: &[SyncBackendType] = &[
+    SyncBackendType::GoogleDrive,
+    SyncBackendType::Peer,
+    SyncBackendType::Sftp,
+];
+
+/// Backends built entirely from `SyncConfig`
+const CONFIGURED_BACKENDS: &[SyncBackendType] = &[SyncBackendType::Peer, SyncBackendType::Sftp];
+
+#[derive(Default)]
+pub struct BackendRegistry {
+    backends: RwLock<BackendMap>,
+}
+
+impl BackendRegistry {
+    async fn create(
+        state: &SyncState,
+        kind: &SyncBackendType,
+    ) -> Result<Box<dyn SyncBackend>, SyncError> {
+        let config = state.get_sync_config();
+        match kind {
+            SyncBackendType::GoogleDrive => {
+                let mut backend = GoogleDriveBackend::new(state.clone());
+                backend.initialize().await?;
+                Ok(Box::new(backend))
+            }
+            SyncBackendType::Peer => {
+                let backend = PeerBackend::from_config(&config)?;
+                Ok(Box::new(backend.with_stats(state.stats.clone())))
+            }
+            SyncBackendType::Sftp => {
+                let backend = SftpBackend::from_config(&config)?
+                    .with_stats(state.stats.clone())
+                    .with_state(state.clone());
+                Ok(Box::new(backend))
+            }
+            SyncBackendType::None => Err(SyncError::NotAuthenticated),
+            other => Err(SyncError::BadRequest(format!(
+                "The {other:?} backend is not supported yet"
+            ))),
+        }
+    }
+
+    /// The backend for `kind`, loaded if needed and with its token refreshed
+    pub async fn get(
+        &self,
+        state: &SyncState,
+        kind: &SyncBackendType,
+    ) -> Result<RwLockReadGuard<'_, dyn SyncBackend>, SyncError> {
+        {
+            let mut backends = self.backends.write().await;
+            if !backends.contains_key(kind) {
+                let backend = Self::create(state, kind).await?;
+                info!("[BACKEND] Loaded {:?} backend", kind);
+                backends.insert(kind.clone(), backend);
+            }
+            if let Some(backend) = backends.get_mut(kind) {
+                if let Err(e) = backend.refresh_token().await {
+                    debug!("Token refresh failed (may be okay): {}", e);
+                }
+            }
+        }
+
+        RwLockReadGuard::try_map(self.backends.read().await, |backends| {
+            backends.get(kind).map(|backend| backend.as_ref())
+        })
+        .map_err(|_| SyncError::NotAuthenticated)
+    }
+
+    /// The backend the config selects
+    pub async fn active(
+        &self,
+        state: &SyncState,
+    ) -> Result<RwLockReadGuard<'_, dyn SyncBackend>, SyncError> {
+        let kind = state.get_sync_config().backend;
+        self.get(state, &kind).await
+    }
+
+    /// Direct access for auth flows that install or replace a backend
+    pub async fn write(&self) -> RwLockWriteGuard<'_, BackendMap> {
+        self.backends.write().await
+    }
+
+    /// Backends currently loaded
+    pub async fn loaded(&self) -> Vec<SyncBackendType> {
+        let mut loaded: Vec<SyncBackendType> = self.backends.read().await.keys().cloned().collect();
+        loaded.sort_by_key(|kind| format!("{kind:?}"));
+        loaded
+    }
+
+    /// Drops the backends built from the config after it changed
+    pub async fn reload_configured(&self) {
+        let mut backends = self.backends.write().await;
+        for kind in CONFIGURED_BACKENDS {
+            backends.remove(kind);
+        }
+    }
+}
@@ -0,0 +1,477 @@
+use crate::backend::{AuthFlow, PushResult, SyncBackend};
+use crate::compression;
+use crate::error::SyncError;
+use crate::proto;
+use crate::state::SyncState;
+use crate::stats::SyncStats;
+use crate::types::{CompressionAlgorithm, SyncConfig, SyncPayload};
+use async_trait::async_trait;
+use base64::Engine as _;
+use sha2::{Digest, Sha256};
+use ssh2::{ErrorCode, HashType, Session, Sftp};
+use std::ffi::CString;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tracing::{info, warn};
+
+// ============================================================================
+// SFTP Backend
+// ============================================================================
+//
+// Stores the same compressed payload as Google Drive in a directory on an SSH server.
+// Uploads go to a temporary file that replaces the sync file with OpenSSH's
+// `posix-rename@openssh.com`, so a reader never sees a partial or missing payload.
+// The etag is the SHA-256 of the file, kept in a `.sha256` file beside it so a push can
+// check for conflicts without downloading the payload.
+//
+// libssh2 is blocking, so every operation opens its own session on the blocking pool.
+
+const SYNC_FILE_NAME: &str = "manatan_sync.proto.gz";
+const CHECKSUM_FILE_NAME: &str = "manatan_sync.proto.gz.sha256";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const SESSION_TIMEOUT_MS: u32 = 120_000;
+/// `SSH_FX_NO_SUCH_FILE`
+const SFTP_NO_SUCH_FILE: i32 = 2;
+
+fn sftp_error(e: impl std::fmt::Display) -> SyncError {
+    SyncError::SftpError(e.to_string())
+}
+
+fn is_not_found(e: &ssh2::Error) -> bool {
+    matches!(e.code(), ErrorCode::SFTP(SFTP_NO_SUCH_FILE))
+}
+
+fn content_etag(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+#[derive(Clone)]
+pub struct SftpBackend {
+    host: String,
+    port: u16,
+    username: String,
+    password: String,
+    private_key: String,
+    key_passphrase: String,
+    /// Shared between clones so a key trusted on first use stays pinned
+    host_fingerprint: Arc<Mutex<String>>,
+    remote_dir: PathBuf,
+    compression: CompressionAlgorithm,
+    zstd_level: i32,
+    stats: Arc<SyncStats>,
+    /// Where a key trusted on first use is saved
+    state: Option<SyncState>,
+}
+
+impl SftpBackend {
+    pub fn from_config(config: &SyncConfig) -> Result<Self, SyncError> {
+        let host = config.sftp_host.trim().to_string();
+        let username = config.sftp_username.trim().to_string();
+        let has_credentials =
+            !config.sftp_private_key.trim().is_empty() || !config.sftp_password.is_empty();
+        if host.is_empty() || username.is_empty() || !has_credentials {
+            return Err(SyncError::NotAuthenticated);
+        }
+
+        let remote_dir = match config.sftp_remote_dir.trim() {
+            "" => PathBuf::from("."),
+            dir => PathBuf::from(dir),
+        };
+
+        Ok(Self {
+            host,
+            port: config.sftp_port,
+            username,
+            password: config.sftp_password.clone(),
+            private_key: config.sftp_private_key.trim().to_string(),
+            key_passphrase: config.sftp_key_passphrase.clone(),
+            host_fingerprint: Arc::new(Mutex::new(
+                config.sftp_host_fingerprint.trim().to_string(),
+            )),
+            remote_dir,
+            compression: config.compression.clone(),
+            zstd_level: config.zstd_level,
+            stats: Arc::default(),
+            state: None,
+        })
+    }
+
+    /// Reports transfers to `stats`
+    pub fn with_stats(mut self, stats: Arc<SyncStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Saves the host key trusted on first use to the config in `state`
+    pub fn with_state(mut self, state: SyncState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    fn sync_file(&self) -> PathBuf {
+        self.remote_dir.join(SYNC_FILE_NAME)
+    }
+
+    fn checksum_file(&self) -> PathBuf {
+        self.remote_dir.join(CHECKSUM_FILE_NAME)
+    }
+
+    /// Runs a blocking SFTP operation off the async runtime
+    async fn run<T, F>(&self, op: F) -> Result<T, SyncError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SftpBackend, &Session, &Sftp) -> Result<T, SyncError> + Send + 'static,
+    {
+        let backend = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let (session, sftp) = backend.connect()?;
+            op(&backend, &session, &sftp)
+        })
+        .await
+        .map_err(sftp_error)?
+    }
+
+    fn connect(&self) -> Result<(Session, Sftp), SyncError> {
+        let addrs = (self.host.as_str(), self.port)
+            .to_socket_addrs()
+            .map_err(|e| sftp_error(format!("Could not resolve {}: {e}", self.host)))?;
+        let tcp = addrs
+            .filter_map(|addr| TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).ok())
+            .next()
+            .ok_or_else(|| {
+                sftp_error(format!("Could not connect to {}:{}", self.host, self.port))
+            })?;
+
+        let mut session = Session::new().map_err(sftp_error)?;
+        session.set_tcp_stream(tcp);
+        session.set_timeout(SESSION_TIMEOUT_MS);
+        session.handshake().map_err(sftp_error)?;
+        self.verify_host_key(&session)?;
+
+        if self.private_key.is_empty() {
+            session
+                .userauth_password(&self.username, &self.password)
+                .map_err(sftp_error)?;
+        } else {
+            let passphrase = Some(self.key_passphrase.as_str()).filter(|p| !p.is_empty());
+            // The key is either the PEM itself or a path to it
+            let result = if self.private_key.contains("-----BEGIN") {
+                session.userauth_pubkey_memory(&self.username, None, &self.private_key, passphrase)
+            } else {
+                session.userauth_pubkey_file(
+                    &self.username,
+                    None,
+                    Path::new(&self.private_key),
+                    passphrase,
+                )
+            };
+            result.map_err(sftp_error)?;
+        }
+        if !session.authenticated() {
+            return Err(SyncError::NotAuthenticated);
+        }
+
+        let sftp = session.sftp().map_err(sftp_error)?;
+        Ok((session, sftp))
+    }
+
+    /// Checks the server key against `sftp_host_fingerprint` (`SHA256:...`, as printed by
+    /// `ssh-keygen -lf`). Without a pinned fingerprint the key is trusted on first use and
+    /// pinned from then on.
+    fn verify_host_key(&self, session: &Session) -> Result<(), SyncError> {
+        let hash = session
+            .host_key_hash(HashType::Sha256)
+            .ok_or_else(|| sftp_error("Server did not present a host key"))?;
+        let fingerprint = format!(
+            "SHA256:{}",
+            base64::engine::general_purpose::STANDARD_NO_PAD.encode(hash)
+        );
+
+        let mut pinned = self
+            .host_fingerprint
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if pinned.is_empty() {
+            info!(
+                "[SFTP] Trusting host key {fingerprint} of {} on first use",
+                self.host
+            );
+            *pinned = fingerprint.clone();
+            self.save_host_key(&fingerprint);
+            return Ok(());
+        }
+        if pinned.trim_end_matches('=') != fingerprint {
+            return Err(sftp_error(format!(
+                "Host key mismatch for {}: expected {}, got {fingerprint}",
+                self.host, *pinned
+            )));
+        }
+        Ok(())
+    }
+
+    /// Stores a newly trusted key, unless the settings moved on to another server meanwhile
+    fn save_host_key(&self, fingerprint: &str) {
+        let Some(state) = &self.state else {
+            return;
+        };
+        let mut config = state.get_sync_config();
+        if config.sftp_host.trim() != self.host
+            || config.sftp_port != self.port
+            || !config.sftp_host_fingerprint.trim().is_empty()
+        {
+            return;
+        }
+        config.sftp_host_fingerprint = fingerprint.to_string();
+        if let Err(e) = state.set_sync_config(&config) {
+            warn!("[SFTP] Could not save the host key of {}: {e}", self.host);
+        }
+    }
+
+    /// Contents of a remote file, or `None` if it does not exist
+    fn read_file(sftp: &Sftp, path: &Path) -> Result<Option<Vec<u8>>, SyncError> {
+        let mut file = match sftp.open(path) {
+            Ok(file) => file,
+            Err(e) if is_not_found(&e) => return Ok(None),
+            Err(e) => return Err(sftp_error(e)),
+        };
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+
+    /// The stored checksum of the sync file, or its hash when there is none
+    fn current_etag(&self, sftp: &Sftp) -> Result<Option<String>, SyncError> {
+        let stored = Self::read_file(sftp, &self.checksum_file())?
+            .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+            .filter(|checksum| !checksum.is_empty());
+        if let Some(checksum) = stored {
+            return Ok(Some(checksum));
+        }
+        Ok(Self::read_file(sftp, &self.sync_file())?.map(|bytes| content_etag(&bytes)))
+    }
+
+    fn ensure_remote_dir(&self, sftp: &Sftp) -> Result<(), SyncError> {
+        let mut dir = PathBuf::new();
+        for component in self.remote_dir.components() {
+            dir.push(component);
+            match sftp.stat(&dir) {
+                Ok(_) => {}
+                Err(e) if is_not_found(&e) => {
+                    info!("[SFTP] Creating directory {}", dir.display());
+                    sftp.mkdir(&dir, 0o755).map_err(sftp_error)?;
+                }
+                Err(e) => return Err(sftp_error(e)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `bytes` to the sync file and its checksum beside it. The old checksum is
+    /// removed first so it never describes a newer payload.
+    fn upload(&self, session: &Session, sftp: &Sftp, bytes: &[u8]) -> Result<String, SyncError> {
+        let etag = content_etag(bytes);
+        if let Err(e) = sftp.unlink(&self.checksum_file())
+            && !is_not_found(&e)
+        {
+            return Err(sftp_error(e));
+        }
+        self.write_file(session, sftp, &self.sync_file(), bytes)?;
+        if let Err(e) = self.write_file(session, sftp, &self.checksum_file(), etag.as_bytes()) {
+            warn!("[SFTP] Could not store the sync file checksum: {e}");
+        }
+        Ok(etag)
+    }
+
+    /// Writes `bytes` next to `target` and renames it into place
+    fn write_file(
+        &self,
+        session: &Session,
+        sftp: &Sftp,
+        target: &Path,
+        bytes: &[u8],
+    ) -> Result<(), SyncError> {
+        let temp = self
+            .remote_dir
+            .join(format!("{SYNC_FILE_NAME}.{}.tmp", uuid::Uuid::new_v4()));
+
+        let written = sftp
+            .create(&temp)
+            .map_err(sftp_error)
+            .and_then(|mut file| Ok(file.write_all(bytes)?));
+        let replaced = written.and_then(|()| match posix_rename(session, &temp, target)? {
+            true => Ok(()),
+            false => self.replace_aside(sftp, &temp, target),
+        });
+        if replaced.is_err() {
+            let _ = sftp.unlink(&temp);
+        }
+        replaced
+    }
+
+    /// Replaces `target` on servers without `posix-rename@openssh.com`, whose plain rename
+    /// refuses to overwrite. The old file is moved aside and only deleted once the new one
+    /// is in place, so a failure leaves it restorable.
+    fn replace_aside(&self, sftp: &Sftp, temp: &Path, target: &Path) -> Result<(), SyncError> {
+        warn!(
+            "[SFTP] Server lacks posix-rename, moving {} aside",
+            target.display()
+        );
+        let old = self
+            .remote_dir
+            .join(format!("{SYNC_FILE_NAME}.{}.old", uuid::Uuid::new_v4()));
+        let moved_aside = match sftp.rename(target, &old, None) {
+            Ok(()) => true,
+            Err(e) if is_not_found(&e) => false,
+            Err(e) => return Err(sftp_error(e)),
+        };
+
+        if let Err(e) = sftp.rename(temp, target, None) {
+            if moved_aside && let Err(restore) = sftp.rename(&old, target, None) {
+                warn!(
+                    "[SFTP] Could not restore {} from {}: {restore}",
+                    target.display(),
+                    old.display()
+                );
+            }
+            return Err(sftp_error(e));
+        }
+        if moved_aside && let Err(e) = sftp.unlink(&old) {
+            warn!("[SFTP] Could not remove {}: {e}", old.display());
+        }
+        Ok(())
+    }
+}
+
+fn path_cstring(path: &Path) -> Result<CString, SyncError> {
+    CString::new(path.to_string_lossy().into_owned()).map_err(sftp_error)
+}
+
+// ssh2 does not wrap this libssh2 call, though the bundled libssh2 provides it
+unsafe extern "C" {
+    fn libssh2_sftp_posix_rename_ex(
+        sftp: *mut libssh2_sys::LIBSSH2_SFTP,
+        source_filename: *const std::ffi::c_char,
+        source_filename_len: usize,
+        dest_filename: *const std::ffi::c_char,
+        dest_filename_len: usize,
+    ) -> std::ffi::c_int;
+}
+
+/// Atomically renames `from` over `to` with `posix-rename@openssh.com`. Returns `false`
+/// when the server does not offer the extension.
+fn posix_rename(session: &Session, from: &Path, to: &Path) -> Result<bool, SyncError> {
+    let (from, to) = (path_cstring(from)?, path_cstring(to)?);
+    let mut raw_session = session.raw();
+    // SAFETY: the session lock is held throughout and the SFTP handle opened here is shut
+    // down before it is released
+    unsafe {
+        let sftp = libssh2_sys::libssh2_sftp_init(&mut *raw_session);
+        if sftp.is_null() {
+            return Err(sftp_error("Could not open an SFTP channel for the rename"));
+        }
+        let rc = libssh2_sftp_posix_rename_ex(
+            sftp,
+            from.as_ptr(),
+            from.as_bytes().len(),
+            to.as_ptr(),
+            to.as_bytes().len(),
+        );
+        let status = libssh2_sys::libssh2_sftp_last_error(sftp);
+        libssh2_sys::libssh2_sftp_shutdown(sftp);
+        match rc {
+            0 => Ok(true),
+            libssh2_sys::LIBSSH2_FX_OP_UNSUPPORTED => Ok(false),
+            _ => Err(sftp_error(format!(
+                "posix-rename failed (error {rc}, SFTP status {status})"
+            ))),
+        }
+    }
+}
+
+#[async_trait]
+impl SyncBackend for SftpBackend {
+    async fn pull(&self) -> Result<Option<(SyncPayload, String)>, SyncError> {
+        info!("[SFTP] Pulling from {}", self.host);
+        let downloaded = self
+            .run(|backend, _session, sftp| Self::read_file(sftp, &backend.sync_file()))
+            .await?;
+
+        let Some(compressed) = downloaded else {
+            info!("[SFTP] No sync file found");
+            return Ok(None);
+        };
+        let etag = content_etag(&compressed);
+        info!("[SFTP] Found sync file, etag: {}", etag);
+
+        let decompressed = compression::decompress(&compressed)?;
+        self.stats
+            .record_download(compressed.len(), decompressed.len());
+        let payload = proto::decode_payload(&decompressed)?;
+        Ok(Some((payload, etag)))
+    }
+
+    async fn push(&self, data: &SyncPayload, etag: Option<&str>) -> Result<PushResult, SyncError> {
+        let encoded = proto::encode_payload(data);
+        let compressed = compression::compress(&encoded, &self.compression, self.zstd_level)?;
+        info!(
+            "[SFTP] Pushing {} bytes ({} uncompressed) to {}",
+            compressed.len(),
+            encoded.len(),
+            self.host
+        );
+
+        let sizes = (compressed.len(), encoded.len());
+        let expected_etag = etag.map(str::to_string);
+        let result = self
+            .run(move |backend, session, sftp| {
+                backend.ensure_remote_dir(sftp)?;
+                let current = backend.current_etag(sftp)?;
+                match (expected_etag, current) {
+                    (Some(expected), Some(current)) if expected != current => {
+                        Ok(PushResult::Conflict {
+                            remote_etag: current,
+                        })
+                    }
+                    _ => Ok(PushResult::Success {
+                        etag: backend.upload(session, sftp, &compressed)?,
+                    }),
+                }
+            })
+            .await?;
+        if matches!(result, PushResult::Success { .. }) {
+            self.stats.record_upload(sizes.0, sizes.1);
+        }
+        Ok(result)
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        !self.host.is_empty() && !self.username.is_empty()
+    }
+
+    async fn get_user_info(&self) -> Result<Option<String>, SyncError> {
+        Ok(Some(format!("{}@{}", self.username, self.host)))
+    }
+
+    fn start_auth(&self, _redirect_uri: &str) -> Result<AuthFlow, SyncError> {
+        Err(SyncError::BadRequest(
+            "SFTP sync uses a password or key instead of OAuth".to_string(),
+        ))
+    }
+
+    async fn complete_auth(&mut self, _code: &str, _redirect_uri: &str) -> Result<(), SyncError> {
+        Err(SyncError::BadRequest(
+            "SFTP sync uses a password or key instead of OAuth".to_string(),
+        ))
+    }
+
+    async fn disconnect(&mut self) -> Result<(), SyncError> {
+        Ok(())
+    }
+
+    async fn refresh_token(&mut self) -> Result<(), SyncError> {
+        Ok(())
+    }
+}
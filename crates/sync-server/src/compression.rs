@@ -0,0 +1,72 @@
+use crate::types::CompressionAlgorithm;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::io::{self, Read, Write};
+
+// ============================================================================
+// Sync File Compression
+// ============================================================================
+//
+// The first byte of a sync file tells how it is compressed. Gzip files are written
+// as plain gzip, starting with its magic byte, so older versions can still read
+// them; zstd files start with `ZSTD_HEADER`.
+
+const GZIP_MAGIC: u8 = 0x1f;
+const ZSTD_HEADER: u8 = 0x02;
+/// Largest decompressed sync file or archive accepted, so a small gzip or zstd bomb
+/// from a remote can't exhaust memory
+pub const MAX_DECOMPRESSED_BYTES: u64 = 512 * 1024 * 1024;
+
+pub fn compress(
+    data: &[u8],
+    algorithm: &CompressionAlgorithm,
+    zstd_level: i32,
+) -> io::Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        CompressionAlgorithm::Zstd => {
+            let mut compressed = vec![ZSTD_HEADER];
+            zstd::stream::copy_encode(data, &mut compressed, zstd_level)?;
+            Ok(compressed)
+        }
+    }
+}
+
+/// Decompresses a sync file written by `compress` with any algorithm
+pub fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    match data.first() {
+        Some(&GZIP_MAGIC) => read_capped(GzDecoder::new(data)),
+        Some(&ZSTD_HEADER) => read_capped(zstd::stream::read::Decoder::new(&data[1..])?),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unknown sync file compression",
+        )),
+    }
+}
+
+/// Reads a decoder to the end, failing with `FileTooLarge` past `MAX_DECOMPRESSED_BYTES`
+pub fn read_capped(decoder: impl Read) -> io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    decoder
+        .take(MAX_DECOMPRESSED_BYTES + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::FileTooLarge,
+            format!("larger than {MAX_DECOMPRESSED_BYTES} bytes uncompressed"),
+        ));
+    }
+    Ok(decompressed)
+}
+
+pub fn mime_type(algorithm: &CompressionAlgorithm) -> &'static str {
+    match algorithm {
+        CompressionAlgorithm::Gzip => "application/gzip",
+        CompressionAlgorithm::Zstd => "application/zstd",
+    }
+}
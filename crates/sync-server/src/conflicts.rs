@@ -0,0 +1,175 @@
+use crate::merge::merge_with_base;
+use crate::types::{SyncBase, SyncPayload};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+
+// ============================================================================
+// Pending Conflicts
+// ============================================================================
+//
+// When a push loses the etag race, the payload this device meant to push and the
+// remote payload that won are both kept. The user then picks local, remote or the
+// automatic merge per record, and the result is pushed against the remote etag.
+
+/// Payload section a conflicted record belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecordKind {
+    LnProgress,
+    LnMetadata,
+    LnContent,
+    LnFiles,
+    FileManifest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Resolution {
+    Local,
+    Remote,
+    /// What the automatic merge chose
+    #[default]
+    Merged,
+}
+
+/// Both versions of a conflicted push
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingConflict {
+    /// The payload this device tried to push
+    pub local: SyncPayload,
+    pub remote: SyncPayload,
+    /// Etag of `remote`; the resolved payload is pushed against it
+    pub remote_etag: String,
+    pub detected_at: i64,
+}
+
+/// A record that differs between the two versions. Content and files are shown as
+/// summaries since their values are whole books.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictRecord {
+    pub kind: RecordKind,
+    pub id: String,
+    pub local: Option<Value>,
+    pub remote: Option<Value>,
+    pub merged: Option<Value>,
+}
+
+impl PendingConflict {
+    /// The automatic merge of both versions
+    pub fn merged(&self, base: Option<&SyncBase>, device_id: &str) -> SyncPayload {
+        merge_with_base(base, self.local.clone(), self.remote.clone(), device_id).0
+    }
+
+    /// Records whose local and remote versions differ
+    pub fn records(&self, merged: &SyncPayload) -> Vec<ConflictRecord> {
+        let mut records = Vec::new();
+        diff_records(
+            RecordKind::LnProgress,
+            [&self.local, &self.remote, merged].map(|p| &p.ln_progress),
+            PartialEq::eq,
+            |progress| json!(progress),
+            &mut records,
+        );
+        diff_records(
+            RecordKind::LnMetadata,
+            [&self.local, &self.remote, merged].map(|p| &p.ln_metadata),
+            PartialEq::eq,
+            |metadata| json!(metadata),
+            &mut records,
+        );
+        diff_records(
+            RecordKind::LnContent,
+            [&self.local, &self.remote, merged].map(|p| &p.ln_content),
+            |_, _| true,
+            |book| json!({ "chapters": book.chapters.len() }),
+            &mut records,
+        );
+        diff_records(
+            RecordKind::LnFiles,
+            [&self.local, &self.remote, merged].map(|p| &p.ln_files),
+            PartialEq::eq,
+            |file| json!({ "base64Length": file.len() }),
+            &mut records,
+        );
+        diff_records(
+            RecordKind::FileManifest,
+            [&self.local, &self.remote, merged].map(|p| &p.file_manifest),
+            PartialEq::eq,
+            |reference| json!(reference),
+            &mut records,
+        );
+        records.sort_by(|a, b| a.id.cmp(&b.id));
+        records
+    }
+
+    /// Applies `choices` to the automatic merge. Records without a choice use `default`.
+    pub fn resolve(
+        &self,
+        mut merged: SyncPayload,
+        choices: &HashMap<(RecordKind, String), Resolution>,
+        default: Resolution,
+    ) -> SyncPayload {
+        for record in self.records(&merged) {
+            let choice = choices
+                .get(&(record.kind, record.id.clone()))
+                .copied()
+                .unwrap_or(default);
+            let source = match choice {
+                Resolution::Local => &self.local,
+                Resolution::Remote => &self.remote,
+                Resolution::Merged => continue,
+            };
+            let id = &record.id;
+            match record.kind {
+                RecordKind::LnProgress => take(&mut merged.ln_progress, &source.ln_progress, id),
+                RecordKind::LnMetadata => take(&mut merged.ln_metadata, &source.ln_metadata, id),
+                RecordKind::LnContent => take(&mut merged.ln_content, &source.ln_content, id),
+                RecordKind::LnFiles => take(&mut merged.ln_files, &source.ln_files, id),
+                RecordKind::FileManifest => {
+                    take(&mut merged.file_manifest, &source.file_manifest, id)
+                }
+            }
+        }
+        merged
+    }
+}
+
+fn diff_records<V>(
+    kind: RecordKind,
+    [local, remote, merged]: [&HashMap<String, V>; 3],
+    same: impl Fn(&V, &V) -> bool,
+    summarize: impl Fn(&V) -> Value,
+    records: &mut Vec<ConflictRecord>,
+) {
+    let ids: HashSet<&String> = local.keys().chain(remote.keys()).collect();
+    for id in ids {
+        let differs = match (local.get(id), remote.get(id)) {
+            (Some(l), Some(r)) => !same(l, r),
+            _ => true,
+        };
+        if differs {
+            records.push(ConflictRecord {
+                kind,
+                id: id.clone(),
+                local: local.get(id).map(&summarize),
+                remote: remote.get(id).map(&summarize),
+                merged: merged.get(id).map(&summarize),
+            });
+        }
+    }
+}
+
+/// Replaces the record `id` of `target` with the one in `source`, or removes it
+fn take<V: Clone>(target: &mut HashMap<String, V>, source: &HashMap<String, V>, id: &str) {
+    match source.get(id) {
+        Some(value) => {
+            target.insert(id.to_string(), value.clone());
+        }
+        None => {
+            target.remove(id);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::routes::sync::run_merge;
+use crate::state::SyncState;
+use crate::types::SyncPayload;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+// ============================================================================
+// Sync on Change
+// ============================================================================
+//
+// The app reports each local change with its current payload. Every report restarts
+// the quiet period; once no change has arrived for `sync_debounce_secs`, the latest
+// payload is merged like a `/merge` request. Syncs never overlap: a change that
+// arrives during a sync waits for it and is then synced on its own.
+
+#[derive(Default)]
+pub struct ChangeDebouncer {
+    pending: Mutex<Pending>,
+    /// Held while a debounced sync runs
+    running: Mutex<()>,
+    last: Mutex<LastRun>,
+}
+
+fn schedule(state: &SyncState, generation: u64, delay: Duration) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+        state.changes.run(&state, generation).await;
+    });
+}
+
+#[derive(Default)]
+struct Pending {
+    generation: u64,
+    payload: Option<SyncPayload>,
+}
+
+#[derive(Clone, Default)]
+struct LastRun {
+    finished_at: Option<i64>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeStatus {
+    /// A change is waiting for its quiet period or for a running sync
+    pub pending: bool,
+    pub last_run: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+impl ChangeDebouncer {
+    /// Records a change and schedules a sync after `quiet` without further changes
+    pub async fn notify(&self, state: &SyncState, payload: SyncPayload, quiet: Duration) {
+        let generation = {
+            let mut pending = self.pending.lock().await;
+            pending.generation += 1;
+            pending.payload = Some(payload);
+            pending.generation
+        };
+
+        schedule(state, generation, quiet);
+    }
+
+    async fn run(&self, state: &SyncState, generation: u64) {
+        let _running = self.running.lock().await;
+
+        let payload = {
+            let mut pending = self.pending.lock().await;
+            // A newer change restarted the quiet period and will run instead
+            if pending.generation != generation {
+                return;
+            }
+            // A sync started elsewhere; keep the change for after it
+            if state.sync_lock.current().is_some() {
+                let quiet = Duration::from_secs(state.get_sync_config().sync_debounce_secs);
+                schedule(state, generation, quiet);
+                return;
+            }
+            pending.payload.take()
+        };
+        let Some(payload) = payload else {
+            return;
+        };
+
+        info!("[CHANGE] Local data settled, syncing...");
+        let error = match run_merge(state, payload).await {
+            Ok(response) => {
+                info!(
+                    "[CHANGE] Sync complete: {} conflicts",
+                    response.conflicts.len()
+                );
+                None
+            }
+            Err(e) => {
+                warn!("[CHANGE] Sync failed: {}", e);
+                Some(e.to_string())
+            }
+        };
+
+        *self.last.lock().await = LastRun {
+            finished_at: Some(chrono::Utc::now().timestamp_millis()),
+            error,
+        };
+    }
+
+    pub async fn status(&self) -> ChangeStatus {
+        let pending = self.pending.lock().await.payload.is_some();
+        let last = self.last.lock().await.clone();
+        ChangeStatus {
+            pending,
+            last_run: last.finished_at,
+            last_error: last.error,
+        }
+    }
+}
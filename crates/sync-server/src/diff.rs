@@ -0,0 +1,127 @@
+use crate::merge::merge_with_base;
+use crate::types::{SyncBase, SyncPayload};
+use serde::Serialize;
+use std::collections::HashMap;
+
+// ============================================================================
+// Sync Preview
+// ============================================================================
+//
+// A dry run of `/merge`: both payloads are merged as usual, and each side is
+// compared with the result to show what a sync would change on it.
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordChanges {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl RecordChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Changes a sync would apply to one side, per payload section
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadChanges {
+    pub ln_progress: RecordChanges,
+    pub ln_metadata: RecordChanges,
+    pub ln_content: RecordChanges,
+    pub ln_files: RecordChanges,
+    pub file_manifest: RecordChanges,
+}
+
+impl PayloadChanges {
+    /// What turns `from` into `to`
+    pub fn between(from: &SyncPayload, to: &SyncPayload) -> Self {
+        Self {
+            ln_progress: changes(&from.ln_progress, &to.ln_progress, PartialEq::eq),
+            ln_metadata: changes(&from.ln_metadata, &to.ln_metadata, PartialEq::eq),
+            // Parsed books don't change once imported
+            ln_content: changes(&from.ln_content, &to.ln_content, |_, _| true),
+            ln_files: changes(&from.ln_files, &to.ln_files, PartialEq::eq),
+            file_manifest: changes(&from.file_manifest, &to.file_manifest, PartialEq::eq),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ln_progress.is_empty()
+            && self.ln_metadata.is_empty()
+            && self.ln_content.is_empty()
+            && self.ln_files.is_empty()
+            && self.file_manifest.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncDiff {
+    /// Whether the remote has any data yet
+    pub remote_exists: bool,
+    /// Applied to the local data when the merged payload is returned
+    pub to_local: PayloadChanges,
+    /// Applied to the remote data when the merged payload is pushed
+    pub to_remote: PayloadChanges,
+    pub conflicts: usize,
+}
+
+impl SyncDiff {
+    /// Compares both sides with their merge, as `/merge` would compute it
+    pub fn compute(
+        base: Option<&SyncBase>,
+        local: &SyncPayload,
+        remote: Option<&SyncPayload>,
+        device_id: &str,
+    ) -> Self {
+        let Some(remote) = remote else {
+            return Self {
+                remote_exists: false,
+                to_local: PayloadChanges::default(),
+                to_remote: PayloadChanges::between(&SyncPayload::default(), local),
+                conflicts: 0,
+            };
+        };
+
+        let (merged, conflicts) = if remote.device_id == device_id {
+            // Same device: `/merge` overwrites the remote
+            (local.clone(), Vec::new())
+        } else {
+            merge_with_base(base, local.clone(), remote.clone(), device_id)
+        };
+        Self {
+            remote_exists: true,
+            to_local: PayloadChanges::between(local, &merged),
+            to_remote: PayloadChanges::between(remote, &merged),
+            conflicts: conflicts.len(),
+        }
+    }
+}
+
+fn changes<V>(
+    from: &HashMap<String, V>,
+    to: &HashMap<String, V>,
+    same: impl Fn(&V, &V) -> bool,
+) -> RecordChanges {
+    let mut result = RecordChanges::default();
+    for (id, value) in to {
+        match from.get(id) {
+            None => result.added.push(id.clone()),
+            Some(old) if !same(old, value) => result.updated.push(id.clone()),
+            Some(_) => {}
+        }
+    }
+    result.removed = from
+        .keys()
+        .filter(|id| !to.contains_key(*id))
+        .cloned()
+        .collect();
+
+    result.added.sort();
+    result.updated.sort();
+    result.removed.sort();
+    result
+}
@@ -6,6 +6,8 @@ use axum::{
 use serde_json::json;
 use tracing::warn;
 
+use crate::lock::SyncStage;
+
 #[derive(Debug, thiserror::Error)]
 pub enum SyncError {
     #[error("Not authenticated with sync backend")]
@@ -17,18 +19,45 @@ pub enum SyncError {
     #[error("Google Drive error: {0}")]
     DriveError(String),
 
+    #[error("Peer sync error: {0}")]
+    PeerError(String),
+
+    #[error("SFTP error: {0}")]
+    SftpError(String),
+
     #[error("Database error: {0}")]
     DatabaseError(#[from] sled::Error),
 
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
+    #[error("Protobuf decode error: {0}")]
+    DecodeError(#[from] prost::DecodeError),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("Rate limited by the sync backend, retry in {retry_after_secs}s")]
+    Throttled { retry_after_secs: u64 },
+
+    #[error("Not enough storage: {needed} bytes needed, {available} available")]
+    InsufficientStorage { needed: u64, available: u64 },
+
+    #[error("Remote sync data corrupted: {0}")]
+    RemoteCorrupted(String),
+
     #[error("Sync conflict: {0}")]
     Conflict(String),
 
+    #[error("A {operation} sync is already in progress ({})", .stage.as_str())]
+    SyncInProgress {
+        operation: &'static str,
+        stage: SyncStage,
+    },
+
+    #[error("Sync server is shutting down")]
+    ShuttingDown,
+
     #[error("Upload incomplete: {uploaded}/{total} bytes")]
     UploadIncomplete { uploaded: u64, total: u64 },
 
@@ -42,6 +71,22 @@ pub enum SyncError {
     Other(#[from] anyhow::Error),
 }
 
+/// `1.5 MB` style sizes for user messages
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
 impl SyncError {
     fn oauth_detail(message: &str) -> Option<String> {
         let raw = message
@@ -116,35 +161,79 @@ impl SyncError {
             SyncError::DriveError(_) => {
                 "Google Drive request failed. Please try again later.".to_string()
             }
+            SyncError::Throttled { retry_after_secs } => format!(
+                "Google Drive is temporarily throttling sync requests. Sync will resume in about {retry_after_secs} seconds."
+            ),
+            SyncError::InsufficientStorage { needed, available } => format!(
+                "Your Google Drive is full: syncing needs {} more but only {} is free. Free up space in Google Drive, then sync again.",
+                format_bytes(*needed),
+                format_bytes(*available)
+            ),
+            SyncError::RemoteCorrupted(_) => {
+                "The remote sync data is corrupted. Roll back to an earlier version or push this device's data again.".to_string()
+            }
+            SyncError::SyncInProgress { stage, .. } => format!(
+                "A sync is already in progress ({}). Try again once it finishes.",
+                stage.as_str()
+            ),
             _ => self.to_string(),
         }
     }
-}
 
-impl IntoResponse for SyncError {
-    fn into_response(self) -> Response {
-        let (status, error_type) = match &self {
+    /// The `error` field of error responses, e.g. `drive_error`
+    pub fn error_type(&self) -> &'static str {
+        self.status_and_type().1
+    }
+
+    fn status_and_type(&self) -> (StatusCode, &'static str) {
+        match self {
             SyncError::NotAuthenticated => (StatusCode::UNAUTHORIZED, "not_authenticated"),
             SyncError::OAuthError(_) => (StatusCode::BAD_REQUEST, "oauth_error"),
             SyncError::DriveError(_) => (StatusCode::BAD_GATEWAY, "drive_error"),
+            SyncError::PeerError(_) => (StatusCode::BAD_GATEWAY, "peer_error"),
+            SyncError::SftpError(_) => (StatusCode::BAD_GATEWAY, "sftp_error"),
+            SyncError::Throttled { .. } => (StatusCode::TOO_MANY_REQUESTS, "throttled"),
+            SyncError::InsufficientStorage { .. } => {
+                (StatusCode::INSUFFICIENT_STORAGE, "insufficient_storage")
+            }
+            SyncError::RemoteCorrupted(_) => (StatusCode::BAD_GATEWAY, "remote_corrupted"),
             SyncError::Conflict(_) => (StatusCode::CONFLICT, "conflict"),
+            SyncError::SyncInProgress { .. } => (StatusCode::CONFLICT, "sync_in_progress"),
+            SyncError::ShuttingDown => (StatusCode::SERVICE_UNAVAILABLE, "shutting_down"),
             SyncError::UploadIncomplete { .. } => {
                 (StatusCode::PARTIAL_CONTENT, "upload_incomplete")
             }
             SyncError::FileNotFound(_) => (StatusCode::NOT_FOUND, "file_not_found"),
             SyncError::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
-        };
+        }
+    }
+}
 
-        if matches!(&self, SyncError::OAuthError(_) | SyncError::DriveError(_)) {
+impl IntoResponse for SyncError {
+    fn into_response(self) -> Response {
+        let (status, error_type) = self.status_and_type();
+
+        if matches!(
+            &self,
+            SyncError::OAuthError(_)
+                | SyncError::DriveError(_)
+                | SyncError::PeerError(_)
+                | SyncError::SftpError(_)
+                | SyncError::RemoteCorrupted(_)
+        ) {
             warn!("Sync request failed [{}]: {}", error_type, self);
         }
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_type,
             "message": self.user_message(),
-        }));
+        });
+        if let SyncError::SyncInProgress { operation, stage } = &self {
+            body["operation"] = json!(operation);
+            body["stage"] = json!(stage);
+        }
 
-        (status, body).into_response()
+        (status, Json(body)).into_response()
     }
 }
@@ -0,0 +1,60 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+// ============================================================================
+// Sync Status Events
+// ============================================================================
+//
+// Each step of a sync is broadcast to the `/sync/events` stream. Nothing is kept:
+// a client only sees events sent while it is connected, and one that falls behind
+// skips the events it missed.
+
+const EVENT_BUFFER: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum SyncEvent {
+    Pulling,
+    Merging,
+    Pushing,
+    #[serde(rename_all = "camelCase")]
+    Conflict {
+        message: String,
+    },
+    /// Another device pushed; the new version was pulled
+    #[serde(rename_all = "camelCase")]
+    RemoteChanged {
+        etag: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Done {
+        sync_timestamp: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Error {
+        error: String,
+        message: String,
+    },
+}
+
+pub struct SyncEvents {
+    sender: broadcast::Sender<SyncEvent>,
+}
+
+impl Default for SyncEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+}
+
+impl SyncEvents {
+    pub fn send(&self, event: SyncEvent) {
+        // Fails only when nobody is listening
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SyncEvent> {
+        self.sender.subscribe()
+    }
+}
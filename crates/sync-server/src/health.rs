@@ -0,0 +1,71 @@
+use crate::backend::peer::PeerBackend;
+use crate::backend::sftp::SftpBackend;
+use crate::lock::RunningSync;
+use crate::state::SyncState;
+use crate::types::SyncBackendType;
+use serde::Serialize;
+
+// ============================================================================
+// Health
+// ============================================================================
+//
+// Whether the configured backend can sync, judged from local state only: stored
+// Google tokens, or a usable peer/SFTP config. Nothing is sent to the backend, so the
+// gateway's `/healthz` stays cheap. With sync turned off the subsystem is healthy.
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHealth {
+    /// `ok`, or `degraded` when the configured backend cannot sync
+    pub status: &'static str,
+    pub backend: SyncBackendType,
+    pub authenticated: bool,
+    /// Google Drive only; the refresh task renews it, so this alone is not an error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token_expired: Option<bool>,
+    pub last_sync: Option<i64>,
+    pub running: Option<RunningSync>,
+}
+
+#[derive(Clone)]
+pub struct HealthProbe {
+    state: SyncState,
+}
+
+impl HealthProbe {
+    pub(crate) fn new(state: SyncState) -> Self {
+        Self { state }
+    }
+
+    pub fn check(&self) -> SyncHealth {
+        let state = &self.state;
+        let config = state.get_sync_config();
+        let mut access_token_expired = None;
+        let authenticated = match config.backend {
+            SyncBackendType::None => false,
+            SyncBackendType::GoogleDrive => {
+                access_token_expired = state
+                    .get_token_expires_at()
+                    .map(|expires_at| expires_at <= chrono::Utc::now().timestamp_millis());
+                state.get_refresh_token().is_some()
+            }
+            SyncBackendType::Peer => PeerBackend::from_config(&config).is_ok(),
+            SyncBackendType::Sftp => SftpBackend::from_config(&config).is_ok(),
+            SyncBackendType::WebDav | SyncBackendType::SyncYomi => false,
+        };
+        let status = if config.backend == SyncBackendType::None || authenticated {
+            "ok"
+        } else {
+            "degraded"
+        };
+
+        SyncHealth {
+            status,
+            backend: config.backend,
+            authenticated,
+            access_token_expired,
+            last_sync: state.get_last_sync(),
+            running: state.sync_lock.current(),
+        }
+    }
+}
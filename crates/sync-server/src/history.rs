@@ -0,0 +1,135 @@
+use crate::error::SyncError;
+use crate::types::{SyncBackendType, SyncPayload};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sled::{Db, Tree};
+use std::io::{Read, Write};
+
+// ============================================================================
+// Snapshot History
+// ============================================================================
+//
+// Every payload pushed from this device is kept locally under an increasing
+// version number, so a bad merge or lost data can be rolled back. Only the last
+// `history_limit` versions are kept.
+
+const TREE_SNAPSHOTS: &str = "sync_history";
+const TREE_PAYLOADS: &str = "sync_history_payloads";
+
+/// A pushed payload version, without the payload itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub version: u64,
+    pub pushed_at: i64,
+    pub etag: String,
+    pub backend: SyncBackendType,
+    pub device_id: String,
+    pub progress_count: usize,
+    pub metadata_count: usize,
+    pub content_count: usize,
+    pub files_count: usize,
+    /// Size of the compressed payload
+    pub size: u64,
+}
+
+#[derive(Clone)]
+pub struct SnapshotHistory {
+    snapshots: Tree,
+    payloads: Tree,
+}
+
+impl SnapshotHistory {
+    pub fn open(db: &Db) -> Result<Self, sled::Error> {
+        Ok(Self {
+            snapshots: db.open_tree(TREE_SNAPSHOTS)?,
+            payloads: db.open_tree(TREE_PAYLOADS)?,
+        })
+    }
+
+    /// Stores `payload` as a new version and drops versions beyond `limit`
+    pub fn record(
+        &self,
+        payload: &SyncPayload,
+        etag: &str,
+        backend: SyncBackendType,
+        limit: usize,
+    ) -> Result<SnapshotInfo, SyncError> {
+        let json_bytes = serde_json::to_vec(payload)?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json_bytes)?;
+        let compressed = encoder.finish()?;
+
+        let version = self.latest_version().map_or(1, |v| v + 1);
+        let info = SnapshotInfo {
+            version,
+            pushed_at: chrono::Utc::now().timestamp_millis(),
+            etag: etag.to_string(),
+            backend,
+            device_id: payload.device_id.clone(),
+            progress_count: payload.ln_progress.len(),
+            metadata_count: payload.ln_metadata.len(),
+            content_count: payload.ln_content.len(),
+            files_count: payload.ln_files.len(),
+            size: compressed.len() as u64,
+        };
+
+        let key = version.to_be_bytes();
+        self.payloads.insert(key, compressed)?;
+        self.snapshots.insert(key, serde_json::to_vec(&info)?)?;
+        self.prune(limit)?;
+        self.snapshots.flush()?;
+        self.payloads.flush()?;
+        Ok(info)
+    }
+
+    /// Kept versions, newest first
+    pub fn list(&self) -> Vec<SnapshotInfo> {
+        self.snapshots
+            .iter()
+            .rev()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+            .collect()
+    }
+
+    pub fn get(&self, version: u64) -> Option<SnapshotInfo> {
+        self.snapshots
+            .get(version.to_be_bytes())
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    /// The payload pushed as `version`, if it is still kept
+    pub fn load(&self, version: u64) -> Result<Option<SyncPayload>, SyncError> {
+        let Some(compressed) = self.payloads.get(version.to_be_bytes())? else {
+            return Ok(None);
+        };
+        let mut decompressed = Vec::new();
+        GzDecoder::new(&compressed[..]).read_to_end(&mut decompressed)?;
+        Ok(Some(serde_json::from_slice(&decompressed)?))
+    }
+
+    fn latest_version(&self) -> Option<u64> {
+        let (key, _) = self.snapshots.last().ok().flatten()?;
+        Some(u64::from_be_bytes(key.as_ref().try_into().ok()?))
+    }
+
+    fn prune(&self, limit: usize) -> Result<(), sled::Error> {
+        let excess = self.snapshots.len().saturating_sub(limit.max(1));
+        let stale: Vec<_> = self
+            .snapshots
+            .iter()
+            .keys()
+            .take(excess)
+            .collect::<Result<_, _>>()?;
+        for key in stale {
+            self.snapshots.remove(&key)?;
+            self.payloads.remove(&key)?;
+        }
+        Ok(())
+    }
+}
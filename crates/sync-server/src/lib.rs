@@ -1,29 +1,84 @@
 use axum::{extract::DefaultBodyLimit, Router};
 use std::path::PathBuf;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::compression::predicate::{DefaultPredicate, NotForContentType, Predicate};
+use tower_http::compression::CompressionLayer;
 
+pub mod archive;
 pub mod backend;
+pub mod compression;
+pub mod conflicts;
+pub mod debounce;
+pub mod diff;
 pub mod error;
+pub mod events;
+pub mod health;
+pub mod history;
+pub mod lock;
 pub mod merge;
+pub mod proto;
+pub mod prune;
+pub mod remote_watch;
 pub mod routes;
+pub mod shutdown;
 pub mod state;
+pub mod stats;
+pub mod token_refresh;
 pub mod types;
 
 pub use error::SyncError;
+pub use shutdown::ShutdownHandle;
 pub use state::SyncState;
 pub use types::*;
 
+const BODY_LIMIT_MB_ENV: &str = "MANATAN_SYNC_BODY_LIMIT_MB";
+/// Large enough for a full LN library with its EPUB files
+const DEFAULT_BODY_LIMIT_MB: usize = 100;
+
+fn body_limit() -> usize {
+    manatan_config::var(BODY_LIMIT_MB_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_BODY_LIMIT_MB)
+        * 1024
+        * 1024
+}
+
 pub fn create_router(data_dir: PathBuf) -> Router {
+    create_router_with_shutdown(data_dir).0
+}
+
+/// Creates the sync Router, along with the handle that finishes a running sync on shutdown
+pub fn create_router_with_shutdown(data_dir: PathBuf) -> (Router, ShutdownHandle) {
+    let (router, handles) = create_router_with_handles(data_dir);
+    (router, handles.shutdown)
+}
+
+/// What an embedding server keeps of the sync server besides its router
+pub struct Handles {
+    pub shutdown: ShutdownHandle,
+    pub health: health::HealthProbe,
+}
+
+pub fn create_router_with_handles(data_dir: PathBuf) -> (Router, Handles) {
     let state = SyncState::new(data_dir);
+    token_refresh::spawn_token_refresh_task(&state);
+    remote_watch::spawn_remote_watch_task(&state);
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    // Archive exports are gzipped already, and the default predicate skips `/events`
+    let compression = CompressionLayer::new().compress_when(
+        DefaultPredicate::new().and(NotForContentType::const_new("application/gzip")),
+    );
 
-    routes::router()
-        .layer(cors)
-        // Allow up to 100MB request bodies for large LN data
-        .layer(DefaultBodyLimit::max(100 * 1024 * 1024))
-        .with_state(state)
+    let handles = Handles {
+        shutdown: ShutdownHandle::new(state.clone()),
+        health: health::HealthProbe::new(state.clone()),
+    };
+    // Peers send their peer token rather than the API key
+    let router = manatan_auth::protect(routes::router())
+        .merge(routes::peer_router())
+        .layer(compression)
+        .layer(manatan_auth::cors::layer())
+        .layer(DefaultBodyLimit::max(body_limit()))
+        .with_state(state);
+    (router, handles)
 }
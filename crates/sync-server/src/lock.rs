@@ -0,0 +1,97 @@
+use crate::error::SyncError;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+// ============================================================================
+// Sync Lock
+// ============================================================================
+//
+// Only one sync writes to the backend at a time. A sync holds the lease while it
+// runs; one started meanwhile (a manual sync while a debounced one is pushing) fails
+// with `SyncInProgress` and the stage the running sync is at. The sync database can
+// only be opened by one process, so the lease lives in memory. Once closed for
+// shutdown, no new sync starts.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncStage {
+    Starting,
+    Pulling,
+    Merging,
+    Pushing,
+}
+
+impl SyncStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncStage::Starting => "starting",
+            SyncStage::Pulling => "pulling",
+            SyncStage::Merging => "merging",
+            SyncStage::Pushing => "pushing",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunningSync {
+    /// What started the sync, e.g. `merge` or `rollback`
+    pub operation: &'static str,
+    pub stage: SyncStage,
+    pub started_at: i64,
+}
+
+#[derive(Default)]
+pub struct SyncLock {
+    running: Mutex<Option<RunningSync>>,
+    closed: AtomicBool,
+}
+
+/// Held for the duration of a sync; dropping it releases the lock
+pub struct SyncLease {
+    lock: Arc<SyncLock>,
+}
+
+impl SyncLock {
+    pub fn try_acquire(self: &Arc<Self>, operation: &'static str) -> Result<SyncLease, SyncError> {
+        let mut running = self.running.lock().expect("lock poisoned");
+        if self.closed.load(Ordering::SeqCst) {
+            return Err(SyncError::ShuttingDown);
+        }
+        if let Some(current) = running.as_ref() {
+            return Err(SyncError::SyncInProgress {
+                operation: current.operation,
+                stage: current.stage,
+            });
+        }
+        *running = Some(RunningSync {
+            operation,
+            stage: SyncStage::Starting,
+            started_at: chrono::Utc::now().timestamp_millis(),
+        });
+        Ok(SyncLease { lock: self.clone() })
+    }
+
+    /// Records the stage of the running sync, if any
+    pub fn set_stage(&self, stage: SyncStage) {
+        if let Some(current) = self.running.lock().expect("lock poisoned").as_mut() {
+            current.stage = stage;
+        }
+    }
+
+    /// Refuses every later `try_acquire`; a running sync keeps its lease
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn current(&self) -> Option<RunningSync> {
+        self.running.lock().expect("lock poisoned").clone()
+    }
+}
+
+impl Drop for SyncLease {
+    fn drop(&mut self) {
+        *self.lock.running.lock().expect("lock poisoned") = None;
+    }
+}
@@ -1,5 +1,5 @@
-use crate::types::{ConflictInfo, LNMetadata, LNProgress, SyncPayload};
-use std::collections::HashMap;
+use crate::types::{ConflictInfo, LNMetadata, LNProgress, SyncBase, SyncPayload, Tombstone};
+use std::collections::{HashMap, HashSet};
 use tracing::debug;
 
 /// Merge two sync payloads, returning the merged result
@@ -29,7 +29,7 @@ pub fn merge_payloads(
     // Merge file manifest
     let merged_manifest = merge_simple_maps(local.file_manifest, remote.file_manifest);
 
-    let merged = SyncPayload {
+    let mut merged = SyncPayload {
         schema_version: SyncPayload::CURRENT_SCHEMA_VERSION,
         device_id: local_device_id.to_string(),
         last_modified: chrono::Utc::now().timestamp_millis(),
@@ -38,7 +38,9 @@ pub fn merge_payloads(
         ln_content: merged_content,
         ln_files: merged_files,
         file_manifest: merged_manifest,
+        tombstones: merge_tombstones(local.tombstones, remote.tombstones),
     };
+    apply_tombstones(&mut merged);
 
     (merged, conflicts)
 }
@@ -181,4 +183,283 @@ fn merge_simple_maps<V: Clone>(
         merged.entry(k).or_insert(v);
     }
     merged
-}
\ No newline at end of file
+}
+
+// ============================================================================
+// Three-way Merge
+// ============================================================================
+//
+// With the base of the last sync each record can be merged on its own: a side that
+// did not touch a record since the base takes the other side's version, including its
+// deletion. Only records changed on both sides need a rule: reading progress keeps the
+// furthest position, metadata the newest edit. Content and files are collections, so
+// their keys are unioned, except keys the base had and one side removed.
+
+/// Merge two sync payloads against the base of the last sync. Without a base this
+/// is `merge_payloads`.
+pub fn merge_with_base(
+    base: Option<&SyncBase>,
+    local: SyncPayload,
+    remote: SyncPayload,
+    local_device_id: &str,
+) -> (SyncPayload, Vec<ConflictInfo>) {
+    let Some(base) = base else {
+        return merge_payloads(local, remote, local_device_id);
+    };
+    let mut conflicts = Vec::new();
+
+    let ln_progress = merge_records(
+        "progress",
+        &base.ln_progress,
+        local.ln_progress,
+        remote.ln_progress,
+        &mut conflicts,
+        |book_id, l, r| {
+            let remote_wins = r.is_further_than(l) || (!l.is_further_than(r) && r.is_newer_than(l));
+            let (chosen, side) = if remote_wins {
+                (r, "remote")
+            } else {
+                (l, "local")
+            };
+            let conflict = ConflictInfo {
+                book_id: book_id.to_string(),
+                field: "progress".to_string(),
+                local_value: format!("{:.1}%", l.total_progress * 100.0),
+                remote_value: format!("{:.1}%", r.total_progress * 100.0),
+                resolution: format!("{side} (further)"),
+            };
+            (chosen.clone(), conflict)
+        },
+    );
+
+    let ln_metadata = merge_records(
+        "metadata",
+        &base.ln_metadata,
+        local.ln_metadata,
+        remote.ln_metadata,
+        &mut conflicts,
+        |book_id, l, r| {
+            let remote_newer = match (l.last_modified, r.last_modified) {
+                (Some(lt), Some(rt)) if lt != rt => rt > lt,
+                _ => r.sync_version.unwrap_or_default() > l.sync_version.unwrap_or_default(),
+            };
+            let (chosen, side) = if remote_newer {
+                (r, "remote")
+            } else {
+                (l, "local")
+            };
+            let conflict = ConflictInfo {
+                book_id: book_id.to_string(),
+                field: "metadata".to_string(),
+                local_value: l.title.clone(),
+                remote_value: r.title.clone(),
+                resolution: format!("{side} (newer)"),
+            };
+            (chosen.clone(), conflict)
+        },
+    );
+
+    let file_manifest = merge_records(
+        "file",
+        &base.file_manifest,
+        local.file_manifest,
+        remote.file_manifest,
+        &mut conflicts,
+        |book_id, l, r| {
+            let remote_newer = r.last_modified > l.last_modified;
+            let (chosen, side) = if remote_newer {
+                (r, "remote")
+            } else {
+                (l, "local")
+            };
+            let conflict = ConflictInfo {
+                book_id: book_id.to_string(),
+                field: "file".to_string(),
+                local_value: l.file_hash.clone(),
+                remote_value: r.file_hash.clone(),
+                resolution: format!("{side} (newer)"),
+            };
+            (chosen.clone(), conflict)
+        },
+    );
+
+    let mut merged = SyncPayload {
+        schema_version: SyncPayload::CURRENT_SCHEMA_VERSION,
+        device_id: local_device_id.to_string(),
+        last_modified: chrono::Utc::now().timestamp_millis(),
+        ln_progress,
+        ln_metadata,
+        ln_content: merge_collection(&base.ln_content, local.ln_content, remote.ln_content),
+        ln_files: merge_collection(&base.ln_files, local.ln_files, remote.ln_files),
+        file_manifest,
+        tombstones: merge_tombstones(local.tombstones, remote.tombstones),
+    };
+    apply_tombstones(&mut merged);
+
+    (merged, conflicts)
+}
+
+/// Merges one record map; `resolve` picks the version when both sides changed a record
+fn merge_records<V, F>(
+    kind: &str,
+    base: &HashMap<String, V>,
+    mut local: HashMap<String, V>,
+    mut remote: HashMap<String, V>,
+    conflicts: &mut Vec<ConflictInfo>,
+    resolve: F,
+) -> HashMap<String, V>
+where
+    V: Clone + PartialEq,
+    F: Fn(&str, &V, &V) -> (V, ConflictInfo),
+{
+    let all_keys: HashSet<String> = local.keys().chain(remote.keys()).cloned().collect();
+    let mut merged = HashMap::new();
+
+    for key in all_keys {
+        let base_value = base.get(&key);
+        let chosen = match (local.remove(&key), remote.remove(&key)) {
+            (Some(l), Some(r)) => {
+                if l == r || base_value == Some(&r) {
+                    Some(l)
+                } else if base_value == Some(&l) {
+                    Some(r)
+                } else {
+                    let (chosen, conflict) = resolve(&key, &l, &r);
+                    debug!(
+                        "{} for {}: changed on both sides, {}",
+                        kind, key, conflict.resolution
+                    );
+                    conflicts.push(conflict);
+                    Some(chosen)
+                }
+            }
+            (Some(kept), None) | (None, Some(kept)) => match base_value {
+                // Deleted on the other side and untouched here
+                Some(b) if *b == kept => {
+                    debug!("{} for {}: deleted on one side", kind, key);
+                    None
+                }
+                Some(_) => {
+                    debug!("{} for {}: edited after a deletion, keeping it", kind, key);
+                    conflicts.push(ConflictInfo {
+                        book_id: key.clone(),
+                        field: kind.to_string(),
+                        local_value: String::new(),
+                        remote_value: String::new(),
+                        resolution: "kept (edited after deletion)".to_string(),
+                    });
+                    Some(kept)
+                }
+                None => Some(kept),
+            },
+            (None, None) => None,
+        };
+
+        if let Some(value) = chosen {
+            merged.insert(key, value);
+        }
+    }
+
+    merged
+}
+
+/// Unions two collections, dropping keys the base had and either side removed
+fn merge_collection<V>(
+    base: &HashSet<String>,
+    local: HashMap<String, V>,
+    remote: HashMap<String, V>,
+) -> HashMap<String, V> {
+    let local_keys: HashSet<String> = local.keys().cloned().collect();
+    let remote_keys: HashSet<String> = remote.keys().cloned().collect();
+    let deleted = |key: &String| {
+        base.contains(key) && !(local_keys.contains(key) && remote_keys.contains(key))
+    };
+
+    let mut merged: HashMap<String, V> = remote
+        .into_iter()
+        .filter(|(key, _)| !deleted(key))
+        .collect();
+    for (key, value) in local {
+        if !deleted(&key) {
+            merged.insert(key, value);
+        }
+    }
+    merged
+}
+
+// ============================================================================
+// Tombstones
+// ============================================================================
+//
+// A deleted book leaves a tombstone in the payload. Both sides' tombstones are kept,
+// and each one removes the book's records unless they changed after the deletion,
+// in which case the book was edited or re-added and the tombstone is dropped.
+// Content and files carry no timestamps, so they only survive with another record.
+
+fn merge_tombstones(
+    local: HashMap<String, Tombstone>,
+    remote: HashMap<String, Tombstone>,
+) -> HashMap<String, Tombstone> {
+    let mut merged = remote;
+    for (book_id, tombstone) in local {
+        match merged.get(&book_id) {
+            Some(existing) if existing.deleted_at >= tombstone.deleted_at => {}
+            _ => {
+                merged.insert(book_id, tombstone);
+            }
+        }
+    }
+    merged
+}
+
+/// Removes the records of deleted books, or the tombstones of books changed since
+fn apply_tombstones(payload: &mut SyncPayload) {
+    let tombstones = std::mem::take(&mut payload.tombstones);
+    for (book_id, tombstone) in tombstones {
+        let changed_at = [
+            payload
+                .ln_progress
+                .get(&book_id)
+                .and_then(|p| p.last_modified.or(p.last_read)),
+            payload
+                .ln_metadata
+                .get(&book_id)
+                .map(|m| m.last_modified.unwrap_or(m.added_at)),
+            payload
+                .file_manifest
+                .values()
+                .filter(|reference| reference.book_id == book_id)
+                .map(|reference| reference.last_modified)
+                .max(),
+        ]
+        .into_iter()
+        .flatten()
+        .max();
+
+        if changed_at.is_some_and(|changed_at| changed_at > tombstone.deleted_at) {
+            debug!("{}: changed after its deletion, keeping it", book_id);
+            continue;
+        }
+
+        debug!("{}: deleted at {}", book_id, tombstone.deleted_at);
+        payload.ln_progress.remove(&book_id);
+        payload.ln_metadata.remove(&book_id);
+        payload.ln_content.remove(&book_id);
+        payload.ln_files.remove(&book_id);
+        payload
+            .file_manifest
+            .retain(|_, reference| reference.book_id != book_id);
+        payload.tombstones.insert(book_id, tombstone);
+    }
+}
+
+/// Drops tombstones older than `retention_days`. Returns how many were dropped.
+pub fn collect_tombstones(payload: &mut SyncPayload, retention_days: u32) -> usize {
+    let cutoff =
+        chrono::Utc::now().timestamp_millis() - i64::from(retention_days) * 24 * 60 * 60 * 1000;
+    let before = payload.tombstones.len();
+    payload
+        .tombstones
+        .retain(|_, tombstone| tombstone.deleted_at >= cutoff);
+    before - payload.tombstones.len()
+}
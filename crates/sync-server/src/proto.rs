@@ -0,0 +1,445 @@
+use crate::error::SyncError;
+use crate::types::{
+    BlockIndexMap, BookStats, FileReference, FileType, LNMetadata, LNParsedBook, LNProgress,
+    SyncPayload, TocItem, Tombstone,
+};
+use prost::Message;
+use std::collections::HashMap;
+
+// ============================================================================
+// Protobuf Encoding
+// ============================================================================
+//
+// The sync file holds the payload as the protobuf messages below. Tags are never
+// reused: new fields get new tags, so older versions skip fields they don't know.
+// Files written before protobuf hold JSON, which always starts with `{`. A protobuf
+// payload can't: that byte would be the key of field 15, which is unused.
+
+#[derive(Clone, PartialEq, Message)]
+pub struct PayloadMessage {
+    #[prost(uint32, tag = "1")]
+    pub schema_version: u32,
+    #[prost(string, tag = "2")]
+    pub device_id: String,
+    #[prost(int64, tag = "3")]
+    pub last_modified: i64,
+    #[prost(map = "string, message", tag = "4")]
+    pub ln_progress: HashMap<String, ProgressMessage>,
+    #[prost(map = "string, message", tag = "5")]
+    pub ln_metadata: HashMap<String, MetadataMessage>,
+    #[prost(map = "string, message", tag = "6")]
+    pub ln_content: HashMap<String, ParsedBookMessage>,
+    #[prost(map = "string, string", tag = "7")]
+    pub ln_files: HashMap<String, String>,
+    #[prost(map = "string, message", tag = "8")]
+    pub file_manifest: HashMap<String, FileReferenceMessage>,
+    #[prost(map = "string, message", tag = "9")]
+    pub tombstones: HashMap<String, TombstoneMessage>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ProgressMessage {
+    #[prost(int32, tag = "1")]
+    pub chapter_index: i32,
+    #[prost(int32, optional, tag = "2")]
+    pub page_number: Option<i32>,
+    #[prost(int32, tag = "3")]
+    pub chapter_char_offset: i32,
+    #[prost(int32, tag = "4")]
+    pub total_chars_read: i32,
+    #[prost(string, tag = "5")]
+    pub sentence_text: String,
+    #[prost(double, tag = "6")]
+    pub chapter_progress: f64,
+    #[prost(double, tag = "7")]
+    pub total_progress: f64,
+    #[prost(string, optional, tag = "8")]
+    pub block_id: Option<String>,
+    #[prost(int32, optional, tag = "9")]
+    pub block_local_offset: Option<i32>,
+    #[prost(string, optional, tag = "10")]
+    pub context_snippet: Option<String>,
+    #[prost(int64, optional, tag = "11")]
+    pub last_read: Option<i64>,
+    #[prost(int64, optional, tag = "12")]
+    pub last_modified: Option<i64>,
+    #[prost(int32, optional, tag = "13")]
+    pub sync_version: Option<i32>,
+    #[prost(string, optional, tag = "14")]
+    pub device_id: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MetadataMessage {
+    #[prost(string, tag = "1")]
+    pub id: String,
+    #[prost(string, tag = "2")]
+    pub title: String,
+    #[prost(string, tag = "3")]
+    pub author: String,
+    #[prost(string, optional, tag = "4")]
+    pub cover: Option<String>,
+    #[prost(int64, tag = "5")]
+    pub added_at: i64,
+    #[prost(bool, optional, tag = "6")]
+    pub is_processing: Option<bool>,
+    #[prost(bool, optional, tag = "7")]
+    pub is_error: Option<bool>,
+    #[prost(string, optional, tag = "8")]
+    pub error_msg: Option<String>,
+    #[prost(message, optional, tag = "9")]
+    pub stats: Option<BookStatsMessage>,
+    #[prost(int32, tag = "10")]
+    pub chapter_count: i32,
+    #[prost(message, repeated, tag = "11")]
+    pub toc: Vec<TocItemMessage>,
+    #[prost(bool, optional, tag = "12")]
+    pub has_progress: Option<bool>,
+    #[prost(int64, optional, tag = "13")]
+    pub last_modified: Option<i64>,
+    #[prost(int32, optional, tag = "14")]
+    pub sync_version: Option<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BookStatsMessage {
+    #[prost(int32, repeated, tag = "1")]
+    pub chapter_lengths: Vec<i32>,
+    #[prost(int32, tag = "2")]
+    pub total_length: i32,
+    #[prost(message, repeated, tag = "3")]
+    pub block_maps: Vec<BlockIndexMapMessage>,
+    /// Tells an empty `block_maps` from a missing one
+    #[prost(bool, tag = "4")]
+    pub has_block_maps: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockIndexMapMessage {
+    #[prost(string, tag = "1")]
+    pub block_id: String,
+    #[prost(int32, tag = "2")]
+    pub start_offset: i32,
+    #[prost(int32, tag = "3")]
+    pub end_offset: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TocItemMessage {
+    #[prost(string, tag = "1")]
+    pub label: String,
+    #[prost(string, tag = "2")]
+    pub href: String,
+    #[prost(int32, tag = "3")]
+    pub chapter_index: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ParsedBookMessage {
+    #[prost(string, repeated, tag = "1")]
+    pub chapters: Vec<String>,
+    #[prost(map = "string, string", tag = "2")]
+    pub image_blobs: HashMap<String, String>,
+    #[prost(string, repeated, tag = "3")]
+    pub chapter_filenames: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FileReferenceMessage {
+    #[prost(string, tag = "1")]
+    pub book_id: String,
+    #[prost(enumeration = "FileTypeMessage", tag = "2")]
+    pub file_type: i32,
+    #[prost(string, tag = "3")]
+    pub file_hash: String,
+    #[prost(uint64, tag = "4")]
+    pub file_size: u64,
+    #[prost(int64, tag = "5")]
+    pub last_modified: i64,
+    #[prost(string, optional, tag = "6")]
+    pub drive_file_id: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum FileTypeMessage {
+    Epub = 0,
+    Content = 1,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TombstoneMessage {
+    #[prost(int64, tag = "1")]
+    pub deleted_at: i64,
+    #[prost(string, optional, tag = "2")]
+    pub device_id: Option<String>,
+}
+
+/// Serializes a payload for the sync file
+pub fn encode_payload(payload: &SyncPayload) -> Vec<u8> {
+    PayloadMessage::from(payload).encode_to_vec()
+}
+
+/// Reads a sync file payload, in protobuf or the older JSON
+pub fn decode_payload(bytes: &[u8]) -> Result<SyncPayload, SyncError> {
+    if bytes.first() == Some(&b'{') {
+        return Ok(serde_json::from_slice(bytes)?);
+    }
+    Ok(PayloadMessage::decode(bytes)?.into())
+}
+
+fn convert_map<A, B>(map: &HashMap<String, A>) -> HashMap<String, B>
+where
+    for<'a> &'a A: Into<B>,
+{
+    map.iter()
+        .map(|(key, value)| (key.clone(), value.into()))
+        .collect()
+}
+
+impl From<&SyncPayload> for PayloadMessage {
+    fn from(payload: &SyncPayload) -> Self {
+        Self {
+            schema_version: payload.schema_version,
+            device_id: payload.device_id.clone(),
+            last_modified: payload.last_modified,
+            ln_progress: convert_map(&payload.ln_progress),
+            ln_metadata: convert_map(&payload.ln_metadata),
+            ln_content: convert_map(&payload.ln_content),
+            ln_files: payload.ln_files.clone(),
+            file_manifest: convert_map(&payload.file_manifest),
+            tombstones: convert_map(&payload.tombstones),
+        }
+    }
+}
+
+impl From<PayloadMessage> for SyncPayload {
+    fn from(message: PayloadMessage) -> Self {
+        Self {
+            schema_version: message.schema_version,
+            device_id: message.device_id,
+            last_modified: message.last_modified,
+            ln_progress: convert_map(&message.ln_progress),
+            ln_metadata: convert_map(&message.ln_metadata),
+            ln_content: convert_map(&message.ln_content),
+            ln_files: message.ln_files,
+            file_manifest: convert_map(&message.file_manifest),
+            tombstones: convert_map(&message.tombstones),
+        }
+    }
+}
+
+impl From<&LNProgress> for ProgressMessage {
+    fn from(progress: &LNProgress) -> Self {
+        Self {
+            chapter_index: progress.chapter_index,
+            page_number: progress.page_number,
+            chapter_char_offset: progress.chapter_char_offset,
+            total_chars_read: progress.total_chars_read,
+            sentence_text: progress.sentence_text.clone(),
+            chapter_progress: progress.chapter_progress,
+            total_progress: progress.total_progress,
+            block_id: progress.block_id.clone(),
+            block_local_offset: progress.block_local_offset,
+            context_snippet: progress.context_snippet.clone(),
+            last_read: progress.last_read,
+            last_modified: progress.last_modified,
+            sync_version: progress.sync_version,
+            device_id: progress.device_id.clone(),
+        }
+    }
+}
+
+impl From<&ProgressMessage> for LNProgress {
+    fn from(message: &ProgressMessage) -> Self {
+        Self {
+            chapter_index: message.chapter_index,
+            page_number: message.page_number,
+            chapter_char_offset: message.chapter_char_offset,
+            total_chars_read: message.total_chars_read,
+            sentence_text: message.sentence_text.clone(),
+            chapter_progress: message.chapter_progress,
+            total_progress: message.total_progress,
+            block_id: message.block_id.clone(),
+            block_local_offset: message.block_local_offset,
+            context_snippet: message.context_snippet.clone(),
+            last_read: message.last_read,
+            last_modified: message.last_modified,
+            sync_version: message.sync_version,
+            device_id: message.device_id.clone(),
+        }
+    }
+}
+
+impl From<&LNMetadata> for MetadataMessage {
+    fn from(metadata: &LNMetadata) -> Self {
+        Self {
+            id: metadata.id.clone(),
+            title: metadata.title.clone(),
+            author: metadata.author.clone(),
+            cover: metadata.cover.clone(),
+            added_at: metadata.added_at,
+            is_processing: metadata.is_processing,
+            is_error: metadata.is_error,
+            error_msg: metadata.error_msg.clone(),
+            stats: Some((&metadata.stats).into()),
+            chapter_count: metadata.chapter_count,
+            toc: metadata.toc.iter().map(Into::into).collect(),
+            has_progress: metadata.has_progress,
+            last_modified: metadata.last_modified,
+            sync_version: metadata.sync_version,
+        }
+    }
+}
+
+impl From<&MetadataMessage> for LNMetadata {
+    fn from(message: &MetadataMessage) -> Self {
+        Self {
+            id: message.id.clone(),
+            title: message.title.clone(),
+            author: message.author.clone(),
+            cover: message.cover.clone(),
+            added_at: message.added_at,
+            is_processing: message.is_processing,
+            is_error: message.is_error,
+            error_msg: message.error_msg.clone(),
+            stats: message.stats.as_ref().map(Into::into).unwrap_or_default(),
+            chapter_count: message.chapter_count,
+            toc: message.toc.iter().map(Into::into).collect(),
+            has_progress: message.has_progress,
+            last_modified: message.last_modified,
+            sync_version: message.sync_version,
+        }
+    }
+}
+
+impl From<&BookStats> for BookStatsMessage {
+    fn from(stats: &BookStats) -> Self {
+        Self {
+            chapter_lengths: stats.chapter_lengths.clone(),
+            total_length: stats.total_length,
+            block_maps: stats
+                .block_maps
+                .iter()
+                .flatten()
+                .map(|map| BlockIndexMapMessage {
+                    block_id: map.block_id.clone(),
+                    start_offset: map.start_offset,
+                    end_offset: map.end_offset,
+                })
+                .collect(),
+            has_block_maps: stats.block_maps.is_some(),
+        }
+    }
+}
+
+impl From<&BookStatsMessage> for BookStats {
+    fn from(message: &BookStatsMessage) -> Self {
+        let block_maps = message
+            .block_maps
+            .iter()
+            .map(|map| BlockIndexMap {
+                block_id: map.block_id.clone(),
+                start_offset: map.start_offset,
+                end_offset: map.end_offset,
+            })
+            .collect();
+        Self {
+            chapter_lengths: message.chapter_lengths.clone(),
+            total_length: message.total_length,
+            block_maps: message.has_block_maps.then_some(block_maps),
+        }
+    }
+}
+
+impl From<&TocItem> for TocItemMessage {
+    fn from(item: &TocItem) -> Self {
+        Self {
+            label: item.label.clone(),
+            href: item.href.clone(),
+            chapter_index: item.chapter_index,
+        }
+    }
+}
+
+impl From<&TocItemMessage> for TocItem {
+    fn from(message: &TocItemMessage) -> Self {
+        Self {
+            label: message.label.clone(),
+            href: message.href.clone(),
+            chapter_index: message.chapter_index,
+        }
+    }
+}
+
+impl From<&LNParsedBook> for ParsedBookMessage {
+    fn from(book: &LNParsedBook) -> Self {
+        Self {
+            chapters: book.chapters.clone(),
+            image_blobs: book.image_blobs.clone(),
+            chapter_filenames: book.chapter_filenames.clone(),
+        }
+    }
+}
+
+impl From<&ParsedBookMessage> for LNParsedBook {
+    fn from(message: &ParsedBookMessage) -> Self {
+        Self {
+            chapters: message.chapters.clone(),
+            image_blobs: message.image_blobs.clone(),
+            chapter_filenames: message.chapter_filenames.clone(),
+        }
+    }
+}
+
+impl From<&FileReference> for FileReferenceMessage {
+    fn from(reference: &FileReference) -> Self {
+        let file_type = match reference.file_type {
+            FileType::Epub => FileTypeMessage::Epub,
+            FileType::Content => FileTypeMessage::Content,
+        };
+        Self {
+            book_id: reference.book_id.clone(),
+            file_type: file_type.into(),
+            file_hash: reference.file_hash.clone(),
+            file_size: reference.file_size,
+            last_modified: reference.last_modified,
+            drive_file_id: reference.drive_file_id.clone(),
+        }
+    }
+}
+
+impl From<&FileReferenceMessage> for FileReference {
+    fn from(message: &FileReferenceMessage) -> Self {
+        let file_type = match message.file_type() {
+            FileTypeMessage::Epub => FileType::Epub,
+            FileTypeMessage::Content => FileType::Content,
+        };
+        Self {
+            book_id: message.book_id.clone(),
+            file_type,
+            file_hash: message.file_hash.clone(),
+            file_size: message.file_size,
+            last_modified: message.last_modified,
+            drive_file_id: message.drive_file_id.clone(),
+        }
+    }
+}
+
+impl From<&Tombstone> for TombstoneMessage {
+    fn from(tombstone: &Tombstone) -> Self {
+        Self {
+            deleted_at: tombstone.deleted_at,
+            device_id: tombstone.device_id.clone(),
+        }
+    }
+}
+
+impl From<&TombstoneMessage> for Tombstone {
+    fn from(message: &TombstoneMessage) -> Self {
+        Self {
+            deleted_at: message.deleted_at,
+            device_id: message.device_id.clone(),
+        }
+    }
+}
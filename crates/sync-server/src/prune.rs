@@ -0,0 +1,116 @@
+use crate::proto;
+use crate::types::{LNParsedBook, SyncConfig, SyncPayload};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use tracing::info;
+
+// ============================================================================
+// Payload Pruning
+// ============================================================================
+//
+// Parsed content and EPUB files make up most of a payload. Before a push, those of
+// books not read for `prune_after_days` are left out, and if the payload is still
+// over `max_payload_mb`, files and then content of the least recently read books go
+// too. Progress and metadata are always pushed, so a device with a small quota keeps
+// syncing what matters. Pruning only affects what is pushed: the merged payload
+// returned to the app keeps everything.
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// `payload` without what the pruning settings leave out
+pub fn prune_for_push<'a>(payload: &'a SyncPayload, config: &SyncConfig) -> Cow<'a, SyncPayload> {
+    let cutoff = (config.prune_after_days > 0).then(|| {
+        chrono::Utc::now().timestamp_millis() - i64::from(config.prune_after_days) * DAY_MS
+    });
+    let max_bytes = config.max_payload_mb as usize * 1024 * 1024;
+    if cutoff.is_none() && max_bytes == 0 {
+        return Cow::Borrowed(payload);
+    }
+
+    // Books with content or files, least recently read first
+    let mut books: Vec<(i64, &String)> = payload
+        .ln_content
+        .keys()
+        .chain(payload.ln_files.keys())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(|id| (last_activity(payload, id), id))
+        .collect();
+    books.sort();
+
+    let mut size = if max_bytes > 0 {
+        proto::encode_payload(payload).len()
+    } else {
+        0
+    };
+    let is_stale = |last_active: i64| cutoff.is_some_and(|cutoff| last_active < cutoff);
+
+    let mut dropped_files = HashSet::new();
+    for &(last_active, id) in &books {
+        let Some(file) = payload.ln_files.get(id) else {
+            continue;
+        };
+        if is_stale(last_active) || size > max_bytes {
+            size = size.saturating_sub(file.len());
+            dropped_files.insert(id);
+        }
+    }
+    let mut dropped_content = HashSet::new();
+    for &(last_active, id) in &books {
+        let Some(book) = payload.ln_content.get(id) else {
+            continue;
+        };
+        if is_stale(last_active) || size > max_bytes {
+            size = size.saturating_sub(content_size(book));
+            dropped_content.insert(id);
+        }
+    }
+
+    if dropped_files.is_empty() && dropped_content.is_empty() {
+        return Cow::Borrowed(payload);
+    }
+    info!(
+        "[PRUNE] Leaving out content of {} books and {} files from the push",
+        dropped_content.len(),
+        dropped_files.len()
+    );
+
+    Cow::Owned(SyncPayload {
+        schema_version: payload.schema_version,
+        device_id: payload.device_id.clone(),
+        last_modified: payload.last_modified,
+        ln_progress: payload.ln_progress.clone(),
+        ln_metadata: payload.ln_metadata.clone(),
+        ln_content: payload
+            .ln_content
+            .iter()
+            .filter(|(id, _)| !dropped_content.contains(id))
+            .map(|(id, book)| (id.clone(), book.clone()))
+            .collect(),
+        ln_files: payload
+            .ln_files
+            .iter()
+            .filter(|(id, _)| !dropped_files.contains(id))
+            .map(|(id, file)| (id.clone(), file.clone()))
+            .collect(),
+        file_manifest: payload.file_manifest.clone(),
+        tombstones: payload.tombstones.clone(),
+    })
+}
+
+/// When the book was last read, or else last changed or added
+fn last_activity(payload: &SyncPayload, book_id: &str) -> i64 {
+    let progress = payload.ln_progress.get(book_id);
+    let metadata = payload.ln_metadata.get(book_id);
+    progress
+        .and_then(|progress| progress.last_read.or(progress.last_modified))
+        .or_else(|| metadata.and_then(|metadata| metadata.last_modified))
+        .or_else(|| metadata.map(|metadata| metadata.added_at))
+        .unwrap_or(0)
+}
+
+fn content_size(book: &LNParsedBook) -> usize {
+    let chapters: usize = book.chapters.iter().map(String::len).sum();
+    let images: usize = book.image_blobs.values().map(String::len).sum();
+    chapters + images
+}
@@ -0,0 +1,64 @@
+use crate::events::SyncEvent;
+use crate::routes::sync::pull_remote;
+use crate::state::SyncState;
+use crate::types::SyncBackendType;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+// ============================================================================
+// Remote Change Watch
+// ============================================================================
+//
+// Every `remote_poll_secs` the active backend is asked whether another device
+// pushed, which Google Drive answers from its changes feed without downloading the
+// sync file. A new version is pulled right away and announced on `/sync/events`, so
+// the app can merge it instead of waiting for its next scheduled sync.
+
+/// How often a disabled watch checks whether it was turned on
+const DISABLED_RECHECK: Duration = Duration::from_secs(60);
+
+pub fn spawn_remote_watch_task(state: &SyncState) {
+    let state = state.clone();
+    manatan_tasks::spawn("sync.remote-watch", move || {
+        let state = state.clone();
+        async move {
+            loop {
+                let config = state.get_sync_config();
+                if config.remote_poll_secs == 0 {
+                    tokio::time::sleep(DISABLED_RECHECK).await;
+                    continue;
+                }
+                tokio::time::sleep(Duration::from_secs(config.remote_poll_secs)).await;
+
+                // A running sync sees the remote anyway
+                if config.backend == SyncBackendType::None || state.sync_lock.current().is_some() {
+                    continue;
+                }
+                let changed = async {
+                    state
+                        .backends
+                        .active(&state)
+                        .await?
+                        .poll_remote_changes()
+                        .await
+                }
+                .await;
+                match changed {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => {
+                        debug!("[WATCH] Failed to check for remote changes: {}", e);
+                        continue;
+                    }
+                }
+
+                info!("[WATCH] Another device pushed, pulling...");
+                match pull_remote(&state).await {
+                    Ok(Some((_, etag))) => state.events.send(SyncEvent::RemoteChanged { etag }),
+                    Ok(None) => {}
+                    Err(e) => warn!("[WATCH] Failed to pull remote changes: {}", e),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,130 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+    routing::{get, post},
+};
+use serde::Deserialize;
+use tracing::info;
+
+use super::sync::{pull_remote, push_remote, record_snapshot, run_merge};
+use crate::archive::SyncArchive;
+use crate::backend::PushResult;
+use crate::error::SyncError;
+use crate::state::SyncState;
+use crate::types::{MergeResponse, SyncBase};
+
+pub fn router() -> Router<SyncState> {
+    Router::new()
+        .route("/export", get(export_archive))
+        .route("/import", post(import_archive))
+}
+
+/// Downloads the remote payload as an archive. Without a backend, or with no remote
+/// data yet, the last payload pushed from this device is exported instead.
+async fn export_archive(State(state): State<SyncState>) -> Result<impl IntoResponse, SyncError> {
+    let config = state.get_sync_config();
+    let remote = match pull_remote(&state).await {
+        Ok(remote) => remote,
+        Err(SyncError::NotAuthenticated) => None,
+        Err(e) => return Err(e),
+    };
+
+    let archive =
+        match remote {
+            Some((payload, etag)) => {
+                SyncArchive::new(payload, state.get_device_id(), config.backend, etag)
+            }
+            None => {
+                let snapshot =
+                    state.history.list().into_iter().next().ok_or_else(|| {
+                        SyncError::FileNotFound("No sync data to export".to_string())
+                    })?;
+                let payload = state
+                    .history
+                    .load(snapshot.version)?
+                    .ok_or_else(|| SyncError::FileNotFound("No sync data to export".to_string()))?;
+                SyncArchive::new(
+                    payload,
+                    state.get_device_id(),
+                    snapshot.backend,
+                    String::new(),
+                )
+            }
+        };
+
+    let bytes = archive.to_bytes()?;
+    info!(
+        "[ARCHIVE] Exported {} progress, {} metadata entries ({} bytes)",
+        archive.payload.ln_progress.len(),
+        archive.payload.ln_metadata.len(),
+        bytes.len()
+    );
+
+    let filename = format!(
+        "manatan-sync-{}.json.gz",
+        chrono::Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    /// Merge the archive into the remote data instead of replacing it
+    #[serde(default)]
+    pub merge: bool,
+}
+
+/// Restores an archive to the active backend
+async fn import_archive(
+    State(state): State<SyncState>,
+    Query(query): Query<ImportQuery>,
+    body: Bytes,
+) -> Result<Json<MergeResponse>, SyncError> {
+    let archive = SyncArchive::from_bytes(&body)?;
+    info!(
+        "[ARCHIVE] Importing archive from {} exported at {} (merge: {})",
+        archive.device_id, archive.exported_at, query.merge
+    );
+
+    if query.merge {
+        return Ok(Json(run_merge(&state, archive.payload).await?));
+    }
+
+    let _lease = state.sync_lock.try_acquire("import")?;
+    let payload = archive.payload;
+    let remote_etag = pull_remote(&state).await?.map(|(_, etag)| etag);
+    match push_remote(&state, &payload, remote_etag.as_deref()).await? {
+        PushResult::Success { etag } => {
+            let now = chrono::Utc::now().timestamp_millis();
+            state.set_last_sync(now)?;
+            state.set_last_etag(&etag)?;
+            state.set_sync_base(&SyncBase::from_payload(&payload))?;
+            record_snapshot(&state, state.get_sync_config().backend, &payload, &etag);
+            info!("[ARCHIVE] Imported, new etag: {}", etag);
+
+            Ok(Json(MergeResponse {
+                payload,
+                sync_timestamp: now,
+                files_to_upload: vec![],
+                files_to_download: vec![],
+                conflicts: vec![],
+            }))
+        }
+        PushResult::Conflict { remote_etag } => Err(SyncError::Conflict(format!(
+            "The remote changed during the import (etag {remote_etag}), try again"
+        ))),
+    }
+}
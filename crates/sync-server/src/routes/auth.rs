@@ -7,10 +7,13 @@ use axum::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::backend::google_drive::GoogleDriveBackend;
-use crate::backend::{AuthFlow, SyncBackend};
+use crate::backend::google_drive::{GoogleDriveBackend, TokenPath};
+use crate::backend::peer::PeerBackend;
+use crate::backend::sftp::SftpBackend;
+use crate::backend::{AuthFlow, DeviceAuthFlow, DeviceAuthStatus, RemoteMetadata, SyncBackend};
 use crate::error::SyncError;
 use crate::state::SyncState;
+use crate::types::SyncBackendType;
 
 pub fn router() -> Router<SyncState> {
     Router::new()
@@ -18,6 +21,8 @@ pub fn router() -> Router<SyncState> {
         .route("/google/start", post(google_start))
         .route("/google/callback", get(google_callback))
         .route("/google/callback", post(google_callback_post))
+        .route("/google/device/start", post(google_device_start))
+        .route("/google/device/poll", post(google_device_poll))
         .route("/disconnect", get(disconnect).post(disconnect))
 }
 
@@ -29,14 +34,44 @@ pub struct AuthStatusResponse {
     pub email: Option<String>,
     pub last_sync: Option<i64>,
     pub device_id: String,
+    /// The remote sync file, once something was pushed
+    pub remote: Option<RemoteMetadata>,
+    /// Whether Google Drive tokens last came through the OAuth broker
+    pub token_path: Option<TokenPath>,
 }
 
 async fn auth_status(State(state): State<SyncState>) -> Result<impl IntoResponse, SyncError> {
+    // Peer and SFTP sync have no OAuth session; they are connected once configured
+    let config = state.get_sync_config();
+    let configured = match config.backend {
+        SyncBackendType::Peer => Some(("peer", PeerBackend::from_config(&config).is_ok(), None)),
+        SyncBackendType::Sftp => Some((
+            "sftp",
+            SftpBackend::from_config(&config).is_ok(),
+            Some(format!("{}@{}", config.sftp_username, config.sftp_host)),
+        )),
+        _ => None,
+    };
+    if let Some((backend, connected, email)) = configured {
+        return Ok((
+            HeaderMap::new(),
+            Json(AuthStatusResponse {
+                connected,
+                backend: backend.to_string(),
+                email: email.filter(|_| connected),
+                last_sync: state.get_last_sync(),
+                device_id: state.get_device_id(),
+                remote: None,
+                token_path: None,
+            }),
+        ));
+    }
+
     // 1. Get a WRITE lock so we can modify the backend state and refresh tokens
-    let mut gdrive = state.google_drive.write().await;
+    let mut backends = state.backends.write().await;
 
     // 2. If backend is not initialized (e.g., server restarted), try to initialize it from DB
-    if gdrive.is_none() {
+    if !backends.contains_key(&SyncBackendType::GoogleDrive) {
         let access_token = state.get_access_token();
         let refresh_token = state.get_refresh_token();
         
@@ -45,14 +80,15 @@ async fn auth_status(State(state): State<SyncState>) -> Result<impl IntoResponse
             let mut backend = GoogleDriveBackend::new(state.clone());
             // If initialization fails, we just don't set the backend
             if let Ok(_) = backend.initialize().await {
-                *gdrive = Some(backend);
+                backends.insert(SyncBackendType::GoogleDrive, Box::new(backend));
             }
         }
     }
 
     // 3. Check authentication status and get email
     let mut did_refresh = false;
-    let (connected, email) = if let Some(backend) = gdrive.as_mut() {
+    let drive = backends.get_mut(&SyncBackendType::GoogleDrive);
+    let (connected, email, remote) = if let Some(backend) = drive {
         let is_auth = backend.is_authenticated().await;
         
         let mut user_email = if is_auth {
@@ -74,21 +110,26 @@ async fn auth_status(State(state): State<SyncState>) -> Result<impl IntoResponse
             }
         }
 
-        (is_auth, user_email)
+        let remote = if is_auth {
+            backend.remote_metadata().await.ok().flatten()
+        } else {
+            None
+        };
+        (is_auth, user_email, remote)
     } else {
         // Fallback: Check if tokens exist in DB even if backend isn't ready
         let has_tokens = state.get_access_token().is_some() && state.get_refresh_token().is_some();
-        (has_tokens, None)
+        (has_tokens, None, None)
     };
 
-    let config = state.get_sync_config();
-
     let response = Json(AuthStatusResponse {
         connected,
         backend: format!("{:?}", config.backend).to_lowercase(),
         email,
         last_sync: state.get_last_sync(),
         device_id: state.get_device_id(),
+        remote,
+        token_path: state.token_path(),
     });
 
     let mut headers = axum::http::HeaderMap::new();
@@ -123,7 +164,11 @@ async fn google_start(
     let auth_flow = backend.start_auth(&req.redirect_uri)?;
 
     // Store backend for later (write lock)
-    *state.google_drive.write().await = Some(backend);
+    state
+        .backends
+        .write()
+        .await
+        .insert(SyncBackendType::GoogleDrive, Box::new(backend));
 
     Ok(Json(auth_flow))
 }
@@ -199,8 +244,10 @@ async fn google_callback_post(
         }
     }
 
-    let mut gdrive = state.google_drive.write().await;
-    let backend = gdrive.get_or_insert_with(|| GoogleDriveBackend::new(state.clone()));
+    let mut backends = state.backends.write().await;
+    let backend = backends
+        .entry(SyncBackendType::GoogleDrive)
+        .or_insert_with(|| Box::new(GoogleDriveBackend::new(state.clone())));
 
     backend.complete_auth(&body.code, &body.redirect_uri).await?;
 
@@ -214,6 +261,34 @@ async fn google_callback_post(
     }))
 }
 
+async fn google_device_start(
+    State(state): State<SyncState>,
+) -> Result<Json<DeviceAuthFlow>, SyncError> {
+    let backend = GoogleDriveBackend::new(state.clone());
+    Ok(Json(backend.start_auth_device().await?))
+}
+
+async fn google_device_poll(
+    State(state): State<SyncState>,
+) -> Result<Json<DeviceAuthStatus>, SyncError> {
+    let mut backend = GoogleDriveBackend::new(state.clone());
+    let status = backend.poll_auth_device().await?;
+
+    if matches!(status, DeviceAuthStatus::Complete) {
+        state
+            .backends
+            .write()
+            .await
+            .insert(SyncBackendType::GoogleDrive, Box::new(backend));
+
+        let mut config = state.get_sync_config();
+        config.backend = SyncBackendType::GoogleDrive;
+        state.set_sync_config(&config)?;
+    }
+
+    Ok(Json(status))
+}
+
 async fn handle_callback(
     state: SyncState,
     code: String,
@@ -231,8 +306,10 @@ async fn handle_callback(
         return Err(SyncError::OAuthError("No stored redirect_uri found".to_string()));
     };
 
-    let mut gdrive = state.google_drive.write().await;
-    let backend = gdrive.get_or_insert_with(|| GoogleDriveBackend::new(state.clone()));
+    let mut backends = state.backends.write().await;
+    let backend = backends
+        .entry(SyncBackendType::GoogleDrive)
+        .or_insert_with(|| Box::new(GoogleDriveBackend::new(state.clone())));
 
     backend.complete_auth(&code, &redirect_uri).await?;
 
@@ -246,9 +323,9 @@ async fn handle_callback(
 }
 
 async fn disconnect(State(state): State<SyncState>) -> Result<Json<CallbackResponse>, SyncError> {
-    let mut gdrive = state.google_drive.write().await;
+    let mut backends = state.backends.write().await;
 
-    if let Some(backend) = gdrive.as_mut() {
+    if let Some(backend) = backends.get_mut(&SyncBackendType::GoogleDrive) {
         backend.disconnect().await?;
     } else {
         // If backend is not currently initialized, we still need to clear any
@@ -256,10 +333,13 @@ async fn disconnect(State(state): State<SyncState>) -> Result<Json<CallbackRespo
         state.clear_tokens()?;
     }
 
-    *gdrive = None;
+    backends.remove(&SyncBackendType::GoogleDrive);
+    drop(backends);
+    state.backends.reload_configured().await;
     let _ = state.clear_auth_state();
     let _ = state.clear_auth_code_verifier();
     let _ = state.clear_auth_redirect_uri();
+    let _ = state.clear_auth_device_code();
 
     let mut config = state.get_sync_config();
     config.backend = crate::types::SyncBackendType::None;
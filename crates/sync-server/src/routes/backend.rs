@@ -0,0 +1,141 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::sync::record_snapshot;
+use crate::backend::PushResult;
+use crate::backend::google_drive;
+use crate::backend::registry::AVAILABLE_BACKENDS;
+use crate::error::SyncError;
+use crate::merge::merge_payloads;
+use crate::state::SyncState;
+use crate::types::{SyncBackendType, SyncBase};
+
+pub fn router() -> Router<SyncState> {
+    Router::new()
+        .route("/", get(backend_status))
+        .route("/switch", post(switch_backend))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendStatusResponse {
+    pub active: SyncBackendType,
+    pub loaded: Vec<SyncBackendType>,
+    pub available: Vec<SyncBackendType>,
+    /// Seconds until Google Drive calls are allowed again after a rate limit
+    pub throttled_for_secs: Option<u64>,
+}
+
+async fn backend_status(State(state): State<SyncState>) -> Json<BackendStatusResponse> {
+    Json(BackendStatusResponse {
+        active: state.get_sync_config().backend,
+        loaded: state.backends.loaded().await,
+        available: AVAILABLE_BACKENDS.to_vec(),
+        throttled_for_secs: google_drive::throttled_for()
+            .map(|remaining| remaining.as_secs().max(1)),
+    })
+}
+
+fn default_migrate() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchBackendRequest {
+    pub backend: SyncBackendType,
+    /// Copy the payload of the current backend to the new one
+    #[serde(default = "default_migrate")]
+    pub migrate: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchBackendResponse {
+    pub previous: SyncBackendType,
+    pub active: SyncBackendType,
+    pub migrated: bool,
+}
+
+async fn switch_backend(
+    State(state): State<SyncState>,
+    Json(req): Json<SwitchBackendRequest>,
+) -> Result<Json<SwitchBackendResponse>, SyncError> {
+    let mut config = state.get_sync_config();
+    let previous = config.backend.clone();
+    if previous == req.backend {
+        return Ok(Json(SwitchBackendResponse {
+            previous,
+            active: req.backend,
+            migrated: false,
+        }));
+    }
+    info!(
+        "[BACKEND] Switching from {:?} to {:?}",
+        previous, req.backend
+    );
+
+    let payload = if req.migrate && previous != SyncBackendType::None {
+        state
+            .backends
+            .get(&state, &previous)
+            .await?
+            .pull()
+            .await?
+            .map(|(payload, _)| payload)
+    } else {
+        None
+    };
+
+    // Loading the new backend first means a misconfigured one is never selected
+    let target = state.backends.get(&state, &req.backend).await?;
+    let migrated = payload.is_some();
+    if let Some(payload) = payload {
+        let (payload, etag) = match target.pull().await? {
+            Some((existing, etag)) => {
+                let (merged, conflicts) = merge_payloads(payload, existing, &state.get_device_id());
+                info!(
+                    "[BACKEND] Merged with existing data on {:?}, {} conflicts",
+                    req.backend,
+                    conflicts.len()
+                );
+                (merged, Some(etag))
+            }
+            None => (payload, None),
+        };
+
+        match target.push(&payload, etag.as_deref()).await? {
+            PushResult::Success { etag } => {
+                info!("[BACKEND] Migrated payload, new etag: {}", etag);
+                state.set_last_etag(&etag)?;
+                state.set_sync_base(&SyncBase::from_payload(&payload))?;
+                record_snapshot(&state, req.backend.clone(), &payload, &etag);
+            }
+            PushResult::Conflict { remote_etag } => {
+                return Err(SyncError::Conflict(format!(
+                    "{:?} changed during the migration (etag {remote_etag}), try again",
+                    req.backend
+                )));
+            }
+        }
+    }
+    drop(target);
+    if !migrated {
+        // The base describes the previous backend's data
+        state.clear_sync_base()?;
+    }
+
+    config.backend = req.backend.clone();
+    state.set_sync_config(&config)?;
+
+    Ok(Json(SwitchBackendResponse {
+        previous,
+        active: req.backend,
+        migrated,
+    }))
+}
@@ -3,29 +3,101 @@ use axum::{
     routing::{get, put},
     Json, Router,
 };
+use serde_json::{Map, Value};
 use tracing::info;
 
 use crate::error::SyncError;
 use crate::state::SyncState;
 use crate::types::SyncConfig;
 
+/// Config fields that are write-only: `GET` reports `has<Field>` in their place, and a
+/// `PUT` leaving one out keeps the stored value (`""` clears it)
+const SECRET_FIELDS: &[&str] = &[
+    "peerToken",
+    "peerServeToken",
+    "sftpPassword",
+    "sftpPrivateKey",
+    "sftpKeyPassphrase",
+];
+
 pub fn router() -> Router<SyncState> {
     Router::new()
         .route("/", get(get_config))
         .route("/", put(set_config))
 }
 
-async fn get_config(State(state): State<SyncState>) -> Json<SyncConfig> {
+/// The config without its secrets, e.g. `hasPeerToken: true` in place of `peerToken`
+fn redacted(config: &SyncConfig) -> Result<Value, SyncError> {
+    let mut value = serde_json::to_value(config)?;
+    if let Some(fields) = value.as_object_mut() {
+        for field in SECRET_FIELDS {
+            let is_set = fields
+                .remove(*field)
+                .is_some_and(|secret| secret.as_str().is_some_and(|secret| !secret.is_empty()));
+            fields.insert(has_field(field), Value::Bool(is_set));
+        }
+    }
+    Ok(value)
+}
+
+fn has_field(field: &str) -> String {
+    let mut chars = field.chars();
+    let first = chars.next().map(|c| c.to_ascii_uppercase());
+    format!("has{}{}", first.unwrap_or_default(), chars.as_str())
+}
+
+/// Fills in the stored secrets the update leaves out
+fn keep_stored_secrets(
+    update: &mut Map<String, Value>,
+    stored: &SyncConfig,
+) -> Result<(), SyncError> {
+    let Value::Object(stored) = serde_json::to_value(stored)? else {
+        return Ok(());
+    };
+    for field in SECRET_FIELDS {
+        if !update.contains_key(*field)
+            && let Some(secret) = stored.get(*field)
+        {
+            update.insert(field.to_string(), secret.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the SFTP host key pinned on first connect while the server stays the same, and
+/// unpins it when the update points at another server without naming a key for it
+fn keep_pinned_host_key(config: &mut SyncConfig, named: bool, stored: &SyncConfig) {
+    let same_server =
+        config.sftp_host.trim() == stored.sftp_host.trim() && config.sftp_port == stored.sftp_port;
+    if same_server && !named {
+        config.sftp_host_fingerprint = stored.sftp_host_fingerprint.clone();
+    } else if !same_server && config.sftp_host_fingerprint == stored.sftp_host_fingerprint {
+        config.sftp_host_fingerprint.clear();
+    }
+}
+
+async fn get_config(State(state): State<SyncState>) -> Result<Json<Value>, SyncError> {
     info!("[CONFIG] Config retrieved");
-    Json(state.get_sync_config())
+    Ok(Json(redacted(&state.get_sync_config())?))
 }
 
 async fn set_config(
     State(state): State<SyncState>,
-    Json(config): Json<SyncConfig>,
-) -> Result<Json<SyncConfig>, SyncError> {
+    Json(update): Json<Value>,
+) -> Result<Json<Value>, SyncError> {
+    let Value::Object(mut update) = update else {
+        return Err(SyncError::BadRequest("Config must be a JSON object".to_string()));
+    };
+    let stored = state.get_sync_config();
+    keep_stored_secrets(&mut update, &stored)?;
+    let names_host_key = update.contains_key("sftpHostFingerprint");
+    let mut config: SyncConfig = serde_json::from_value(Value::Object(update))
+        .map_err(|e| SyncError::BadRequest(format!("Invalid config: {}", e)))?;
+    keep_pinned_host_key(&mut config, names_host_key, &stored);
+
     info!("[CONFIG] Config updated - sync settings: progress={}, metadata={}, content={}, files={}",
           config.ln_progress, config.ln_metadata, config.ln_content, config.ln_files);
     state.set_sync_config(&config)?;
-    Ok(Json(config))
-}
\ No newline at end of file
+    state.backends.reload_configured().await;
+    Ok(Json(redacted(&config)?))
+}
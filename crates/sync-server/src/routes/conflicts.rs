@@ -0,0 +1,119 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use super::sync::{keep_conflict, push_remote, record_snapshot};
+use crate::backend::PushResult;
+use crate::conflicts::{ConflictRecord, RecordKind, Resolution};
+use crate::error::SyncError;
+use crate::state::SyncState;
+use crate::types::{MergeResponse, SyncBase};
+
+pub fn router() -> Router<SyncState> {
+    Router::new()
+        .route("/", get(list_conflicts).delete(discard_conflict))
+        .route("/resolve", post(resolve_conflict))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictsResponse {
+    pub pending: bool,
+    pub detected_at: Option<i64>,
+    pub records: Vec<ConflictRecord>,
+}
+
+async fn list_conflicts(State(state): State<SyncState>) -> Json<ConflictsResponse> {
+    let Some(conflict) = state.get_pending_conflict() else {
+        return Json(ConflictsResponse {
+            pending: false,
+            detected_at: None,
+            records: Vec::new(),
+        });
+    };
+
+    let merged = conflict.merged(state.get_sync_base().as_ref(), &state.get_device_id());
+    Json(ConflictsResponse {
+        pending: true,
+        detected_at: Some(conflict.detected_at),
+        records: conflict.records(&merged),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordChoice {
+    pub kind: RecordKind,
+    pub id: String,
+    pub choice: Resolution,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveRequest {
+    #[serde(default)]
+    pub choices: Vec<RecordChoice>,
+    /// Used for records without a choice
+    #[serde(default)]
+    pub default: Resolution,
+}
+
+async fn resolve_conflict(
+    State(state): State<SyncState>,
+    Json(req): Json<ResolveRequest>,
+) -> Result<Json<MergeResponse>, SyncError> {
+    let _lease = state.sync_lock.try_acquire("resolve")?;
+    let conflict = state
+        .get_pending_conflict()
+        .ok_or_else(|| SyncError::BadRequest("No sync conflict to resolve".to_string()))?;
+
+    let choices: HashMap<(RecordKind, String), Resolution> = req
+        .choices
+        .into_iter()
+        .map(|record| ((record.kind, record.id), record.choice))
+        .collect();
+    let merged = conflict.merged(state.get_sync_base().as_ref(), &state.get_device_id());
+    let payload = conflict.resolve(merged, &choices, req.default);
+
+    info!(
+        "[CONFLICT] Pushing resolution with {} explicit choices",
+        choices.len()
+    );
+    match push_remote(&state, &payload, Some(&conflict.remote_etag)).await? {
+        PushResult::Success { etag } => {
+            let now = chrono::Utc::now().timestamp_millis();
+            state.set_last_sync(now)?;
+            state.set_last_etag(&etag)?;
+            state.set_sync_base(&SyncBase::from_payload(&payload))?;
+            record_snapshot(&state, state.get_sync_config().backend, &payload, &etag);
+            state.clear_pending_conflict()?;
+            info!("[CONFLICT] Resolved, new etag: {}", etag);
+
+            Ok(Json(MergeResponse {
+                payload,
+                sync_timestamp: now,
+                files_to_upload: vec![],
+                files_to_download: vec![],
+                conflicts: vec![],
+            }))
+        }
+        PushResult::Conflict { remote_etag } => {
+            // The remote moved again; review against its new version
+            keep_conflict(&state, conflict.local).await?;
+            Err(SyncError::Conflict(format!(
+                "The remote changed again (etag {remote_etag}), review the conflicts again"
+            )))
+        }
+    }
+}
+
+async fn discard_conflict(State(state): State<SyncState>) -> Result<Json<bool>, SyncError> {
+    let had_conflict = state.get_pending_conflict().is_some();
+    state.clear_pending_conflict()?;
+    Ok(Json(had_conflict))
+}
@@ -1,13 +1,25 @@
 use axum::Router;
 use crate::state::SyncState;
 
+mod archive;
 mod auth;
+mod backend;
 mod config;
-mod sync;
+mod conflicts;
+mod peer;
+pub(crate) mod sync;
 
 pub fn router() -> Router<SyncState> {
     Router::new()
         .nest("/auth", auth::router())
+        .nest("/backend", backend::router())
         .nest("/config", config::router())
+        .nest("/conflicts", conflicts::router())
         .merge(sync::router())
+        .merge(archive::router())
+}
+
+/// The endpoints of peer sync, which check the peer token themselves
+pub fn peer_router() -> Router<SyncState> {
+    Router::new().nest("/peer", peer::router())
 }
@@ -0,0 +1,110 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{
+        HeaderMap, HeaderValue, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MATCH},
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::backend::PushResult;
+use crate::backend::peer::{quote_etag, unquote_etag};
+use crate::error::SyncError;
+use crate::state::SyncState;
+use crate::types::SyncPayload;
+
+/// Endpoints other devices use when this one is their peer backend
+pub fn router() -> Router<SyncState> {
+    Router::new()
+        .route("/pull", get(peer_pull))
+        .route("/push", post(peer_push))
+}
+
+/// Checks the bearer token against `peer_serve_token`; peer sync is off while it is empty
+fn authorize(state: &SyncState, headers: &HeaderMap) -> Result<(), SyncError> {
+    let expected = state.get_sync_config().peer_serve_token;
+    let expected = expected.trim();
+    if expected.is_empty() {
+        return Err(SyncError::NotAuthenticated);
+    }
+
+    let provided = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or_default();
+
+    // Compare digests so the check takes the same time however much of the token matches
+    if Sha256::digest(provided.as_bytes()) != Sha256::digest(expected.as_bytes()) {
+        warn!("[PEER] Rejected request with an invalid token");
+        return Err(SyncError::NotAuthenticated);
+    }
+    Ok(())
+}
+
+fn with_etag(mut response: Response, etag: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(&quote_etag(etag)) {
+        response.headers_mut().insert(ETAG, value);
+    }
+    response
+}
+
+async fn peer_pull(
+    State(state): State<SyncState>,
+    headers: HeaderMap,
+) -> Result<Response, SyncError> {
+    authorize(&state, &headers)?;
+
+    let Some((bytes, etag)) = state.get_peer_payload() else {
+        info!("[PEER] Pull: no sync data stored yet");
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    };
+
+    info!("[PEER] Pull: serving {} bytes, etag: {}", bytes.len(), etag);
+    let response = ([(CONTENT_TYPE, "application/json")], bytes).into_response();
+    Ok(with_etag(response, &etag))
+}
+
+async fn peer_push(
+    State(state): State<SyncState>,
+    headers: HeaderMap,
+    Json(payload): Json<SyncPayload>,
+) -> Result<Response, SyncError> {
+    authorize(&state, &headers)?;
+
+    let expected_etag = headers
+        .get(IF_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(unquote_etag);
+    let bytes = serde_json::to_vec(&payload)?;
+
+    match state.store_peer_payload(&bytes, expected_etag)? {
+        PushResult::Success { etag } => {
+            info!(
+                "[PEER] Push from {}: stored {} bytes, etag: {}",
+                payload.device_id,
+                bytes.len(),
+                etag
+            );
+            let response = Json(serde_json::json!({ "etag": etag })).into_response();
+            Ok(with_etag(response, &etag))
+        }
+        PushResult::Conflict { remote_etag } => {
+            info!(
+                "[PEER] Push from {} conflicted, current etag: {}",
+                payload.device_id, remote_etag
+            );
+            let body = Json(serde_json::json!({
+                "error": "conflict",
+                "message": "The sync data changed since it was pulled",
+            }));
+            let response = (StatusCode::PRECONDITION_FAILED, body).into_response();
+            Ok(with_etag(response, &remote_etag))
+        }
+    }
+}
@@ -1,48 +1,80 @@
 use axum::{
-    extract::State,
-    routing::{get, post},
     Json, Router,
+    extract::{Path, State},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post},
 };
-use tracing::{debug, info};
-
-use crate::backend::google_drive::GoogleDriveBackend;
-use crate::backend::{PushResult, SyncBackend};
+use futures::Stream;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
+
+use crate::backend::PushResult;
+use crate::conflicts::PendingConflict;
+use crate::debounce::ChangeStatus;
+use crate::diff::SyncDiff;
 use crate::error::SyncError;
-use crate::merge::merge_payloads;
+use crate::events::SyncEvent;
+use crate::history::SnapshotInfo;
+use crate::lock::SyncStage;
+use crate::merge::{collect_tombstones, merge_with_base};
+use crate::prune::prune_for_push;
 use crate::state::SyncState;
-use crate::types::{MergeRequest, MergeResponse, SyncPayload};
+use crate::stats::{SyncOperation, SyncStatsReport};
+use crate::types::{MergeRequest, MergeResponse, SyncBackendType, SyncBase, SyncPayload};
 
 pub fn router() -> Router<SyncState> {
     Router::new()
         .route("/merge", post(merge_handler))
         .route("/pull", get(pull_handler))
         .route("/push", post(push_handler))
+        .route("/diff", get(last_pushed_diff_handler).post(diff_handler))
+        .route(
+            "/notify-change",
+            post(notify_change_handler).get(change_status_handler),
+        )
+        .route("/stats", get(stats_handler))
+        .route("/events", get(events_handler))
+        .route("/history", get(history_handler))
+        .route("/rollback/{version}", post(rollback_handler))
 }
 
-async fn ensure_backend(state: &SyncState) -> Result<(), SyncError> {
-    let mut gdrive = state.google_drive.write().await;
-
-    if gdrive.is_none() {
-        let access_token = state.get_access_token();
-        let refresh_token = state.get_refresh_token();
-
-        if access_token.is_some() && refresh_token.is_some() {
-            let mut backend = GoogleDriveBackend::new(state.clone());
-            backend.initialize().await?;
-            *gdrive = Some(backend);
-        } else {
-            return Err(SyncError::NotAuthenticated);
-        }
-    }
+/// Pull from the configured backend
+pub(crate) async fn pull_remote(
+    state: &SyncState,
+) -> Result<Option<(SyncPayload, String)>, SyncError> {
+    state.events.send(SyncEvent::Pulling);
+    state.sync_lock.set_stage(SyncStage::Pulling);
+    let started = Instant::now();
+    let result = async { state.backends.active(state).await?.pull().await }.await;
+    state.stats.record_operation(
+        SyncOperation::Pull,
+        started.elapsed(),
+        result.as_ref().err(),
+    );
+    result
+}
 
-    // Refresh token before operations
-    if let Some(backend) = gdrive.as_mut() {
-        if let Err(e) = backend.refresh_token().await {
-            debug!("Token refresh failed (may be okay): {}", e);
-        }
+/// Push to the configured backend
+pub(crate) async fn push_remote(
+    state: &SyncState,
+    payload: &SyncPayload,
+    etag: Option<&str>,
+) -> Result<PushResult, SyncError> {
+    state.events.send(SyncEvent::Pushing);
+    state.sync_lock.set_stage(SyncStage::Pushing);
+    let started = Instant::now();
+    let result = async {
+        let backend = state.backends.active(state).await?;
+        backend.push(payload, etag).await
     }
-
-    Ok(())
+    .await;
+    state.stats.record_operation(
+        SyncOperation::Push,
+        started.elapsed(),
+        result.as_ref().err(),
+    );
+    result
 }
 
 async fn merge_handler(
@@ -50,17 +82,55 @@ async fn merge_handler(
     Json(req): Json<MergeRequest>,
 ) -> Result<Json<MergeResponse>, SyncError> {
     info!("[MERGE] Starting sync operation...");
-    ensure_backend(&state).await?;
 
     // Apply config if provided
     if let Some(config) = req.config {
         state.set_sync_config(&config)?;
+        state.backends.reload_configured().await;
         info!("[MERGE] Config updated - sync settings: progress={}, metadata={}, content={}, files={}",
               config.ln_progress, config.ln_metadata, config.ln_content, config.ln_files);
     }
 
+    Ok(Json(run_merge(&state, req.payload).await?))
+}
+
+/// Pulls, merges `local_payload` into the remote data and pushes the result
+pub(crate) async fn run_merge(
+    state: &SyncState,
+    local_payload: SyncPayload,
+) -> Result<MergeResponse, SyncError> {
+    let _lease = state.sync_lock.try_acquire("merge")?;
+    let started = Instant::now();
+    let result = merge_remote(state, local_payload).await;
+    state.stats.record_operation(
+        SyncOperation::Merge,
+        started.elapsed(),
+        result.as_ref().err(),
+    );
+    let outcome = result.as_ref().map(|response| response.sync_timestamp);
+    send_outcome(state, outcome);
+    result
+}
+
+/// Broadcasts how a sync ended
+fn send_outcome(state: &SyncState, outcome: Result<i64, &SyncError>) {
+    state.events.send(match outcome {
+        Ok(sync_timestamp) => SyncEvent::Done { sync_timestamp },
+        Err(SyncError::Conflict(_)) => SyncEvent::Conflict {
+            message: "Another device pushed first, resolve it via /conflicts".to_string(),
+        },
+        Err(e) => SyncEvent::Error {
+            error: e.error_type().to_string(),
+            message: e.user_message(),
+        },
+    });
+}
+
+async fn merge_remote(
+    state: &SyncState,
+    local_payload: SyncPayload,
+) -> Result<MergeResponse, SyncError> {
     let device_id = state.get_device_id();
-    let local_payload = req.payload;
     
     // Log local data summary
     let local_progress_count = local_payload.ln_progress.len();
@@ -75,13 +145,12 @@ async fn merge_handler(
           local_progress_count, local_metadata_count, local_content_count, local_files_count);
 
     // Pull remote data
-    let gdrive = state.google_drive.read().await;
-    let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
-
-    info!("[MERGE] Downloading remote data from Google Drive...");
-    let remote_result = backend.pull().await?;
+    info!("[MERGE] Downloading remote data...");
+    let remote_result = pull_remote(state).await?;
 
-    let (merged_payload, conflicts, etag) = if let Some((remote_payload, remote_etag)) = remote_result {
+    let (mut merged_payload, conflicts, etag) = if let Some((remote_payload, remote_etag)) =
+        remote_result
+    {
         let remote_progress_count = remote_payload.ln_progress.len();
         let remote_metadata_count = remote_payload.ln_metadata.len();
         
@@ -100,7 +169,11 @@ async fn merge_handler(
         } else {
             info!("[MERGE] Different device detected. Local device: {}, Remote device: {}", device_id, remote_device_id);
             info!("[MERGE] Merging payloads...");
-            let (merged, conflicts) = merge_payloads(local_payload, remote_payload, &device_id);
+            state.events.send(SyncEvent::Merging);
+            state.sync_lock.set_stage(SyncStage::Merging);
+            let base = state.get_sync_base();
+            let (merged, conflicts) =
+                merge_with_base(base.as_ref(), local_payload, remote_payload, &device_id);
             
             let merged_progress = merged.ln_progress.len();
             let merged_metadata = merged.ln_metadata.len();
@@ -114,23 +187,34 @@ async fn merge_handler(
         (local_payload, vec![], None)
     };
 
-    drop(gdrive);
+    let retention_days = state.get_sync_config().tombstone_retention_days;
+    let collected = collect_tombstones(&mut merged_payload, retention_days);
+    if collected > 0 {
+        info!(
+            "[MERGE] Dropped {} tombstones older than {} days",
+            collected, retention_days
+        );
+    }
 
     // Push merged data
-    let gdrive = state.google_drive.read().await;
-    let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
-
-    info!("[MERGE] Uploading merged data to Google Drive...");
-    let push_result = backend.push(&merged_payload, etag.as_deref()).await?;
+    info!("[MERGE] Uploading merged data...");
+    let config = state.get_sync_config();
+    let pushed_payload = prune_for_push(&merged_payload, &config);
+    let push_result = push_remote(state, &pushed_payload, etag.as_deref()).await?;
 
     match push_result {
         PushResult::Success { etag: new_etag } => {
             info!("[MERGE] Upload successful! New etag: {}", new_etag);
             state.set_last_etag(&new_etag)?;
+            // The base is what the remote holds, so pruned books aren't seen as deleted
+            state.set_sync_base(&SyncBase::from_payload(&pushed_payload))?;
+            record_snapshot(state, config.backend, &pushed_payload, &new_etag);
         }
         PushResult::Conflict { remote_etag } => {
+            drop(pushed_payload);
+            keep_conflict(state, merged_payload).await?;
             return Err(SyncError::Conflict(format!(
-                "[MERGE] Conflict detected! Expected etag: {:?}, got: {}",
+                "[MERGE] Conflict detected! Expected etag: {:?}, got: {}. Resolve it via /conflicts",
                 etag, remote_etag
             )));
         }
@@ -147,24 +231,51 @@ async fn merge_handler(
     info!("[MERGE] Conflicts resolved: {}", conflicts.len());
     info!("[MERGE] ==================================");
 
-    Ok(Json(MergeResponse {
+    Ok(MergeResponse {
         payload: merged_payload,
         sync_timestamp: now,
         files_to_upload: vec![],
         files_to_download: vec![],
         conflicts,
-    }))
+    })
 }
 
-async fn pull_handler(State(state): State<SyncState>) -> Result<Json<Option<SyncPayload>>, SyncError> {
-    info!("[PULL] Starting pull operation...");
-    ensure_backend(&state).await?;
+/// Stores `local` with the remote payload that won, for manual resolution
+pub(crate) async fn keep_conflict(state: &SyncState, local: SyncPayload) -> Result<(), SyncError> {
+    let Some((remote, remote_etag)) = pull_remote(state).await? else {
+        return Ok(());
+    };
+    info!(
+        "[MERGE] Keeping both versions, remote etag: {}",
+        remote_etag
+    );
+    state.set_pending_conflict(&PendingConflict {
+        local,
+        remote,
+        remote_etag,
+        detected_at: chrono::Utc::now().timestamp_millis(),
+    })?;
+    Ok(())
+}
 
-    let gdrive = state.google_drive.read().await;
-    let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
+/// Keeps a pushed payload in the local history. A failure here never fails the
+/// sync, it only means this version cannot be rolled back to.
+pub(crate) fn record_snapshot(
+    state: &SyncState,
+    backend: SyncBackendType,
+    payload: &SyncPayload,
+    etag: &str,
+) {
+    let limit = state.get_sync_config().history_limit;
+    match state.history.record(payload, etag, backend, limit) {
+        Ok(snapshot) => debug!("[HISTORY] Kept version {}", snapshot.version),
+        Err(e) => warn!("[HISTORY] Failed to keep pushed payload: {}", e),
+    }
+}
 
-    info!("[PULL] Downloading from Google Drive...");
-    let result = backend.pull().await?;
+async fn pull_handler(State(state): State<SyncState>) -> Result<Json<Option<SyncPayload>>, SyncError> {
+    info!("[PULL] Starting pull operation...");
+    let result = pull_remote(&state).await?;
 
     match &result {
         Some((payload, etag)) => {
@@ -201,24 +312,31 @@ async fn push_handler(
     Json(req): Json<PushRequest>,
 ) -> Result<Json<PushResponse>, SyncError> {
     info!("[PUSH] Starting push operation...");
+    let _lease = state.sync_lock.try_acquire("push")?;
     
     let payload_size = req.payload.ln_progress.len();
     let metadata_size = req.payload.ln_metadata.len();
     info!("[PUSH] Pushing: {} progress, {} metadata entries", payload_size, metadata_size);
     
-    ensure_backend(&state).await?;
-
-    let gdrive = state.google_drive.read().await;
-    let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
-
-    info!("[PUSH] Uploading to Google Drive...");
-    let result = backend.push(&req.payload, req.etag.as_deref()).await?;
+    info!("[PUSH] Uploading...");
+    let config = state.get_sync_config();
+    let pushed_payload = prune_for_push(&req.payload, &config);
+    let result = match push_remote(&state, &pushed_payload, req.etag.as_deref()).await {
+        Ok(result) => result,
+        Err(e) => {
+            send_outcome(&state, Err(&e));
+            return Err(e);
+        }
+    };
 
     match result {
         PushResult::Success { etag } => {
             let now = chrono::Utc::now().timestamp_millis();
             state.set_last_sync(now)?;
             state.set_last_etag(&etag)?;
+            state.set_sync_base(&SyncBase::from_payload(&pushed_payload))?;
+            record_snapshot(&state, config.backend, &pushed_payload, &etag);
+            send_outcome(&state, Ok(now));
             
             info!("[PUSH] Upload successful! Timestamp: {}, etag: {}", now, etag);
             
@@ -228,10 +346,161 @@ async fn push_handler(
                 sync_timestamp: now,
             }))
         }
+        PushResult::Conflict { remote_etag } => {
+            drop(pushed_payload);
+            keep_conflict(&state, req.payload).await?;
+            let error = SyncError::Conflict(format!(
+                "[PUSH] Conflict detected! Remote etag: {}. Resolve it via /conflicts",
+                remote_etag
+            ));
+            send_outcome(&state, Err(&error));
+            Err(error)
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffRequest {
+    pub payload: SyncPayload,
+}
+
+/// Previews a merge of the given local payload without applying anything
+async fn diff_handler(
+    State(state): State<SyncState>,
+    Json(req): Json<DiffRequest>,
+) -> Result<Json<SyncDiff>, SyncError> {
+    Ok(Json(preview_sync(&state, &req.payload).await?))
+}
+
+/// Previews a merge of the last payload pushed from this device, which shows what
+/// other devices changed since
+async fn last_pushed_diff_handler(
+    State(state): State<SyncState>,
+) -> Result<Json<SyncDiff>, SyncError> {
+    let local = match state.history.list().first() {
+        Some(snapshot) => state.history.load(snapshot.version)?,
+        None => None,
+    }
+    .ok_or_else(|| {
+        SyncError::BadRequest("Nothing pushed yet, POST the local payload instead".to_string())
+    })?;
+    Ok(Json(preview_sync(&state, &local).await?))
+}
+
+async fn preview_sync(state: &SyncState, local: &SyncPayload) -> Result<SyncDiff, SyncError> {
+    info!("[DIFF] Downloading remote data for preview...");
+    let remote = pull_remote(state).await?.map(|(payload, _)| payload);
+    let base = state.get_sync_base();
+    let device_id = state.get_device_id();
+    let diff = SyncDiff::compute(base.as_ref(), local, remote.as_ref(), &device_id);
+    info!(
+        "[DIFF] Preview ready: local changes: {}, remote changes: {}, {} conflicts",
+        !diff.to_local.is_empty(),
+        !diff.to_remote.is_empty(),
+        diff.conflicts
+    );
+    Ok(diff)
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyChangeRequest {
+    /// Local payload after the change
+    pub payload: SyncPayload,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyChangeResponse {
+    pub scheduled: bool,
+    pub debounce_secs: u64,
+}
+
+async fn notify_change_handler(
+    State(state): State<SyncState>,
+    Json(req): Json<NotifyChangeRequest>,
+) -> Json<NotifyChangeResponse> {
+    let config = state.get_sync_config();
+    if config.sync_on_change {
+        let quiet = Duration::from_secs(config.sync_debounce_secs);
+        debug!("[CHANGE] Change reported, syncing in {:?}", quiet);
+        state.changes.notify(&state, req.payload, quiet).await;
+    }
+
+    Json(NotifyChangeResponse {
+        scheduled: config.sync_on_change,
+        debounce_secs: config.sync_debounce_secs,
+    })
+}
+
+async fn change_status_handler(State(state): State<SyncState>) -> Json<ChangeStatus> {
+    Json(state.changes.status().await)
+}
+
+/// Streams sync progress as server-sent events
+async fn events_handler(
+    State(state): State<SyncState>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let receiver = state.events.subscribe();
+    let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    return Some((Event::default().json_data(&event), receiver));
+                }
+                // Skip what a slow client missed
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn stats_handler(State(state): State<SyncState>) -> Json<SyncStatsReport> {
+    Json(state.stats.report())
+}
+
+async fn history_handler(State(state): State<SyncState>) -> Json<Vec<SnapshotInfo>> {
+    Json(state.history.list())
+}
+
+/// Pushes a kept version over the current remote data
+async fn rollback_handler(
+    State(state): State<SyncState>,
+    Path(version): Path<u64>,
+) -> Result<Json<MergeResponse>, SyncError> {
+    let payload = state
+        .history
+        .load(version)?
+        .ok_or_else(|| SyncError::FileNotFound(format!("History version {version}")))?;
+    info!("[HISTORY] Rolling back to version {}", version);
+    let _lease = state.sync_lock.try_acquire("rollback")?;
+
+    let remote_etag = pull_remote(&state).await?.map(|(_, etag)| etag);
+    match push_remote(&state, &payload, remote_etag.as_deref()).await? {
+        PushResult::Success { etag } => {
+            let now = chrono::Utc::now().timestamp_millis();
+            state.set_last_sync(now)?;
+            state.set_last_etag(&etag)?;
+            state.set_sync_base(&SyncBase::from_payload(&payload))?;
+            record_snapshot(&state, state.get_sync_config().backend, &payload, &etag);
+            info!(
+                "[HISTORY] Rolled back to version {}, new etag: {}",
+                version, etag
+            );
+
+            Ok(Json(MergeResponse {
+                payload,
+                sync_timestamp: now,
+                files_to_upload: vec![],
+                files_to_download: vec![],
+                conflicts: vec![],
+            }))
+        }
         PushResult::Conflict { remote_etag } => Err(SyncError::Conflict(format!(
-            "[PUSH] Conflict detected! Remote etag: {}",
-            remote_etag
+            "The remote changed during the rollback (etag {remote_etag}), try again"
         ))),
     }
 }
-
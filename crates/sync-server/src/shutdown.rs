@@ -0,0 +1,65 @@
+use crate::state::SyncState;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// ============================================================================
+// Graceful Shutdown
+// ============================================================================
+//
+// Draining closes the sync lock so no new sync starts, waits up to
+// `MANATAN_SYNC_SHUTDOWN_DEADLINE_SECS` (default 30) for a running one to finish
+// its push, then flushes the sync database. A sync still running at the deadline
+// is cut off with the process; the lease only lives in memory, so the next start
+// syncs normally.
+
+const SHUTDOWN_DEADLINE_ENV: &str = "MANATAN_SYNC_SHUTDOWN_DEADLINE_SECS";
+const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(30);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returned by `create_router_with_shutdown`; call `drain` before the process exits
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    state: SyncState,
+    deadline: Duration,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new(state: SyncState) -> Self {
+        let deadline = manatan_config::var(SHUTDOWN_DEADLINE_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SHUTDOWN_DEADLINE);
+        Self { state, deadline }
+    }
+
+    pub async fn drain(&self) {
+        let state = &self.state;
+        state.sync_lock.close();
+
+        if let Some(running) = state.sync_lock.current() {
+            info!(
+                "[SHUTDOWN] Waiting for the running {} sync ({})",
+                running.operation,
+                running.stage.as_str()
+            );
+            let started = Instant::now();
+            while state.sync_lock.current().is_some() && started.elapsed() < self.deadline {
+                tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+            }
+            if let Some(running) = state.sync_lock.current() {
+                warn!(
+                    "[SHUTDOWN] Deadline of {:?} reached with a {} sync still {}",
+                    self.deadline,
+                    running.operation,
+                    running.stage.as_str()
+                );
+            }
+        }
+
+        match state.db.flush_async().await {
+            Ok(_) => info!("[SHUTDOWN] Flushed the sync database"),
+            Err(e) => warn!("[SHUTDOWN] Failed to flush the sync database: {}", e),
+        }
+    }
+}
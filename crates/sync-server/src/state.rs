@@ -1,25 +1,46 @@
-use crate::backend::google_drive::GoogleDriveBackend;
-use crate::types::SyncConfig;
+use crate::backend::PushResult;
+use crate::backend::google_drive::TokenPath;
+use crate::backend::peer::payload_etag;
+use crate::backend::registry::BackendRegistry;
+use crate::conflicts::PendingConflict;
+use crate::debounce::ChangeDebouncer;
+use crate::events::SyncEvents;
+use crate::history::SnapshotHistory;
+use crate::lock::SyncLock;
+use crate::stats::SyncStats;
+use crate::types::{SyncBase, SyncConfig};
 use sled::Db;
 use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex};
 
 const DB_KEY_DEVICE_ID: &[u8] = b"device_id";
 const DB_KEY_ACCESS_TOKEN: &[u8] = b"google_access_token";
 const DB_KEY_REFRESH_TOKEN: &[u8] = b"google_refresh_token";
+const DB_KEY_TOKEN_EXPIRES_AT: &[u8] = b"google_token_expires_at";
 const DB_KEY_LAST_SYNC: &[u8] = b"last_sync_timestamp";
 const DB_KEY_LAST_ETAG: &[u8] = b"last_sync_etag";
 const DB_KEY_SYNC_CONFIG: &[u8] = b"sync_config";
+const DB_KEY_SYNC_BASE: &[u8] = b"sync_base";
+const DB_KEY_PENDING_CONFLICT: &[u8] = b"pending_conflict";
 const DB_KEY_AUTH_STATE: &[u8] = b"oauth_state";
 const DB_KEY_AUTH_REDIRECT_URI: &[u8] = b"oauth_redirect_uri";
 const DB_KEY_AUTH_CODE_VERIFIER: &[u8] = b"oauth_code_verifier";
+const DB_KEY_AUTH_DEVICE_CODE: &[u8] = b"oauth_device_code";
+const DB_KEY_PEER_PAYLOAD: &[u8] = b"peer_payload";
+const DB_KEY_DRIVE_CHANGES_TOKEN: &[u8] = b"drive_changes_page_token";
 
 #[derive(Clone)]
 pub struct SyncState {
     pub db: Db,
     pub data_dir: PathBuf,
-    pub google_drive: Arc<RwLock<Option<GoogleDriveBackend>>>,
+    pub backends: Arc<BackendRegistry>,
+    pub changes: Arc<ChangeDebouncer>,
+    pub history: SnapshotHistory,
+    pub stats: Arc<SyncStats>,
+    pub events: Arc<SyncEvents>,
+    pub sync_lock: Arc<SyncLock>,
+    /// How the last token request since startup reached Google
+    token_path: Arc<Mutex<Option<TokenPath>>>,
 }
 
 impl SyncState {
@@ -37,10 +58,18 @@ impl SyncState {
                 .expect("Failed to generate device ID");
         }
 
+        let history = SnapshotHistory::open(&db).expect("Failed to open sync history");
+
         let state = Self {
             db,
             data_dir: sync_dir,
-            google_drive: Arc::new(RwLock::new(None)),
+            backends: Arc::new(BackendRegistry::default()),
+            changes: Arc::new(ChangeDebouncer::default()),
+            history,
+            stats: Arc::new(SyncStats::default()),
+            events: Arc::new(SyncEvents::default()),
+            sync_lock: Arc::new(SyncLock::default()),
+            token_path: Arc::new(Mutex::new(None)),
         };
 
         // Try to initialize Google Drive if tokens exist
@@ -63,6 +92,14 @@ impl SyncState {
             .unwrap_or_else(|| "unknown".to_string())
     }
 
+    pub fn token_path(&self) -> Option<TokenPath> {
+        *self.token_path.lock().expect("lock poisoned")
+    }
+
+    pub fn set_token_path(&self, path: TokenPath) {
+        *self.token_path.lock().expect("lock poisoned") = Some(path);
+    }
+
     // OAuth Tokens
     pub fn get_access_token(&self) -> Option<String> {
         self.db
@@ -92,9 +129,31 @@ impl SyncState {
         Ok(())
     }
 
+    /// When the access token expires, in milliseconds; unknown for tokens stored
+    /// before expiry was tracked
+    pub fn get_token_expires_at(&self) -> Option<i64> {
+        self.db
+            .get(DB_KEY_TOKEN_EXPIRES_AT)
+            .ok()
+            .flatten()
+            .and_then(|v| {
+                let bytes: [u8; 8] = v.as_ref().try_into().ok()?;
+                Some(i64::from_le_bytes(bytes))
+            })
+    }
+
+    pub fn set_token_expires_at(&self, expires_at: i64) -> Result<(), sled::Error> {
+        self.db
+            .insert(DB_KEY_TOKEN_EXPIRES_AT, &expires_at.to_le_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
     pub fn clear_tokens(&self) -> Result<(), sled::Error> {
         self.db.remove(DB_KEY_ACCESS_TOKEN)?;
         self.db.remove(DB_KEY_REFRESH_TOKEN)?;
+        self.db.remove(DB_KEY_TOKEN_EXPIRES_AT)?;
+        self.db.remove(DB_KEY_DRIVE_CHANGES_TOKEN)?;
         self.db.flush()?;
         Ok(())
     }
@@ -141,6 +200,43 @@ impl SyncState {
         Ok(())
     }
 
+    // OAuth device code (pending device authorization)
+    pub fn set_auth_device_code(&self, code: &str) -> Result<(), sled::Error> {
+        self.db.insert(DB_KEY_AUTH_DEVICE_CODE, code.as_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn get_auth_device_code(&self) -> Option<String> {
+        self.db
+            .get(DB_KEY_AUTH_DEVICE_CODE)
+            .ok()
+            .flatten()
+            .map(|v| String::from_utf8_lossy(&v).to_string())
+    }
+
+    pub fn clear_auth_device_code(&self) -> Result<(), sled::Error> {
+        self.db.remove(DB_KEY_AUTH_DEVICE_CODE)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Drive changes feed position, so each poll only sees newer changes
+    pub fn get_drive_changes_token(&self) -> Option<String> {
+        self.db
+            .get(DB_KEY_DRIVE_CHANGES_TOKEN)
+            .ok()
+            .flatten()
+            .map(|v| String::from_utf8_lossy(&v).to_string())
+    }
+
+    pub fn set_drive_changes_token(&self, token: &str) -> Result<(), sled::Error> {
+        self.db
+            .insert(DB_KEY_DRIVE_CHANGES_TOKEN, token.as_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
+
     // OAuth Redirect URI (stored during auth start for callback)
     pub fn set_auth_redirect_uri(&self, uri: &str) -> Result<(), sled::Error> {
         self.db.insert(DB_KEY_AUTH_REDIRECT_URI, uri.as_bytes())?;
@@ -207,6 +303,97 @@ impl SyncState {
         Ok(())
     }
 
+    // Merge base (the payload of the last successful sync)
+    pub fn get_sync_base(&self) -> Option<SyncBase> {
+        self.db
+            .get(DB_KEY_SYNC_BASE)
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    pub fn set_sync_base(&self, base: &SyncBase) -> Result<(), sled::Error> {
+        let bytes = serde_json::to_vec(base).unwrap_or_default();
+        self.db.insert(DB_KEY_SYNC_BASE, bytes)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn clear_sync_base(&self) -> Result<(), sled::Error> {
+        self.db.remove(DB_KEY_SYNC_BASE)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Pending conflict (both versions of a push that lost the etag race)
+    pub fn get_pending_conflict(&self) -> Option<PendingConflict> {
+        self.db
+            .get(DB_KEY_PENDING_CONFLICT)
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    pub fn set_pending_conflict(&self, conflict: &PendingConflict) -> Result<(), sled::Error> {
+        let bytes = serde_json::to_vec(conflict).unwrap_or_default();
+        self.db.insert(DB_KEY_PENDING_CONFLICT, bytes)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn clear_pending_conflict(&self) -> Result<(), sled::Error> {
+        self.db.remove(DB_KEY_PENDING_CONFLICT)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    // Peer storage (the payload this device keeps for its peers)
+    pub fn get_peer_payload(&self) -> Option<(Vec<u8>, String)> {
+        self.db
+            .get(DB_KEY_PEER_PAYLOAD)
+            .ok()
+            .flatten()
+            .map(|v| (v.to_vec(), payload_etag(&v)))
+    }
+
+    /// Stores the payload JSON if the stored one still has `expected_etag`.
+    /// Without an etag, or with nothing stored yet, it is stored unconditionally.
+    pub fn store_peer_payload(
+        &self,
+        bytes: &[u8],
+        expected_etag: Option<&str>,
+    ) -> Result<PushResult, sled::Error> {
+        let etag = payload_etag(bytes);
+        let Some(expected_etag) = expected_etag else {
+            self.db.insert(DB_KEY_PEER_PAYLOAD, bytes)?;
+            self.db.flush()?;
+            return Ok(PushResult::Success { etag });
+        };
+
+        let current = self.db.get(DB_KEY_PEER_PAYLOAD)?;
+        if let Some(current_etag) = current.as_deref().map(payload_etag) {
+            if current_etag != expected_etag {
+                return Ok(PushResult::Conflict {
+                    remote_etag: current_etag,
+                });
+            }
+        }
+
+        // Another push may have landed since the read above
+        match self
+            .db
+            .compare_and_swap(DB_KEY_PEER_PAYLOAD, current, Some(bytes))?
+        {
+            Ok(()) => {
+                self.db.flush()?;
+                Ok(PushResult::Success { etag })
+            }
+            Err(err) => Ok(PushResult::Conflict {
+                remote_etag: err.current.as_deref().map(payload_etag).unwrap_or_default(),
+            }),
+        }
+    }
+
     // Upload tracking (for resumable uploads)
     pub fn get_upload_state(&self, upload_id: &str) -> Option<UploadState> {
         let key = format!("upload:{}", upload_id);
@@ -235,6 +422,31 @@ impl SyncState {
         self.db.flush()?;
         Ok(())
     }
+
+    // Download tracking (for resumable downloads)
+    pub fn get_download_state(&self, file_id: &str) -> Option<DownloadState> {
+        let key = format!("download:{}", file_id);
+        self.db
+            .get(key.as_bytes())
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::from_slice(&v).ok())
+    }
+
+    pub fn set_download_state(&self, state: &DownloadState) -> Result<(), sled::Error> {
+        let key = format!("download:{}", state.file_id);
+        let bytes = serde_json::to_vec(state).unwrap_or_default();
+        self.db.insert(key.as_bytes(), bytes)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    pub fn clear_download_state(&self, file_id: &str) -> Result<(), sled::Error> {
+        let key = format!("download:{}", file_id);
+        self.db.remove(key.as_bytes())?;
+        self.db.flush()?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -249,3 +461,14 @@ pub struct UploadState {
     pub started_at: i64,
     pub last_chunk_at: i64,
 }
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DownloadState {
+    pub file_id: String,
+    /// Version being downloaded; received bytes of another version are discarded
+    pub etag: String,
+    pub total_size: u64,
+    pub downloaded_bytes: u64,
+    pub started_at: i64,
+    pub last_chunk_at: i64,
+}
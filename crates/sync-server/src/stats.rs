@@ -0,0 +1,132 @@
+use crate::error::SyncError;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// ============================================================================
+// Sync Statistics
+// ============================================================================
+//
+// Counters since the server started, shown in the app's sync settings. Backends
+// report the bytes they transfer; `/sync` routes report each operation.
+
+#[derive(Debug, Clone, Copy)]
+pub enum SyncOperation {
+    Pull,
+    Push,
+    /// A full pull, merge and push
+    Merge,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Counters {
+    bytes_uploaded: u64,
+    bytes_downloaded: u64,
+    payload_bytes_uploaded: u64,
+    payload_bytes_downloaded: u64,
+    pulls: u64,
+    pushes: u64,
+    merges: u64,
+    errors: u64,
+    total_merge_ms: u64,
+    last_merge_ms: Option<u64>,
+    last_error: Option<String>,
+    last_error_at: Option<i64>,
+}
+
+pub struct SyncStats {
+    started_at: i64,
+    counters: Mutex<Counters>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatsReport {
+    pub since: i64,
+    /// Bytes sent to and received from the backend, as stored there
+    pub bytes_uploaded: u64,
+    pub bytes_downloaded: u64,
+    /// Size of the same payloads before compression
+    pub payload_bytes_uploaded: u64,
+    pub payload_bytes_downloaded: u64,
+    /// Stored size over payload size, across all transfers
+    pub compression_ratio: Option<f64>,
+    pub pulls: u64,
+    pub pushes: u64,
+    pub merges: u64,
+    /// Failed pulls, pushes and merges
+    pub errors: u64,
+    pub last_merge_ms: Option<u64>,
+    pub average_merge_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+}
+
+impl Default for SyncStats {
+    fn default() -> Self {
+        Self {
+            started_at: chrono::Utc::now().timestamp_millis(),
+            counters: Mutex::default(),
+        }
+    }
+}
+
+impl SyncStats {
+    pub fn record_upload(&self, stored_bytes: usize, payload_bytes: usize) {
+        let mut counters = self.counters.lock().expect("lock poisoned");
+        counters.bytes_uploaded += stored_bytes as u64;
+        counters.payload_bytes_uploaded += payload_bytes as u64;
+    }
+
+    pub fn record_download(&self, stored_bytes: usize, payload_bytes: usize) {
+        let mut counters = self.counters.lock().expect("lock poisoned");
+        counters.bytes_downloaded += stored_bytes as u64;
+        counters.payload_bytes_downloaded += payload_bytes as u64;
+    }
+
+    pub fn record_operation(
+        &self,
+        operation: SyncOperation,
+        elapsed: Duration,
+        error: Option<&SyncError>,
+    ) {
+        let mut counters = self.counters.lock().expect("lock poisoned");
+        match operation {
+            SyncOperation::Pull => counters.pulls += 1,
+            SyncOperation::Push => counters.pushes += 1,
+            SyncOperation::Merge => {
+                counters.merges += 1;
+                let elapsed_ms = elapsed.as_millis() as u64;
+                counters.total_merge_ms += elapsed_ms;
+                counters.last_merge_ms = Some(elapsed_ms);
+            }
+        }
+        if let Some(error) = error {
+            counters.errors += 1;
+            counters.last_error = Some(error.to_string());
+            counters.last_error_at = Some(chrono::Utc::now().timestamp_millis());
+        }
+    }
+
+    pub fn report(&self) -> SyncStatsReport {
+        let counters = self.counters.lock().expect("lock poisoned").clone();
+        let stored = counters.bytes_uploaded + counters.bytes_downloaded;
+        let payload = counters.payload_bytes_uploaded + counters.payload_bytes_downloaded;
+        SyncStatsReport {
+            since: self.started_at,
+            bytes_uploaded: counters.bytes_uploaded,
+            bytes_downloaded: counters.bytes_downloaded,
+            payload_bytes_uploaded: counters.payload_bytes_uploaded,
+            payload_bytes_downloaded: counters.payload_bytes_downloaded,
+            compression_ratio: (payload > 0).then(|| stored as f64 / payload as f64),
+            pulls: counters.pulls,
+            pushes: counters.pushes,
+            merges: counters.merges,
+            errors: counters.errors,
+            last_merge_ms: counters.last_merge_ms,
+            average_merge_ms: counters.total_merge_ms.checked_div(counters.merges),
+            last_error: counters.last_error,
+            last_error_at: counters.last_error_at,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use crate::backend::google_drive::token_needs_refresh;
+use crate::state::SyncState;
+use crate::types::SyncBackendType;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// ============================================================================
+// Proactive Token Refresh
+// ============================================================================
+//
+// Google access tokens last about an hour. Rather than waiting for a request to
+// fail, the stored expiry is checked every minute and the token is refreshed
+// shortly before it runs out, so a scheduled sync never starts with a dead token.
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn spawn_token_refresh_task(state: &SyncState) {
+    let state = state.clone();
+    manatan_tasks::spawn("sync.token-refresh", move || {
+        let state = state.clone();
+        async move {
+            let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                ticker.tick().await;
+                // Not signed in to Google Drive
+                if state.get_refresh_token().is_none() || !token_needs_refresh(&state) {
+                    continue;
+                }
+
+                // Loading the backend refreshes its token when it is due
+                match state
+                    .backends
+                    .get(&state, &SyncBackendType::GoogleDrive)
+                    .await
+                {
+                    Ok(_) if !token_needs_refresh(&state) => {
+                        info!("[AUTH] Refreshed the Google Drive access token before it expired");
+                    }
+                    Ok(_) => warn!("[AUTH] Google Drive access token is still due for a refresh"),
+                    Err(e) => warn!(
+                        "[AUTH] Failed to refresh the Google Drive access token: {}",
+                        e
+                    ),
+                }
+            }
+        }
+    });
+}
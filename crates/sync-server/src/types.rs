@@ -1,12 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 // ============================================================================
 // Light Novel Progress
 // ============================================================================
 
 /// Reading progress for a light novel - matches TypeScript LNProgress
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct LNProgress {
     /// Current chapter index
@@ -100,7 +100,7 @@ impl LNProgress {
 // ============================================================================
 
 /// Block index mapping for navigation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BlockIndexMap {
     #[serde(alias = "blockId")]
@@ -112,7 +112,7 @@ pub struct BlockIndexMap {
 }
 
 /// Book statistics
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct BookStats {
     pub chapter_lengths: Vec<i32>,
@@ -122,7 +122,7 @@ pub struct BookStats {
 }
 
 /// Table of contents item
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct TocItem {
     pub label: String,
@@ -132,7 +132,7 @@ pub struct TocItem {
 }
 
 /// Light novel metadata - matches TypeScript LNMetadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct LNMetadata {
     pub id: String,
@@ -200,7 +200,7 @@ pub struct LNParsedBook {
 }
 
 /// Reference to a synced file (for file manifest)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct FileReference {
     #[serde(alias = "bookId")]
@@ -262,6 +262,20 @@ pub struct SyncPayload {
     /// File manifest for resumable sync
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub file_manifest: HashMap<String, FileReference>,
+
+    /// Deleted books (bookId → tombstone), so other devices don't bring them back
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tombstones: HashMap<String, Tombstone>,
+}
+
+/// Marks a book as deleted. Records of the book changed after `deleted_at` survive
+/// the deletion.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    pub deleted_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
 }
 
 impl SyncPayload {
@@ -277,6 +291,36 @@ impl SyncPayload {
     }
 }
 
+/// The data both sides agreed on at the last successful sync, the base of the
+/// three-way merge. Content and files are kept as keys only since they never change
+/// after import and are too large to store twice.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncBase {
+    #[serde(default)]
+    pub ln_progress: HashMap<String, LNProgress>,
+    #[serde(default)]
+    pub ln_metadata: HashMap<String, LNMetadata>,
+    #[serde(default)]
+    pub ln_content: HashSet<String>,
+    #[serde(default)]
+    pub ln_files: HashSet<String>,
+    #[serde(default)]
+    pub file_manifest: HashMap<String, FileReference>,
+}
+
+impl SyncBase {
+    pub fn from_payload(payload: &SyncPayload) -> Self {
+        Self {
+            ln_progress: payload.ln_progress.clone(),
+            ln_metadata: payload.ln_metadata.clone(),
+            ln_content: payload.ln_content.keys().cloned().collect(),
+            ln_files: payload.ln_files.keys().cloned().collect(),
+            file_manifest: payload.file_manifest.clone(),
+        }
+    }
+}
+
 /// Request body for merge endpoint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -341,16 +385,108 @@ pub struct SyncConfig {
     pub sync_on_chapter_open: bool,
     pub sync_on_app_start: bool,
     pub sync_on_app_resume: bool,
+    /// Sync after local changes reported to `/notify-change` settle
+    #[serde(default)]
+    pub sync_on_change: bool,
+    /// Seconds without changes before that sync starts
+    #[serde(default = "default_sync_debounce_secs")]
+    pub sync_debounce_secs: u64,
+    /// Days a deletion is remembered; devices offline for longer may bring the book back
+    #[serde(default = "default_tombstone_retention_days")]
+    pub tombstone_retention_days: u32,
+    /// Number of pushed payload versions kept for rollback
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize,
+    /// Days without reading after which a book's content and files are no longer
+    /// pushed; 0 pushes them all
+    #[serde(default)]
+    pub prune_after_days: u32,
+    /// Pushed payloads over this many megabytes leave out the files, then the content,
+    /// of the least recently read books; 0 for no limit
+    #[serde(default)]
+    pub max_payload_mb: u32,
+    /// Seconds between checks for pushes from other devices; 0 disables them
+    #[serde(default = "default_remote_poll_secs")]
+    pub remote_poll_secs: u64,
 
     // Backend selection
     pub backend: SyncBackendType,
 
+    // Compression of the sync file
+    #[serde(default)]
+    pub compression: CompressionAlgorithm,
+    /// 1 (fastest) to 22 (smallest)
+    #[serde(default = "default_zstd_level")]
+    pub zstd_level: i32,
+
     // Google Drive settings
+    /// Folder holding the sync file; `/` separates nested folders, e.g. `Backups/Manatan`
     pub google_drive_folder: String,
     pub google_drive_folder_type: GoogleDriveFolderType,
 
     // Deletion behavior
     pub deletion_behavior: DeletionBehavior,
+
+    // Peer-to-peer settings
+    /// Base URL of the other Manatan server, e.g. `http://192.168.1.20:4568`
+    #[serde(default)]
+    pub peer_url: String,
+    /// Token sent to the peer
+    #[serde(default)]
+    pub peer_token: String,
+    /// Token other devices must send to sync through this one; empty disables the peer endpoints
+    #[serde(default)]
+    pub peer_serve_token: String,
+
+    // SFTP settings
+    #[serde(default)]
+    pub sftp_host: String,
+    #[serde(default = "default_sftp_port")]
+    pub sftp_port: u16,
+    #[serde(default)]
+    pub sftp_username: String,
+    #[serde(default)]
+    pub sftp_password: String,
+    /// PEM-encoded private key, or a path to one; used instead of the password when set
+    #[serde(default)]
+    pub sftp_private_key: String,
+    #[serde(default)]
+    pub sftp_key_passphrase: String,
+    /// Expected host key, e.g. `SHA256:...`; while empty, the key seen on the first
+    /// connection is trusted and stored here
+    #[serde(default)]
+    pub sftp_host_fingerprint: String,
+    /// Directory holding the sync file, relative to the login directory unless absolute
+    #[serde(default = "default_sftp_remote_dir")]
+    pub sftp_remote_dir: String,
+}
+
+fn default_sync_debounce_secs() -> u64 {
+    10
+}
+
+fn default_tombstone_retention_days() -> u32 {
+    90
+}
+
+fn default_history_limit() -> usize {
+    10
+}
+
+fn default_remote_poll_secs() -> u64 {
+    60
+}
+
+fn default_zstd_level() -> i32 {
+    3
+}
+
+fn default_sftp_port() -> u16 {
+    22
+}
+
+fn default_sftp_remote_dir() -> String {
+    "Manatan".to_string()
 }
 
 impl Default for SyncConfig {
@@ -364,10 +500,30 @@ impl Default for SyncConfig {
             sync_on_chapter_open: false,
             sync_on_app_start: false,
             sync_on_app_resume: false,
+            sync_on_change: false,
+            sync_debounce_secs: default_sync_debounce_secs(),
+            tombstone_retention_days: default_tombstone_retention_days(),
+            history_limit: default_history_limit(),
+            prune_after_days: 0,
+            max_payload_mb: 0,
+            remote_poll_secs: default_remote_poll_secs(),
             backend: SyncBackendType::None,
+            compression: CompressionAlgorithm::Gzip,
+            zstd_level: default_zstd_level(),
             google_drive_folder: "Manatan".to_string(),
             google_drive_folder_type: GoogleDriveFolderType::Public,
             deletion_behavior: DeletionBehavior::KeepEverywhere,
+            peer_url: String::new(),
+            peer_token: String::new(),
+            peer_serve_token: String::new(),
+            sftp_host: String::new(),
+            sftp_port: default_sftp_port(),
+            sftp_username: String::new(),
+            sftp_password: String::new(),
+            sftp_private_key: String::new(),
+            sftp_key_passphrase: String::new(),
+            sftp_host_fingerprint: String::new(),
+            sftp_remote_dir: default_sftp_remote_dir(),
         }
     }
 }
@@ -391,12 +547,24 @@ pub enum DeletionBehavior {
     AskEachTime,
 }
 
+/// Compression of the sync file; readers detect it from the file
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    #[default]
+    Gzip,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
 pub enum SyncBackendType {
     #[default]
     None,
     GoogleDrive,
     WebDav,
     SyncYomi,
+    /// Another Manatan server, typically on the same LAN
+    Peer,
+    Sftp,
 }
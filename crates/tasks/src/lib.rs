@@ -0,0 +1,181 @@
+//! Supervision of the servers' long-running background tasks.
+//!
+//! A task is started from a factory so it can be started again: when a run panics, the
+//! task is restarted after a backoff that doubles from 1s up to 5 minutes, and starts
+//! over at 1s once a run has lasted longer than that. A run that returns means the
+//! task is done on purpose (e.g. on shutdown) and it is not restarted.
+//!
+//! `GET /tasks` ([`tasks_handler`]) lists every task with its state, restart count and
+//! last error. The registry is process-wide, like the settings of `manatan-config`.
+
+use std::{
+    any::Any,
+    future::Future,
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::Json;
+use serde::Serialize;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+static TASKS: LazyLock<Mutex<Vec<Arc<Mutex<TaskStatus>>>>> = LazyLock::new(Mutex::default);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Crashed and waiting out its backoff.
+    Restarting,
+    Finished,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    pub restarts: u32,
+    /// Unix seconds when the current run started.
+    pub started_at: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<u64>,
+    /// Unix seconds when a crashed task starts again.
+    pub restart_at: Option<u64>,
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return (*message).to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    "panicked".to_string()
+}
+
+/// Runs `factory()` as a supervised task named `name`, see the module docs.
+pub fn spawn<F, Fut>(name: impl Into<String>, factory: F)
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let name = name.into();
+    let status = Arc::new(Mutex::new(TaskStatus {
+        name: name.clone(),
+        state: TaskState::Running,
+        restarts: 0,
+        started_at: now_unix(),
+        last_error: None,
+        last_error_at: None,
+        restart_at: None,
+    }));
+    TASKS.lock().expect("lock poisoned").push(status.clone());
+
+    tokio::spawn(async move {
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            let started = Instant::now();
+            let outcome = tokio::spawn(factory()).await;
+
+            let error = match outcome {
+                Ok(()) => None,
+                Err(err) if err.is_panic() => Some(panic_message(err.into_panic())),
+                // Aborted with the runtime
+                Err(_) => None,
+            };
+            let Some(error) = error else {
+                status.lock().expect("lock poisoned").state = TaskState::Finished;
+                return;
+            };
+
+            if started.elapsed() > MAX_BACKOFF {
+                backoff = INITIAL_BACKOFF;
+            }
+            tracing::error!(
+                "[Tasks] {name} crashed: {error}; restarting in {}s",
+                backoff.as_secs()
+            );
+            {
+                let mut status = status.lock().expect("lock poisoned");
+                status.state = TaskState::Restarting;
+                status.last_error = Some(error);
+                status.last_error_at = Some(now_unix());
+                status.restart_at = Some(now_unix() + backoff.as_secs());
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+
+            let mut status = status.lock().expect("lock poisoned");
+            status.state = TaskState::Running;
+            status.restarts += 1;
+            status.started_at = now_unix();
+            status.restart_at = None;
+        }
+    });
+}
+
+/// Every task spawned so far, in spawn order.
+pub fn list() -> Vec<TaskStatus> {
+    TASKS
+        .lock()
+        .expect("lock poisoned")
+        .iter()
+        .map(|status| status.lock().expect("lock poisoned").clone())
+        .collect()
+}
+
+pub async fn tasks_handler() -> Json<Vec<TaskStatus>> {
+    Json(list())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    use super::{TaskState, list, spawn};
+
+    fn status(name: &str) -> super::TaskStatus {
+        list()
+            .into_iter()
+            .find(|status| status.name == name)
+            .expect("task is registered")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn crashed_task_is_restarted_and_finished_task_is_not() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let task_runs = runs.clone();
+        spawn("test.crash-once", move || {
+            let runs = task_runs.clone();
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("first run fails");
+                }
+            }
+        });
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        let crashed = status("test.crash-once");
+        assert_eq!(crashed.state, TaskState::Restarting);
+        assert_eq!(crashed.last_error.as_deref(), Some("first run fails"));
+
+        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        let finished = status("test.crash-once");
+        assert_eq!(finished.state, TaskState::Finished);
+        assert_eq!(finished.restarts, 1);
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,142 @@
+//! HTTPS for the Manatan listeners.
+//!
+//! - `MANATAN_TLS_CERT` and `MANATAN_TLS_KEY`: PEM certificate chain and private key to
+//!   serve with.
+//! - `MANATAN_TLS_SELF_SIGNED`: with no certificate given, serve a self-signed one for
+//!   `localhost`. It is generated once and kept in the data directory, so a browser
+//!   exception made for it keeps working across restarts.
+//!
+//! Browsers only expose the clipboard and some extension APIs to secure origins, which
+//! plain HTTP is not unless it is served on `localhost`.
+
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use axum::Router;
+use axum_server::{Handle, tls_rustls::RustlsConfig};
+
+const CERT_ENV: &str = "MANATAN_TLS_CERT";
+const KEY_ENV: &str = "MANATAN_TLS_KEY";
+const SELF_SIGNED_ENV: &str = "MANATAN_TLS_SELF_SIGNED";
+const SELF_SIGNED_DIR: &str = "tls";
+const SELF_SIGNED_CERT: &str = "self-signed-cert.pem";
+const SELF_SIGNED_KEY: &str = "self-signed-key.pem";
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("{CERT_ENV} and {KEY_ENV} must be set together")]
+    Incomplete,
+
+    #[error("Failed to access {}: {source}", .path.display())]
+    Io { path: PathBuf, source: io::Error },
+
+    #[error("Failed to load the TLS certificate: {0}")]
+    Load(io::Error),
+
+    #[error("Failed to generate a self-signed certificate: {0}")]
+    Generate(#[from] rcgen::Error),
+}
+
+#[derive(Clone, Debug)]
+pub enum TlsSettings {
+    Files { cert: PathBuf, key: PathBuf },
+    SelfSigned,
+}
+
+impl TlsSettings {
+    /// The configured TLS settings, or `None` to serve plain HTTP.
+    pub fn from_env() -> Result<Option<Self>, TlsError> {
+        match (setting(CERT_ENV), setting(KEY_ENV)) {
+            (Some(cert), Some(key)) => Ok(Some(Self::Files {
+                cert: cert.into(),
+                key: key.into(),
+            })),
+            (None, None) => {
+                let self_signed = setting(SELF_SIGNED_ENV).is_some_and(|value| {
+                    matches!(value.to_lowercase().as_str(), "1" | "true" | "yes")
+                });
+                Ok(self_signed.then_some(Self::SelfSigned))
+            }
+            _ => Err(TlsError::Incomplete),
+        }
+    }
+
+    /// Loads the certificate, generating the self-signed one in `data_dir` if needed.
+    pub async fn rustls_config(&self, data_dir: &Path) -> Result<RustlsConfig, TlsError> {
+        let (cert, key) = match self {
+            Self::Files { cert, key } => (cert.clone(), key.clone()),
+            Self::SelfSigned => self_signed_files(data_dir)?,
+        };
+        RustlsConfig::from_pem_file(cert, key)
+            .await
+            .map_err(TlsError::Load)
+    }
+}
+
+fn setting(key: &str) -> Option<String> {
+    manatan_config::var(key)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// `"https"` when TLS is configured, else `"http"`; for building links to the server.
+pub fn scheme() -> &'static str {
+    match TlsSettings::from_env() {
+        Ok(Some(_)) => "https",
+        _ => "http",
+    }
+}
+
+fn self_signed_files(data_dir: &Path) -> Result<(PathBuf, PathBuf), TlsError> {
+    let dir = data_dir.join(SELF_SIGNED_DIR);
+    let cert_path = dir.join(SELF_SIGNED_CERT);
+    let key_path = dir.join(SELF_SIGNED_KEY);
+    if cert_path.is_file() && key_path.is_file() {
+        return Ok((cert_path, key_path));
+    }
+
+    let names = ["localhost", "127.0.0.1", "::1"].map(String::from).to_vec();
+    let rcgen::CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(names)?;
+    let write = |path: &Path, contents: String| {
+        std::fs::write(path, contents).map_err(|source| TlsError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    };
+    std::fs::create_dir_all(&dir).map_err(|source| TlsError::Io {
+        path: dir.clone(),
+        source,
+    })?;
+    write(&key_path, key_pair.serialize_pem())?;
+    write(&cert_path, cert.pem())?;
+    tracing::info!(
+        "[TLS] Generated a self-signed certificate at {}",
+        cert_path.display()
+    );
+    Ok((cert_path, key_path))
+}
+
+/// Serves `router` over HTTPS on `addr` until `shutdown` resolves, letting open
+/// connections finish.
+pub async fn serve(
+    addr: SocketAddr,
+    router: Router,
+    config: RustlsConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> io::Result<()> {
+    let handle = Handle::new();
+    let shutdown_handle = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        shutdown_handle.graceful_shutdown(None);
+    });
+    axum_server::bind_rustls(addr, config)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await
+}
@@ -1,8 +1,12 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
     Json,
-    extract::{Multipart, Query, State},
+    extract::{Multipart, Query, State, multipart::Field},
     http::StatusCode,
 };
 use regex::Regex;
@@ -11,6 +15,7 @@ use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, Value as JsonValue, json};
 use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
 use tracing::{error, info, warn};
 use wordbase_api::{DictionaryId, Record, Term, dict::yomitan::GlossaryTag};
 
@@ -1626,6 +1631,45 @@ pub async fn list_dictionaries_handler(State(state): State<ServerState>) -> Json
     )
 }
 
+/// Writes an uploaded archive to a file in the data directory, chunk by chunk, so the
+/// upload never sits in memory whole.
+async fn spool_upload(field: &mut Field<'_>, path: &Path) -> Result<u64, String> {
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to create upload file: {}", e))?;
+    let mut written = 0u64;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| format!("Upload Failed: {}", e))?
+    {
+        written += chunk.len() as u64;
+        if written > import::MAX_ARCHIVE_BYTES {
+            return Err(format!(
+                "Archive is too large (max {} bytes).",
+                import::MAX_ARCHIVE_BYTES
+            ));
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write upload file: {}", e))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write upload file: {}", e))?;
+    Ok(written)
+}
+
+fn upload_path(app_state: &AppState) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    app_state
+        .data_dir
+        .join(format!("import-{}-{}.zip.part", std::process::id(), nanos))
+}
+
 pub async fn import_handler(
     State(state): State<ServerState>,
     mut multipart: Multipart,
@@ -1634,34 +1678,34 @@ pub async fn import_handler(
 
     loop {
         match multipart.next_field().await {
-            Ok(Some(field)) => {
+            Ok(Some(mut field)) => {
                 if field.name() == Some("file") {
-                    match field.bytes().await {
-                        Ok(data) => {
-                            info!("📥 [Import API] Received upload ({} bytes)", data.len());
+                    let path = upload_path(&state.app);
+                    let res = match spool_upload(&mut field, &path).await {
+                        Ok(size) => {
+                            info!("📥 [Import API] Received upload ({} bytes)", size);
                             let app_state = state.app.clone();
-                            let res = tokio::task::spawn_blocking(move || {
-                                import::import_zip(&app_state, &data)
+                            let import_path = path.clone();
+                            tokio::task::spawn_blocking(move || {
+                                import::import_zip_file(&app_state, &import_path)
+                                    .map_err(|e| e.to_string())
                             })
                             .await
-                            .unwrap();
-                            return match res {
-                                Ok(msg) => {
-                                    info!("✅ {}", msg);
-                                    Json(json!({ "status": "ok", "message": msg }))
-                                }
-                                Err(e) => {
-                                    error!("❌ {}", e);
-                                    Json(json!({ "status": "error", "message": e.to_string() }))
-                                }
-                            };
+                            .unwrap_or_else(|e| Err(e.to_string()))
+                        }
+                        Err(e) => Err(e),
+                    };
+                    let _ = tokio::fs::remove_file(&path).await;
+                    return match res {
+                        Ok(msg) => {
+                            info!("✅ {}", msg);
+                            Json(json!({ "status": "ok", "message": msg }))
                         }
                         Err(e) => {
-                            return Json(
-                                json!({ "status": "error", "message": format!("Upload Failed: {}", e) }),
-                            );
+                            error!("❌ {}", e);
+                            Json(json!({ "status": "error", "message": e }))
                         }
-                    }
+                    };
                 }
             }
             Ok(None) => break,
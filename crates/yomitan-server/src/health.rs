@@ -0,0 +1,56 @@
+//! Readiness of the dictionary index for the gateway's `/healthz`.
+//!
+//! Lookups answer 503 while an import or reset runs, and find nothing without an
+//! enabled dictionary; both report `degraded`. An unreachable database is `unavailable`.
+
+use serde::Serialize;
+
+use crate::state::AppState;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DictHealth {
+    pub status: &'static str,
+    pub database: bool,
+    pub loading: bool,
+    pub dictionaries: usize,
+    pub enabled: usize,
+}
+
+#[derive(Clone)]
+pub struct HealthProbe {
+    state: AppState,
+}
+
+impl HealthProbe {
+    pub(crate) fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub fn check(&self) -> DictHealth {
+        let state = &self.state;
+        let database = state
+            .pool
+            .get()
+            .is_ok_and(|conn| conn.query_row("SELECT 1", [], |_| Ok(())).is_ok());
+        let loading = state.is_writing();
+        let (dictionaries, enabled) = {
+            let dicts = state.dictionaries.read().expect("lock");
+            (dicts.len(), dicts.values().filter(|d| d.enabled).count())
+        };
+
+        let status = if !database {
+            "unavailable"
+        } else if loading || enabled == 0 {
+            "degraded"
+        } else {
+            "ok"
+        };
+        DictHealth {
+            status,
+            database,
+            loading,
+            dictionaries,
+            enabled,
+        }
+    }
+}
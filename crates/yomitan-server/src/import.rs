@@ -1,8 +1,10 @@
 use std::{
     collections::HashSet,
     fmt,
-    io::Read,
+    fs::File,
+    io::{BufReader, Read, Seek},
     marker::PhantomData,
+    path::Path,
 };
 
 use anyhow::{Result, anyhow};
@@ -21,6 +23,8 @@ use crate::state::{AppState, DictionaryData, StoredRecord};
 const MAX_IMPORT_ARCHIVE_BYTES: usize = 2 * 1024 * 1024;
 #[cfg(not(test))]
 const MAX_IMPORT_ARCHIVE_BYTES: usize = 384 * 1024 * 1024;
+/// Largest archive `import_zip_file` accepts; uploads are cut off at this size.
+pub const MAX_ARCHIVE_BYTES: u64 = MAX_IMPORT_ARCHIVE_BYTES as u64;
 const MAX_TOTAL_UNCOMPRESSED_BYTES: u64 = 1024 * 1024 * 1024;
 const MAX_JSON_ENTRY_BYTES: u64 = 192 * 1024 * 1024;
 const MAX_INDEX_JSON_BYTES: u64 = 4 * 1024 * 1024;
@@ -236,21 +240,39 @@ where
     Ok(count)
 }
 
-pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
-    if data.len() > MAX_IMPORT_ARCHIVE_BYTES {
+fn check_archive_size(size: u64) -> Result<()> {
+    if size > MAX_ARCHIVE_BYTES {
         return Err(anyhow!(
             "Archive is too large ({} bytes, max {}).",
-            data.len(),
-            MAX_IMPORT_ARCHIVE_BYTES
+            size,
+            MAX_ARCHIVE_BYTES
         ));
     }
+    Ok(())
+}
 
-    info!(
-        "📦 [Import] Starting ZIP import (size: {} bytes)...",
-        data.len()
-    );
+pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
+    check_archive_size(data.len() as u64)?;
+    import_archive(state, std::io::Cursor::new(data), data.len() as u64)
+}
 
-    let mut zip = ZipArchive::new(std::io::Cursor::new(data))?;
+/// Imports an archive spooled to disk. Only the entry being parsed is read into
+/// memory, a bank at a time through `parse_json_array_stream`.
+pub fn import_zip_file(state: &AppState, path: &Path) -> Result<String> {
+    let file = File::open(path)?;
+    let size = file.metadata()?.len();
+    check_archive_size(size)?;
+    import_archive(state, BufReader::new(file), size)
+}
+
+fn import_archive<R: Read + Seek>(state: &AppState, reader: R, size: u64) -> Result<String> {
+    let _guard = state
+        .begin_import()
+        .ok_or_else(|| anyhow!("Server is shutting down, try the import again later."))?;
+
+    info!("📦 [Import] Starting ZIP import (size: {} bytes)...", size);
+
+    let mut zip = ZipArchive::new(reader)?;
     validate_zip_archive(&mut zip)?;
 
     // 1. Find index.json
@@ -583,6 +605,30 @@ mod tests {
         });
     }
 
+    #[test]
+    fn imports_dictionary_from_file() {
+        with_state("imports-file", |state| {
+            let zip = build_zip(
+                r#"{"format":3,"title":"File Dict","revision":"1"}"#,
+                &[(
+                    "term_bank_1.json",
+                    r#"[["犬","いぬ","n",null,10,["dog"],0,""]]"#,
+                )],
+            );
+            let path = state.data_dir.join("upload.zip");
+            fs::write(&path, &zip).expect("write upload");
+
+            let msg = import_zip_file(state, &path).expect("import should succeed");
+            assert!(msg.contains("Imported 'File Dict'"));
+
+            let conn = state.pool.get().expect("db connection");
+            let term_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM terms", [], |row| row.get(0))
+                .expect("term count query");
+            assert_eq!(term_count, 2);
+        });
+    }
+
     #[test]
     fn rejects_duplicate_dictionary_name() {
         with_state("duplicate-name", |state| {
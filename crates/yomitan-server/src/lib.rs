@@ -5,12 +5,20 @@ use axum::{
     extract::DefaultBodyLimit,
     routing::{get, post},
 };
-use tower_http::{cors::CorsLayer, limit::RequestBodyLimitLayer};
+use tower_http::{
+    compression::{
+        CompressionLayer,
+        predicate::{DefaultPredicate, NotForContentType, Predicate},
+    },
+    limit::RequestBodyLimitLayer,
+};
 
 pub mod deinflector;
 pub mod handlers;
+pub mod health;
 pub mod import;
 pub mod lookup;
+pub mod shutdown;
 pub mod state;
 
 use handlers::{
@@ -19,8 +27,12 @@ use handlers::{
     unload_handler,
 };
 use lookup::LookupService;
+use shutdown::ShutdownHandle;
 use state::AppState;
 
+const BODY_LIMIT_MB_ENV: &str = "MANATAN_DICT_BODY_LIMIT_MB";
+const DEFAULT_BODY_LIMIT_MB: usize = 1024;
+
 #[derive(Clone)]
 pub struct ServerState {
     pub app: AppState,
@@ -28,14 +40,39 @@ pub struct ServerState {
 }
 
 pub fn create_router(data_dir: PathBuf) -> Router {
+    create_router_with_shutdown(data_dir).0
+}
+
+/// Creates the dictionary Router, along with the handle that waits for imports on shutdown.
+pub fn create_router_with_shutdown(data_dir: PathBuf) -> (Router, ShutdownHandle) {
+    let (router, handles) = create_router_with_handles(data_dir);
+    (router, handles.shutdown)
+}
+
+/// What an embedding server keeps of the dictionary server besides its router.
+pub struct Handles {
+    pub shutdown: ShutdownHandle,
+    pub health: health::HealthProbe,
+}
+
+pub fn create_router_with_handles(data_dir: PathBuf) -> (Router, Handles) {
     let state = ServerState {
         app: AppState::new(data_dir),
         lookup: Arc::new(LookupService::new()),
     };
+    let handles = Handles {
+        shutdown: ShutdownHandle::new(state.app.clone()),
+        health: health::HealthProbe::new(state.app.clone()),
+    };
 
-    let limit = 1024 * 1024 * 1024;
+    let limit = manatan_config::var(BODY_LIMIT_MB_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_BODY_LIMIT_MB)
+        * 1024
+        * 1024;
 
-    Router::new()
+    let router = Router::new()
         .route("/lookup", get(lookup_handler))
         .route("/audio", get(audio_handler))
         .route("/dictionaries", get(list_dictionaries_handler))
@@ -44,9 +81,17 @@ pub fn create_router(data_dir: PathBuf) -> Router {
         .route("/manage", post(manage_dictionaries_handler))
         .route("/install-defaults", post(install_defaults_handler))
         .route("/install-language", post(install_language_handler))
-        .route("/unload", post(unload_handler))
-        .layer(CorsLayer::permissive())
+        .route("/unload", post(unload_handler));
+
+    // `/audio` clips are compressed already
+    let compression = CompressionLayer::new()
+        .compress_when(DefaultPredicate::new().and(NotForContentType::const_new("audio/")));
+
+    let router = manatan_auth::protect(router)
+        .layer(compression)
+        .layer(manatan_auth::cors::layer())
         .layer(DefaultBodyLimit::max(limit))
         .layer(RequestBodyLimitLayer::new(limit))
-        .with_state(state)
+        .with_state(state);
+    (router, handles)
 }
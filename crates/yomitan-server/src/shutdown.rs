@@ -0,0 +1,61 @@
+//! Graceful shutdown for dictionary imports.
+//!
+//! Draining refuses new imports and waits up to `MANATAN_DICT_SHUTDOWN_DEADLINE_SECS`
+//! (default 20) for a running import or reset to commit. The database uses a rollback
+//! journal, so a committed transaction is already in `yomitan.db`; an import cut off
+//! by the deadline is rolled back from the journal on the next start.
+
+use std::{
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
+
+use tracing::{info, warn};
+
+use crate::state::AppState;
+
+const SHUTDOWN_DEADLINE_ENV: &str = "MANATAN_DICT_SHUTDOWN_DEADLINE_SECS";
+const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(20);
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returned by `create_router_with_shutdown`; call `drain` before the process exits.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    state: AppState,
+    deadline: Duration,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new(state: AppState) -> Self {
+        let deadline = manatan_config::var(SHUTDOWN_DEADLINE_ENV)
+            .ok()
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_SHUTDOWN_DEADLINE);
+        Self { state, deadline }
+    }
+
+    pub async fn drain(&self) {
+        let state = &self.state;
+        if state.shutting_down.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        if !state.is_writing() {
+            return;
+        }
+
+        info!("🛑 [Yomitan] Waiting for the running import to commit...");
+        let started = Instant::now();
+        while state.is_writing() && started.elapsed() < self.deadline {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+        if state.is_writing() {
+            warn!(
+                "⚠️ [Yomitan] Deadline of {:?} reached with an import still running, it will be rolled back",
+                self.deadline
+            );
+        } else {
+            info!("✅ [Yomitan] Import committed");
+        }
+    }
+}
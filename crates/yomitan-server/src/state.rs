@@ -2,7 +2,7 @@ use std::{
     collections::HashMap,
     path::PathBuf,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, RwLock,
     },
     time::{Duration, Instant},
@@ -31,6 +31,9 @@ pub struct AppState {
     pub pool: DbPool,
     pub data_dir: PathBuf,
     pub loading: Arc<AtomicBool>,
+    /// Set when `ShutdownHandle::drain` starts; new imports are refused.
+    pub shutting_down: Arc<AtomicBool>,
+    pub imports_in_flight: Arc<AtomicUsize>,
     startup_instant: Instant,
 }
 
@@ -54,6 +57,16 @@ impl AppState {
         if !data_dir.exists() {
             let _ = std::fs::create_dir_all(&data_dir);
         }
+        // Uploads left behind by an import that was cut off
+        if let Ok(entries) = std::fs::read_dir(&data_dir) {
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                if name.starts_with("import-") && name.ends_with(".zip.part") {
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
         let db_path = data_dir.join("yomitan.db");
         let manager = SqliteConnectionManager::file(&db_path);
 
@@ -130,6 +143,8 @@ impl AppState {
             pool,
             data_dir,
             loading: Arc::new(AtomicBool::new(false)),
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            imports_in_flight: Arc::new(AtomicUsize::new(0)),
             startup_instant: Instant::now(),
         }
     }
@@ -142,6 +157,20 @@ impl AppState {
         self.loading.load(Ordering::Relaxed)
     }
 
+    /// Counts an import as in flight until the guard drops, unless shutting down.
+    pub fn begin_import(&self) -> Option<ImportGuard> {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return None;
+        }
+        self.imports_in_flight.fetch_add(1, Ordering::SeqCst);
+        Some(ImportGuard(self.imports_in_flight.clone()))
+    }
+
+    /// Whether an import or a reset is writing to the database.
+    pub fn is_writing(&self) -> bool {
+        self.is_loading() || self.imports_in_flight.load(Ordering::SeqCst) > 0
+    }
+
     pub fn is_import_startup_guard_active(&self) -> bool {
         self.startup_instant.elapsed() < IMPORT_STARTUP_GUARD
     }
@@ -153,11 +182,20 @@ impl AppState {
     }
 }
 
+pub struct ImportGuard(Arc<AtomicUsize>);
+
+impl Drop for ImportGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
         fs,
         path::PathBuf,
+        sync::atomic::Ordering,
         time::{Duration, SystemTime, UNIX_EPOCH},
     };
 
@@ -198,4 +236,20 @@ mod tests {
         drop(state);
         let _ = fs::remove_dir_all(dir);
     }
+
+    #[test]
+    fn imports_are_refused_once_shutting_down() {
+        let dir = test_data_dir("shutdown-import");
+        let state = AppState::new(dir.clone());
+
+        let guard = state.begin_import().expect("import before shutdown");
+        assert!(state.is_writing());
+        state.shutting_down.store(true, Ordering::SeqCst);
+        assert!(state.begin_import().is_none());
+        drop(guard);
+        assert!(!state.is_writing());
+
+        drop(state);
+        let _ = fs::remove_dir_all(dir);
+    }
 }